@@ -0,0 +1,598 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+
+use proc_macro2::TokenStream as TokenStream2;
+
+use quote::quote;
+
+use syn::{
+    parse::{
+        Parse,
+        ParseStream,
+    },
+    parse_macro_input,
+    Lit,
+    Token,
+    LitStr,
+    TypeTuple,
+    Data,
+    Meta,
+    Field,
+    Fields,
+    Attribute,
+    NestedMeta,
+    DeriveInput,
+};
+
+#[proc_macro_derive(FromRow, attributes(cassandra))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match from_row(&input) {
+        Ok(tokens) =>
+            tokens.into(),
+        Err(error) =>
+            error.to_compile_error().into(),
+    }
+}
+
+#[proc_macro_derive(ToParams, attributes(cassandra))]
+pub fn derive_to_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match to_params(&input) {
+        Ok(tokens) =>
+            tokens.into(),
+        Err(error) =>
+            error.to_compile_error().into(),
+    }
+}
+
+#[proc_macro_derive(ToNamedParams, attributes(cassandra))]
+pub fn derive_to_named_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match to_named_params(&input) {
+        Ok(tokens) =>
+            tokens.into(),
+        Err(error) =>
+            error.to_compile_error().into(),
+    }
+}
+
+#[proc_macro_derive(Udt, attributes(cassandra))]
+pub fn derive_udt(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match udt(&input) {
+        Ok(tokens) =>
+            tokens.into(),
+        Err(error) =>
+            error.to_compile_error().into(),
+    }
+}
+
+#[proc_macro]
+pub fn cql(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as CqlInput);
+    match cql_statement(&input) {
+        Ok(tokens) =>
+            tokens.into(),
+        Err(error) =>
+            error.to_compile_error().into(),
+    }
+}
+
+enum Column {
+    Index(usize),
+    Name(String),
+    Skip,
+}
+
+fn from_row(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = match input.data {
+        Data::Struct(ref data) =>
+            &data.fields,
+        _ =>
+            return Err(syn::Error::new_spanned(input, "FromRow can only be derived for structs")),
+    };
+
+    let body = match fields {
+        Fields::Named(named) => {
+            let inits = named.named.iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().unwrap();
+                    let default_column = Column::Name(ident.to_string().trim_start_matches("r#").to_string());
+                    let getter = column_getter(field, default_column)?;
+                    Ok(quote! { #ident: #getter })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! { #name { #(#inits,)* } }
+        },
+        Fields::Unnamed(unnamed) => {
+            let inits = unnamed.unnamed.iter()
+                .enumerate()
+                .map(|(position, field)| column_getter(field, Column::Index(position)))
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! { #name(#(#inits,)*) }
+        },
+        Fields::Unit =>
+            quote! { { let _ = row; #name } },
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::ero_cassandra::FromRow for #name #ty_generics #where_clause {
+            fn from_row(row: &::ero_cassandra::row::Row) -> ::std::result::Result<Self, ::ero_cassandra::RowError> {
+                ::std::result::Result::Ok(#body)
+            }
+        }
+    })
+}
+
+fn column_getter(field: &Field, default_column: Column) -> syn::Result<TokenStream2> {
+    let column = column_attr(&field.attrs)?.unwrap_or(default_column);
+    Ok(match column {
+        Column::Index(index) =>
+            quote! { ::ero_cassandra::row::column(row, #index)? },
+        Column::Name(name) =>
+            quote! { ::ero_cassandra::row::column_by_name(row, #name)? },
+        Column::Skip =>
+            quote! { ::std::default::Default::default() },
+    })
+}
+
+fn to_params(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = match input.data {
+        Data::Struct(ref data) =>
+            &data.fields,
+        _ =>
+            return Err(syn::Error::new_spanned(input, "ToParams can only be derived for structs")),
+    };
+
+    let mut values = Vec::new();
+    for (position, field) in fields.iter().enumerate() {
+        if let Some(Column::Skip) = column_attr(&field.attrs)? {
+            continue;
+        }
+        let member = match field.ident {
+            Some(ref ident) =>
+                quote! { #ident },
+            None => {
+                let index = syn::Index::from(position);
+                quote! { #index }
+            },
+        };
+        values.push(quote! { ::ero_cassandra::ToCqlValue::to_cql_value(&self.#member) });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::ero_cassandra::ToParams for #name #ty_generics #where_clause {
+            fn to_params(&self) -> ::std::vec::Vec<::ero_cassandra::CqlValue> {
+                vec![#(#values),*]
+            }
+        }
+    })
+}
+
+fn to_named_params(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = match input.data {
+        Data::Struct(ref data) =>
+            match data.fields {
+                Fields::Named(ref named) =>
+                    &named.named,
+                _ =>
+                    return Err(syn::Error::new_spanned(input, "ToNamedParams can only be derived for structs with named fields")),
+            },
+        _ =>
+            return Err(syn::Error::new_spanned(input, "ToNamedParams can only be derived for structs")),
+    };
+
+    let mut values = Vec::new();
+    for field in fields.iter() {
+        let ident = field.ident.as_ref().unwrap();
+        let marker = match column_attr(&field.attrs)? {
+            Some(Column::Skip) =>
+                continue,
+            Some(Column::Name(marker)) =>
+                marker,
+            Some(Column::Index(..)) =>
+                return Err(syn::Error::new_spanned(field, "named markers are matched by name, `index` is not supported")),
+            None =>
+                ident.to_string().trim_start_matches("r#").to_string(),
+        };
+        values.push(quote! {
+            (#marker.to_string(), ::ero_cassandra::ToCqlValue::to_cql_value(&self.#ident))
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::ero_cassandra::ToNamedParams for #name #ty_generics #where_clause {
+            fn to_named_params(&self) -> ::std::vec::Vec<(::std::string::String, ::ero_cassandra::CqlValue)> {
+                vec![#(#values),*]
+            }
+        }
+    })
+}
+
+fn udt(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = match input.data {
+        Data::Struct(ref data) =>
+            match data.fields {
+                Fields::Named(ref named) =>
+                    &named.named,
+                _ =>
+                    return Err(syn::Error::new_spanned(input, "Udt can only be derived for structs with named fields")),
+            },
+        _ =>
+            return Err(syn::Error::new_spanned(input, "Udt can only be derived for structs")),
+    };
+
+    let mut values = Vec::new();
+    let mut inits = Vec::new();
+    for field in fields.iter() {
+        let ident = field.ident.as_ref().unwrap();
+        let field_name = match column_attr(&field.attrs)? {
+            Some(Column::Skip) => {
+                inits.push(quote! { #ident: ::std::default::Default::default() });
+                continue;
+            },
+            Some(Column::Name(field_name)) =>
+                field_name,
+            Some(Column::Index(..)) =>
+                return Err(syn::Error::new_spanned(field, "UDT fields are matched by name, `index` is not supported")),
+            None =>
+                ident.to_string().trim_start_matches("r#").to_string(),
+        };
+        values.push(quote! {
+            (#field_name.to_string(), ::ero_cassandra::ToCqlValue::to_cql_value(&self.#ident))
+        });
+        inits.push(quote! { #ident: ::ero_cassandra::udt::field(&fields, #field_name)? });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::ero_cassandra::ToCqlValue for #name #ty_generics #where_clause {
+            fn to_cql_value(&self) -> ::ero_cassandra::CqlValue {
+                ::ero_cassandra::CqlValue::Udt(vec![#(#values),*])
+            }
+        }
+
+        impl #impl_generics ::ero_cassandra::FromValue for #name #ty_generics #where_clause {
+            fn from_value(value: &::ero_cassandra::udt::Value) -> ::std::result::Result<Self, ::ero_cassandra::udt::Error> {
+                let fields = ::ero_cassandra::udt::fields(value)?;
+                ::std::result::Result::Ok(#name { #(#inits,)* })
+            }
+        }
+    })
+}
+
+// Every `#[cassandra(...)]` entry on a field is checked: at most one of
+// `name`, `index` and `skip` may be given, once.
+fn column_attr(attrs: &[Attribute]) -> syn::Result<Option<Column>> {
+    let mut column = None;
+    for attr in attrs {
+        if !attr.path.is_ident("cassandra") {
+            continue;
+        }
+        let list = match attr.parse_meta()? {
+            Meta::List(list) =>
+                list,
+            other =>
+                return Err(syn::Error::new_spanned(other, "expected #[cassandra(name = \"...\")] or #[cassandra(index = N)]")),
+        };
+        for nested in list.nested.iter() {
+            let found = match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") =>
+                    Column::Skip,
+                NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("name") =>
+                    match name_value.lit {
+                        Lit::Str(ref lit) =>
+                            Column::Name(lit.value()),
+                        ref lit =>
+                            return Err(syn::Error::new_spanned(lit, "expected a string literal column name")),
+                    },
+                NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("index") =>
+                    match name_value.lit {
+                        Lit::Int(ref lit) =>
+                            Column::Index(lit.base10_parse()?),
+                        ref lit =>
+                            return Err(syn::Error::new_spanned(lit, "expected an integer column index")),
+                    },
+                other =>
+                    return Err(syn::Error::new_spanned(other, "expected `name = \"...\"`, `index = N` or `skip`")),
+            };
+            if let Some(ref previous) = column {
+                let message = match (previous, &found) {
+                    (Column::Skip, Column::Skip) | (Column::Name(..), Column::Name(..)) | (Column::Index(..), Column::Index(..)) =>
+                        "column attribute is given more than once",
+                    _ =>
+                        "`name`, `index` and `skip` are mutually exclusive",
+                };
+                return Err(syn::Error::new_spanned(nested, message));
+            }
+            column = Some(found);
+        }
+    }
+    Ok(column)
+}
+
+// `cql!("SELECT ... WHERE id = ?", (Uuid,))`; the parameter tuple may be
+// omitted for queries without bind markers.
+struct CqlInput {
+    query: LitStr,
+    params: Option<TypeTuple>,
+}
+
+impl Parse for CqlInput {
+    fn parse(input: ParseStream) -> syn::Result<CqlInput> {
+        let query = input.parse()?;
+        let mut params = None;
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            if !input.is_empty() {
+                params = Some(input.parse()?);
+                if input.peek(Token![,]) {
+                    input.parse::<Token![,]>()?;
+                }
+            }
+        }
+        Ok(CqlInput { query, params, })
+    }
+}
+
+// Expands to a closure building a statement on a `SharedSession` and binding
+// the parameter tuple to it.
+fn cql_statement(input: &CqlInput) -> syn::Result<TokenStream2> {
+    let query = input.query.value();
+    let markers = count_bind_markers(&query)
+        .map_err(|message| syn::Error::new_spanned(&input.query, message))?;
+    let params = match input.params {
+        Some(ref params) =>
+            quote! { #params },
+        None =>
+            quote! { () },
+    };
+    let param_count = input.params.as_ref().map_or(0, |params| params.elems.len());
+    if markers != param_count {
+        let message = format!("query has {} bind markers but {} parameter types are given", markers, param_count);
+        return Err(match input.params {
+            Some(ref params) => syn::Error::new_spanned(params, message),
+            None => syn::Error::new_spanned(&input.query, message),
+        });
+    }
+    let query = &input.query;
+
+    Ok(quote! {
+        |session: &::ero_cassandra::SharedSession, params: &#params| {
+            let mut statement = session.statement(#query, #markers);
+            let bound = ::ero_cassandra::BindParams::bind_params(&mut statement, params)
+                .map(|_statement| ());
+            bound.map(move |()| statement)
+        }
+    })
+}
+
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "SELECT", "INSERT", "UPDATE", "DELETE", "BEGIN", "APPLY", "USE", "TRUNCATE",
+    "CREATE", "ALTER", "DROP", "GRANT", "REVOKE", "LIST",
+];
+
+const LITERAL_KEYWORDS: &[&str] = &["TRUE", "FALSE", "NULL", "NAN", "INFINITY"];
+
+fn named_marker_error(name: &str) -> String {
+    format!("named bind marker `:{}` is not supported, use `?` markers or bind by name with `ToNamedParams`", name)
+}
+
+// A lexical check only: quotes, comments and brackets must be balanced, the
+// statement must start with a CQL keyword and a semicolon may only be
+// followed by whitespace and comments.
+// Returns the number of positional markers. Named markers are rejected:
+// `cql!` binds its parameter tuple by position.
+fn count_bind_markers(query: &str) -> Result<usize, String> {
+    let keyword: String = query.trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    if !STATEMENT_KEYWORDS.iter().any(|known| known.eq_ignore_ascii_case(&keyword)) {
+        return Err(format!("expected a CQL statement, found `{}`", query.split_whitespace().next().unwrap_or("")));
+    }
+
+    let mut markers = 0;
+    let mut brackets = Vec::new();
+    let mut terminated = false;
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        let comment = matches!((c, chars.peek()), ('-', Some('-')) | ('/', Some('/')) | ('/', Some('*')));
+        if terminated && !c.is_whitespace() && !comment {
+            return Err("unexpected input after `;`, only a single statement is allowed".to_string());
+        }
+        match c {
+            '\'' | '"' =>
+                loop {
+                    match chars.next() {
+                        None =>
+                            return Err(format!("unterminated {}", if c == '"' { "quoted identifier" } else { "string literal" })),
+                        Some(d) if d == c && chars.peek() == Some(&c) => {
+                            chars.next();
+                        },
+                        Some(d) if d == c =>
+                            break,
+                        Some(_) =>
+                            (),
+                    }
+                },
+            '$' if chars.peek() == Some(&'$') => {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        None =>
+                            return Err("unterminated $$ string literal".to_string()),
+                        Some('$') if chars.peek() == Some(&'$') => {
+                            chars.next();
+                            break;
+                        },
+                        Some(_) =>
+                            (),
+                    }
+                }
+            },
+            '-' | '/' if chars.peek() == Some(&c) => {
+                for d in chars.by_ref() {
+                    if d == '\n' {
+                        break;
+                    }
+                }
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut closed = false;
+                while let Some(d) = chars.next() {
+                    if d == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        closed = true;
+                        break;
+                    }
+                }
+                if !closed {
+                    return Err("unterminated /* comment".to_string());
+                }
+            },
+            '(' | '[' | '{' =>
+                brackets.push(c),
+            ')' | ']' | '}' => {
+                let open = match c { ')' => '(', ']' => '[', _ => '{' };
+                if brackets.pop() != Some(open) {
+                    return Err(format!("unbalanced `{}`", c));
+                }
+            },
+            ';' =>
+                terminated = true,
+            '?' =>
+                markers += 1,
+            ':' if chars.peek() == Some(&'"') =>
+                return Err(named_marker_error("\"...\"")),
+            ':' if matches!(chars.peek(), Some(d) if d.is_ascii_alphabetic() || *d == '_') => {
+                let mut name = String::new();
+                while let Some(&d) = chars.peek() {
+                    if !(d.is_ascii_alphanumeric() || d == '_') {
+                        break;
+                    }
+                    name.push(d);
+                    chars.next();
+                }
+                // A literal or function call in a UDT or map literal, e.g.
+                // `{flag:true}`.
+                let is_term = chars.peek() == Some(&'(') ||
+                    LITERAL_KEYWORDS.iter().any(|literal| literal.eq_ignore_ascii_case(&name));
+                if !is_term {
+                    return Err(named_marker_error(&name));
+                }
+            },
+            _ =>
+                (),
+        }
+    }
+    if let Some(open) = brackets.pop() {
+        return Err(format!("unclosed `{}`", open));
+    }
+    Ok(markers)
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn field_column(input: DeriveInput) -> syn::Result<Option<Column>> {
+        match input.data {
+            Data::Struct(ref data) =>
+                column_attr(&data.fields.iter().next().unwrap().attrs),
+            _ =>
+                unreachable!(),
+        }
+    }
+
+    #[test]
+    fn column_attr_single() {
+        let column = field_column(parse_quote! { struct S { #[cassandra(name = "x")] a: i32 } }).unwrap();
+        assert!(matches!(column, Some(Column::Name(ref name)) if name == "x"));
+        let column = field_column(parse_quote! { struct S { #[cassandra(index = 3)] a: i32 } }).unwrap();
+        assert!(matches!(column, Some(Column::Index(3))));
+        let column = field_column(parse_quote! { struct S { #[cassandra(skip)] a: i32 } }).unwrap();
+        assert!(matches!(column, Some(Column::Skip)));
+        let column = field_column(parse_quote! { struct S { #[serde(skip)] a: i32 } }).unwrap();
+        assert!(column.is_none());
+    }
+
+    #[test]
+    fn column_attr_conflicts() {
+        assert!(field_column(parse_quote! { struct S { #[cassandra(skip, name = "x")] a: i32 } }).is_err());
+        assert!(field_column(parse_quote! { struct S { #[cassandra(name = "x")] #[cassandra(index = 1)] a: i32 } }).is_err());
+        assert!(field_column(parse_quote! { struct S { #[cassandra(name = "x", name = "y")] a: i32 } }).is_err());
+        assert!(field_column(parse_quote! { struct S { #[cassandra(rename = "x")] a: i32 } }).is_err());
+    }
+
+    #[test]
+    fn markers_are_counted() {
+        assert_eq!(count_bind_markers("SELECT * FROM t"), Ok(0));
+        assert_eq!(count_bind_markers("SELECT * FROM t WHERE a = ? AND b = ?"), Ok(2));
+        assert_eq!(count_bind_markers("  select * from t where a in (?, ?)"), Ok(2));
+        assert_eq!(count_bind_markers("UPDATE t SET l = [?, ?], m = {?: ?} WHERE k = ?"), Ok(5));
+    }
+
+    #[test]
+    fn quoted_markers_are_skipped() {
+        assert_eq!(count_bind_markers("SELECT * FROM t WHERE a = '?' AND \"b?\" = ?"), Ok(1));
+        assert_eq!(count_bind_markers("INSERT INTO t (s, v) VALUES ('it''s ?', ?)"), Ok(1));
+        assert_eq!(count_bind_markers("INSERT INTO t (s, v) VALUES ($$ it's ? $$, ?)"), Ok(1));
+    }
+
+    #[test]
+    fn commented_markers_are_skipped() {
+        assert_eq!(count_bind_markers("SELECT * FROM t -- a = ?\nWHERE b = ? /* c = ? */ // d = ?"), Ok(1));
+    }
+
+    #[test]
+    fn trailing_semicolon_is_allowed() {
+        assert_eq!(count_bind_markers("SELECT * FROM t WHERE a = ?; \n"), Ok(1));
+        assert_eq!(count_bind_markers("SELECT * FROM t WHERE a = ?; -- done"), Ok(1));
+        assert!(count_bind_markers("SELECT * FROM t; SELECT * FROM u").is_err());
+    }
+
+    #[test]
+    fn malformed_queries_are_rejected() {
+        assert!(count_bind_markers("FETCH * FROM t").is_err());
+        assert!(count_bind_markers("SELECT * FROM t WHERE a IN (?, ?").is_err());
+        assert!(count_bind_markers("SELECT * FROM t WHERE a IN (?, ?]").is_err());
+        assert!(count_bind_markers("SELECT * FROM t WHERE a = ')").is_err());
+        assert!(count_bind_markers("INSERT INTO t (s) VALUES ($$ ?)").is_err());
+        assert!(count_bind_markers("SELECT * FROM t /* ?").is_err());
+    }
+
+    #[test]
+    fn named_markers_are_rejected() {
+        assert_eq!(
+            count_bind_markers("SELECT * FROM t WHERE a = :a AND b = ?"),
+            Err(named_marker_error("a")),
+        );
+        assert!(count_bind_markers("SELECT * FROM t WHERE a = :\"A\"").is_err());
+        assert_eq!(count_bind_markers("UPDATE t SET u = {flag:true, at:now()}, m = {'a':1, 'b': ?} WHERE k = ?"), Ok(2));
+        assert_eq!(count_bind_markers("SELECT * FROM t WHERE a = ':a'"), Ok(0));
+        let error = cql_statement(&parse_quote!("SELECT * FROM t WHERE id = :id")).unwrap_err();
+        assert!(error.to_string().contains("ToNamedParams"));
+    }
+
+    #[test]
+    fn marker_count_must_match_params() {
+        assert!(cql_statement(&parse_quote!("SELECT * FROM t WHERE a = ? AND b = ?", (i32, String))).is_ok());
+        assert!(cql_statement(&parse_quote!("SELECT * FROM t")).is_ok());
+        let error = cql_statement(&parse_quote!("SELECT * FROM t WHERE a = ? AND b = ?", (i32,))).unwrap_err();
+        assert_eq!(error.to_string(), "query has 2 bind markers but 1 parameter types are given");
+        assert!(cql_statement(&parse_quote!("SELECT * FROM t WHERE a = ?")).is_err());
+    }
+}
@@ -10,7 +10,6 @@ use futures::{
     Stream,
     Future,
     future::{
-        lazy,
         result,
         Either,
     },
@@ -19,17 +18,19 @@ use futures::{
 
 use cassandra_cpp::{
     stmt,
-    Value,
     Consistency,
 };
 
 use log::{info, error};
 
+use ero_cassandra::{
+    FromRow,
+    UsingSession,
+};
+
 use ero::{
-    Loop,
     ErrorSeverity,
     RestartStrategy,
-    lode::UsingResource,
     supervisor::Supervisor,
 };
 
@@ -43,8 +44,9 @@ fn main() {
     info!("running {} with contact_points = {}, keyspace = {} and query = {}", program, contact_points, keyspace, query);
 
     let cluster_params = ero_cassandra::ClusterParams {
-        contact_points,
-        keyspace,
+        contact_points: ero_cassandra::ContactPoint::list(&contact_points).unwrap(),
+        keyspace: Some(keyspace),
+        default_consistency: Some(Consistency::ONE),
         ..Default::default()
     };
 
@@ -62,6 +64,7 @@ fn main() {
                     restart_after: Duration::from_secs(2),
                 },
             },
+            configure: None,
         },
     );
 
@@ -84,84 +87,39 @@ fn main() {
         let notify_tx = tx.clone();
         let client_future = resource
             .clone()
-            .using_resource_loop(
+            .using_session(
                 (0, query.clone()),
                 move |session, (counter, query)| {
-                    let future = lazy(move || {
-                        info!("performing query: {}, this is {} time for task {}", query, counter, task_index);
-                        let mut stmt = stmt!(&query);
-                        match stmt.set_consistency(Consistency::ONE) {
-                            Ok(..) =>
-                                Ok((stmt, query, counter)),
-                            Err(error) => {
-                                error!("error set_consistency: {:?}", error);
-                                Err(ErrorSeverity::Fatal(()))
-                            }
-                        }
-                    });
-                    let future = future
-                        .and_then(move |(stmt, query, counter)| {
-                            session.execute(&stmt)
-                                .then(move |result| {
-                                    match result {
-                                        Ok(cass_result) =>
-                                            Ok((cass_result, query, counter)),
-                                        Err(error) => {
-                                            error!("error executing statement: {:?}", error);
-                                            Err(ErrorSeverity::Fatal(()))
-                                        },
-                                    }
-                                })
-                        })
-                        .and_then(|(cass_result, query, counter)| {
+                    if counter >= 3 {
+                        return Either::B(result(Ok(())));
+                    }
+                    info!("performing query: {}, this is {} time for task {}", query, counter, task_index);
+                    let stmt = stmt!(&query);
+                    let future = session.execute_guarded(stmt)
+                        .then(move |execute_result| -> Result<(), ErrorSeverity<_, ()>> {
+                            let cass_result = execute_result.map_err(|error| {
+                                error!("error executing statement: {:?}", error);
+                                ErrorSeverity::Fatal(())
+                            })?;
                             match cass_result.first_row() {
-                                None => {
-                                    info!("empty response on query: {}", query);
-                                    Ok(Loop::Continue((counter + 1, query)))
-                                },
+                                None =>
+                                    info!("empty response on query: {}", query),
                                 Some(ref row) =>
-                                    match row.get_column(0) {
-                                        Ok(ref value) =>
-                                            if value.is_null() {
-                                                info!("null column for first row");
-                                                Ok(Loop::Continue((counter + 1, query)))
-                                            } else {
-                                                match Value::get_string(value) {
-                                                    Ok(data) => {
-                                                        info!("column = {} for first row", data);
-                                                        Ok(Loop::Continue((counter + 1, query)))
-                                                    },
-                                                    Err(error) => {
-                                                        error!("error Value::get_string for row: {:?}", error);
-                                                        Err(ErrorSeverity::Fatal(()))
-                                                    },
-                                                }
-                                            },
+                                    match <(Option<String>,)>::from_row(row) {
+                                        Ok((None,)) =>
+                                            info!("null column for first row"),
+                                        Ok((Some(data),)) =>
+                                            info!("column = {} for first row", data),
                                         Err(error) => {
-                                            error!("error get_column(0) for row: {:?}", error);
-                                            Err(ErrorSeverity::Fatal(()))
+                                            error!("error reading first row: {}", error);
+                                            return Err(ErrorSeverity::Fatal(()));
                                         },
                                     },
                             }
+                            info!("everything ok, triggering restart...");
+                            Err(ErrorSeverity::Recoverable { state: (counter + 1, query), })
                         });
-                    let future = future
-                        .then(|query_result| {
-                            match query_result {
-                                Ok(Loop::Continue(state)) => {
-                                    info!("everything ok, triggering restart...");
-                                    Err(ErrorSeverity::Recoverable { state, })
-                                },
-                                Ok(Loop::Break(value)) =>
-                                    Ok((UsingResource::Lost, Loop::Break(value))),
-                                Err(error) =>
-                                    Err(error),
-                            }
-                        });
-                    if counter < 3 {
-                        Either::A(future)
-                    } else {
-                        Either::B(result(Ok((UsingResource::Lost, Loop::Break(())))))
-                    }
+                    Either::A(future)
                 },
             )
             .then(move |_result| {
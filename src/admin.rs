@@ -0,0 +1,105 @@
+use std::{
+    time::Duration,
+    fmt::Write as FmtWrite,
+};
+
+use super::{
+    SharedSession,
+    export::write_json_string,
+    usage::StatementReport,
+};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AdminResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+#[derive(Clone)]
+pub struct AdminConsole {
+    session: SharedSession,
+}
+
+impl AdminConsole {
+    pub fn new(session: SharedSession) -> AdminConsole {
+        AdminConsole { session, }
+    }
+
+    // Plug into any HTTP server: pass the request method and the path with
+    // the mount prefix already stripped.
+    pub fn handle(&self, method: &str, path: &str) -> AdminResponse {
+        match (method, path.trim_end_matches('/')) {
+            ("GET", "/status") =>
+                json(200, self.status()),
+            ("GET", "/metrics") =>
+                json(200, self.metrics()),
+            ("GET", "/prepared") =>
+                json(200, self.prepared()),
+            ("POST", "/reconnect") => {
+                self.session.request_reconnect();
+                json(202, "{\"reconnect_pending\":true}".to_string())
+            },
+            (_, "/status") | (_, "/metrics") | (_, "/prepared") | (_, "/reconnect") =>
+                json(405, "{\"error\":\"method not allowed\"}".to_string()),
+            _ =>
+                json(404, "{\"error\":\"not found\"}".to_string()),
+        }
+    }
+
+    fn status(&self) -> String {
+        let detached = self.session.detached_stats();
+        format!(
+            "{{\"reconnect_pending\":{},\"max_frame_size\":{},\"prepared_statements\":{},\
+             \"detached\":{{\"in_flight\":{},\"limit\":{},\"completed\":{},\"failed\":{},\"rejected\":{}}}}}",
+            self.session.reconnect_pending(),
+            self.session.max_frame_size(),
+            self.session.prepared.len(),
+            detached.in_flight,
+            detached.limit,
+            detached.completed,
+            detached.failed,
+            detached.rejected,
+        )
+    }
+
+    fn metrics(&self) -> String {
+        statements_json(self.session.usage_report().statements.iter())
+    }
+
+    fn prepared(&self) -> String {
+        let report = self.session.usage_report();
+        statements_json(report.statements.iter().filter(|statement| self.session.prepared.contains_key(&statement.key)))
+    }
+}
+
+fn json(status: u16, body: String) -> AdminResponse {
+    AdminResponse { status, content_type: "application/json", body, }
+}
+
+fn statements_json<'a, I>(statements: I) -> String where I: Iterator<Item = &'a StatementReport> {
+    let mut body = String::from("{\"statements\":[");
+    for (index, statement) in statements.enumerate() {
+        if index > 0 {
+            body.push(',');
+        }
+        body.push_str("{\"key\":");
+        write_json_string(&statement.key, &mut body);
+        let _ = write!(
+            body,
+            ",\"invocations\":{},\"errors\":{},\"error_rate\":{},\"latency_p50_ms\":{},\"latency_p90_ms\":{},\"latency_p99_ms\":{}}}",
+            statement.invocations,
+            statement.errors,
+            statement.error_rate,
+            millis(statement.latency_p50),
+            millis(statement.latency_p90),
+            millis(statement.latency_p99),
+        );
+    }
+    body.push_str("]}");
+    body
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0
+}
@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+use cassandra_cpp::Cluster;
+
+use log::debug;
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+// A secure connect bundle replaces contact points and TLS settings; the
+// client id and secret are the bundle's username and password.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(deny_unknown_fields))]
+pub struct AstraBundle {
+    pub path: PathBuf,
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+pub(crate) fn configure<'a>(cluster: &'a mut Cluster, bundle: &AstraBundle) -> Result<&'a mut Cluster, cassandra_cpp::Error> {
+    debug!("using secure connect bundle {:?}", bundle.path);
+    cluster.set_cloud_secure_connection_bundle(&bundle.path.to_string_lossy())?;
+    cluster.set_credentials(&bundle.client_id, &bundle.client_secret)
+}
@@ -0,0 +1,271 @@
+use std::{
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::SystemTime,
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        loop_fn,
+        Loop,
+        Either,
+    },
+};
+
+use cassandra_cpp::{
+    ErrorKind,
+    CassResult,
+    Statement,
+    Consistency,
+    CassErrorCode,
+};
+
+use log::warn;
+
+use super::{
+    SharedSession,
+    RetryBudget,
+    retry::IdempotentStatement,
+    audit_sink::AuditedStatement,
+};
+
+const RECENT_EVENTS: usize = 256;
+
+pub type DowngradeHook = Arc<dyn Fn(&DowngradeEvent) + Send + Sync>;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct DowngradeEvent {
+    pub statement: String,
+    pub original: Consistency,
+    pub achieved: Consistency,
+    pub reason: String,
+    pub at: SystemTime,
+}
+
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct DowngradeReport {
+    pub total: u64,
+    pub by_statement: Vec<(String, u64)>,
+    pub recent: Vec<DowngradeEvent>,
+}
+
+pub struct Audit {
+    inner: Mutex<AuditInner>,
+}
+
+#[derive(Default)]
+struct AuditInner {
+    total: u64,
+    by_statement: HashMap<String, u64>,
+    recent: VecDeque<DowngradeEvent>,
+}
+
+impl Audit {
+    pub fn new() -> Audit {
+        Audit {
+            inner: Mutex::new(AuditInner::default()),
+        }
+    }
+
+    pub fn record(&self, event: DowngradeEvent) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.total += 1;
+        *inner.by_statement.entry(event.statement.clone()).or_insert(0) += 1;
+        if inner.recent.len() >= RECENT_EVENTS {
+            inner.recent.pop_front();
+        }
+        inner.recent.push_back(event);
+    }
+
+    pub fn report(&self) -> DowngradeReport {
+        let inner = self.inner.lock().unwrap();
+        let mut by_statement: Vec<_> = inner.by_statement.iter()
+            .map(|(statement, count)| (statement.clone(), *count))
+            .collect();
+        by_statement.sort();
+        DowngradeReport {
+            total: inner.total,
+            by_statement,
+            recent: inner.recent.iter().cloned().collect(),
+        }
+    }
+}
+
+// Levels to try in order, strongest first.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ConsistencyLadder {
+    pub levels: Vec<Consistency>,
+}
+
+impl ConsistencyLadder {
+    pub fn new(levels: Vec<Consistency>) -> ConsistencyLadder {
+        ConsistencyLadder { levels, }
+    }
+
+    // The usual steps down from `level`, staying within the local data
+    // center for the LOCAL_* levels.
+    pub fn descending_from(level: Consistency) -> ConsistencyLadder {
+        let levels = match level {
+            Consistency::ALL =>
+                vec![Consistency::ALL, Consistency::QUORUM, Consistency::ONE],
+            Consistency::QUORUM =>
+                vec![Consistency::QUORUM, Consistency::ONE],
+            Consistency::THREE =>
+                vec![Consistency::THREE, Consistency::TWO, Consistency::ONE],
+            Consistency::TWO =>
+                vec![Consistency::TWO, Consistency::ONE],
+            Consistency::EACH_QUORUM =>
+                vec![Consistency::EACH_QUORUM, Consistency::LOCAL_QUORUM, Consistency::LOCAL_ONE],
+            Consistency::LOCAL_QUORUM =>
+                vec![Consistency::LOCAL_QUORUM, Consistency::LOCAL_ONE],
+            other =>
+                vec![other],
+        };
+        ConsistencyLadder { levels, }
+    }
+}
+
+// Failures that move a fallback one step down the ladder.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum DowngradeOn {
+    Unavailable,
+    // Unavailable replicas and read timeouts.
+    ReadFailures,
+    // Write timeouts and client side timeouts too, only for idempotent
+    // statements since the write may have been applied.
+    AnyTimeout,
+}
+
+fn downgrade_reason(error: &cassandra_cpp::Error, downgrade_on: DowngradeOn) -> Option<&'static str> {
+    match (error.kind(), downgrade_on) {
+        (ErrorKind::CassErrorResult(CassErrorCode::SERVER_UNAVAILABLE, ..), _) =>
+            Some("unavailable"),
+        (ErrorKind::CassErrorResult(CassErrorCode::SERVER_READ_TIMEOUT, ..), DowngradeOn::ReadFailures) |
+        (ErrorKind::CassErrorResult(CassErrorCode::SERVER_READ_TIMEOUT, ..), DowngradeOn::AnyTimeout) =>
+            Some("read timeout"),
+        (ErrorKind::CassErrorResult(CassErrorCode::SERVER_WRITE_TIMEOUT, ..), DowngradeOn::AnyTimeout) =>
+            Some("write timeout"),
+        (ErrorKind::CassError(CassErrorCode::LIB_REQUEST_TIMED_OUT, ..), DowngradeOn::AnyTimeout) =>
+            Some("request timeout"),
+        _ =>
+            None,
+    }
+}
+
+impl SharedSession {
+    pub fn record_downgrade(&self, statement: &str, original: Consistency, achieved: Consistency, reason: &str) {
+        let event = DowngradeEvent {
+            statement: statement.to_string(),
+            original,
+            achieved,
+            reason: reason.to_string(),
+            at: SystemTime::now(),
+        };
+        warn!("consistency downgraded for {:?}: {:?} -> {:?} ({})", statement, original, achieved, reason);
+        if let Some(ref hook) = self.settings.on_consistency_downgrade {
+            hook(&event);
+        }
+        self.shared.audit.record(event);
+    }
+
+    pub fn downgrade_report(&self) -> DowngradeReport {
+        self.shared.audit.report()
+    }
+
+    // Tries `levels` in order, moving to the next one only when the
+    // coordinator reports unavailable replicas or a read timeout. Write
+    // timeouts are not retried since the write may have been applied.
+    pub fn execute_with_fallback(
+        &self,
+        statement_tag: &str,
+        statement: Statement,
+        levels: Vec<Consistency>,
+    )
+        -> impl Future<Item = CassResult, Error = cassandra_cpp::Error>
+    {
+        self.fallback_loop(statement_tag, statement, levels, DowngradeOn::ReadFailures)
+            .map(|(cass_result, _level)| cass_result)
+    }
+
+    // Also steps down on write and request timeouts.
+    pub fn execute_idempotent_with_fallback(
+        &self,
+        statement_tag: &str,
+        statement: IdempotentStatement,
+        ladder: ConsistencyLadder,
+    )
+        -> impl Future<Item = CassResult, Error = cassandra_cpp::Error>
+    {
+        self.fallback_loop(statement_tag, statement.into_inner(), ladder.levels, DowngradeOn::AnyTimeout)
+            .map(|(cass_result, _level)| cass_result)
+    }
+
+    // Resolves with the result and the level it was read or written at.
+    // Every step down takes a retry from `ClusterParams::retry_budget`.
+    pub(crate) fn fallback_loop(
+        &self,
+        statement_tag: &str,
+        statement: Statement,
+        levels: Vec<Consistency>,
+        downgrade_on: DowngradeOn,
+    )
+        -> impl Future<Item = (CassResult, Consistency), Error = cassandra_cpp::Error>
+    {
+        let session = self.clone();
+        let statement_tag = statement_tag.to_string();
+        let statement = Arc::new(Mutex::new(statement));
+        loop_fn((statement, 0, ""), move |(statement, index, last_reason)| {
+            let level = match levels.get(index) {
+                Some(&level) =>
+                    level,
+                None =>
+                    return Either::A(result(Err("no consistency levels given".into()))),
+            };
+            if index == 0 {
+                if let Some(ref budget) = session.settings.retry_budget {
+                    budget.record_request();
+                }
+            }
+            if let Err(error) = statement.lock().unwrap().set_consistency(level) {
+                return Either::A(result(Err(error)));
+            }
+            let session = session.clone();
+            let statement_tag = statement_tag.clone();
+            let original = levels[0];
+            let next_level = levels.get(index + 1).cloned();
+            let level_statement = statement.clone();
+            let audited = AuditedStatement::Key(statement_tag.clone());
+            let future = session.send_audited(audited, Some(level), move |session| session.execute(&level_statement.lock().unwrap()))
+                .then(move |execute_result| match execute_result {
+                    Ok(cass_result) => {
+                        if index > 0 {
+                            session.record_downgrade(&statement_tag, original, level, last_reason);
+                        }
+                        Ok(Loop::Break((cass_result, level)))
+                    },
+                    Err(error) =>
+                        match (downgrade_reason(&error, downgrade_on), next_level) {
+                            (Some(reason), Some(next_level)) => {
+                                if !session.settings.retry_budget.as_ref().map_or(true, RetryBudget::try_retry) {
+                                    warn!("{:?} at {:?} failed with {}, retry budget exhausted", statement_tag, level, reason);
+                                    return Err(error);
+                                }
+                                warn!("{:?} at {:?} failed with {}, retrying at {:?}", statement_tag, level, reason, next_level);
+                                Ok(Loop::Continue((statement, index + 1, reason)))
+                            },
+                            _ =>
+                                Err(error),
+                        },
+                });
+            Either::B(future)
+        })
+    }
+}
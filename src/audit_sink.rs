@@ -0,0 +1,68 @@
+use std::{
+    sync::Arc,
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use cassandra_cpp::{
+    CassResult,
+    Consistency,
+};
+
+use super::SharedSession;
+
+pub type AuditSink = Arc<dyn Fn(&AuditRecord) + Send + Sync>;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AuditedStatement {
+    // A registered or tracked statement key.
+    Key(String),
+    // The statement fingerprint, see `fingerprint`.
+    Query(String),
+    // Neither is known to the helper, e.g. plain `execute`.
+    Unlabeled,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AuditOutcome {
+    Success { rows: usize, },
+    Failure { error: String, },
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct AuditRecord {
+    pub statement: AuditedStatement,
+    // The level the statement was executed with when the helper sets one,
+    // otherwise `default_consistency` (`None` when that is unset too).
+    pub consistency: Option<Consistency>,
+    pub latency: Duration,
+    pub outcome: AuditOutcome,
+}
+
+impl SharedSession {
+    pub(crate) fn audit(
+        &self,
+        statement: AuditedStatement,
+        consistency: Option<Consistency>,
+        started_at: Instant,
+        execute_result: &Result<CassResult, cassandra_cpp::Error>,
+    )
+    {
+        if let Some(ref audit_sink) = self.settings.audit_sink {
+            let record = AuditRecord {
+                statement,
+                consistency: consistency.or(self.settings.default_consistency),
+                latency: started_at.elapsed(),
+                outcome: match execute_result {
+                    Ok(cass_result) =>
+                        AuditOutcome::Success { rows: cass_result.row_count() as usize, },
+                    Err(error) =>
+                        AuditOutcome::Failure { error: format!("{:?}", error), },
+                },
+            };
+            audit_sink(&record);
+        }
+    }
+}
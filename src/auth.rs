@@ -0,0 +1,47 @@
+use std::{
+    error,
+    sync::Arc,
+};
+
+use futures::{
+    Future,
+    IntoFuture,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use log::debug;
+
+use super::{
+    ClusterParams,
+    Credentials,
+};
+
+pub type AuthError = Box<dyn error::Error + Send + Sync>;
+
+pub type AuthFuture = Box<dyn Future<Item = Credentials, Error = AuthError> + Send + 'static>;
+
+// Called on every connect, so rotated secrets are picked up when the lode
+// restarts. Takes precedence over static `credentials`.
+pub type AuthProvider = Arc<dyn Fn() -> AuthFuture + Send + Sync>;
+
+pub fn auth_provider<F, R>(provider: F) -> AuthProvider
+where F: Fn() -> R + Send + Sync + 'static,
+      R: IntoFuture<Item = Credentials, Error = AuthError>,
+      R::Future: Send + 'static,
+{
+    Arc::new(move || Box::new(provider().into_future()))
+}
+
+pub(crate) fn credentials(params: &ClusterParams) -> impl Future<Item = Option<Credentials>, Error = AuthError> {
+    match params.auth_provider {
+        Some(ref auth_provider) => {
+            debug!("requesting credentials from auth provider");
+            Either::A(auth_provider().map(Some))
+        },
+        None =>
+            Either::B(result(Ok(params.credentials.clone()))),
+    }
+}
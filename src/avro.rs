@@ -0,0 +1,254 @@
+use std::{
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+    hash::{
+        Hasher,
+        BuildHasher,
+    },
+    collections::hash_map::RandomState,
+};
+
+use cassandra_cpp::ValueType;
+
+use super::{
+    value::CqlValue,
+    export::{
+        RecordFormat,
+        write_json,
+        write_json_string,
+    },
+    result_columns::ResultColumn,
+};
+
+const MAGIC: &[u8] = b"Obj\x01";
+
+// Writes an uncompressed Avro object container file with one block per
+// page. Every field is a union of `null` and the column type; collections,
+// user types and tuples are written as strings holding their JSON form,
+// since the driver does not report their element types.
+#[derive(Clone, Debug)]
+pub struct Avro {
+    pub record_name: String,
+    // Separates the blocks of the file; random unless given.
+    pub sync_marker: [u8; 16],
+}
+
+impl Default for Avro {
+    fn default() -> Avro {
+        Avro { record_name: "Row".to_string(), sync_marker: random_sync_marker(), }
+    }
+}
+
+impl RecordFormat for Avro {
+    fn write_header(&self, columns: &[ResultColumn], output: &mut Vec<u8>) {
+        output.extend_from_slice(MAGIC);
+        // A map block of two entries, then the empty block ending the map.
+        write_long(2, output);
+        write_bytes(b"avro.schema", output);
+        write_bytes(schema(&self.record_name, columns).as_bytes(), output);
+        write_bytes(b"avro.codec", output);
+        write_bytes(b"null", output);
+        write_long(0, output);
+        output.extend_from_slice(&self.sync_marker);
+    }
+
+    fn write_record(&self, _columns: &[ResultColumn], record: &[CqlValue], output: &mut Vec<u8>) {
+        for value in record {
+            write_value(value, output);
+        }
+    }
+
+    fn write_page(&self, columns: &[ResultColumn], records: &[Vec<CqlValue>], output: &mut Vec<u8>) {
+        if records.is_empty() {
+            return;
+        }
+        let mut block = Vec::new();
+        for record in records {
+            self.write_record(columns, record, &mut block);
+        }
+        write_long(records.len() as i64, output);
+        write_long(block.len() as i64, output);
+        output.extend_from_slice(&block);
+        output.extend_from_slice(&self.sync_marker);
+    }
+}
+
+fn random_sync_marker() -> [u8; 16] {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_nanos())
+        .unwrap_or(0);
+    let mut sync_marker = [0; 16];
+    for (index, half) in sync_marker.chunks_mut(8).enumerate() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(since_epoch);
+        hasher.write_usize(index);
+        half.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+    sync_marker
+}
+
+fn schema(record_name: &str, columns: &[ResultColumn]) -> String {
+    let mut schema = String::from("{\"type\":\"record\",\"name\":");
+    write_json_string(&avro_name(record_name), &mut schema);
+    schema.push_str(",\"fields\":[");
+    for (index, column) in columns.iter().enumerate() {
+        if index > 0 {
+            schema.push(',');
+        }
+        schema.push_str("{\"name\":");
+        write_json_string(&avro_name(&column.name), &mut schema);
+        schema.push_str(",\"type\":[\"null\",");
+        schema.push_str(avro_type(&column.value_type));
+        schema.push_str("],\"default\":null}");
+    }
+    schema.push_str("]}");
+    schema
+}
+
+fn avro_type(value_type: &ValueType) -> &'static str {
+    match value_type {
+        ValueType::BOOLEAN =>
+            "\"boolean\"",
+        ValueType::TINY_INT | ValueType::SMALL_INT | ValueType::INT =>
+            "\"int\"",
+        ValueType::BIGINT | ValueType::COUNTER =>
+            "\"long\"",
+        ValueType::TIMESTAMP =>
+            "{\"type\":\"long\",\"logicalType\":\"timestamp-millis\"}",
+        ValueType::FLOAT =>
+            "\"float\"",
+        ValueType::DOUBLE =>
+            "\"double\"",
+        ValueType::BLOB =>
+            "\"bytes\"",
+        ValueType::UUID | ValueType::TIMEUUID =>
+            "{\"type\":\"string\",\"logicalType\":\"uuid\"}",
+        _ =>
+            "\"string\"",
+    }
+}
+
+// Avro names are `[A-Za-z_][A-Za-z0-9_]*`: anything else becomes `_`.
+fn avro_name(name: &str) -> String {
+    let mut avro_name: String = name.chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() || ch == '_' { ch } else { '_' })
+        .collect();
+    if !avro_name.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '_') {
+        avro_name.insert(0, '_');
+    }
+    avro_name
+}
+
+// The union branch first: 0 for `null`, 1 for the column type.
+fn write_value(value: &CqlValue, output: &mut Vec<u8>) {
+    if let CqlValue::Null = value {
+        write_long(0, output);
+        return;
+    }
+    write_long(1, output);
+    match value {
+        CqlValue::Null =>
+            (),
+        CqlValue::Text(text) =>
+            write_bytes(text.as_bytes(), output),
+        CqlValue::Bool(flag) =>
+            output.push(*flag as u8),
+        CqlValue::TinyInt(number) =>
+            write_long(i64::from(*number), output),
+        CqlValue::SmallInt(number) =>
+            write_long(i64::from(*number), output),
+        CqlValue::Int(number) =>
+            write_long(i64::from(*number), output),
+        CqlValue::BigInt(number) =>
+            write_long(*number, output),
+        CqlValue::Float(number) =>
+            output.extend_from_slice(&number.to_le_bytes()),
+        CqlValue::Double(number) =>
+            output.extend_from_slice(&number.to_le_bytes()),
+        CqlValue::Blob(bytes) =>
+            write_bytes(bytes, output),
+        CqlValue::Uuid(uuid) =>
+            write_bytes(uuid.to_string().as_bytes(), output),
+        CqlValue::List(..) | CqlValue::Set(..) | CqlValue::Map(..) | CqlValue::Udt(..) => {
+            let mut json = String::new();
+            write_json(value, &mut json);
+            write_bytes(json.as_bytes(), output);
+        },
+    }
+}
+
+// Zigzag encoded variable length integer; `int` is written the same way.
+fn write_long(number: i64, output: &mut Vec<u8>) {
+    let mut zigzag = ((number << 1) ^ (number >> 63)) as u64;
+    while zigzag >= 0x80 {
+        output.push(zigzag as u8 | 0x80);
+        zigzag >>= 7;
+    }
+    output.push(zigzag as u8);
+}
+
+fn write_bytes(bytes: &[u8], output: &mut Vec<u8>) {
+    write_long(bytes.len() as i64, output);
+    output.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_cpp::ValueType;
+
+    use super::*;
+
+    fn long(number: i64) -> Vec<u8> {
+        let mut output = Vec::new();
+        write_long(number, &mut output);
+        output
+    }
+
+    fn column(name: &str, value_type: ValueType) -> ResultColumn {
+        ResultColumn { position: 0, name: name.to_string(), value_type, }
+    }
+
+    #[test]
+    fn longs_are_zigzag_varints() {
+        assert_eq!(long(0), [0x00]);
+        assert_eq!(long(-1), [0x01]);
+        assert_eq!(long(1), [0x02]);
+        assert_eq!(long(-64), [0x7f]);
+        assert_eq!(long(64), [0x80, 0x01]);
+        assert_eq!(long(i64::MIN), [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+    }
+
+    #[test]
+    fn schema_has_nullable_fields() {
+        let columns = [column("id", ValueType::UUID), column("2nd value", ValueType::INT)];
+        assert_eq!(
+            schema("Row", &columns),
+            "{\"type\":\"record\",\"name\":\"Row\",\"fields\":[\
+             {\"name\":\"id\",\"type\":[\"null\",{\"type\":\"string\",\"logicalType\":\"uuid\"}],\"default\":null},\
+             {\"name\":\"_2nd_value\",\"type\":[\"null\",\"int\"],\"default\":null}]}",
+        );
+    }
+
+    #[test]
+    fn pages_are_framed_as_blocks() {
+        let avro = Avro { record_name: "Row".to_string(), sync_marker: [7; 16], };
+        let columns = [column("k", ValueType::INT), column("v", ValueType::TEXT)];
+        let mut output = Vec::new();
+        avro.write_header(&columns, &mut output);
+        assert!(output.starts_with(MAGIC));
+        assert!(output.ends_with(&[7; 16]));
+
+        let mut output = Vec::new();
+        avro.write_page(&columns, &[vec![CqlValue::Int(-2), CqlValue::Text("ab".to_string())], vec![CqlValue::Int(1), CqlValue::Null]], &mut output);
+        let mut expected = vec![0x04, 0x12, 0x02, 0x03, 0x02, 0x04, b'a', b'b', 0x02, 0x02, 0x00];
+        expected.extend_from_slice(&[7; 16]);
+        assert_eq!(output, expected);
+
+        let mut output = Vec::new();
+        avro.write_page(&columns, &[], &mut output);
+        assert!(output.is_empty());
+    }
+}
@@ -0,0 +1,380 @@
+use std::{
+    fmt,
+    ops::Deref,
+    sync::Arc,
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        loop_fn,
+        Loop,
+        Either,
+    },
+};
+
+#[cfg(any(feature = "backend-scylla", feature = "backend-cdrs-tokio"))]
+use futures::sync::oneshot;
+
+use tokio::timer::{
+    Delay,
+    Timeout,
+};
+
+use log::{
+    info,
+    debug,
+    warn,
+    error,
+};
+
+use ero::{
+    ErrorSeverity,
+    lode::{self, LodeResource},
+    supervisor::Supervisor,
+};
+
+use super::{
+    spawner,
+    DRAIN_POLL_INTERVAL,
+    backoff::BackoffCurve,
+    retry::full_jitter,
+};
+
+pub type BackendFuture<T, E> = Box<dyn Future<Item = T, Error = E> + Send + 'static>;
+
+// A driver a lode can be spawned over instead of cassandra_cpp, see
+// `spawn_backend_link`. `spawn_link` stays the cassandra_cpp lode: the
+// execute helpers, prepared statements, metrics and the rest of
+// `SharedSession` are built on that driver and are not available here.
+pub trait Backend: Send + Sync + 'static {
+    type Session: Send + Sync + 'static;
+    type Error: fmt::Debug + Send + 'static;
+
+    fn connect(&self) -> BackendFuture<Self::Session, Self::Error>;
+
+    // Runs a cheap query to prove the session still reaches the cluster.
+    fn validate(&self, session: Arc<Self::Session>) -> BackendFuture<(), Self::Error>;
+
+    fn close(&self, session: Self::Session) -> BackendFuture<(), Self::Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct BackendParams {
+    // Delay before an init attempt that follows a failed one.
+    pub init_backoff: Option<BackoffCurve>,
+    pub validate_on_acquire: bool,
+    pub validation_interval: Duration,
+    pub drain_timeout: Duration,
+}
+
+impl Default for BackendParams {
+    fn default() -> BackendParams {
+        BackendParams {
+            init_backoff: None,
+            validate_on_acquire: false,
+            validation_interval: Duration::from_secs(5),
+            drain_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+pub struct BackendSession<B> where B: Backend {
+    session: Arc<B::Session>,
+}
+
+impl<B> Clone for BackendSession<B> where B: Backend {
+    fn clone(&self) -> Self {
+        BackendSession { session: self.session.clone(), }
+    }
+}
+
+impl<B> Deref for BackendSession<B> where B: Backend {
+    type Target = B::Session;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+// The lode semantics of `spawn_link` over `backend`: one session shared by
+// every resource request, reconnected when validation fails and drained for
+// up to `drain_timeout` on close.
+pub fn spawn_backend_link<B, N>(
+    supervisor: &Supervisor,
+    backend: B,
+    params: BackendParams,
+    lode_params: ero::Params<N>,
+)
+    -> LodeResource<BackendSession<B>>
+where B: Backend,
+      N: AsRef<str> + Send + 'static,
+{
+    let state = State {
+        backend: Arc::new(backend),
+        params,
+        failed_attempts: 0,
+    };
+    lode::shared::spawn_link(
+        supervisor,
+        lode_params,
+        state,
+        init::<B>,
+        aquire::<B>,
+        release::<B>,
+        close::<B>,
+    )
+}
+
+struct State<B> {
+    backend: Arc<B>,
+    params: BackendParams,
+    failed_attempts: usize,
+}
+
+struct Connected<B> where B: Backend {
+    session: BackendSession<B>,
+    last_validated: Option<Instant>,
+    state: State<B>,
+}
+
+fn init<B>(
+    state: State<B>,
+)
+    -> impl Future<Item = Connected<B>, Error = ErrorSeverity<State<B>, ()>>
+where B: Backend,
+{
+    if spawner::executor_shutting_down() {
+        info!("executor is shutting down, not connecting");
+        return Either::B(result(Err(ErrorSeverity::Fatal(()))));
+    }
+    let backoff = match state.params.init_backoff {
+        Some(curve) if state.failed_attempts > 0 =>
+            full_jitter(curve.base_delay, curve.max_delay, state.failed_attempts - 1),
+        _ =>
+            Duration::from_secs(0),
+    };
+    let backoff = if backoff > Duration::from_secs(0) {
+        debug!("backing off for {:?} before init attempt {}", backoff, state.failed_attempts + 1);
+        Either::A(Delay::new(Instant::now() + backoff).then(|_timer_result| -> Result<(), ()> { Ok(()) }))
+    } else {
+        Either::B(result(Ok(())))
+    };
+    let future = backoff
+        .then(move |_backoff_result| state.backend.connect().then(move |connect_result| match connect_result {
+            Ok(session) => {
+                info!("backend session connected");
+                let session = BackendSession { session: Arc::new(session), };
+                Ok(Connected { session, last_validated: None, state: State { failed_attempts: 0, ..state }, })
+            },
+            Err(error) => {
+                error!("backend connect failed: {:?}", error);
+                Err(ErrorSeverity::Recoverable { state: State { failed_attempts: state.failed_attempts + 1, ..state }, })
+            },
+        }));
+    Either::A(future)
+}
+
+fn aquire<B>(
+    mut connected: Connected<B>,
+)
+    -> impl Future<Item = (BackendSession<B>, Connected<B>), Error = ErrorSeverity<State<B>, ()>>
+where B: Backend,
+{
+    if spawner::executor_shutting_down() {
+        info!("executor is shutting down, refusing new sessions");
+        let future = close(connected)
+            .then(|_close_result| Err(ErrorSeverity::Fatal(())));
+        return Either::B(Either::A(future));
+    }
+    let params = &connected.state.params;
+    let validated_recently = matches!(
+        connected.last_validated,
+        Some(last_validated) if last_validated.elapsed() < params.validation_interval
+    );
+    let validation_due = params.validate_on_acquire && !validated_recently;
+    if !validation_due {
+        return Either::A(result(Ok((connected.session.clone(), connected))));
+    }
+    let future = connected.state.backend.validate(connected.session.session.clone())
+        .then(move |probe_result| match probe_result {
+            Ok(()) => {
+                connected.last_validated = Some(Instant::now());
+                Ok((connected.session.clone(), connected))
+            },
+            Err(error) => {
+                warn!("backend session failed validation on aquire, reconnecting: {:?}", error);
+                Err(ErrorSeverity::Recoverable { state: connected.state, })
+            },
+        });
+    Either::B(Either::B(future))
+}
+
+fn release<B>(
+    connected: Connected<B>,
+    _maybe_session: Option<BackendSession<B>>,
+)
+    -> impl Future<Item = Connected<B>, Error = ErrorSeverity<State<B>, ()>>
+where B: Backend,
+{
+    result(Ok(connected))
+}
+
+// Waits for handed out clones of the session to be dropped, then closes it.
+fn close<B>(
+    connected: Connected<B>,
+)
+    -> impl Future<Item = State<B>, Error = ()>
+where B: Backend,
+{
+    let Connected { session, state, .. } = connected;
+    let drain_timeout = state.params.drain_timeout;
+    debug!("closing backend session, draining for up to {:?}", drain_timeout);
+    let backend = state.backend.clone();
+    let drained = loop_fn(session.session, move |session| {
+        match Arc::try_unwrap(session) {
+            Ok(session) => {
+                let future = backend.close(session)
+                    .then(|close_result| -> Result<_, ()> {
+                        if let Err(error) = close_result {
+                            warn!("error closing backend session: {:?}", error);
+                        }
+                        Ok(Loop::Break(()))
+                    });
+                Either::A(future)
+            },
+            Err(session) => {
+                let future = Delay::new(Instant::now() + DRAIN_POLL_INTERVAL)
+                    .then(move |_timer_result| Ok(Loop::Continue(session)));
+                Either::B(future)
+            },
+        }
+    });
+    Timeout::new(drained, drain_timeout)
+        .then(move |close_result| {
+            if close_result.is_err() {
+                warn!("drain timeout of {:?} elapsed, dropping backend session still in use", drain_timeout);
+            }
+            Ok(State { failed_attempts: 0, ..state })
+        })
+}
+
+// The driver's tokio 1.x runtime is gone, so its future never completed.
+#[cfg(any(feature = "backend-scylla", feature = "backend-cdrs-tokio"))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RuntimeGone;
+
+// scylla and cdrs-tokio need a tokio 1.x runtime while lodes run on tokio
+// 0.1: their futures are spawned on `runtime` and the result is sent back
+// over a channel.
+#[cfg(any(feature = "backend-scylla", feature = "backend-cdrs-tokio"))]
+pub(crate) fn on_runtime<F, T, E>(runtime: &tokio1::runtime::Handle, future: F) -> BackendFuture<T, E>
+where F: std::future::Future<Output = Result<T, E>> + Send + 'static,
+      T: Send + 'static,
+      E: From<RuntimeGone> + Send + 'static,
+{
+    let (result_tx, result_rx) = oneshot::channel();
+    runtime.spawn(async move {
+        let _send_result = result_tx.send(future.await);
+    });
+    Box::new(result_rx.then(|recv_result| match recv_result {
+        Ok(future_result) =>
+            future_result,
+        Err(oneshot::Canceled) =>
+            Err(E::from(RuntimeGone)),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{
+            Ordering,
+            AtomicUsize,
+        },
+    };
+
+    use futures::{
+        Future,
+        future::result,
+    };
+
+    use tokio::runtime::Runtime;
+
+    use ero::ErrorSeverity;
+
+    use super::{
+        init,
+        aquire,
+        close,
+        State,
+        Backend,
+        BackendFuture,
+        BackendParams,
+    };
+
+    // Fails the first connect and, while `healthy` is zero, every validation.
+    struct Flaky {
+        connects: AtomicUsize,
+        healthy: AtomicUsize,
+        closed: AtomicUsize,
+    }
+
+    impl Backend for Flaky {
+        type Session = usize;
+        type Error = &'static str;
+
+        fn connect(&self) -> BackendFuture<usize, &'static str> {
+            let attempt = self.connects.fetch_add(1, Ordering::SeqCst);
+            Box::new(result(if attempt == 0 { Err("unreachable") } else { Ok(attempt) }))
+        }
+
+        fn validate(&self, _session: Arc<usize>) -> BackendFuture<(), &'static str> {
+            Box::new(result(if self.healthy.load(Ordering::SeqCst) > 0 { Ok(()) } else { Err("timed out") }))
+        }
+
+        fn close(&self, _session: usize) -> BackendFuture<(), &'static str> {
+            self.closed.fetch_add(1, Ordering::SeqCst);
+            Box::new(result(Ok(())))
+        }
+    }
+
+    #[test]
+    fn reconnects_after_failures() {
+        let backend = Arc::new(Flaky { connects: AtomicUsize::new(0), healthy: AtomicUsize::new(0), closed: AtomicUsize::new(0), });
+        let params = BackendParams { validate_on_acquire: true, ..BackendParams::default() };
+        let state = State { backend: backend.clone(), params, failed_attempts: 0, };
+        let state = match init(state).wait() {
+            Err(ErrorSeverity::Recoverable { state, }) =>
+                state,
+            _ =>
+                panic!("first connect should fail"),
+        };
+        assert_eq!(state.failed_attempts, 1);
+        let connected = init(state).wait().ok().unwrap();
+        assert_eq!(*connected.session, 1);
+        assert_eq!(connected.state.failed_attempts, 0);
+        let state = match aquire(connected).wait() {
+            Err(ErrorSeverity::Recoverable { state, }) =>
+                state,
+            _ =>
+                panic!("validation should fail"),
+        };
+        backend.healthy.store(1, Ordering::SeqCst);
+        let connected = init(state).wait().ok().unwrap();
+        let (session, connected) = aquire(connected).wait().ok().unwrap();
+        assert_eq!(*session, 2);
+        assert!(connected.last_validated.is_some());
+
+        let mut runtime = Runtime::new().unwrap();
+        drop(session);
+        runtime.block_on(close(connected)).unwrap();
+        assert_eq!(backend.closed.load(Ordering::SeqCst), 1);
+    }
+}
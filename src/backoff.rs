@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use super::{
+    error::InitFailure,
+    retry::{
+        full_jitter,
+        RetryOn,
+    },
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BackoffCurve {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+// Delay before an init attempt that follows a failed one, on top of the
+// lode's `RestartStrategy`. Overloaded clusters back off along their own,
+// slower curve; every other failure uses `unreachable`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct InitBackoff {
+    pub unreachable: BackoffCurve,
+    pub overloaded: BackoffCurve,
+}
+
+impl Default for InitBackoff {
+    fn default() -> InitBackoff {
+        InitBackoff {
+            unreachable: BackoffCurve {
+                base_delay: Duration::from_millis(500),
+                max_delay: Duration::from_secs(30),
+            },
+            overloaded: BackoffCurve {
+                base_delay: Duration::from_secs(2),
+                max_delay: Duration::from_secs(60),
+            },
+        }
+    }
+}
+
+impl InitBackoff {
+    pub fn delay(&self, last_failure: &InitFailure) -> Duration {
+        let overloaded = last_failure.error.driver_error()
+            .and_then(RetryOn::of)
+            .map_or(false, |retry_on| retry_on == RetryOn::Overloaded);
+        let curve = if overloaded { &self.overloaded } else { &self.unreachable };
+        full_jitter(curve.base_delay, curve.max_delay, last_failure.attempt - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cassandra_cpp::{
+        ErrorKind,
+        CassErrorCode,
+    };
+
+    use super::{
+        BackoffCurve,
+        InitBackoff,
+        super::error::{
+            Error,
+            InitFailure,
+        },
+    };
+
+    fn backoff() -> InitBackoff {
+        InitBackoff {
+            unreachable: BackoffCurve { base_delay: Duration::from_millis(10), max_delay: Duration::from_millis(40), },
+            overloaded: BackoffCurve { base_delay: Duration::from_secs(10), max_delay: Duration::from_secs(60), },
+        }
+    }
+
+    fn overloaded(attempt: usize) -> InitFailure {
+        let error = ErrorKind::CassError(CassErrorCode::SERVER_OVERLOADED, String::new()).into();
+        InitFailure { attempt, error: Error::Verify(error), }
+    }
+
+    fn unreachable(attempt: usize) -> InitFailure {
+        InitFailure { attempt, error: Error::MissingKeyspace { keyspace: "ks".to_string(), }, }
+    }
+
+    #[test]
+    fn first_retry_is_bounded_by_base_delay() {
+        for _ in 0 .. 100 {
+            assert!(backoff().delay(&unreachable(1)) <= Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn later_retries_are_capped() {
+        for attempt in 1 .. 40 {
+            assert!(backoff().delay(&unreachable(attempt)) <= Duration::from_millis(40));
+        }
+    }
+
+    #[test]
+    fn overloaded_uses_its_own_curve() {
+        let delays: Vec<_> = (0 .. 50).map(|_| backoff().delay(&overloaded(3))).collect();
+        assert!(delays.iter().all(|&delay| delay <= Duration::from_secs(40)));
+        assert!(delays.iter().any(|&delay| delay > Duration::from_millis(40)));
+    }
+}
@@ -0,0 +1,110 @@
+use std::mem;
+
+use futures::{
+    Future,
+    future::{
+        result,
+        join_all,
+        Either,
+    },
+};
+
+use cassandra_cpp::{
+    Batch,
+    BatchType,
+    CassResult,
+    Statement,
+};
+
+use log::debug;
+
+use super::{
+    SharedSession,
+    frame::{
+        self,
+        FrameTooLarge,
+        SizedStatement,
+    },
+};
+
+// A statement to batch and, for one built with `build_sized`, its name and
+// estimated size. The size of a plain `Statement` is unknown and only
+// `max_batch_size` applies to it.
+pub struct BatchStatement {
+    statement: Statement,
+    sized: Option<(String, usize)>,
+}
+
+impl From<Statement> for BatchStatement {
+    fn from(statement: Statement) -> BatchStatement {
+        BatchStatement { statement, sized: None, }
+    }
+}
+
+impl From<SizedStatement> for BatchStatement {
+    fn from(sized: SizedStatement) -> BatchStatement {
+        let name = sized.name().to_string();
+        let estimated_size = sized.estimated_size();
+        BatchStatement { statement: sized.into_inner(), sized: Some((name, estimated_size)), }
+    }
+}
+
+impl SharedSession {
+    // Every chunk is a separate batch: atomicity of LOGGED batches holds per
+    // chunk only. A chunk ends after `max_batch_size` statements or before
+    // the known sizes would exceed `max_frame_size`; a statement that does
+    // not fit a batch on its own fails the call with `FrameTooLarge`.
+    pub fn execute_batch<S>(
+        &self,
+        kind: BatchType,
+        statements: Vec<S>,
+        max_batch_size: usize,
+    )
+        -> impl Future<Item = Vec<CassResult>, Error = cassandra_cpp::Error>
+    where S: Into<BatchStatement>,
+    {
+        let max_batch_size = max_batch_size.max(1);
+        let max_frame_size = self.settings.max_frame_size;
+        let mut batches = Vec::new();
+        let mut batch = Batch::new(kind);
+        let mut batch_len = 0;
+        let mut batch_size = frame::BATCH_OVERHEAD;
+        for statement in statements {
+            let BatchStatement { statement, sized, } = statement.into();
+            let statement_size = match sized {
+                Some((name, estimated_size)) => {
+                    if frame::BATCH_OVERHEAD + estimated_size > max_frame_size {
+                        let frame_too_large = FrameTooLarge {
+                            statement: name,
+                            estimated_size: frame::BATCH_OVERHEAD + estimated_size,
+                            max_frame_size,
+                        };
+                        return Either::B(result(Err(frame_too_large.to_string().into())));
+                    }
+                    estimated_size
+                },
+                None =>
+                    0,
+            };
+            if batch_len == max_batch_size || (batch_len > 0 && batch_size + statement_size > max_frame_size) {
+                batches.push(mem::replace(&mut batch, Batch::new(kind)));
+                batch_len = 0;
+                batch_size = frame::BATCH_OVERHEAD;
+            }
+            if let Err(error) = batch.add_statement(&statement) {
+                return Either::B(result(Err(error)));
+            }
+            batch_len += 1;
+            batch_size += statement_size;
+        }
+        if batch_len > 0 {
+            batches.push(batch);
+        }
+
+        debug!("executing {} {:?} batch(es) of up to {} statements", batches.len(), kind, max_batch_size);
+        let futures: Vec<_> = batches.into_iter()
+            .map(|batch| self.send(move |session| session.execute_batch(batch)))
+            .collect();
+        Either::A(join_all(futures))
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{
+            Ordering,
+            AtomicUsize,
+        },
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+    collections::HashMap,
+};
+
+use futures::{
+    stream,
+    Sink,
+    Future,
+    Stream,
+    sync::mpsc,
+    future::{
+        result,
+        loop_fn,
+        join_all,
+        Loop,
+        Either,
+    },
+};
+
+use tokio::timer::Interval;
+
+use cassandra_cpp::BatchType;
+
+use ero::{
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+    supervisor::Supervisor,
+};
+
+use log::{
+    debug,
+    warn,
+    error,
+};
+
+use super::{
+    SharedSession,
+    CqlValue,
+    BatchStatement,
+    token::partition_key_bytes,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct BatchWriterParams {
+    pub max_batch_size: usize,
+    pub flush_interval: Duration,
+    pub batch_type: BatchType,
+    pub channel_capacity: usize,
+}
+
+impl Default for BatchWriterParams {
+    fn default() -> BatchWriterParams {
+        BatchWriterParams {
+            max_batch_size: 100,
+            flush_interval: Duration::from_millis(100),
+            batch_type: BatchType::UNLOGGED,
+            channel_capacity: 1024,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct BatchWriterStats {
+    pub batches: usize,
+    pub statements: usize,
+    pub failed_batches: usize,
+    pub failed_statements: usize,
+}
+
+#[derive(Debug)]
+pub enum BatchWriterError {
+    Closed,
+    InvalidPartitionKey(String),
+}
+
+impl fmt::Display for BatchWriterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchWriterError::Closed =>
+                write!(f, "batch writer has terminated"),
+            BatchWriterError::InvalidPartitionKey(reason) =>
+                write!(f, "invalid partition key: {}", reason),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    batches: AtomicUsize,
+    statements: AtomicUsize,
+    failed_batches: AtomicUsize,
+    failed_statements: AtomicUsize,
+}
+
+// Accumulates statements and writes them as batches once `max_batch_size`
+// statements are pending or `flush_interval` elapses. The session is taken
+// from the lode for every flush. Failed batches are logged and counted, not
+// retried: the statements are consumed by the batch.
+//
+// Statements written with `write_partitioned` are grouped by table and
+// partition key on flush, and every partition goes out as a batch of its
+// own; statements written with `write` are batched together as before.
+#[derive(Clone)]
+pub struct BatchWriter {
+    statements_tx: mpsc::Sender<Write>,
+    counters: Arc<Counters>,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct Partition {
+    table: String,
+    key: Vec<u8>,
+}
+
+struct Write {
+    partition: Option<Partition>,
+    statement: BatchStatement,
+}
+
+enum Event {
+    Write(Write),
+    Tick,
+    Closed,
+}
+
+impl BatchWriter {
+    pub fn spawn_link(
+        supervisor: &Supervisor,
+        resource: LodeResource<SharedSession>,
+        params: BatchWriterParams,
+    )
+        -> BatchWriter
+    {
+        let (statements_tx, statements_rx) = mpsc::channel(params.channel_capacity);
+        let counters = Arc::new(Counters::default());
+        supervisor.spawn_link(run(resource, statements_rx, params, counters.clone()));
+        BatchWriter { statements_tx, counters, }
+    }
+
+    // Resolves when the statement is queued, not when it is written; the
+    // bounded queue makes writers wait while a flush is in progress. Pass a
+    // `SizedStatement` to have flushes split to fit `max_frame_size`.
+    pub fn write<S>(self, statement: S) -> impl Future<Item = BatchWriter, Error = BatchWriterError> where S: Into<BatchStatement> {
+        self.send(Write { partition: None, statement: statement.into(), })
+    }
+
+    // `partition_key` holds the values of the table's partition key columns
+    // in order, typed as the columns are (see `partition_key_bytes`).
+    pub fn write_partitioned<S>(
+        self,
+        table: &str,
+        partition_key: &[CqlValue],
+        statement: S,
+    )
+        -> impl Future<Item = BatchWriter, Error = BatchWriterError>
+    where S: Into<BatchStatement>,
+    {
+        match partition_key_bytes(partition_key) {
+            Ok(key) => {
+                let partition = Partition { table: table.to_string(), key, };
+                Either::A(self.send(Write { partition: Some(partition), statement: statement.into(), }))
+            },
+            Err(reason) =>
+                Either::B(result(Err(BatchWriterError::InvalidPartitionKey(reason)))),
+        }
+    }
+
+    fn send(self, write: Write) -> impl Future<Item = BatchWriter, Error = BatchWriterError> {
+        let BatchWriter { statements_tx, counters, } = self;
+        statements_tx.send(write)
+            .map(move |statements_tx| BatchWriter { statements_tx, counters, })
+            .map_err(|_send_error| BatchWriterError::Closed)
+    }
+
+    pub fn stats(&self) -> BatchWriterStats {
+        BatchWriterStats {
+            batches: self.counters.batches.load(Ordering::Relaxed),
+            statements: self.counters.statements.load(Ordering::Relaxed),
+            failed_batches: self.counters.failed_batches.load(Ordering::Relaxed),
+            failed_statements: self.counters.failed_statements.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Runs until every `BatchWriter` handle is dropped, flushing what is left.
+fn run(
+    resource: LodeResource<SharedSession>,
+    statements_rx: mpsc::Receiver<Write>,
+    params: BatchWriterParams,
+    counters: Arc<Counters>,
+)
+    -> impl Future<Item = (), Error = ()>
+{
+    let statements = statements_rx
+        .map(Event::Write)
+        .chain(stream::once(Ok(Event::Closed)));
+    let ticks = Interval::new(Instant::now() + params.flush_interval, params.flush_interval)
+        .map(|_instant| Event::Tick)
+        .map_err(|timer_error| error!("batch writer timer error: {:?}", timer_error));
+    let events: Box<dyn Stream<Item = Event, Error = ()> + Send> = Box::new(statements.select(ticks));
+
+    loop_fn((events, Vec::new()), move |(events, mut pending)| {
+        let resource = resource.clone();
+        let counters = counters.clone();
+        events.into_future()
+            .map_err(|((), _events)| ())
+            .and_then(move |(maybe_event, events)| {
+                let (batch, next) = match maybe_event {
+                    Some(Event::Write(write)) => {
+                        pending.push(write);
+                        if pending.len() >= params.max_batch_size {
+                            (pending, Loop::Continue((events, Vec::new())))
+                        } else {
+                            (Vec::new(), Loop::Continue((events, pending)))
+                        }
+                    },
+                    Some(Event::Tick) =>
+                        (pending, Loop::Continue((events, Vec::new()))),
+                    Some(Event::Closed) | None =>
+                        (pending, Loop::Break(())),
+                };
+                if batch.is_empty() {
+                    Either::A(result(Ok(next)))
+                } else {
+                    Either::B(flush(resource, group(batch), params, counters).map(move |()| next))
+                }
+            })
+    })
+}
+
+// Unpartitioned statements form one group, every partition another, in the
+// order each was first written to.
+fn group(writes: Vec<Write>) -> Vec<Vec<BatchStatement>> {
+    let mut groups: Vec<Vec<BatchStatement>> = Vec::new();
+    let mut positions = HashMap::new();
+    for Write { partition, statement, } in writes {
+        let position = *positions.entry(partition)
+            .or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+        groups[position].push(statement);
+    }
+    groups
+}
+
+fn flush(
+    resource: LodeResource<SharedSession>,
+    groups: Vec<Vec<BatchStatement>>,
+    params: BatchWriterParams,
+    counters: Arc<Counters>,
+)
+    -> impl Future<Item = (), Error = ()>
+{
+    debug!("flushing {} statement group(s)", groups.len());
+    resource
+        .using_resource_loop(groups, move |session, groups| {
+            let futures: Vec<_> = groups.into_iter()
+                .map(|statements| {
+                    let count = statements.len();
+                    let counters = counters.clone();
+                    session.execute_batch(params.batch_type, statements, params.max_batch_size)
+                        .then(move |batch_result| -> Result<(), ()> {
+                            match batch_result {
+                                Ok(results) => {
+                                    counters.batches.fetch_add(results.len(), Ordering::Relaxed);
+                                    counters.statements.fetch_add(count, Ordering::Relaxed);
+                                },
+                                Err(error) => {
+                                    warn!("batch of {} statement(s) failed: {:?}", count, error);
+                                    counters.failed_batches.fetch_add(1, Ordering::Relaxed);
+                                    counters.failed_statements.fetch_add(count, Ordering::Relaxed);
+                                },
+                            }
+                            Ok(())
+                        })
+                })
+                .collect();
+            join_all(futures)
+                .then(|_join_result| -> Result<_, ErrorSeverity<Vec<Vec<BatchStatement>>, ()>> {
+                    Ok((UsingResource::Lost, ero::Loop::Break(())))
+                })
+        })
+        .map_err(|_error| error!("batch writer session lode terminated"))
+}
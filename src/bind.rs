@@ -0,0 +1,156 @@
+use std::{
+    hash::Hash,
+    collections::{
+        HashMap,
+        HashSet,
+    },
+};
+
+use cassandra_cpp::{
+    Uuid,
+    Statement,
+};
+
+use super::value::CqlValue;
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Blob(pub Vec<u8>);
+
+pub trait ToCqlValue {
+    fn to_cql_value(&self) -> CqlValue;
+}
+
+pub trait ToParams {
+    fn to_params(&self) -> Vec<CqlValue>;
+}
+
+pub trait BindParams {
+    fn bind_params<P>(&mut self, params: &P) -> Result<&mut Self, cassandra_cpp::Error> where P: ToParams + ?Sized;
+}
+
+impl BindParams for Statement {
+    fn bind_params<P>(&mut self, params: &P) -> Result<&mut Self, cassandra_cpp::Error> where P: ToParams + ?Sized {
+        for (index, value) in params.to_params().into_iter().enumerate() {
+            value.bind(self, index)?;
+        }
+        Ok(self)
+    }
+}
+
+macro_rules! impl_to_cql_value {
+    ($($type:ty => $variant:ident),* $(,)*) => {
+        $(
+            impl ToCqlValue for $type {
+                fn to_cql_value(&self) -> CqlValue {
+                    CqlValue::$variant(self.clone())
+                }
+            }
+        )*
+    };
+}
+
+impl_to_cql_value! {
+    String => Text,
+    bool => Bool,
+    i8 => TinyInt,
+    i16 => SmallInt,
+    i32 => Int,
+    i64 => BigInt,
+    f32 => Float,
+    f64 => Double,
+    Uuid => Uuid,
+}
+
+impl ToCqlValue for str {
+    fn to_cql_value(&self) -> CqlValue {
+        CqlValue::Text(self.to_string())
+    }
+}
+
+impl ToCqlValue for Blob {
+    fn to_cql_value(&self) -> CqlValue {
+        CqlValue::Blob(self.0.clone())
+    }
+}
+
+impl ToCqlValue for CqlValue {
+    fn to_cql_value(&self) -> CqlValue {
+        self.clone()
+    }
+}
+
+impl<'a, T> ToCqlValue for &'a T where T: ToCqlValue + ?Sized {
+    fn to_cql_value(&self) -> CqlValue {
+        (**self).to_cql_value()
+    }
+}
+
+impl<T> ToCqlValue for Option<T> where T: ToCqlValue {
+    fn to_cql_value(&self) -> CqlValue {
+        match self {
+            Some(value) =>
+                value.to_cql_value(),
+            None =>
+                CqlValue::Null,
+        }
+    }
+}
+
+impl<T> ToCqlValue for Vec<T> where T: ToCqlValue {
+    fn to_cql_value(&self) -> CqlValue {
+        CqlValue::List(self.iter().map(ToCqlValue::to_cql_value).collect())
+    }
+}
+
+impl<T> ToCqlValue for HashSet<T> where T: ToCqlValue + Eq + Hash {
+    fn to_cql_value(&self) -> CqlValue {
+        CqlValue::Set(self.iter().map(ToCqlValue::to_cql_value).collect())
+    }
+}
+
+impl<K, V> ToCqlValue for HashMap<K, V> where K: ToCqlValue + Eq + Hash, V: ToCqlValue {
+    fn to_cql_value(&self) -> CqlValue {
+        CqlValue::Map(self.iter().map(|(key, value)| (key.to_cql_value(), value.to_cql_value())).collect())
+    }
+}
+
+impl ToParams for [CqlValue] {
+    fn to_params(&self) -> Vec<CqlValue> {
+        self.to_vec()
+    }
+}
+
+impl ToParams for Vec<CqlValue> {
+    fn to_params(&self) -> Vec<CqlValue> {
+        self.clone()
+    }
+}
+
+impl ToParams for () {
+    fn to_params(&self) -> Vec<CqlValue> {
+        Vec::new()
+    }
+}
+
+macro_rules! impl_to_params_tuple {
+    ($($type:ident : $index:tt),*) => {
+        impl<$($type),*> ToParams for ($($type,)*) where $($type: ToCqlValue),* {
+            fn to_params(&self) -> Vec<CqlValue> {
+                vec![$(self.$index.to_cql_value()),*]
+            }
+        }
+    };
+}
+
+impl_to_params_tuple!(A: 0);
+impl_to_params_tuple!(A: 0, B: 1);
+impl_to_params_tuple!(A: 0, B: 1, C: 2);
+impl_to_params_tuple!(A: 0, B: 1, C: 2, D: 3);
+impl_to_params_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_to_params_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_to_params_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_to_params_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+impl_to_params_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8);
+impl_to_params_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9);
+impl_to_params_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10);
+impl_to_params_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11);
@@ -0,0 +1,222 @@
+use std::{
+    mem,
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use futures::{
+    stream,
+    Async,
+    Poll,
+    Future,
+    Stream,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use cassandra_cpp::Statement;
+
+use super::{
+    SharedSession,
+    value::CqlValue,
+    cql::{
+        quote_table,
+        quote_identifier,
+    },
+};
+
+// A table storing each blob as `chunk_size` pieces, one row per piece,
+// clustered by chunk index within the blob's partition. Keeps single
+// mutations well below the write bytes high water mark.
+#[derive(Clone, Debug)]
+pub struct BlobTable {
+    pub table: String,
+    pub key_column: String,
+    // CQL type of the key column, used by `create_query`.
+    pub key_type: String,
+    pub chunk_column: String,
+    pub data_column: String,
+    pub chunk_size: usize,
+}
+
+impl BlobTable {
+    pub fn new<T>(table: T) -> BlobTable where T: Into<String> {
+        BlobTable {
+            table: table.into(),
+            key_column: "key".to_string(),
+            key_type: "text".to_string(),
+            chunk_column: "chunk".to_string(),
+            data_column: "data".to_string(),
+            chunk_size: 256 * 1024,
+        }
+    }
+
+    pub fn create_query(&self) -> String {
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} ({} {}, {} int, {} blob, PRIMARY KEY (({}), {}))",
+            quote_table(&self.table),
+            quote_identifier(&self.key_column),
+            self.key_type,
+            quote_identifier(&self.chunk_column),
+            quote_identifier(&self.data_column),
+            quote_identifier(&self.key_column),
+            quote_identifier(&self.chunk_column),
+        )
+    }
+
+    fn delete_query(&self) -> String {
+        format!(
+            "DELETE FROM {} USING TIMESTAMP ? WHERE {} = ?",
+            quote_table(&self.table),
+            quote_identifier(&self.key_column),
+        )
+    }
+
+    fn insert_query(&self) -> String {
+        format!(
+            "INSERT INTO {} ({}, {}, {}) VALUES (?, ?, ?) USING TIMESTAMP ?",
+            quote_table(&self.table),
+            quote_identifier(&self.key_column),
+            quote_identifier(&self.chunk_column),
+            quote_identifier(&self.data_column),
+        )
+    }
+
+    fn select_query(&self) -> String {
+        format!(
+            "SELECT {} FROM {} WHERE {} = ?",
+            quote_identifier(&self.data_column),
+            quote_table(&self.table),
+            quote_identifier(&self.key_column),
+        )
+    }
+}
+
+// Regroups a byte stream into `chunk_size` pieces; only the last one may
+// be shorter.
+struct Rechunk<S> {
+    bytes: S,
+    buffer: Vec<u8>,
+    chunk_size: usize,
+    done: bool,
+}
+
+impl<S> Stream for Rechunk<S> where S: Stream<Item = Vec<u8>> {
+    type Item = Vec<u8>;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Vec<u8>>, S::Error> {
+        loop {
+            if self.buffer.len() >= self.chunk_size {
+                let rest = self.buffer.split_off(self.chunk_size);
+                return Ok(Async::Ready(Some(mem::replace(&mut self.buffer, rest))));
+            }
+            if self.done {
+                if self.buffer.is_empty() {
+                    return Ok(Async::Ready(None));
+                }
+                return Ok(Async::Ready(Some(mem::take(&mut self.buffer))));
+            }
+            match self.bytes.poll()? {
+                Async::Ready(Some(bytes)) =>
+                    self.buffer.extend_from_slice(&bytes),
+                Async::Ready(None) =>
+                    self.done = true,
+                Async::NotReady =>
+                    return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+impl SharedSession {
+    // Replaces the blob stored under `key` with the contents of `bytes`,
+    // writing one chunk at a time. Resolves with the number of chunks. The
+    // old chunks are deleted with an earlier write timestamp than the new
+    // ones, so a shorter blob leaves no stale tail.
+    pub fn write_blob<S>(
+        &self,
+        blob_table: &BlobTable,
+        key: CqlValue,
+        bytes: S,
+    )
+        -> impl Future<Item = usize, Error = cassandra_cpp::Error>
+    where S: Stream<Item = Vec<u8>, Error = cassandra_cpp::Error>
+    {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64 * 1_000_000 + elapsed.subsec_micros() as i64)
+            .unwrap_or(0);
+        let mut delete = self.statement(&blob_table.delete_query(), 2);
+        let bound = CqlValue::BigInt(timestamp).bind(&mut delete, 0)
+            .and_then(|()| key.clone().bind(&mut delete, 1));
+        if let Err(error) = bound {
+            return Either::A(result(Err(error)));
+        }
+        let chunks = Rechunk {
+            bytes,
+            buffer: Vec::new(),
+            chunk_size: blob_table.chunk_size.max(1),
+            done: false,
+        };
+        let insert_query = blob_table.insert_query();
+        let shared_session = self.clone();
+        let future = self.execute_guarded(delete)
+            .and_then(move |_cass_result| {
+                chunks.fold(0, move |chunk_index, chunk| {
+                    let mut insert = shared_session.statement(&insert_query, 4);
+                    let bound = key.clone().bind(&mut insert, 0)
+                        .and_then(|()| CqlValue::Int(chunk_index as i32).bind(&mut insert, 1))
+                        .and_then(|()| CqlValue::Blob(chunk).bind(&mut insert, 2))
+                        .and_then(|()| CqlValue::BigInt(timestamp + 1).bind(&mut insert, 3));
+                    match bound {
+                        Ok(()) =>
+                            Either::A(shared_session.execute_guarded(insert).map(move |_cass_result| chunk_index + 1)),
+                        Err(error) =>
+                            Either::B(result(Err(error))),
+                    }
+                })
+            });
+        Either::B(future)
+    }
+
+    // The chunks of the blob stored under `key` in order, fetched
+    // `chunks_per_page` at a time. A missing blob is an empty stream.
+    pub fn read_blob(
+        &self,
+        blob_table: &BlobTable,
+        key: CqlValue,
+        chunks_per_page: i32,
+    )
+        -> impl Stream<Item = Vec<u8>, Error = cassandra_cpp::Error>
+    {
+        let mut select: Statement = self.statement(&blob_table.select_query(), 1);
+        if let Err(error) = key.bind(&mut select, 0) {
+            return Either::A(stream::once(Err(error)));
+        }
+        let chunks = self.execute_paged(select, chunks_per_page.max(1))
+            .map(|page| {
+                let chunks: Result<Vec<_>, cassandra_cpp::Error> = page.iter()
+                    .map(|row| row.get_column(0).and_then(|value| value.get_bytes().map(<[u8]>::to_vec)))
+                    .collect();
+                stream::iter_result(chunks.map_or_else(|error| vec![Err(error)], |chunks| chunks.into_iter().map(Ok).collect()))
+            })
+            .flatten();
+        Either::B(chunks)
+    }
+
+    pub fn read_blob_to_end(
+        &self,
+        blob_table: &BlobTable,
+        key: CqlValue,
+        chunks_per_page: i32,
+    )
+        -> impl Future<Item = Vec<u8>, Error = cassandra_cpp::Error>
+    {
+        self.read_blob(blob_table, key, chunks_per_page)
+            .concat2()
+    }
+}
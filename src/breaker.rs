@@ -0,0 +1,98 @@
+use std::{
+    sync::Mutex,
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use ero::ErrorSeverity;
+
+use log::warn;
+
+use super::error;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CircuitBreakerParams {
+    // Consecutive failures within `window` that open the circuit.
+    pub failure_threshold: usize,
+    pub window: Duration,
+    // How long requests fail fast once the circuit is open.
+    pub cool_down: Duration,
+}
+
+impl Default for CircuitBreakerParams {
+    fn default() -> CircuitBreakerParams {
+        CircuitBreakerParams {
+            failure_threshold: 5,
+            window: Duration::from_secs(10),
+            cool_down: Duration::from_secs(30),
+        }
+    }
+}
+
+// Lives as long as the lode, so the cool-down spans the reconnect it
+// triggers.
+#[derive(Default)]
+pub(crate) struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    failures: usize,
+    first_failure_at: Option<Instant>,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn is_open(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(open_until) if Instant::now() < open_until =>
+                true,
+            Some(..) => {
+                state.open_until = None;
+                false
+            },
+            None =>
+                false,
+        }
+    }
+
+    // Returns true when this outcome opened the circuit. Errors that a
+    // reconnect would not fix, such as invalid queries, do not count.
+    pub(crate) fn record<T>(&self, params: &CircuitBreakerParams, outcome: &Result<T, cassandra_cpp::Error>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let error = match outcome {
+            Ok(..) => {
+                state.failures = 0;
+                state.first_failure_at = None;
+                return false;
+            },
+            Err(error) =>
+                error,
+        };
+        if let ErrorSeverity::Fatal(()) = error::classify(error) {
+            return false;
+        }
+        let now = Instant::now();
+        match state.first_failure_at {
+            Some(first_failure_at) if now.duration_since(first_failure_at) <= params.window =>
+                state.failures += 1,
+            _ => {
+                state.failures = 1;
+                state.first_failure_at = Some(now);
+            },
+        }
+        if state.failures >= params.failure_threshold && state.open_until.is_none() {
+            warn!("{} consecutive failures within {:?}, opening circuit for {:?}", state.failures, params.window, params.cool_down);
+            state.failures = 0;
+            state.first_failure_at = None;
+            state.open_until = Some(now + params.cool_down);
+            true
+        } else {
+            false
+        }
+    }
+}
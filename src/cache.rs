@@ -0,0 +1,144 @@
+use std::{
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+    collections::HashMap,
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use cassandra_cpp::CassResult;
+
+use log::debug;
+
+use super::{
+    SharedSession,
+    ToParams,
+    BindParams,
+};
+
+// Query text and the debug rendering of the bound values: `CqlValue` holds
+// floats, so it has no `Eq` or `Hash` of its own.
+type Key = (String, String);
+
+struct Entry {
+    result: Arc<CassResult>,
+    expires_at: Instant,
+}
+
+// Read-through cache for `SharedSession::execute_cached`, bounded by
+// `ClusterParams::query_cache_size` entries.
+pub(crate) struct QueryCache {
+    max_entries: usize,
+    entries: Mutex<HashMap<Key, Entry>>,
+}
+
+impl QueryCache {
+    pub(crate) fn new(max_entries: usize) -> QueryCache {
+        QueryCache {
+            max_entries: max_entries.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &Key) -> Option<Arc<CassResult>> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            None =>
+                return None,
+            Some(entry) if entry.expires_at > Instant::now() =>
+                return Some(entry.result.clone()),
+            Some(..) =>
+                (),
+        }
+        entries.remove(key);
+        None
+    }
+
+    // Drops expired entries first and then the ones closest to expiring.
+    fn insert(&self, key: Key, result: Arc<CassResult>, ttl: Duration) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+            entries.retain(|_key, entry| entry.expires_at > now);
+            while entries.len() >= self.max_entries {
+                let evicted = entries.iter()
+                    .min_by_key(|(_key, entry)| entry.expires_at)
+                    .map(|(key, _entry)| key.clone());
+                match evicted {
+                    Some(evicted) => { entries.remove(&evicted); },
+                    None => break,
+                }
+            }
+        }
+        entries.insert(key, Entry { result, expires_at: now + ttl, });
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub(crate) fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+impl SharedSession {
+    // Executes `query` with `params` bound, serving the result from the
+    // cache while it is younger than `ttl`. Only single page results are
+    // cached. Without `query_cache_size` every call goes to the cluster.
+    pub fn execute_cached<P>(
+        &self,
+        query: &str,
+        params: &P,
+        ttl: Duration,
+    )
+        -> impl Future<Item = Arc<CassResult>, Error = cassandra_cpp::Error>
+    where P: ToParams + ?Sized
+    {
+        let values = params.to_params();
+        let key = (query.to_string(), format!("{:?}", values));
+        if let Some(ref query_cache) = self.settings.query_cache {
+            if let Some(cached) = query_cache.get(&key) {
+                debug!("query cache hit for {:?}", key.0);
+                return Either::A(result(Ok(cached)));
+            }
+        }
+        let mut statement = self.statement(query, values.len());
+        if let Err(error) = statement.bind_params(&values[..]) {
+            return Either::A(result(Err(error)));
+        }
+        let shared_session = self.clone();
+        Either::B(self.execute_guarded(statement).map(move |cass_result| {
+            let cass_result = Arc::new(cass_result);
+            if let Some(ref query_cache) = shared_session.settings.query_cache {
+                if !cass_result.has_more_pages() {
+                    query_cache.insert(key, cass_result.clone(), ttl);
+                }
+            }
+            cass_result
+        }))
+    }
+
+    pub fn query_cache_len(&self) -> usize {
+        self.settings.query_cache.as_ref()
+            .map_or(0, |query_cache| query_cache.len())
+    }
+
+    pub fn clear_query_cache(&self) {
+        if let Some(ref query_cache) = self.settings.query_cache {
+            query_cache.clear();
+        }
+    }
+}
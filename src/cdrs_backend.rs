@@ -0,0 +1,117 @@
+use std::{
+    fmt,
+    sync::Arc,
+};
+
+use cdrs_tokio::{
+    authenticators::StaticPasswordAuthenticatorProvider,
+    cluster::{
+        NodeTcpConfigBuilder,
+        TcpConnectionManager,
+        session::{
+            Session,
+            SessionBuilder,
+            TcpSessionBuilder,
+        },
+    },
+    load_balancing::RoundRobinLoadBalancingStrategy,
+    transport::TransportTcp,
+};
+
+use super::{
+    Credentials,
+    VALIDATION_QUERY,
+    backend::{
+        on_runtime,
+        Backend,
+        BackendFuture,
+        RuntimeGone,
+    },
+};
+
+pub type CdrsSession = Session<
+    TransportTcp,
+    TcpConnectionManager,
+    RoundRobinLoadBalancingStrategy<TransportTcp, TcpConnectionManager>,
+>;
+
+// cdrs-tokio as a lode backend, for clusters and protocol versions the
+// scylla driver does not cover. `runtime` is the tokio 1.x runtime the
+// driver runs on, kept alive by the caller for as long as the lode.
+#[derive(Clone)]
+pub struct CdrsTokio {
+    // `host:port` pairs.
+    pub contact_points: Vec<String>,
+    pub keyspace: Option<String>,
+    pub credentials: Option<Credentials>,
+    pub runtime: tokio1::runtime::Handle,
+}
+
+#[derive(Debug)]
+pub enum CdrsError {
+    Connect(cdrs_tokio::error::Error),
+    Validate(cdrs_tokio::error::Error),
+    RuntimeGone,
+}
+
+impl fmt::Display for CdrsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CdrsError::Connect(error) =>
+                write!(f, "error connecting cdrs-tokio session: {}", error),
+            CdrsError::Validate(error) =>
+                write!(f, "cdrs-tokio session failed validation: {}", error),
+            CdrsError::RuntimeGone =>
+                write!(f, "cdrs-tokio runtime was shut down"),
+        }
+    }
+}
+
+impl From<RuntimeGone> for CdrsError {
+    fn from(_error: RuntimeGone) -> CdrsError {
+        CdrsError::RuntimeGone
+    }
+}
+
+impl Backend for CdrsTokio {
+    type Session = CdrsSession;
+    type Error = CdrsError;
+
+    fn connect(&self) -> BackendFuture<CdrsSession, CdrsError> {
+        let mut config_builder = NodeTcpConfigBuilder::new();
+        for contact_point in &self.contact_points {
+            config_builder = config_builder.with_contact_point(contact_point.as_str().into());
+        }
+        if let Some(ref credentials) = self.credentials {
+            let authenticator = StaticPasswordAuthenticatorProvider::new(&credentials.username, &credentials.password);
+            config_builder = config_builder.with_authenticator_provider(Arc::new(authenticator));
+        }
+        let keyspace = self.keyspace.clone();
+        on_runtime(&self.runtime, async move {
+            let config = config_builder.build().await
+                .map_err(CdrsError::Connect)?;
+            let mut session_builder = TcpSessionBuilder::new(RoundRobinLoadBalancingStrategy::new(), config);
+            if let Some(keyspace) = keyspace {
+                session_builder = session_builder.with_keyspace(keyspace);
+            }
+            Ok(session_builder.build())
+        })
+    }
+
+    fn validate(&self, session: Arc<CdrsSession>) -> BackendFuture<(), CdrsError> {
+        on_runtime(&self.runtime, async move {
+            session.query(VALIDATION_QUERY).await
+                .map(|_envelope| ())
+                .map_err(CdrsError::Validate)
+        })
+    }
+
+    // The connection pools shut down when the session is dropped, which
+    // happens on the runtime they run on.
+    fn close(&self, session: CdrsSession) -> BackendFuture<(), CdrsError> {
+        on_runtime(&self.runtime, async move {
+            drop(session);
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    sync::Arc,
+    net::IpAddr,
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use cassandra_cpp::{
+    Row,
+    Statement,
+};
+
+use log::warn;
+
+use super::{
+    SharedSession,
+    events::read_address,
+};
+
+const LOCAL_QUERY: &str =
+    "SELECT broadcast_address, data_center, rack, release_version, tokens, partitioner, cluster_name FROM system.local";
+
+const PEERS_QUERY: &str = "SELECT peer, data_center, rack, release_version, tokens FROM system.peers";
+
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct NodeInfo {
+    pub address: Option<IpAddr>,
+    pub data_center: Option<String>,
+    pub rack: Option<String>,
+    pub release_version: Option<String>,
+    pub tokens: usize,
+}
+
+// As seen by the coordinator the session talked to: `local` is that node.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct ClusterInfo {
+    pub cluster_name: Option<String>,
+    pub partitioner: Option<String>,
+    pub local: NodeInfo,
+    pub peers: Vec<NodeInfo>,
+}
+
+impl ClusterInfo {
+    pub fn release_version(&self) -> Option<&str> {
+        self.local.release_version.as_ref().map(String::as_str)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &NodeInfo> {
+        Some(&self.local).into_iter().chain(self.peers.iter())
+    }
+}
+
+fn node_info(row: &Row) -> NodeInfo {
+    let text = |index| row.get_column(index).and_then(|value| value.get_string()).ok();
+    NodeInfo {
+        address: row.get_column(0).ok().and_then(|value| read_address(&value)),
+        data_center: text(1),
+        rack: text(2),
+        release_version: text(3),
+        tokens: row.get_column(4)
+            .and_then(|value| value.get_set().map(Iterator::count))
+            .unwrap_or(0),
+    }
+}
+
+impl SharedSession {
+    // Queried once per connection and cached until the lode reconnects;
+    // `refresh_cluster_info` queries again.
+    pub fn cluster_info(&self) -> impl Future<Item = Arc<ClusterInfo>, Error = cassandra_cpp::Error> {
+        if let Some(cluster_info) = self.settings.cluster_info.lock().unwrap().clone() {
+            return Either::A(result(Ok(cluster_info)));
+        }
+        Either::B(self.refresh_cluster_info())
+    }
+
+    pub fn refresh_cluster_info(&self) -> impl Future<Item = Arc<ClusterInfo>, Error = cassandra_cpp::Error> {
+        let local = self.session.execute(&Statement::new(LOCAL_QUERY, 0));
+        let peers = self.session.execute(&Statement::new(PEERS_QUERY, 0));
+        let settings = self.settings.clone();
+        local.join(peers)
+            .map(move |(local, peers)| {
+                let mut cluster_info = ClusterInfo::default();
+                match local.first_row() {
+                    Some(row) => {
+                        cluster_info.local = node_info(&row);
+                        cluster_info.partitioner = row.get_column(5).and_then(|value| value.get_string()).ok();
+                        cluster_info.cluster_name = row.get_column(6).and_then(|value| value.get_string()).ok();
+                    },
+                    None =>
+                        warn!("system.local returned no rows"),
+                }
+                cluster_info.peers = peers.iter()
+                    .map(|row| node_info(&row))
+                    .collect();
+                let cluster_info = Arc::new(cluster_info);
+                *settings.cluster_info.lock().unwrap() = Some(cluster_info.clone());
+                cluster_info
+            })
+    }
+}
@@ -0,0 +1,459 @@
+use std::{
+    fmt,
+    cmp::Ordering,
+    hash::{
+        Hash,
+        Hasher,
+    },
+    any::{
+        self,
+        Any,
+        TypeId,
+    },
+    sync::{
+        Arc,
+        RwLock,
+    },
+    collections::HashMap,
+};
+
+use cassandra_cpp::{
+    Value,
+    ValueType,
+};
+
+use super::{
+    BindError,
+    bind::ToCqlValue,
+    row::FromValue,
+    value::CqlValue,
+};
+
+type Decode<T> = Box<dyn Fn(&Value) -> Result<T, cassandra_cpp::Error> + Send + Sync>;
+type Encode<T> = Box<dyn Fn(&T) -> CqlValue + Send + Sync>;
+
+struct Entry<T> {
+    column_types: Vec<ValueType>,
+    decode: Decode<T>,
+    encode: Encode<T>,
+}
+
+impl<T> Entry<T> {
+    fn decode(&self, value: &Value) -> Result<T, cassandra_cpp::Error> {
+        let value_type = value.get_type();
+        if !self.column_types.is_empty() && !self.column_types.contains(&value_type) {
+            return Err(format!(
+                "codec for {} does not accept {:?} values",
+                any::type_name::<T>(),
+                value_type,
+            ).into());
+        }
+        (self.decode)(value)
+    }
+}
+
+// Conversions between Cassandra values and Rust types the built-in
+// `FromValue` and `ToCqlValue` impls don't know, typically a type from
+// another crate that neither this crate nor the application can implement
+// those traits for. Once installed, such a type is read and bound through
+// the `Codec` wrapper.
+#[derive(Default)]
+pub struct CodecRegistry {
+    codecs: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+    names: Vec<&'static str>,
+}
+
+impl fmt::Debug for CodecRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CodecRegistry")
+            .field("types", &self.names)
+            .finish()
+    }
+}
+
+impl CodecRegistry {
+    pub fn new() -> CodecRegistry {
+        CodecRegistry::default()
+    }
+
+    // `column_types` are the Cassandra types `decode` accepts, empty for
+    // any. A later registration for the same Rust type replaces the earlier.
+    pub fn register<T, D, E>(&mut self, column_types: &[ValueType], decode: D, encode: E) -> &mut CodecRegistry
+    where T: Send + Sync + 'static,
+          D: Fn(&Value) -> Result<T, cassandra_cpp::Error> + Send + Sync + 'static,
+          E: Fn(&T) -> CqlValue + Send + Sync + 'static,
+    {
+        let entry: Entry<T> = Entry {
+            column_types: column_types.to_vec(),
+            decode: Box::new(decode),
+            encode: Box::new(encode),
+        };
+        if self.codecs.insert(TypeId::of::<T>(), Arc::new(entry)).is_none() {
+            self.names.push(any::type_name::<T>());
+        }
+        self
+    }
+
+    pub fn contains<T>(&self) -> bool where T: 'static {
+        self.codecs.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn decode<T>(&self, value: &Value) -> Result<T, cassandra_cpp::Error> where T: 'static {
+        self.entry::<T>()
+            .ok_or_else(|| not_registered::<T>())?
+            .decode(value)
+    }
+
+    pub fn encode<T>(&self, value: &T) -> Result<CqlValue, cassandra_cpp::Error> where T: 'static {
+        self.entry::<T>()
+            .map(|entry| (entry.encode)(value))
+            .ok_or_else(|| not_registered::<T>())
+    }
+
+    // Wraps `value` for binding, failing unless `T` has a codec here.
+    pub fn wrap<T>(&self, value: T) -> Result<Codec<T>, BindError> where T: 'static {
+        self.entry::<T>()
+            .map(|entry| Codec { value, entry, })
+            .ok_or_else(|| BindError::NoCodec(any::type_name::<T>()))
+    }
+
+    fn entry<T>(&self) -> Option<Arc<Entry<T>>> where T: 'static {
+        self.codecs.get(&TypeId::of::<T>())
+            .cloned()
+            .and_then(|entry| entry.downcast::<Entry<T>>().ok())
+    }
+}
+
+fn not_registered<T>() -> cassandra_cpp::Error {
+    format!("no codec registered for {}", any::type_name::<T>()).into()
+}
+
+static INSTALLED: RwLock<Option<Arc<CodecRegistry>>> = RwLock::new(None);
+
+// Makes `registry` the one `Codec` values go through, replacing any
+// installed before. Meant to be called once at startup, before any lode is
+// spawned.
+pub fn install(registry: CodecRegistry) {
+    *INSTALLED.write().unwrap() = Some(Arc::new(registry));
+}
+
+pub fn installed() -> Option<Arc<CodecRegistry>> {
+    INSTALLED.read().unwrap().clone()
+}
+
+// A value read and bound through the installed registry, so it can be used
+// wherever `FromValue` or `ToCqlValue` is expected: `FromRow` tuples and
+// derives, `ToParams` tuples, `Option<Codec<T>>` for nullable columns. It
+// keeps the codec it was made with, so a `Codec` that exists always binds.
+pub struct Codec<T> {
+    value: T,
+    entry: Arc<Entry<T>>,
+}
+
+impl<T> Codec<T> where T: 'static {
+    // Fails with `BindError::NoCodec` unless the installed registry has a
+    // codec for `T`.
+    pub fn new(value: T) -> Result<Codec<T>, BindError> {
+        match installed() {
+            Some(registry) =>
+                registry.wrap(value),
+            None =>
+                Err(BindError::NoCodec(any::type_name::<T>())),
+        }
+    }
+}
+
+impl<T> Codec<T> {
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Clone for Codec<T> where T: Clone {
+    fn clone(&self) -> Codec<T> {
+        Codec { value: self.value.clone(), entry: self.entry.clone(), }
+    }
+}
+
+impl<T> PartialEq for Codec<T> where T: PartialEq {
+    fn eq(&self, other: &Codec<T>) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T> Eq for Codec<T> where T: Eq {}
+
+impl<T> PartialOrd for Codec<T> where T: PartialOrd {
+    fn partial_cmp(&self, other: &Codec<T>) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<T> Ord for Codec<T> where T: Ord {
+    fn cmp(&self, other: &Codec<T>) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+impl<T> Hash for Codec<T> where T: Hash {
+    fn hash<H>(&self, state: &mut H) where H: Hasher {
+        self.value.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Codec<T> where T: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Codec")
+            .field(&self.value)
+            .finish()
+    }
+}
+
+impl<T> FromValue for Codec<T> where T: 'static {
+    fn from_value(value: &Value) -> Result<Self, cassandra_cpp::Error> {
+        let entry = installed()
+            .and_then(|registry| registry.entry::<T>())
+            .ok_or_else(|| not_registered::<T>())?;
+        entry.decode(value)
+            .map(|value| Codec { value, entry, })
+    }
+}
+
+impl<T> ToCqlValue for Codec<T> {
+    fn to_cql_value(&self) -> CqlValue {
+        (self.entry.encode)(&self.value)
+    }
+}
+
+#[cfg(feature = "timestamp")]
+mod timestamp {
+    use chrono::{
+        Utc,
+        DateTime,
+        TimeZone,
+    };
+
+    use cassandra_cpp::Value;
+
+    use super::super::{
+        bind::ToCqlValue,
+        row::FromValue,
+        value::CqlValue,
+    };
+
+    // Timestamps are milliseconds since the Unix epoch; sub millisecond
+    // precision is dropped on write.
+    impl FromValue for DateTime<Utc> {
+        fn from_value(value: &Value) -> Result<Self, cassandra_cpp::Error> {
+            from_millis(value.get_i64()?)
+        }
+    }
+
+    impl ToCqlValue for DateTime<Utc> {
+        fn to_cql_value(&self) -> CqlValue {
+            CqlValue::BigInt(self.timestamp_millis())
+        }
+    }
+
+    fn from_millis(millis: i64) -> Result<DateTime<Utc>, cassandra_cpp::Error> {
+        Utc.timestamp_opt(millis.div_euclid(1000), (millis.rem_euclid(1000) * 1_000_000) as u32)
+            .single()
+            .ok_or_else(|| format!("timestamp {} is out of range", millis).into())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use chrono::{
+            Utc,
+            DateTime,
+            TimeZone,
+            NaiveDate,
+        };
+
+        use super::{
+            from_millis,
+            super::super::{
+                bind::ToCqlValue,
+                value::CqlValue,
+            },
+        };
+
+        fn utc(date: (i32, u32, u32), hour: u32, minute: u32, second: u32, micro: u32) -> DateTime<Utc> {
+            let naive = NaiveDate::from_ymd_opt(date.0, date.1, date.2)
+                .and_then(|day| day.and_hms_micro_opt(hour, minute, second, micro))
+                .unwrap();
+            Utc.from_utc_datetime(&naive)
+        }
+
+        #[test]
+        fn reads_millis_since_epoch() {
+            assert_eq!(from_millis(0).unwrap(), utc((1970, 1, 1), 0, 0, 0, 0));
+            assert_eq!(from_millis(1_600_000_000_123).unwrap(), utc((2020, 9, 13), 12, 26, 40, 123_000));
+        }
+
+        #[test]
+        fn reads_times_before_epoch() {
+            assert_eq!(from_millis(-1).unwrap(), utc((1969, 12, 31), 23, 59, 59, 999_000));
+            assert_eq!(from_millis(-1000).unwrap(), utc((1969, 12, 31), 23, 59, 59, 0));
+        }
+
+        #[test]
+        fn rejects_out_of_range() {
+            assert!(from_millis(i64::MAX).is_err());
+        }
+
+        #[test]
+        fn writes_millis_dropping_sub_millis() {
+            let timestamp = utc((1969, 12, 31), 23, 59, 59, 999_999);
+            assert_eq!(timestamp.to_cql_value(), CqlValue::BigInt(-1));
+            assert_eq!(from_millis(-1).unwrap(), utc((1969, 12, 31), 23, 59, 59, 999_000));
+        }
+    }
+}
+
+#[cfg(feature = "decimal")]
+mod decimal {
+    use rust_decimal::Decimal;
+
+    use cassandra_cpp::Value;
+
+    use super::super::row::FromValue;
+
+    const MAX_SCALE: i32 = 28;
+    const MAX_MANTISSA: i128 = (1 << 96) - 1;
+
+    // Decimals can only be read: the driver has no decimal binding, and
+    // binding the serialized form as bytes is rejected for decimal columns.
+    impl FromValue for Decimal {
+        fn from_value(value: &Value) -> Result<Self, cassandra_cpp::Error> {
+            from_bytes(value.get_bytes()?)
+        }
+    }
+
+    // A decimal is serialized as a 4 byte big endian scale followed by the
+    // unscaled value as a two's complement varint.
+    fn from_bytes(bytes: &[u8]) -> Result<Decimal, cassandra_cpp::Error> {
+        if bytes.len() < 5 || bytes.len() > 4 + 16 {
+            return Err(format!("decimal of {} bytes does not fit rust_decimal", bytes.len()).into());
+        }
+        let mut scale = i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let unscaled = &bytes[4 ..];
+        let fill = if unscaled[0] & 0x80 == 0 { 0x00 } else { 0xff };
+        let mut buffer = [fill; 16];
+        buffer[16 - unscaled.len() ..].copy_from_slice(unscaled);
+        let mut mantissa = i128::from_be_bytes(buffer);
+        if mantissa == 0 {
+            scale = scale.max(0);
+        } else if scale < -38 {
+            // Any other mantissa overflows long before that.
+            return Err("decimal does not fit rust_decimal".into());
+        }
+        while scale < 0 {
+            mantissa = mantissa.checked_mul(10)
+                .ok_or("decimal does not fit rust_decimal")?;
+            scale += 1;
+        }
+        if scale > MAX_SCALE || mantissa.unsigned_abs() > MAX_MANTISSA as u128 {
+            return Err("decimal does not fit rust_decimal".into());
+        }
+        Ok(Decimal::from_i128_with_scale(mantissa, scale as u32))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::str::FromStr;
+
+        use rust_decimal::Decimal;
+
+        use super::from_bytes;
+
+        fn decimal(literal: &str) -> Decimal {
+            Decimal::from_str(literal).unwrap()
+        }
+
+        #[test]
+        fn reads_scale_and_varint() {
+            assert_eq!(from_bytes(&[0, 0, 0, 2, 0x7b]).unwrap(), decimal("1.23"));
+            assert_eq!(from_bytes(&[0, 0, 0, 0, 0x00]).unwrap(), decimal("0"));
+            // A leading zero byte keeps 128 positive.
+            assert_eq!(from_bytes(&[0, 0, 0, 0, 0x00, 0x80]).unwrap(), decimal("128"));
+            assert_eq!(from_bytes(&[0, 0, 0, 3, 0x01, 0x00]).unwrap(), decimal("0.256"));
+        }
+
+        #[test]
+        fn reads_negative_varints() {
+            assert_eq!(from_bytes(&[0, 0, 0, 0, 0xff]).unwrap(), decimal("-1"));
+            assert_eq!(from_bytes(&[0, 0, 0, 0, 0x80]).unwrap(), decimal("-128"));
+            assert_eq!(from_bytes(&[0, 0, 0, 1, 0xff, 0x7f]).unwrap(), decimal("-12.9"));
+        }
+
+        #[test]
+        fn negative_scale_multiplies() {
+            assert_eq!(from_bytes(&[0xff, 0xff, 0xff, 0xfe, 0x05]).unwrap(), decimal("500"));
+        }
+
+        #[test]
+        fn negative_scale_bails_out_early() {
+            assert_eq!(from_bytes(&[0x80, 0, 0, 0, 0x00]).unwrap(), decimal("0"));
+            assert!(from_bytes(&[0xff, 0xff, 0xff, 0xd9, 0x01]).is_err());
+            assert!(from_bytes(&[0x80, 0, 0, 0, 0x01]).is_err());
+        }
+
+        #[test]
+        fn rejects_what_does_not_fit() {
+            assert!(from_bytes(&[0, 0, 0, 0]).is_err());
+            assert!(from_bytes(&[0, 0, 0, 29, 0x01]).is_err());
+            // 2^96 is one past the largest rust_decimal mantissa.
+            assert!(from_bytes(&[0, 0, 0, 0, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+            assert!(from_bytes(&[0, 0, 0, 0, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).is_err());
+            // i128::MIN has no positive counterpart.
+            assert!(from_bytes(&[0, 0, 0, 0, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CodecRegistry,
+        super::{
+            BindError,
+            bind::ToCqlValue,
+            value::CqlValue,
+        },
+    };
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    struct Millis(i64);
+
+    fn registry() -> CodecRegistry {
+        let mut registry = CodecRegistry::new();
+        registry.register(
+            &[],
+            |value| value.get_i64().map(Millis),
+            |millis: &Millis| CqlValue::BigInt(millis.0),
+        );
+        registry
+    }
+
+    #[test]
+    fn wrapped_value_binds() {
+        let codec = registry().wrap(Millis(42)).unwrap();
+        assert_eq!(codec.to_cql_value(), CqlValue::BigInt(42));
+        assert_eq!(codec.into_inner(), Millis(42));
+    }
+
+    #[test]
+    fn wrapping_unregistered_type_fails() {
+        match registry().wrap(42u8) {
+            Err(BindError::NoCodec(type_name)) =>
+                assert_eq!(type_name, "u8"),
+            other =>
+                panic!("unexpected wrap result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    pin::Pin,
+    future::Future as StdFuture,
+};
+
+use futures::future::Future;
+
+use futures03::{
+    Stream as StdStream,
+    compat::{
+        Compat,
+        Future01CompatExt,
+        Stream01CompatExt,
+    },
+};
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+};
+
+use ero::{
+    Loop,
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+};
+
+use super::SharedSession;
+
+// Only an adapter: the lode, `SharedSession` and every helper are still
+// futures 0.1 on tokio 0.1 like `ero` itself, and these wrappers just let
+// async code call into them. An `async fn` init and std futures throughout
+// have to wait until `ero` moves off tokio 0.1.
+
+// Wraps an async block so it can be returned where the lode expects a
+// futures 0.1 future, e.g. from a `using_resource_loop` body.
+pub fn into_01<F, T, E>(future: F) -> Compat<Pin<Box<F>>> where F: StdFuture<Output = Result<T, E>> + Send + 'static {
+    Compat::new(Box::pin(future))
+}
+
+impl SharedSession {
+    pub async fn execute_async(&self, statement: Statement) -> Result<CassResult, cassandra_cpp::Error> {
+        self.execute_guarded(statement).compat().await
+    }
+
+    pub fn execute_paged_async(
+        &self,
+        statement: Statement,
+        page_size: i32,
+    )
+        -> impl StdStream<Item = Result<CassResult, cassandra_cpp::Error>>
+    {
+        self.execute_paged(statement, page_size).compat()
+    }
+}
+
+// Runs `with_session` against the lode's session. A `Recoverable` error
+// restarts the lode and calls `with_session` again on the fresh session.
+pub async fn using_session<F, R, T>(resource: LodeResource<SharedSession>, with_session: F) -> Result<T, ()>
+where F: Fn(SharedSession) -> R + Send + 'static,
+      R: StdFuture<Output = Result<T, ErrorSeverity<(), ()>>> + Send + 'static,
+      T: Send + 'static,
+{
+    resource
+        .using_resource_loop((), move |session, ()| {
+            let future = with_session(session);
+            into_01(async move {
+                match future.await {
+                    Ok(value) =>
+                        Ok((UsingResource::Lost, Loop::Break(value))),
+                    Err(ErrorSeverity::Recoverable { state: (), }) =>
+                        Err(ErrorSeverity::Recoverable { state: (), }),
+                    Err(ErrorSeverity::Fatal(())) =>
+                        Err(ErrorSeverity::Fatal(())),
+                }
+            })
+        })
+        .map_err(|_error| ())
+        .compat()
+        .await
+}
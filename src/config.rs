@@ -0,0 +1,367 @@
+use std::{
+    convert::TryFrom,
+    time::Duration,
+    net::IpAddr,
+    ops::Range,
+    collections::{
+        HashMap,
+        BTreeMap,
+    },
+};
+
+use serde::Deserialize;
+
+use super::{
+    ClusterParams,
+    ContactPoint,
+    AstraBundle,
+    Credentials,
+    ReconnectPolicy,
+    RetryPolicy,
+    TimestampGen,
+    SpeculativeExecution,
+    AdaptiveConcurrency,
+    KeyspaceRewrite,
+    KeyspaceSpec,
+    PreparedRegistry,
+    PagingParams,
+    KeepaliveParams,
+    LeaseParams,
+    WarmupParams,
+    SlowQueryParams,
+    ConnectThrottle,
+    ProfileParams,
+    ProfilePreset,
+    LoadBalancing,
+    ConsistencyLevel,
+    RateLimit,
+    CircuitBreakerParams,
+    InitBackoff,
+    BackoffCurve,
+};
+
+// The file representation of `ClusterParams`: every field is optional and
+// falls back to `ClusterParams::default()`, durations are in milliseconds
+// and consistency levels are names such as "LOCAL_QUORUM". Hooks,
+// migrations, schema expectations and the prometheus registry can only be
+// set from code.
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClusterConfig {
+    pub contact_points: Option<Vec<ContactPoint>>,
+    pub astra: Option<AstraBundle>,
+    pub resolve_contact_points: Option<bool>,
+    pub keyspace: Option<String>,
+    pub credentials: Option<Credentials>,
+    pub connect_timeout_ms: Option<u64>,
+    pub request_timeout_ms: Option<u64>,
+    pub local_address: Option<IpAddr>,
+    pub local_port_range: Option<Range<u16>>,
+    pub num_threads_io: Option<usize>,
+    pub queue_size_io: Option<usize>,
+    pub queue_size_event: Option<usize>,
+    pub core_connections_per_host: Option<usize>,
+    pub max_connections_per_host: Option<usize>,
+    pub max_concurrent_creation: Option<usize>,
+    pub max_requests_per_flush: Option<usize>,
+    pub pool_size: Option<usize>,
+    pub write_bytes_high_water_mark: Option<usize>,
+    pub pending_requests_high_water_mark: Option<usize>,
+    pub write_bytes_low_water_mark: Option<usize>,
+    pub pending_requests_low_water_mark: Option<usize>,
+    pub max_concurrent_requests_threshold: Option<usize>,
+    pub load_balance_round_robin: Option<bool>,
+    pub token_aware_routing: Option<bool>,
+    pub use_schema: Option<bool>,
+    pub reconnect_policy: Option<ReconnectPolicyConfig>,
+    pub init_backoff: Option<InitBackoffConfig>,
+    pub retry_policy: Option<RetryPolicyConfig>,
+    pub timestamp_generator: Option<TimestampGen>,
+    pub max_detached_in_flight: Option<usize>,
+    pub max_in_flight: Option<usize>,
+    pub query_cache_size: Option<usize>,
+    pub rate_limit: Option<RateLimit>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    pub adaptive_concurrency: Option<AdaptiveConcurrency>,
+    pub speculative_execution: Option<SpeculativeExecutionConfig>,
+    pub default_consistency: Option<ConsistencyLevel>,
+    pub default_serial_consistency: Option<ConsistencyLevel>,
+    pub execution_profiles: Option<HashMap<String, ProfileConfig>>,
+    pub keyspace_rewrite: Option<HashMap<String, String>>,
+    pub create_keyspace: Option<KeyspaceSpec>,
+    pub check_keyspace: Option<bool>,
+    pub max_frame_size: Option<usize>,
+    pub drain_timeout_ms: Option<u64>,
+    pub validate_on_acquire: Option<bool>,
+    pub validation_interval_ms: Option<u64>,
+    pub keepalive: Option<KeepaliveConfig>,
+    pub lease: Option<LeaseConfig>,
+    pub verify_connection: Option<bool>,
+    pub topology_poll_interval_ms: Option<u64>,
+    pub warmup_queries: Option<Vec<String>>,
+    pub warmup: Option<WarmupParams>,
+    // Statement key to CQL text.
+    pub prepared: Option<BTreeMap<String, String>>,
+    pub paging: Option<PagingParams>,
+    pub default_paging_size: Option<u32>,
+    pub connect_throttle: Option<ConnectThrottleConfig>,
+    pub slow_query_threshold_ms: Option<u64>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+#[serde(tag = "policy", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ReconnectPolicyConfig {
+    Constant { delay_ms: u64, },
+    Exponential { base_delay_ms: u64, max_delay_ms: u64, },
+}
+
+#[derive(Clone, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryPolicyConfig {
+    Default,
+    DowngradingConsistency,
+    Fallthrough,
+    Logging(Box<RetryPolicyConfig>),
+}
+
+#[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SpeculativeExecutionConfig {
+    pub constant_delay_ms: u64,
+    pub max_executions: usize,
+}
+
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    // Starting point for the workload; the fields below override it.
+    pub preset: Option<ProfilePreset>,
+    pub consistency: Option<ConsistencyLevel>,
+    pub serial_consistency: Option<ConsistencyLevel>,
+    pub request_timeout_ms: Option<u64>,
+    pub retry_policy: Option<RetryPolicyConfig>,
+    pub load_balancing: Option<LoadBalancing>,
+    pub token_aware_routing: Option<bool>,
+}
+
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeepaliveConfig {
+    pub interval_ms: Option<u64>,
+    pub max_failures: Option<usize>,
+}
+
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeaseConfig {
+    pub duration_ms: Option<u64>,
+    pub check_interval_ms: Option<u64>,
+    pub force_release: Option<bool>,
+}
+
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct InitBackoffConfig {
+    pub unreachable: Option<BackoffCurveConfig>,
+    pub overloaded: Option<BackoffCurveConfig>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BackoffCurveConfig {
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    pub failure_threshold: Option<usize>,
+    pub window_ms: Option<u64>,
+    pub cool_down_ms: Option<u64>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectThrottleConfig {
+    pub max_concurrent_connects: usize,
+    pub retry_interval_ms: u64,
+}
+
+impl From<RetryPolicyConfig> for RetryPolicy {
+    fn from(config: RetryPolicyConfig) -> RetryPolicy {
+        match config {
+            RetryPolicyConfig::Default =>
+                RetryPolicy::Default,
+            RetryPolicyConfig::DowngradingConsistency =>
+                RetryPolicy::DowngradingConsistency,
+            RetryPolicyConfig::Fallthrough =>
+                RetryPolicy::Fallthrough,
+            RetryPolicyConfig::Logging(child_policy) =>
+                RetryPolicy::Logging(Box::new((*child_policy).into())),
+        }
+    }
+}
+
+impl TryFrom<ClusterConfig> for ClusterParams {
+    type Error = String;
+
+    fn try_from(config: ClusterConfig) -> Result<ClusterParams, String> {
+        let mut params = ClusterParams::default();
+        macro_rules! apply {
+            ($($field:ident),* $(,)*) => {
+                $(
+                    if let Some(value) = config.$field {
+                        params.$field = value;
+                    }
+                )*
+            };
+        }
+        apply! {
+            contact_points,
+            resolve_contact_points,
+            num_threads_io,
+            queue_size_io,
+            queue_size_event,
+            core_connections_per_host,
+            max_connections_per_host,
+            max_concurrent_creation,
+            max_requests_per_flush,
+            pool_size,
+            write_bytes_high_water_mark,
+            pending_requests_high_water_mark,
+            load_balance_round_robin,
+            token_aware_routing,
+            use_schema,
+            check_keyspace,
+            timestamp_generator,
+            max_detached_in_flight,
+            max_frame_size,
+            validate_on_acquire,
+            verify_connection,
+            warmup_queries,
+            warmup,
+            paging,
+        }
+        if config.keyspace.is_some() {
+            params.keyspace = config.keyspace;
+        }
+        params.astra = config.astra;
+        params.credentials = config.credentials;
+        params.connect_timeout = config.connect_timeout_ms.map(Duration::from_millis);
+        params.request_timeout = config.request_timeout_ms.map(Duration::from_millis);
+        params.local_address = config.local_address;
+        params.local_port_range = config.local_port_range;
+        params.write_bytes_low_water_mark = config.write_bytes_low_water_mark;
+        params.pending_requests_low_water_mark = config.pending_requests_low_water_mark;
+        params.max_concurrent_requests_threshold = config.max_concurrent_requests_threshold;
+        params.max_in_flight = config.max_in_flight;
+        params.query_cache_size = config.query_cache_size;
+        params.default_paging_size = config.default_paging_size;
+        params.rate_limit = config.rate_limit;
+        params.circuit_breaker = config.circuit_breaker
+            .map(|circuit_breaker| {
+                let defaults = CircuitBreakerParams::default();
+                CircuitBreakerParams {
+                    failure_threshold: circuit_breaker.failure_threshold.unwrap_or(defaults.failure_threshold),
+                    window: circuit_breaker.window_ms.map_or(defaults.window, Duration::from_millis),
+                    cool_down: circuit_breaker.cool_down_ms.map_or(defaults.cool_down, Duration::from_millis),
+                }
+            });
+        if let Some(reconnect_policy) = config.reconnect_policy {
+            params.reconnect_policy = match reconnect_policy {
+                ReconnectPolicyConfig::Constant { delay_ms, } =>
+                    ReconnectPolicy::Constant { delay: Duration::from_millis(delay_ms), },
+                ReconnectPolicyConfig::Exponential { base_delay_ms, max_delay_ms, } =>
+                    ReconnectPolicy::Exponential {
+                        base_delay: Duration::from_millis(base_delay_ms),
+                        max_delay: Duration::from_millis(max_delay_ms),
+                    },
+            };
+        }
+        params.init_backoff = config.init_backoff
+            .map(|init_backoff| {
+                let defaults = InitBackoff::default();
+                let curve = |curve: Option<BackoffCurveConfig>, default: BackoffCurve| curve.map_or(default, |curve| BackoffCurve {
+                    base_delay: Duration::from_millis(curve.base_delay_ms),
+                    max_delay: Duration::from_millis(curve.max_delay_ms),
+                });
+                InitBackoff {
+                    unreachable: curve(init_backoff.unreachable, defaults.unreachable),
+                    overloaded: curve(init_backoff.overloaded, defaults.overloaded),
+                }
+            });
+        if let Some(retry_policy) = config.retry_policy {
+            params.retry_policy = retry_policy.into();
+        }
+        params.adaptive_concurrency = config.adaptive_concurrency;
+        params.speculative_execution = config.speculative_execution
+            .map(|speculative_execution| SpeculativeExecution {
+                constant_delay: Duration::from_millis(speculative_execution.constant_delay_ms),
+                max_executions: speculative_execution.max_executions,
+            });
+        params.default_consistency = config.default_consistency.map(Into::into);
+        params.default_serial_consistency = config.default_serial_consistency.map(Into::into);
+        if let Some(execution_profiles) = config.execution_profiles {
+            for (name, profile) in execution_profiles {
+                let preset = profile.preset.map_or_else(ProfileParams::default, ProfilePreset::params);
+                let profile_params = ProfileParams {
+                    consistency: profile.consistency.map(Into::into).or(preset.consistency),
+                    serial_consistency: profile.serial_consistency.map(Into::into).or(preset.serial_consistency),
+                    request_timeout: profile.request_timeout_ms.map(Duration::from_millis).or(preset.request_timeout),
+                    retry_policy: profile.retry_policy.map(Into::into).or(preset.retry_policy),
+                    load_balancing: profile.load_balancing.or(preset.load_balancing),
+                    token_aware_routing: profile.token_aware_routing.or(preset.token_aware_routing),
+                };
+                params.execution_profiles.insert(name, profile_params);
+            }
+        }
+        params.keyspace_rewrite = config.keyspace_rewrite
+            .map(|mapping| {
+                mapping.into_iter()
+                    .fold(KeyspaceRewrite::new(), |keyspace_rewrite, (from, to)| keyspace_rewrite.replace(from, to))
+            });
+        params.create_keyspace = config.create_keyspace;
+        if let Some(drain_timeout_ms) = config.drain_timeout_ms {
+            params.drain_timeout = Duration::from_millis(drain_timeout_ms);
+        }
+        if let Some(validation_interval_ms) = config.validation_interval_ms {
+            params.validation_interval = Duration::from_millis(validation_interval_ms);
+        }
+        params.keepalive = config.keepalive
+            .map(|keepalive| {
+                let defaults = KeepaliveParams::default();
+                KeepaliveParams {
+                    interval: keepalive.interval_ms.map_or(defaults.interval, Duration::from_millis),
+                    max_failures: keepalive.max_failures.unwrap_or(defaults.max_failures),
+                }
+            });
+        params.lease = config.lease
+            .map(|lease| {
+                let defaults = LeaseParams::default();
+                LeaseParams {
+                    duration: lease.duration_ms.map_or(defaults.duration, Duration::from_millis),
+                    check_interval: lease.check_interval_ms.map_or(defaults.check_interval, Duration::from_millis),
+                    force_release: lease.force_release.unwrap_or(defaults.force_release),
+                }
+            });
+        if let Some(topology_poll_interval_ms) = config.topology_poll_interval_ms {
+            params.topology_poll_interval = Duration::from_millis(topology_poll_interval_ms);
+        }
+        if let Some(prepared) = config.prepared {
+            params.prepared = prepared.into_iter()
+                .fold(PreparedRegistry::new(), |registry, (key, query)| registry.register(key, query));
+        }
+        params.connect_throttle = config.connect_throttle
+            .map(|connect_throttle| ConnectThrottle::new(
+                connect_throttle.max_concurrent_connects,
+                Duration::from_millis(connect_throttle.retry_interval_ms),
+            ));
+        params.slow_query = config.slow_query_threshold_ms
+            .map(|threshold_ms| SlowQueryParams {
+                threshold: Duration::from_millis(threshold_ms),
+                ..SlowQueryParams::default()
+            });
+        Ok(params)
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    fmt,
+    convert::TryFrom,
+    str::FromStr,
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+    Consistency,
+};
+
+#[cfg(feature = "serde")]
+use serde::{
+    ser,
+    Serialize,
+    Serializer,
+    Deserialize,
+};
+
+use super::{
+    SharedSession,
+    lwt::LwtResult,
+    audit_sink::AuditedStatement,
+    audit::{
+        DowngradeOn,
+        ConsistencyLadder,
+    },
+};
+
+// A `Consistency` that parses from and prints as its CQL name, such as
+// "LOCAL_QUORUM" (case insensitive), for config files and command lines.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(try_from = "String"))]
+pub struct ConsistencyLevel(pub Consistency);
+
+impl ConsistencyLevel {
+    // `None` for the driver's `UNKNOWN`, which no CQL name parses back to.
+    pub fn name(&self) -> Option<&'static str> {
+        match self.0 {
+            Consistency::ANY => Some("ANY"),
+            Consistency::ONE => Some("ONE"),
+            Consistency::TWO => Some("TWO"),
+            Consistency::THREE => Some("THREE"),
+            Consistency::QUORUM => Some("QUORUM"),
+            Consistency::ALL => Some("ALL"),
+            Consistency::LOCAL_QUORUM => Some("LOCAL_QUORUM"),
+            Consistency::EACH_QUORUM => Some("EACH_QUORUM"),
+            Consistency::SERIAL => Some("SERIAL"),
+            Consistency::LOCAL_SERIAL => Some("LOCAL_SERIAL"),
+            Consistency::LOCAL_ONE => Some("LOCAL_ONE"),
+            Consistency::UNKNOWN => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UnknownConsistency(pub String);
+
+impl fmt::Display for UnknownConsistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown consistency level {:?}", self.0)
+    }
+}
+
+impl FromStr for ConsistencyLevel {
+    type Err = UnknownConsistency;
+
+    fn from_str(value: &str) -> Result<ConsistencyLevel, UnknownConsistency> {
+        let consistency = match value.trim().to_uppercase().as_str() {
+            "ANY" => Consistency::ANY,
+            "ONE" => Consistency::ONE,
+            "TWO" => Consistency::TWO,
+            "THREE" => Consistency::THREE,
+            "QUORUM" => Consistency::QUORUM,
+            "ALL" => Consistency::ALL,
+            "LOCAL_QUORUM" => Consistency::LOCAL_QUORUM,
+            "EACH_QUORUM" => Consistency::EACH_QUORUM,
+            "SERIAL" => Consistency::SERIAL,
+            "LOCAL_SERIAL" => Consistency::LOCAL_SERIAL,
+            "LOCAL_ONE" => Consistency::LOCAL_ONE,
+            _ => return Err(UnknownConsistency(value.to_string())),
+        };
+        Ok(ConsistencyLevel(consistency))
+    }
+}
+
+impl TryFrom<String> for ConsistencyLevel {
+    type Error = UnknownConsistency;
+
+    fn try_from(value: String) -> Result<ConsistencyLevel, UnknownConsistency> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for ConsistencyLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name().unwrap_or("UNKNOWN"))
+    }
+}
+
+// Fails for `UNKNOWN` rather than writing a name `Deserialize` rejects.
+#[cfg(feature = "serde")]
+impl Serialize for ConsistencyLevel {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        match self.name() {
+            Some(name) =>
+                serializer.serialize_str(name),
+            None =>
+                Err(ser::Error::custom("consistency level UNKNOWN can't be serialized")),
+        }
+    }
+}
+
+impl From<ConsistencyLevel> for String {
+    fn from(level: ConsistencyLevel) -> String {
+        level.to_string()
+    }
+}
+
+impl From<Consistency> for ConsistencyLevel {
+    fn from(consistency: Consistency) -> ConsistencyLevel {
+        ConsistencyLevel(consistency)
+    }
+}
+
+impl From<ConsistencyLevel> for Consistency {
+    fn from(level: ConsistencyLevel) -> Consistency {
+        level.0
+    }
+}
+
+// Result of `execute_local_fallback`; `degraded` is set when it was read at
+// LOCAL_ONE and so may be stale.
+pub struct FallbackRead {
+    pub result: CassResult,
+    pub degraded: bool,
+}
+
+// Levels for a single call; `None` keeps whatever the statement or the
+// session default already has.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct ConsistencyOverride {
+    pub consistency: Option<Consistency>,
+    pub serial_consistency: Option<Consistency>,
+}
+
+impl ConsistencyOverride {
+    pub fn new(consistency: Consistency) -> ConsistencyOverride {
+        ConsistencyOverride {
+            consistency: Some(consistency),
+            serial_consistency: None,
+        }
+    }
+
+    pub fn serial(mut self, serial_consistency: Consistency) -> ConsistencyOverride {
+        self.serial_consistency = Some(serial_consistency);
+        self
+    }
+
+    pub fn apply(&self, statement: &mut Statement) -> Result<(), cassandra_cpp::Error> {
+        if let Some(consistency) = self.consistency {
+            statement.set_consistency(consistency)?;
+        }
+        if let Some(serial_consistency) = self.serial_consistency {
+            statement.set_serial_consistency(serial_consistency)?;
+        }
+        Ok(())
+    }
+
+    // For helpers taking the statement by value, such as `execute_paged`
+    // or `query_stream`.
+    pub fn applied_to(&self, mut statement: Statement) -> Result<Statement, cassandra_cpp::Error> {
+        self.apply(&mut statement)?;
+        Ok(statement)
+    }
+}
+
+impl SharedSession {
+    // The statement is taken by value, so a statement shared between calls
+    // is never left at an overridden level.
+    pub fn execute_with_consistency(
+        &self,
+        statement: Statement,
+        consistency: Consistency,
+        serial_consistency: Option<Consistency>,
+    )
+        -> impl Future<Item = CassResult, Error = cassandra_cpp::Error>
+    {
+        let consistency_override = ConsistencyOverride { consistency: Some(consistency), serial_consistency, };
+        match consistency_override.applied_to(statement) {
+            Ok(statement) => {
+                Either::A(self.execute_audited(statement, AuditedStatement::Unlabeled, Some(consistency))),
+            },
+            Err(error) =>
+                Either::B(result(Err(error))),
+        }
+    }
+
+    // Reads at LOCAL_QUORUM and, only if the coordinator answers
+    // Unavailable, once more at LOCAL_ONE. Timeouts and other errors are
+    // returned as they are: a timed out replica may still be slow, not down.
+    // A degraded read is recorded under `statement_tag` like the other
+    // fallbacks, see `downgrade_report`.
+    pub fn execute_local_fallback(&self, statement_tag: &str, statement: Statement) -> impl Future<Item = FallbackRead, Error = cassandra_cpp::Error> {
+        let ladder = ConsistencyLadder::descending_from(Consistency::LOCAL_QUORUM);
+        self.fallback_loop(statement_tag, statement, ladder.levels, DowngradeOn::Unavailable)
+            .map(|(result, level)| FallbackRead { result, degraded: level != Consistency::LOCAL_QUORUM, })
+    }
+
+    pub fn execute_lwt_with_consistency(
+        &self,
+        statement: Statement,
+        consistency_override: ConsistencyOverride,
+    )
+        -> impl Future<Item = LwtResult, Error = cassandra_cpp::Error>
+    {
+        match consistency_override.applied_to(statement) {
+            Ok(statement) =>
+                Either::A(self.execute_lwt(statement)),
+            Err(error) =>
+                Either::B(result(Err(error))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_cpp::Consistency;
+
+    use super::ConsistencyLevel;
+
+    #[test]
+    fn names_parse_back() {
+        let levels = [
+            Consistency::ANY,
+            Consistency::ONE,
+            Consistency::TWO,
+            Consistency::THREE,
+            Consistency::QUORUM,
+            Consistency::ALL,
+            Consistency::LOCAL_QUORUM,
+            Consistency::EACH_QUORUM,
+            Consistency::SERIAL,
+            Consistency::LOCAL_SERIAL,
+            Consistency::LOCAL_ONE,
+        ];
+        for &consistency in levels.iter() {
+            let level = ConsistencyLevel(consistency);
+            assert_eq!(level.to_string().parse::<ConsistencyLevel>(), Ok(level));
+        }
+        assert_eq!(" local_quorum ".parse::<ConsistencyLevel>(), Ok(ConsistencyLevel(Consistency::LOCAL_QUORUM)));
+    }
+
+    #[test]
+    fn unknown_has_no_name() {
+        assert_eq!(ConsistencyLevel(Consistency::UNKNOWN).name(), None);
+        assert!("UNKNOWN".parse::<ConsistencyLevel>().is_err());
+    }
+
+    #[cfg(all(feature = "serde", feature = "json"))]
+    #[test]
+    fn unknown_fails_to_serialize() {
+        assert_eq!(serde_json::to_string(&ConsistencyLevel(Consistency::LOCAL_ONE)).unwrap(), "\"LOCAL_ONE\"");
+        assert!(serde_json::to_string(&ConsistencyLevel(Consistency::UNKNOWN)).is_err());
+    }
+}
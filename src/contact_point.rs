@@ -0,0 +1,170 @@
+use std::{
+    fmt,
+    str::FromStr,
+    net::Ipv6Addr,
+};
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+pub const DEFAULT_PORT: u16 = 9042;
+
+// The driver has a single port for the whole cluster, so every contact
+// point of a cluster has to use the same one.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(deny_unknown_fields))]
+pub struct ContactPoint {
+    pub host: String,
+    #[cfg_attr(feature = "serde", serde(default = "default_port"))]
+    pub port: u16,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct InvalidContactPoint(pub String);
+
+impl fmt::Display for InvalidContactPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid contact point {:?}", self.0)
+    }
+}
+
+impl ContactPoint {
+    pub fn new<H>(host: H) -> ContactPoint where H: Into<String> {
+        ContactPoint {
+            host: host.into(),
+            port: DEFAULT_PORT,
+        }
+    }
+
+    pub fn with_port(mut self, port: u16) -> ContactPoint {
+        self.port = port;
+        self
+    }
+
+    // Parses the comma separated form formerly accepted by
+    // `ClusterParams::contact_points`, e.g. "10.0.0.1, 10.0.0.2:9043".
+    pub fn list(contact_points: &str) -> Result<Vec<ContactPoint>, InvalidContactPoint> {
+        contact_points.split(',')
+            .map(str::trim)
+            .filter(|contact_point| !contact_point.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+// "host", "host:port", a bare ipv6 address or "[ipv6]:port".
+impl FromStr for ContactPoint {
+    type Err = InvalidContactPoint;
+
+    fn from_str(value: &str) -> Result<ContactPoint, InvalidContactPoint> {
+        let invalid = || InvalidContactPoint(value.to_string());
+        let value = value.trim();
+        if value.parse::<Ipv6Addr>().is_ok() {
+            return Ok(ContactPoint::new(value));
+        }
+        let (host, port) = if value.starts_with('[') {
+            let close = value.find(']').ok_or_else(invalid)?;
+            let rest = &value[close + 1 ..];
+            let port = if rest.is_empty() {
+                None
+            } else {
+                Some(rest.strip_prefix(':').ok_or_else(invalid)?)
+            };
+            (&value[1 .. close], port)
+        } else {
+            match value.rfind(':') {
+                Some(colon) =>
+                    (&value[.. colon], Some(&value[colon + 1 ..])),
+                None =>
+                    (value, None),
+            }
+        };
+        if host.is_empty() || host.contains(|c: char| c.is_whitespace() || c == ',') {
+            return Err(invalid());
+        }
+        let port = match port {
+            None =>
+                DEFAULT_PORT,
+            Some(port) =>
+                port.parse().map_err(|_| invalid())?,
+        };
+        Ok(ContactPoint { host: host.to_string(), port, })
+    }
+}
+
+impl fmt::Display for ContactPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bracketed = self.host.contains(':');
+        match (bracketed, self.port == DEFAULT_PORT) {
+            (false, true) =>
+                write!(f, "{}", self.host),
+            (false, false) =>
+                write!(f, "{}:{}", self.host, self.port),
+            (true, true) =>
+                write!(f, "[{}]", self.host),
+            (true, false) =>
+                write!(f, "[{}]:{}", self.host, self.port),
+        }
+    }
+}
+
+pub(crate) fn display(contact_points: &[ContactPoint]) -> String {
+    let contact_points: Vec<_> = contact_points.iter()
+        .map(ContactPoint::to_string)
+        .collect();
+    contact_points.join(",")
+}
+
+#[cfg(feature = "serde")]
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ContactPoint,
+        InvalidContactPoint,
+        DEFAULT_PORT,
+    };
+
+    fn parse(value: &str) -> Result<ContactPoint, InvalidContactPoint> {
+        value.parse()
+    }
+
+    #[test]
+    fn parses_hosts_and_ports() {
+        assert_eq!(parse("10.0.0.1"), Ok(ContactPoint::new("10.0.0.1")));
+        assert_eq!(parse(" cassandra.local:9043 "), Ok(ContactPoint::new("cassandra.local").with_port(9043)));
+        assert_eq!(parse("::1"), Ok(ContactPoint::new("::1")));
+        assert_eq!(parse("fe80::1:2"), Ok(ContactPoint::new("fe80::1:2")));
+        assert_eq!(parse("[::1]"), Ok(ContactPoint::new("::1")));
+        assert_eq!(parse("[fe80::1]:9043"), Ok(ContactPoint::new("fe80::1").with_port(9043)));
+        assert_eq!(parse("host").unwrap().port, DEFAULT_PORT);
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for value in &["", ":9042", "host:", "host:port", "host:70000", "[::1", "[::1]9042", "two hosts", "a,b"] {
+            assert_eq!(parse(value), Err(InvalidContactPoint(value.to_string())), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for value in &["10.0.0.1", "host:9043", "[::1]", "[fe80::1]:9043"] {
+            let contact_point = parse(value).unwrap();
+            assert_eq!(contact_point.to_string(), *value);
+            assert_eq!(parse(&contact_point.to_string()), Ok(contact_point));
+        }
+    }
+
+    #[test]
+    fn lists_skip_empty_entries() {
+        assert_eq!(
+            ContactPoint::list("10.0.0.1, [::1]:9043,,"),
+            Ok(vec![ContactPoint::new("10.0.0.1"), ContactPoint::new("::1").with_port(9043)]),
+        );
+        assert!(ContactPoint::list("10.0.0.1, bad:port").is_err());
+    }
+}
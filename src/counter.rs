@@ -0,0 +1,226 @@
+use std::{
+    fmt,
+    collections::HashMap,
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        join_all,
+        Either,
+    },
+};
+
+use cassandra_cpp::BatchType;
+
+use super::{
+    SharedSession,
+    value::CqlValue,
+    bind::ToCqlValue,
+    cql::{
+        quote_identifier,
+        QueryBuilder,
+    },
+    schema::{
+        ColumnType,
+        TableSchema,
+    },
+};
+
+#[derive(Debug)]
+pub enum CounterError {
+    UnknownColumn {
+        table: String,
+        column: String,
+    },
+    NotCounter {
+        table: String,
+        column: String,
+        column_type: ColumnType,
+    },
+    // `decrement` by `i64::MIN`, which has no positive counterpart.
+    Overflow {
+        delta: i64,
+    },
+    Driver(cassandra_cpp::Error),
+}
+
+impl fmt::Display for CounterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CounterError::UnknownColumn { table, column, } =>
+                write!(f, "no column {:?} in table {:?}", column, table),
+            CounterError::NotCounter { table, column, column_type, } =>
+                write!(f, "column {:?} of table {:?} is {}, not counter", column, table, column_type),
+            CounterError::Overflow { delta, } =>
+                write!(f, "can't decrement a counter by {}", delta),
+            CounterError::Driver(error) =>
+                write!(f, "error updating counter: {:?}", error),
+        }
+    }
+}
+
+// `UPDATE ... SET column = column + ?` for one row; counters can only be
+// changed by a delta, never set.
+#[derive(Clone, Debug)]
+pub struct CounterUpdate {
+    keyspace: String,
+    table: String,
+    column: String,
+    delta: i64,
+    key: Vec<(String, CqlValue)>,
+}
+
+impl CounterUpdate {
+    pub fn increment(keyspace: &str, table: &str, column: &str, delta: i64) -> CounterUpdate {
+        CounterUpdate {
+            keyspace: keyspace.to_string(),
+            table: table.to_string(),
+            column: column.to_string(),
+            delta,
+            key: Vec::new(),
+        }
+    }
+
+    pub fn decrement(keyspace: &str, table: &str, column: &str, delta: i64) -> Result<CounterUpdate, CounterError> {
+        delta.checked_neg()
+            .map(|delta| CounterUpdate::increment(keyspace, table, column, delta))
+            .ok_or(CounterError::Overflow { delta, })
+    }
+
+    // Every primary key column must be given.
+    pub fn key<V>(mut self, column: &str, value: V) -> CounterUpdate where V: ToCqlValue {
+        self.key.push((column.to_string(), value.to_cql_value()));
+        self
+    }
+
+    fn check(&self, schema: &TableSchema) -> Result<(), CounterError> {
+        match schema.column_type(&self.column) {
+            Some(ColumnType::Counter) =>
+                Ok(()),
+            Some(column_type) =>
+                Err(CounterError::NotCounter {
+                    table: self.table.clone(),
+                    column: self.column.clone(),
+                    column_type: column_type.clone(),
+                }),
+            None =>
+                Err(CounterError::UnknownColumn { table: self.table.clone(), column: self.column.clone(), }),
+        }
+    }
+}
+
+impl QueryBuilder for CounterUpdate {
+    fn query(&self) -> String {
+        let column = quote_identifier(&self.column);
+        let relations: Vec<_> = self.key.iter()
+            .map(|(key_column, _value)| format!("{} = ?", quote_identifier(key_column)))
+            .collect();
+        format!(
+            "UPDATE {}.{} SET {} = {} + ? WHERE {}",
+            quote_identifier(&self.keyspace),
+            quote_identifier(&self.table),
+            column,
+            column,
+            relations.join(" AND "),
+        )
+    }
+
+    fn values(&self) -> Vec<CqlValue> {
+        let mut values = vec![CqlValue::BigInt(self.delta)];
+        values.extend(self.key.iter().map(|(_key_column, value)| value.clone()));
+        values
+    }
+}
+
+impl SharedSession {
+    // The column type is read from `system_schema` on every call, so a
+    // mistyped column fails with `NotCounter` instead of a server error.
+    pub fn update_counter(&self, update: CounterUpdate) -> impl Future<Item = (), Error = CounterError> {
+        self.update_counters(vec![update], 1)
+    }
+
+    // Sent as `COUNTER` batches of up to `max_batch_size` updates; a single
+    // update is executed without a batch and an empty list sends nothing.
+    pub fn update_counters(
+        &self,
+        updates: Vec<CounterUpdate>,
+        max_batch_size: usize,
+    )
+        -> impl Future<Item = (), Error = CounterError>
+    {
+        if updates.is_empty() {
+            return Either::A(result(Ok(())));
+        }
+        let mut tables: Vec<_> = updates.iter()
+            .map(|update| (update.keyspace.clone(), update.table.clone()))
+            .collect();
+        tables.sort();
+        tables.dedup();
+        let schemas: Vec<_> = tables.iter()
+            .map(|(keyspace, table)| self.table_schema(keyspace, table))
+            .collect();
+        let session = self.clone();
+        let future = join_all(schemas)
+            .map_err(CounterError::Driver)
+            .and_then(move |schemas| {
+                let schemas: HashMap<_, _> = tables.into_iter().zip(schemas).collect();
+                let checked = updates.iter().try_for_each(|update| {
+                    let table = (update.keyspace.clone(), update.table.clone());
+                    update.check(&schemas[&table])
+                });
+                let statements = checked.and_then(|()| {
+                    updates.iter()
+                        .map(|update| session.build_sized(update))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(CounterError::Driver)
+                });
+                match statements {
+                    Ok(mut statements) =>
+                        if statements.len() == 1 {
+                            let statement = statements.pop().unwrap().into_inner();
+                            Either::A(Either::A(session.execute_guarded(statement).map(|_cass_result| ()).map_err(CounterError::Driver)))
+                        } else {
+                            let future = session.execute_batch(BatchType::COUNTER, statements, max_batch_size)
+                                .map(|_cass_results| ())
+                                .map_err(CounterError::Driver);
+                            Either::A(Either::B(future))
+                        },
+                    Err(error) =>
+                        Either::B(result(Err(error))),
+                }
+            });
+        Either::B(future)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CounterError,
+        CounterUpdate,
+        super::{
+            value::CqlValue,
+            cql::QueryBuilder,
+        },
+    };
+
+    #[test]
+    fn decrement_negates_delta() {
+        let update = CounterUpdate::decrement("ks", "hits", "count", 5).unwrap()
+            .key("page", "/");
+        assert_eq!(update.query(), "UPDATE ks.hits SET count = count + ? WHERE page = ?");
+        assert_eq!(update.values(), vec![CqlValue::BigInt(-5), CqlValue::Text("/".to_string())]);
+    }
+
+    #[test]
+    fn decrement_by_min_overflows() {
+        match CounterUpdate::decrement("ks", "hits", "count", i64::MIN) {
+            Err(CounterError::Overflow { delta, }) =>
+                assert_eq!(delta, i64::MIN),
+            other =>
+                panic!("expected overflow, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,899 @@
+use std::{
+    fmt,
+    time::Duration,
+};
+
+use cassandra_cpp::Statement;
+
+use super::{
+    SharedSession,
+    bind::ToCqlValue,
+    value::CqlValue,
+    frame::SizedStatement,
+    token::{
+        self,
+        TokenRange,
+    },
+};
+
+// CQL reserved keywords can't be used as bare identifiers. `key` is not
+// reserved, but quoting a lowercase name never changes it, so it is quoted
+// too rather than tracking which versions accept it.
+const RESERVED: &[&str] = &[
+    "add", "allow", "alter", "and", "apply", "asc", "authorize", "batch", "begin", "by",
+    "columnfamily", "create", "default", "delete", "desc", "describe", "drop", "entries",
+    "execute", "from", "full", "grant", "if", "in", "index", "infinity", "insert", "into",
+    "is", "key", "keyspace", "limit", "materialized", "mbean", "mbeans", "modify", "nan",
+    "norecursive", "not", "null", "of", "on", "or", "order", "primary", "rename", "replace",
+    "revoke", "schema", "select", "set", "table", "to", "token", "truncate", "unlogged",
+    "unset", "update", "use", "using", "view", "where", "with",
+];
+
+pub fn quote_identifier(identifier: &str) -> String {
+    let is_plain = identifier.chars().next().map_or(false, |ch| ch.is_ascii_lowercase()) &&
+        identifier.chars().all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '_') &&
+        !RESERVED.contains(&identifier);
+    if is_plain {
+        identifier.to_string()
+    } else {
+        format!("\"{}\"", identifier.replace('"', "\"\""))
+    }
+}
+
+pub fn quote_literal(literal: &str) -> String {
+    format!("'{}'", literal.replace('\'', "''"))
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ScriptStatement {
+    // 1-based line of the statement's first character.
+    pub line: usize,
+    pub text: String,
+}
+
+pub fn split_statements(script: &str) -> Vec<String> {
+    split_script(script).into_iter()
+        .map(|statement| statement.text)
+        .collect()
+}
+
+pub fn split_script(script: &str) -> Vec<ScriptStatement> {
+    let bytes = script.as_bytes();
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut statement_start = None;
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let start = offset;
+        match bytes[offset] {
+            quote @ b'\'' | quote @ b'"' => {
+                offset = skip_quoted(bytes, offset, quote);
+                current.push_str(&script[start .. offset]);
+                statement_start.get_or_insert(start);
+            },
+            b'$' if bytes.get(offset + 1) == Some(&b'$') => {
+                offset = match script[offset + 2 ..].find("$$") {
+                    Some(index) => offset + 2 + index + 2,
+                    None => bytes.len(),
+                };
+                current.push_str(&script[start .. offset]);
+                statement_start.get_or_insert(start);
+            },
+            b'-' if bytes.get(offset + 1) == Some(&b'-') =>
+                offset = skip_line_comment(script, offset),
+            b'/' if bytes.get(offset + 1) == Some(&b'/') =>
+                offset = skip_line_comment(script, offset),
+            b'/' if bytes.get(offset + 1) == Some(&b'*') => {
+                offset = match script[offset + 2 ..].find("*/") {
+                    Some(index) => offset + 2 + index + 2,
+                    None => bytes.len(),
+                };
+                current.push(' ');
+            },
+            b';' => {
+                push_statement(script, &mut statements, &mut current, statement_start.take());
+                offset += 1;
+            },
+            _ => {
+                let ch = script[offset ..].chars().next().unwrap();
+                offset += ch.len_utf8();
+                current.push(ch);
+                if !ch.is_whitespace() {
+                    statement_start.get_or_insert(start);
+                }
+            },
+        }
+    }
+    push_statement(script, &mut statements, &mut current, statement_start);
+    statements
+}
+
+fn push_statement(script: &str, statements: &mut Vec<ScriptStatement>, current: &mut String, statement_start: Option<usize>) {
+    let statement = current.trim();
+    if !statement.is_empty() {
+        let line = statement_start.map_or(0, |start| script[.. start].matches('\n').count()) + 1;
+        statements.push(ScriptStatement { line, text: statement.to_string(), });
+    }
+    current.clear();
+}
+
+fn skip_line_comment(script: &str, offset: usize) -> usize {
+    match script[offset ..].find('\n') {
+        Some(index) => offset + index,
+        None => script.len(),
+    }
+}
+
+pub(crate) fn skip_quoted(bytes: &[u8], mut offset: usize, quote: u8) -> usize {
+    offset += 1;
+    while offset < bytes.len() {
+        if bytes[offset] == quote {
+            if bytes.get(offset + 1) == Some(&quote) {
+                offset += 2;
+                continue;
+            }
+            return offset + 1;
+        }
+        offset += 1;
+    }
+    bytes.len()
+}
+
+pub fn quote_table(table: &str) -> String {
+    table.split('.')
+        .map(quote_identifier)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// A table name, optionally qualified with its keyspace, so sessions without
+// a default keyspace can address several keyspaces. Both parts are quoted
+// separately; `From<&str>` splits "ks.table" at the first dot.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Table {
+    keyspace: Option<String>,
+    name: String,
+}
+
+impl Table {
+    pub fn new<K, N>(keyspace: K, name: N) -> Table where K: Into<String>, N: Into<String> {
+        Table { keyspace: Some(keyspace.into()), name: name.into(), }
+    }
+
+    // Resolved against the session keyspace.
+    pub fn unqualified<N>(name: N) -> Table where N: Into<String> {
+        Table { keyspace: None, name: name.into(), }
+    }
+
+    pub fn keyspace(&self) -> Option<&str> {
+        self.keyspace.as_ref().map(String::as_str)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn quoted(&self) -> String {
+        match self.keyspace {
+            Some(ref keyspace) =>
+                format!("{}.{}", quote_identifier(keyspace), quote_identifier(&self.name)),
+            None =>
+                quote_identifier(&self.name),
+        }
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.quoted())
+    }
+}
+
+impl<'a> From<&'a str> for Table {
+    fn from(table: &'a str) -> Table {
+        let mut parts = table.splitn(2, '.');
+        match (parts.next(), parts.next()) {
+            (Some(keyspace), Some(name)) =>
+                Table::new(keyspace, name),
+            _ =>
+                Table::unqualified(table),
+        }
+    }
+}
+
+impl<'a> From<&'a String> for Table {
+    fn from(table: &'a String) -> Table {
+        Table::from(table.as_str())
+    }
+}
+
+impl From<String> for Table {
+    fn from(table: String) -> Table {
+        Table::from(table.as_str())
+    }
+}
+
+impl<'a> From<&'a Table> for Table {
+    fn from(table: &'a Table) -> Table {
+        table.clone()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Op {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+pub trait QueryBuilder {
+    fn query(&self) -> String;
+    fn values(&self) -> Vec<CqlValue>;
+
+    // Catches what `query` can't render, like an empty `IN` list; both
+    // `statement` and `SharedSession::build` fail with it.
+    fn check(&self) -> Result<(), cassandra_cpp::Error> {
+        Ok(())
+    }
+
+    fn statement(&self) -> Result<Statement, cassandra_cpp::Error> {
+        self.check()?;
+        let values = self.values();
+        bind_values(Statement::new(&self.query(), values.len()), values)
+    }
+}
+
+pub(crate) fn bind_values(mut statement: Statement, values: Vec<CqlValue>) -> Result<Statement, cassandra_cpp::Error> {
+    for (index, value) in values.into_iter().enumerate() {
+        value.bind(&mut statement, index)?;
+    }
+    Ok(statement)
+}
+
+impl SharedSession {
+    // Rejects a statement that would not fit in `max_frame_size`.
+    pub fn build<B>(&self, builder: &B) -> Result<Statement, cassandra_cpp::Error> where B: QueryBuilder {
+        self.build_sized(builder).map(SizedStatement::into_inner)
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+struct Clauses {
+    relations: Vec<String>,
+    values: Vec<CqlValue>,
+    // The first column given an empty `where_in`.
+    empty_in: Option<String>,
+}
+
+impl Clauses {
+    fn push<V>(&mut self, column: &str, op: Op, value: V) where V: ToCqlValue {
+        self.relations.push(format!("{} {} ?", quote_identifier(column), op.as_str()));
+        self.values.push(value.to_cql_value());
+    }
+
+    fn push_in<I, V>(&mut self, column: &str, values: I) where I: IntoIterator<Item = V>, V: ToCqlValue {
+        let values: Vec<_> = values.into_iter().map(|value| value.to_cql_value()).collect();
+        if values.is_empty() && self.empty_in.is_none() {
+            self.empty_in = Some(column.to_string());
+        }
+        let markers = vec!["?"; values.len()].join(", ");
+        self.relations.push(format!("{} IN ({})", quote_identifier(column), markers));
+        self.values.extend(values);
+    }
+
+    fn push_token_range(&mut self, partition_key: &[&str], range: &TokenRange) {
+        let columns: Vec<_> = partition_key.iter().map(|column| quote_identifier(column)).collect();
+        let columns: Vec<_> = columns.iter().map(String::as_str).collect();
+        self.relations.push(token::token_range_clause(&columns));
+        self.values.push(CqlValue::BigInt(range.start.0));
+        self.values.push(CqlValue::BigInt(range.end.0));
+    }
+
+    fn check(&self) -> Result<(), cassandra_cpp::Error> {
+        match self.empty_in {
+            Some(ref column) =>
+                Err(format!("empty IN list for column {:?}", column).into()),
+            None =>
+                Ok(()),
+        }
+    }
+
+    fn render(&self, keyword: &str, query: &mut String) {
+        if !self.relations.is_empty() {
+            query.push(' ');
+            query.push_str(keyword);
+            query.push(' ');
+            query.push_str(&self.relations.join(" AND "));
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Select {
+    table: Table,
+    columns: Vec<String>,
+    clauses: Clauses,
+    order_by: Vec<(String, Order)>,
+    limit: Option<usize>,
+    allow_filtering: bool,
+}
+
+impl Select {
+    pub fn table<T>(table: T) -> Select where T: Into<Table> {
+        Select {
+            table: table.into(),
+            columns: Vec::new(),
+            clauses: Clauses::default(),
+            order_by: Vec::new(),
+            limit: None,
+            allow_filtering: false,
+        }
+    }
+
+    pub fn columns<C>(mut self, columns: &[C]) -> Select where C: AsRef<str> {
+        self.columns.extend(columns.iter().map(|column| column.as_ref().to_string()));
+        self
+    }
+
+    pub fn where_eq<V>(self, column: &str, value: V) -> Select where V: ToCqlValue {
+        self.where_op(column, Op::Eq, value)
+    }
+
+    pub fn where_op<V>(mut self, column: &str, op: Op, value: V) -> Select where V: ToCqlValue {
+        self.clauses.push(column, op, value);
+        self
+    }
+
+    pub fn where_in<I, V>(mut self, column: &str, values: I) -> Select where I: IntoIterator<Item = V>, V: ToCqlValue {
+        self.clauses.push_in(column, values);
+        self
+    }
+
+    pub fn where_token_range(mut self, partition_key: &[&str], range: &TokenRange) -> Select {
+        self.clauses.push_token_range(partition_key, range);
+        self
+    }
+
+    pub fn order_by(mut self, column: &str, order: Order) -> Select {
+        self.order_by.push((column.to_string(), order));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Select {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn allow_filtering(mut self) -> Select {
+        self.allow_filtering = true;
+        self
+    }
+}
+
+impl QueryBuilder for Select {
+    fn query(&self) -> String {
+        let columns = if self.columns.is_empty() {
+            "*".to_string()
+        } else {
+            self.columns.iter()
+                .map(|column| quote_identifier(column))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut query = format!("SELECT {} FROM {}", columns, self.table.quoted());
+        self.clauses.render("WHERE", &mut query);
+        if !self.order_by.is_empty() {
+            let order_by: Vec<_> = self.order_by.iter()
+                .map(|(column, order)| match order {
+                    Order::Asc => format!("{} ASC", quote_identifier(column)),
+                    Order::Desc => format!("{} DESC", quote_identifier(column)),
+                })
+                .collect();
+            query.push_str(" ORDER BY ");
+            query.push_str(&order_by.join(", "));
+        }
+        if let Some(limit) = self.limit {
+            query.push_str(&format!(" LIMIT {}", limit));
+        }
+        if self.allow_filtering {
+            query.push_str(" ALLOW FILTERING");
+        }
+        query
+    }
+
+    fn check(&self) -> Result<(), cassandra_cpp::Error> {
+        self.clauses.check()
+    }
+
+    fn values(&self) -> Vec<CqlValue> {
+        self.clauses.values.clone()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct WriteOptions {
+    pub ttl: Option<Duration>,
+    pub timestamp: Option<i64>,
+}
+
+impl WriteOptions {
+    pub fn using_clause(&self) -> &'static str {
+        match (self.ttl, self.timestamp) {
+            (Some(..), Some(..)) => " USING TTL ? AND TIMESTAMP ?",
+            (Some(..), None) => " USING TTL ?",
+            (None, Some(..)) => " USING TIMESTAMP ?",
+            (None, None) => "",
+        }
+    }
+
+    // Values for the markers of `using_clause`, in the same order.
+    pub fn values(&self) -> Vec<CqlValue> {
+        let mut values = Vec::new();
+        if let Some(ttl) = self.ttl {
+            values.push(CqlValue::Int(ttl.as_secs() as i32));
+        }
+        if let Some(timestamp) = self.timestamp {
+            values.push(CqlValue::BigInt(timestamp));
+        }
+        values
+    }
+
+    // Binds starting at `index` and returns the index of the next marker.
+    pub fn bind(&self, statement: &mut Statement, mut index: usize) -> Result<usize, cassandra_cpp::Error> {
+        for value in self.values() {
+            value.bind(statement, index)?;
+            index += 1;
+        }
+        Ok(index)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Insert {
+    table: Table,
+    columns: Vec<String>,
+    values: Vec<CqlValue>,
+    if_not_exists: bool,
+    options: WriteOptions,
+}
+
+impl Insert {
+    pub fn table<T>(table: T) -> Insert where T: Into<Table> {
+        Insert {
+            table: table.into(),
+            columns: Vec::new(),
+            values: Vec::new(),
+            if_not_exists: false,
+            options: WriteOptions::default(),
+        }
+    }
+
+    pub fn value<V>(mut self, column: &str, value: V) -> Insert where V: ToCqlValue {
+        self.columns.push(column.to_string());
+        self.values.push(value.to_cql_value());
+        self
+    }
+
+    pub fn if_not_exists(mut self) -> Insert {
+        self.if_not_exists = true;
+        self
+    }
+
+    pub fn options(mut self, options: WriteOptions) -> Insert {
+        self.options = options;
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Insert {
+        self.options.ttl = Some(ttl);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: i64) -> Insert {
+        self.options.timestamp = Some(timestamp);
+        self
+    }
+}
+
+impl QueryBuilder for Insert {
+    fn query(&self) -> String {
+        let columns: Vec<_> = self.columns.iter().map(|column| quote_identifier(column)).collect();
+        let mut query = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            self.table.quoted(),
+            columns.join(", "),
+            vec!["?"; columns.len()].join(", "),
+        );
+        if self.if_not_exists {
+            query.push_str(" IF NOT EXISTS");
+        }
+        query.push_str(self.options.using_clause());
+        query
+    }
+
+    fn values(&self) -> Vec<CqlValue> {
+        let mut values = self.values.clone();
+        values.extend(self.options.values());
+        values
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Update {
+    table: Table,
+    assignments: Vec<String>,
+    assignment_values: Vec<CqlValue>,
+    clauses: Clauses,
+    conditions: Clauses,
+    if_exists: bool,
+    options: WriteOptions,
+}
+
+impl Update {
+    pub fn table<T>(table: T) -> Update where T: Into<Table> {
+        Update {
+            table: table.into(),
+            assignments: Vec::new(),
+            assignment_values: Vec::new(),
+            clauses: Clauses::default(),
+            conditions: Clauses::default(),
+            if_exists: false,
+            options: WriteOptions::default(),
+        }
+    }
+
+    pub fn set<V>(mut self, column: &str, value: V) -> Update where V: ToCqlValue {
+        self.assignments.push(format!("{} = ?", quote_identifier(column)));
+        self.assignment_values.push(value.to_cql_value());
+        self
+    }
+
+    pub fn where_eq<V>(self, column: &str, value: V) -> Update where V: ToCqlValue {
+        self.where_op(column, Op::Eq, value)
+    }
+
+    pub fn where_op<V>(mut self, column: &str, op: Op, value: V) -> Update where V: ToCqlValue {
+        self.clauses.push(column, op, value);
+        self
+    }
+
+    pub fn where_in<I, V>(mut self, column: &str, values: I) -> Update where I: IntoIterator<Item = V>, V: ToCqlValue {
+        self.clauses.push_in(column, values);
+        self
+    }
+
+    pub fn if_op<V>(mut self, column: &str, op: Op, value: V) -> Update where V: ToCqlValue {
+        self.conditions.push(column, op, value);
+        self
+    }
+
+    pub fn if_exists(mut self) -> Update {
+        self.if_exists = true;
+        self
+    }
+
+    pub fn options(mut self, options: WriteOptions) -> Update {
+        self.options = options;
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Update {
+        self.options.ttl = Some(ttl);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: i64) -> Update {
+        self.options.timestamp = Some(timestamp);
+        self
+    }
+}
+
+impl QueryBuilder for Update {
+    fn query(&self) -> String {
+        let mut query = format!("UPDATE {}", self.table.quoted());
+        query.push_str(self.options.using_clause());
+        query.push_str(" SET ");
+        query.push_str(&self.assignments.join(", "));
+        self.clauses.render("WHERE", &mut query);
+        if self.if_exists {
+            query.push_str(" IF EXISTS");
+        } else {
+            self.conditions.render("IF", &mut query);
+        }
+        query
+    }
+
+    fn check(&self) -> Result<(), cassandra_cpp::Error> {
+        self.clauses.check()
+    }
+
+    fn values(&self) -> Vec<CqlValue> {
+        let mut values = self.options.values();
+        values.extend(self.assignment_values.iter().cloned());
+        values.extend(self.clauses.values.iter().cloned());
+        if !self.if_exists {
+            values.extend(self.conditions.values.iter().cloned());
+        }
+        values
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Delete {
+    table: Table,
+    columns: Vec<String>,
+    clauses: Clauses,
+    conditions: Clauses,
+    if_exists: bool,
+    timestamp: Option<i64>,
+}
+
+impl Delete {
+    pub fn table<T>(table: T) -> Delete where T: Into<Table> {
+        Delete {
+            table: table.into(),
+            columns: Vec::new(),
+            clauses: Clauses::default(),
+            conditions: Clauses::default(),
+            if_exists: false,
+            timestamp: None,
+        }
+    }
+
+    pub fn columns<C>(mut self, columns: &[C]) -> Delete where C: AsRef<str> {
+        self.columns.extend(columns.iter().map(|column| column.as_ref().to_string()));
+        self
+    }
+
+    pub fn where_eq<V>(self, column: &str, value: V) -> Delete where V: ToCqlValue {
+        self.where_op(column, Op::Eq, value)
+    }
+
+    pub fn where_op<V>(mut self, column: &str, op: Op, value: V) -> Delete where V: ToCqlValue {
+        self.clauses.push(column, op, value);
+        self
+    }
+
+    pub fn where_in<I, V>(mut self, column: &str, values: I) -> Delete where I: IntoIterator<Item = V>, V: ToCqlValue {
+        self.clauses.push_in(column, values);
+        self
+    }
+
+    pub fn if_op<V>(mut self, column: &str, op: Op, value: V) -> Delete where V: ToCqlValue {
+        self.conditions.push(column, op, value);
+        self
+    }
+
+    pub fn if_exists(mut self) -> Delete {
+        self.if_exists = true;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: i64) -> Delete {
+        self.timestamp = Some(timestamp);
+        self
+    }
+}
+
+impl QueryBuilder for Delete {
+    fn query(&self) -> String {
+        let mut query = "DELETE".to_string();
+        if !self.columns.is_empty() {
+            let columns: Vec<_> = self.columns.iter().map(|column| quote_identifier(column)).collect();
+            query.push(' ');
+            query.push_str(&columns.join(", "));
+        }
+        query.push_str(" FROM ");
+        query.push_str(&self.table.quoted());
+        if self.timestamp.is_some() {
+            query.push_str(" USING TIMESTAMP ?");
+        }
+        self.clauses.render("WHERE", &mut query);
+        if self.if_exists {
+            query.push_str(" IF EXISTS");
+        } else {
+            self.conditions.render("IF", &mut query);
+        }
+        query
+    }
+
+    fn check(&self) -> Result<(), cassandra_cpp::Error> {
+        self.clauses.check()
+    }
+
+    fn values(&self) -> Vec<CqlValue> {
+        let mut values = Vec::new();
+        if let Some(timestamp) = self.timestamp {
+            values.push(CqlValue::BigInt(timestamp));
+        }
+        values.extend(self.clauses.values.iter().cloned());
+        if !self.if_exists {
+            values.extend(self.conditions.values.iter().cloned());
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        quote_identifier,
+        split_script,
+        split_statements,
+        ScriptStatement,
+        Op,
+        Order,
+        Select,
+        Insert,
+        Update,
+        Delete,
+        QueryBuilder,
+        super::value::CqlValue,
+    };
+
+    #[test]
+    fn identifiers_are_quoted_when_needed() {
+        assert_eq!(quote_identifier("user_id2"), "user_id2");
+        assert_eq!(quote_identifier("userId"), "\"userId\"");
+        assert_eq!(quote_identifier("2fa"), "\"2fa\"");
+        assert_eq!(quote_identifier("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(quote_identifier("order"), "\"order\"");
+        assert_eq!(quote_identifier("key"), "\"key\"");
+        assert_eq!(quote_identifier("orders"), "orders");
+    }
+
+    #[test]
+    fn select_renders_clauses_in_order() {
+        let select = Select::table("ks.events")
+            .columns(&["id", "order"])
+            .where_eq("id", 7)
+            .where_op("at", Op::Ge, 100i64)
+            .order_by("at", Order::Desc)
+            .limit(10)
+            .allow_filtering();
+        assert_eq!(
+            select.query(),
+            "SELECT id, \"order\" FROM ks.events WHERE id = ? AND at >= ? ORDER BY at DESC LIMIT 10 ALLOW FILTERING",
+        );
+        assert_eq!(select.values(), vec![CqlValue::Int(7), CqlValue::BigInt(100)]);
+        assert!(select.check().is_ok());
+    }
+
+    #[test]
+    fn where_in_expands_markers() {
+        let select = Select::table("t").where_in("k", vec![1, 2, 3]);
+        assert_eq!(select.query(), "SELECT * FROM t WHERE k IN (?, ?, ?)");
+        assert_eq!(select.values(), vec![CqlValue::Int(1), CqlValue::Int(2), CqlValue::Int(3)]);
+    }
+
+    #[test]
+    fn empty_where_in_is_rejected() {
+        assert!(Select::table("t").where_in("k", Vec::<i32>::new()).check().is_err());
+        assert!(Select::table("t").where_in("k", Vec::<i32>::new()).statement().is_err());
+        assert!(Update::table("t").set("v", 1).where_in("k", Vec::<i32>::new()).check().is_err());
+        assert!(Delete::table("t").where_in("k", Vec::<i32>::new()).check().is_err());
+    }
+
+    #[test]
+    fn insert_puts_options_after_values() {
+        let insert = Insert::table("t")
+            .value("k", 1)
+            .value("key", "a")
+            .if_not_exists()
+            .timestamp(5);
+        assert_eq!(insert.query(), "INSERT INTO t (k, \"key\") VALUES (?, ?) IF NOT EXISTS USING TIMESTAMP ?");
+        assert_eq!(insert.values(), vec![CqlValue::Int(1), CqlValue::Text("a".to_string()), CqlValue::BigInt(5)]);
+    }
+
+    #[test]
+    fn update_binds_using_values_first() {
+        let update = Update::table("t")
+            .timestamp(5)
+            .set("v", 2)
+            .where_eq("k", 1)
+            .if_op("v", Op::Eq, 3);
+        assert_eq!(update.query(), "UPDATE t USING TIMESTAMP ? SET v = ? WHERE k = ? IF v = ?");
+        assert_eq!(update.values(), vec![CqlValue::BigInt(5), CqlValue::Int(2), CqlValue::Int(1), CqlValue::Int(3)]);
+    }
+
+    #[test]
+    fn if_exists_replaces_conditions() {
+        let delete = Delete::table("t")
+            .columns(&["v"])
+            .where_eq("k", 1)
+            .if_op("v", Op::Eq, 3)
+            .if_exists();
+        assert_eq!(delete.query(), "DELETE v FROM t WHERE k = ? IF EXISTS");
+        assert_eq!(delete.values(), vec![CqlValue::Int(1)]);
+    }
+
+    #[test]
+    fn splits_at_semicolons() {
+        assert_eq!(
+            split_statements("CREATE TABLE t (k int PRIMARY KEY);\nINSERT INTO t (k) VALUES (1);;"),
+            vec!["CREATE TABLE t (k int PRIMARY KEY)", "INSERT INTO t (k) VALUES (1)"],
+        );
+        assert_eq!(split_statements("SELECT k FROM t"), vec!["SELECT k FROM t"]);
+        assert!(split_statements(" ; \n ;").is_empty());
+    }
+
+    #[test]
+    fn semicolons_in_quotes_are_kept() {
+        assert_eq!(
+            split_statements("INSERT INTO t (s) VALUES ('a;b'); INSERT INTO t (s) VALUES ('it''s; fine')"),
+            vec!["INSERT INTO t (s) VALUES ('a;b')", "INSERT INTO t (s) VALUES ('it''s; fine')"],
+        );
+        assert_eq!(
+            split_statements("SELECT \"odd;name\" FROM t; SELECT 1"),
+            vec!["SELECT \"odd;name\" FROM t", "SELECT 1"],
+        );
+    }
+
+    #[test]
+    fn dollar_quoted_bodies_are_kept() {
+        let script = "CREATE FUNCTION f (x int) RETURNS NULL ON NULL INPUT RETURNS int LANGUAGE java AS $$ return x; $$;\nSELECT f(k) FROM t;";
+        assert_eq!(
+            split_statements(script),
+            vec![
+                "CREATE FUNCTION f (x int) RETURNS NULL ON NULL INPUT RETURNS int LANGUAGE java AS $$ return x; $$",
+                "SELECT f(k) FROM t",
+            ],
+        );
+    }
+
+    #[test]
+    fn comments_are_dropped() {
+        let script = "-- setup; not a statement\nSELECT 1; // trailing; comment\n/* block; comment */ SELECT 2;";
+        assert_eq!(split_statements(script), vec!["SELECT 1", "SELECT 2"]);
+        assert_eq!(split_statements("SELECT 1 /* inline */ FROM t"), vec!["SELECT 1   FROM t"]);
+        assert!(split_statements("-- only a comment").is_empty());
+    }
+
+    #[test]
+    fn comment_markers_in_quotes_are_kept() {
+        assert_eq!(
+            split_statements("INSERT INTO t (s) VALUES ('-- not; a comment /* */')"),
+            vec!["INSERT INTO t (s) VALUES ('-- not; a comment /* */')"],
+        );
+    }
+
+    #[test]
+    fn unterminated_quote_runs_to_the_end() {
+        assert_eq!(split_statements("SELECT 'a; b"), vec!["SELECT 'a; b"]);
+        assert_eq!(split_statements("AS $$ x; y"), vec!["AS $$ x; y"]);
+    }
+
+    #[test]
+    fn statements_carry_their_first_line() {
+        let script = "\n\nCREATE TABLE t (k int PRIMARY KEY);\n-- seed\nINSERT INTO t (k)\nVALUES (1);\n'x\ny'; SELECT 1";
+        assert_eq!(
+            split_script(script),
+            vec![
+                ScriptStatement { line: 3, text: "CREATE TABLE t (k int PRIMARY KEY)".to_string(), },
+                ScriptStatement { line: 5, text: "INSERT INTO t (k)\nVALUES (1)".to_string(), },
+                ScriptStatement { line: 7, text: "'x\ny'".to_string(), },
+                ScriptStatement { line: 8, text: "SELECT 1".to_string(), },
+            ],
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    fmt,
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+};
+
+use super::{
+    SharedSession,
+    TimeoutError,
+};
+
+// An end-to-end deadline handed down from the caller (an HTTP request, say).
+// Whatever time is left becomes the statement request timeout, and retries
+// stop once the next attempt could not start before it.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Deadline {
+    at: Instant,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DeadlineExceeded;
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "deadline exceeded")
+    }
+}
+
+impl Deadline {
+    pub fn at(at: Instant) -> Deadline {
+        Deadline { at, }
+    }
+
+    pub fn after(timeout: Duration) -> Deadline {
+        Deadline::at(Instant::now() + timeout)
+    }
+
+    pub fn instant(&self) -> Instant {
+        self.at
+    }
+
+    // `None` once the deadline has passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        let now = Instant::now();
+        if now < self.at {
+            Some(self.at - now)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_none()
+    }
+
+    // The earlier of the two, e.g. a caller deadline capped by a local one.
+    pub fn min(self, other: Deadline) -> Deadline {
+        Deadline { at: self.at.min(other.at), }
+    }
+
+    pub fn apply(&self, statement: &mut Statement) -> Result<Duration, DeadlineExceeded> {
+        let remaining = self.remaining().ok_or(DeadlineExceeded)?;
+        statement.set_request_timeout(Some(remaining));
+        Ok(remaining)
+    }
+}
+
+impl SharedSession {
+    // `execute_timeout` with whatever is left of `deadline`; an expired
+    // deadline fails without sending the statement.
+    pub fn execute_by(&self, statement: Statement, deadline: Deadline) -> impl Future<Item = CassResult, Error = TimeoutError> {
+        match deadline.remaining() {
+            Some(remaining) =>
+                Either::A(self.execute_timeout(statement, remaining)),
+            None =>
+                Either::B(result(Err(TimeoutError::TimedOut { elapsed: Duration::from_secs(0), }))),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::{
+    Arc,
+    atomic::{
+        Ordering,
+        AtomicUsize,
+    },
+};
+
+use futures::Future;
+
+use cassandra_cpp::Statement;
+
+use log::error;
+
+use super::{
+    limit::{
+        Limiter,
+        AdaptiveConcurrency,
+    },
+    SharedSession,
+    spawner::{
+        self,
+        SpawnError,
+    },
+};
+
+pub struct Detached {
+    limiter: Arc<Limiter>,
+    completed: AtomicUsize,
+    failed: AtomicUsize,
+    rejected: AtomicUsize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct DetachedStats {
+    pub in_flight: usize,
+    pub limit: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub rejected: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DetachedError {
+    LimitExceeded { limit: usize, },
+    ShuttingDown,
+    ExecutorAtCapacity,
+}
+
+impl Detached {
+    pub fn new(limit: usize, adaptive: Option<AdaptiveConcurrency>) -> Detached {
+        Detached {
+            limiter: Arc::new(Limiter::new(limit, adaptive)),
+            completed: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            rejected: AtomicUsize::new(0),
+        }
+    }
+
+    fn stats(&self) -> DetachedStats {
+        DetachedStats {
+            in_flight: self.limiter.in_flight(),
+            limit: self.limiter.limit(),
+            completed: self.completed.load(Ordering::SeqCst),
+            failed: self.failed.load(Ordering::SeqCst),
+            rejected: self.rejected.load(Ordering::SeqCst),
+        }
+    }
+}
+
+impl SharedSession {
+    // Rejected rather than queued once `max_detached_in_flight` statements
+    // are running; accepted ones still go through the execute path.
+    pub fn execute_detached(&self, statement: Statement) -> Result<(), DetachedError> {
+        let detached = self.detached.clone();
+        let permit = match Limiter::try_permit(&detached.limiter) {
+            Some(permit) =>
+                permit,
+            None => {
+                detached.rejected.fetch_add(1, Ordering::SeqCst);
+                return Err(DetachedError::LimitExceeded { limit: detached.limiter.limit(), });
+            },
+        };
+
+        let future = self.execute_guarded(statement)
+            .then(move |result| -> Result<(), ()> {
+                permit.finish(&result);
+                match result {
+                    Ok(_cass_result) => {
+                        detached.completed.fetch_add(1, Ordering::SeqCst);
+                    },
+                    Err(error) => {
+                        error!("error executing detached statement: {:?}", error);
+                        detached.failed.fetch_add(1, Ordering::SeqCst);
+                    },
+                }
+                Ok(())
+            });
+        if let Err(spawn_error) = spawner::spawn(self.shared.task_spawner.as_ref(), Box::new(future)) {
+            error!("error spawning detached statement: {:?}", spawn_error);
+            self.detached.rejected.fetch_add(1, Ordering::SeqCst);
+            return Err(match spawn_error {
+                SpawnError::Shutdown =>
+                    DetachedError::ShuttingDown,
+                SpawnError::AtCapacity =>
+                    DetachedError::ExecutorAtCapacity,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn detached_stats(&self) -> DetachedStats {
+        self.detached.stats()
+    }
+}
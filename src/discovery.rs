@@ -0,0 +1,125 @@
+use std::{
+    error,
+    thread,
+    sync::Arc,
+    net::{
+        IpAddr,
+        ToSocketAddrs,
+    },
+};
+
+use futures::{
+    Async,
+    Future,
+    IntoFuture,
+    sync::oneshot,
+    future::{
+        result,
+        poll_fn,
+        Either,
+    },
+};
+
+use log::{
+    debug,
+    info,
+};
+
+use super::{
+    ClusterParams,
+    ContactPoint,
+    contact_point::display,
+};
+
+pub type DiscoveryError = Box<dyn error::Error + Send + Sync>;
+
+pub type DiscoveryFuture = Box<dyn Future<Item = String, Error = DiscoveryError> + Send + 'static>;
+
+// Returns a fresh comma separated contact points list (in the form taken by
+// `ContactPoint::list`) on every connect, replacing `contact_points`.
+pub type ContactPointsDiscovery = Arc<dyn Fn() -> DiscoveryFuture + Send + Sync>;
+
+pub fn contact_points_discovery<F, R>(discovery: F) -> ContactPointsDiscovery
+where F: Fn() -> R + Send + Sync + 'static,
+      R: IntoFuture<Item = String, Error = DiscoveryError>,
+      R::Future: Send + 'static,
+{
+    Arc::new(move || Box::new(discovery().into_future()))
+}
+
+pub(crate) fn contact_points(params: &ClusterParams) -> impl Future<Item = Vec<ContactPoint>, Error = DiscoveryError> {
+    let discovered = match params.contact_points_discovery {
+        Some(ref discovery) => {
+            debug!("discovering contact points");
+            Either::A(discovery().and_then(|contact_points| {
+                ContactPoint::list(&contact_points)
+                    .map_err(|error| -> DiscoveryError { error.to_string().into() })
+            }))
+        },
+        None =>
+            Either::B(result(Ok(params.contact_points.clone()))),
+    };
+    let resolve = params.resolve_contact_points;
+    discovered.and_then(move |contact_points| {
+        if resolve {
+            Either::A(resolve_off_reactor(contact_points))
+        } else {
+            Either::B(result(Ok(contact_points)))
+        }
+    })
+}
+
+// The system resolver blocks for the duration of the lookup, so it runs
+// through `tokio_threadpool::blocking` on the threadpool runtime, and on a
+// thread of its own elsewhere (a current thread runtime).
+fn resolve_off_reactor(contact_points: Vec<ContactPoint>) -> impl Future<Item = Vec<ContactPoint>, Error = DiscoveryError> {
+    let mut resolved_rx: Option<oneshot::Receiver<Result<Vec<ContactPoint>, DiscoveryError>>> = None;
+    poll_fn(move || loop {
+        if let Some(ref mut resolved_rx) = resolved_rx {
+            return match resolved_rx.poll() {
+                Ok(Async::Ready(resolve_result)) =>
+                    resolve_result.map(Async::Ready),
+                Ok(Async::NotReady) =>
+                    Ok(Async::NotReady),
+                Err(oneshot::Canceled) =>
+                    Err("contact points resolver thread terminated".into()),
+            };
+        }
+        match tokio_threadpool::blocking(|| resolve_hostnames(&contact_points)) {
+            Ok(Async::Ready(resolve_result)) =>
+                return resolve_result.map(Async::Ready),
+            Ok(Async::NotReady) =>
+                return Ok(Async::NotReady),
+            Err(_not_on_threadpool) => {
+                let (resolved_tx, rx) = oneshot::channel();
+                let contact_points = contact_points.clone();
+                thread::spawn(move || resolved_tx.send(resolve_hostnames(&contact_points)));
+                resolved_rx = Some(rx);
+            },
+        }
+    })
+}
+
+fn resolve_hostnames(contact_points: &[ContactPoint]) -> Result<Vec<ContactPoint>, DiscoveryError> {
+    let mut resolved: Vec<ContactPoint> = Vec::new();
+    for contact_point in contact_points {
+        if contact_point.host.parse::<IpAddr>().is_ok() {
+            resolved.push(contact_point.clone());
+            continue;
+        }
+        let addresses = (contact_point.host.as_str(), contact_point.port).to_socket_addrs()
+            .map_err(|error| format!("error resolving contact point {:?}: {}", contact_point.host, error))?;
+        for socket_address in addresses {
+            let address = ContactPoint::new(socket_address.ip().to_string())
+                .with_port(contact_point.port);
+            if !resolved.contains(&address) {
+                resolved.push(address);
+            }
+        }
+    }
+    if resolved.is_empty() {
+        return Err(format!("contact points {:?} resolved to no addresses", display(contact_points)).into());
+    }
+    info!("contact points {:?} resolved to {}", display(contact_points), display(&resolved));
+    Ok(resolved)
+}
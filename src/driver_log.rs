@@ -0,0 +1,79 @@
+use std::sync::{
+    Arc,
+    Once,
+    RwLock,
+};
+
+use slog::{
+    o,
+    Drain,
+    Level,
+    Logger,
+    Record,
+    OwnedKVList,
+};
+
+use cassandra_cpp::LogLevel;
+
+use log::debug;
+
+static INSTALL: Once = Once::new();
+
+static SERVER_WARNING_HOOK: RwLock<Option<ServerWarningHook>> = RwLock::new(None);
+
+// Prefix the driver logs warnings sent back with a response under.
+const SERVER_WARNING_PREFIX: &str = "Server-side warning: ";
+
+// Receives CQL warnings sent by the server with a response, e.g. batch
+// size or tombstone threshold warnings. The driver only reports them
+// through its log and does not tell which request they belong to.
+pub type ServerWarningHook = Arc<dyn Fn(&str) + Send + Sync>;
+
+struct ServerWarnings<D> {
+    drain: D,
+}
+
+impl<D> Drain for ServerWarnings<D> where D: Drain {
+    type Ok = D::Ok;
+    type Err = D::Err;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<D::Ok, D::Err> {
+        if record.level() == Level::Warning {
+            let message = record.msg().to_string();
+            if let Some(warning) = message.strip_prefix(SERVER_WARNING_PREFIX) {
+                if let Some(ref hook) = *SERVER_WARNING_HOOK.read().unwrap() {
+                    hook(warning);
+                }
+            }
+        }
+        self.drain.log(record, values)
+    }
+}
+
+// Routes the C++ driver's log output through the `log` crate (and from
+// there into `tracing` if a `tracing-log` bridge is installed). The driver
+// logger is process wide, so only the first call has an effect.
+pub fn install_driver_logging(level: LogLevel) {
+    INSTALL.call_once(|| {
+        let drain = ServerWarnings { drain: slog_stdlog::StdLog.fuse(), };
+        let logger = Logger::root(drain, o!("source" => "cassandra_cpp"));
+        cassandra_cpp::set_logger(Some(logger));
+        cassandra_cpp::set_level(level);
+    });
+}
+
+// The driver has a single log level for the whole process, shared by
+// every lode, so it is set here rather than per cluster. Applies whether
+// or not `install_driver_logging` was called.
+pub fn set_driver_log_level(level: LogLevel) {
+    debug!("setting driver log level to {:?}", level);
+    cassandra_cpp::set_level(level);
+}
+
+// Process wide like the driver logger. Leaves the driver logging as it
+// is: warnings only arrive once `install_driver_logging` has been called
+// with `WARN` or a more verbose level.
+pub fn set_server_warning_hook(hook: Option<ServerWarningHook>) {
+    debug!("{} server warning hook", if hook.is_some() { "setting" } else { "clearing" });
+    *SERVER_WARNING_HOOK.write().unwrap() = hook;
+}
@@ -0,0 +1,113 @@
+use std::{
+    fmt,
+    env,
+    time::Duration,
+    str::FromStr,
+};
+
+use super::{
+    ClusterParams,
+    Credentials,
+    ContactPoint,
+    ConsistencyLevel,
+};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EnvError {
+    pub variable: &'static str,
+    pub value: String,
+    pub reason: String,
+}
+
+impl fmt::Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid value {:?} for {}: {}", self.value, self.variable, self.reason)
+    }
+}
+
+impl ClusterParams {
+    // Unset variables keep their `Default` values.
+    pub fn from_env() -> Result<ClusterParams, EnvError> {
+        ClusterParams::from_vars(|variable| env::var(variable).ok())
+    }
+
+    pub fn from_vars<F>(lookup: F) -> Result<ClusterParams, EnvError> where F: Fn(&str) -> Option<String> {
+        let vars = Vars { lookup, };
+        let mut params = ClusterParams::default();
+        if let Some(contact_points) = vars.get("CASSANDRA_CONTACT_POINTS") {
+            params.contact_points = ContactPoint::list(&contact_points)
+                .map_err(|error| EnvError {
+                    variable: "CASSANDRA_CONTACT_POINTS",
+                    reason: error.to_string(),
+                    value: contact_points,
+                })?;
+        }
+        if let Some(keyspace) = vars.get("CASSANDRA_KEYSPACE") {
+            params.keyspace = if keyspace.is_empty() { None } else { Some(keyspace) };
+        }
+        match (vars.get("CASSANDRA_USERNAME"), vars.get("CASSANDRA_PASSWORD")) {
+            (Some(username), Some(password)) =>
+                params.credentials = Some(Credentials { username, password, }),
+            (None, None) =>
+                (),
+            (Some(username), None) =>
+                return Err(EnvError {
+                    variable: "CASSANDRA_PASSWORD",
+                    value: String::new(),
+                    reason: format!("must be set together with CASSANDRA_USERNAME ({:?})", username),
+                }),
+            (None, Some(_password)) =>
+                return Err(EnvError {
+                    variable: "CASSANDRA_USERNAME",
+                    value: String::new(),
+                    reason: "must be set together with CASSANDRA_PASSWORD".to_string(),
+                }),
+        }
+        if let Some(millis) = vars.parse::<u64>("CASSANDRA_CONNECT_TIMEOUT_MS")? {
+            params.connect_timeout = Some(Duration::from_millis(millis));
+        }
+        if let Some(millis) = vars.parse::<u64>("CASSANDRA_REQUEST_TIMEOUT_MS")? {
+            params.request_timeout = Some(Duration::from_millis(millis));
+        }
+        if let Some(value) = vars.parse("CASSANDRA_NUM_THREADS_IO")? {
+            params.num_threads_io = value;
+        }
+        if let Some(value) = vars.parse("CASSANDRA_CORE_CONNECTIONS_PER_HOST")? {
+            params.core_connections_per_host = value;
+        }
+        if let Some(value) = vars.parse("CASSANDRA_MAX_CONNECTIONS_PER_HOST")? {
+            params.max_connections_per_host = value;
+        }
+        if let Some(value) = vars.parse("CASSANDRA_POOL_SIZE")? {
+            params.pool_size = value;
+        }
+        if let Some(value) = vars.parse::<bool>("CASSANDRA_TOKEN_AWARE_ROUTING")? {
+            params.token_aware_routing = value;
+        }
+        if let Some(ConsistencyLevel(consistency)) = vars.parse("CASSANDRA_CONSISTENCY")? {
+            params.default_consistency = Some(consistency);
+        }
+        Ok(params)
+    }
+}
+
+struct Vars<F> {
+    lookup: F,
+}
+
+impl<F> Vars<F> where F: Fn(&str) -> Option<String> {
+    fn get(&self, variable: &str) -> Option<String> {
+        (self.lookup)(variable).map(|value| value.trim().to_string())
+    }
+
+    fn parse<T>(&self, variable: &'static str) -> Result<Option<T>, EnvError> where T: FromStr, T::Err: fmt::Display {
+        match self.get(variable) {
+            None =>
+                Ok(None),
+            Some(value) =>
+                value.parse()
+                    .map(Some)
+                    .map_err(|error: T::Err| EnvError { variable, reason: error.to_string(), value, }),
+        }
+    }
+}
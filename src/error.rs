@@ -0,0 +1,196 @@
+use std::{
+    fmt,
+    sync::Arc,
+};
+
+use cassandra_cpp::{
+    ErrorKind,
+    CassErrorCode,
+};
+
+use ero::ErrorSeverity;
+
+use super::{
+    auth::AuthError,
+    discovery::DiscoveryError,
+    migrations::MigrationError,
+    prepared::PrepareError,
+    warmup::WarmupError,
+    schema_check::SchemaCheckError,
+};
+
+pub type ErrorHook = Arc<dyn Fn(&Error) + Send + Sync>;
+
+pub type InitFailureHook = Arc<dyn Fn(&InitFailure) + Send + Sync>;
+
+// Carried in the lode's recoverable state between init attempts; `attempt`
+// counts consecutive failures since the last successful connect.
+#[derive(Debug)]
+pub struct InitFailure {
+    pub attempt: usize,
+    pub error: Error,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Config {
+        step: &'static str,
+        error: cassandra_cpp::Error,
+    },
+    Connect {
+        contact_points: String,
+        keyspace: Option<String>,
+        error: cassandra_cpp::Error,
+    },
+    CreateKeyspace {
+        keyspace: String,
+        error: cassandra_cpp::Error,
+    },
+    CheckKeyspace {
+        keyspace: String,
+        error: cassandra_cpp::Error,
+    },
+    MissingKeyspace {
+        keyspace: String,
+    },
+    Migration(MigrationError),
+    SchemaCheck(SchemaCheckError),
+    Prepare(PrepareError),
+    ConnectThrottle(tokio::timer::Error),
+    Warmup(WarmupError),
+    Verify(cassandra_cpp::Error),
+    Auth(AuthError),
+    Discovery(DiscoveryError),
+    // Reasons passed to `on_disconnected`.
+    ReconnectRequested,
+    Validation(cassandra_cpp::Error),
+    Closed,
+}
+
+impl Error {
+    pub fn driver_error(&self) -> Option<&cassandra_cpp::Error> {
+        match self {
+            Error::Config { error, .. } |
+            Error::Connect { error, .. } |
+            Error::CreateKeyspace { error, .. } |
+            Error::CheckKeyspace { error, .. } |
+            Error::Verify(error) |
+            Error::Validation(error) =>
+                Some(error),
+            Error::Migration(migration_error) =>
+                Some(&migration_error.error),
+            Error::Prepare(prepare_error) =>
+                Some(&prepare_error.error),
+            Error::Warmup(warmup_error) =>
+                Some(&warmup_error.error),
+            Error::SchemaCheck(SchemaCheckError::Driver(error)) =>
+                Some(error),
+            Error::SchemaCheck(SchemaCheckError::Mismatch(..)) |
+            Error::ConnectThrottle(..) |
+            Error::Auth(..) |
+            Error::Discovery(..) |
+            Error::MissingKeyspace { .. } |
+            Error::ReconnectRequested |
+            Error::Closed =>
+                None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Config { step, error, } =>
+                write!(f, "error setting {}: {:?}", step, error),
+            Error::Connect { contact_points, keyspace: Some(keyspace), error, } =>
+                write!(f, "error connecting to {:?} with keyspace {:?}: {:?}", contact_points, keyspace, error),
+            Error::Connect { contact_points, keyspace: None, error, } =>
+                write!(f, "error connecting to {:?}: {:?}", contact_points, error),
+            Error::CreateKeyspace { keyspace, error, } =>
+                write!(f, "error creating keyspace {:?}: {:?}", keyspace, error),
+            Error::CheckKeyspace { keyspace, error, } =>
+                write!(f, "error checking keyspace {:?}: {:?}", keyspace, error),
+            Error::MissingKeyspace { keyspace, } =>
+                write!(f, "keyspace {:?} does not exist", keyspace),
+            Error::Migration(MigrationError { migration: Some(migration), statement, error, }) =>
+                write!(f, "error applying migration {:?} at {:?}: {:?}", migration, statement, error),
+            Error::Migration(MigrationError { migration: None, statement, error, }) =>
+                write!(f, "error preparing migrations table at {:?}: {:?}", statement, error),
+            Error::SchemaCheck(error) =>
+                write!(f, "{}", error),
+            Error::Prepare(PrepareError { key, error, }) =>
+                write!(f, "error preparing statement {:?}: {:?}", key, error),
+            Error::ConnectThrottle(error) =>
+                write!(f, "connect throttle timer error: {:?}", error),
+            Error::Warmup(WarmupError { query, error, }) =>
+                write!(f, "error running warmup query {:?}: {:?}", query, error),
+            Error::Verify(error) =>
+                write!(f, "error verifying connection: {:?}", error),
+            Error::Auth(error) =>
+                write!(f, "error obtaining credentials: {}", error),
+            Error::Discovery(error) =>
+                write!(f, "error discovering contact points: {}", error),
+            Error::ReconnectRequested =>
+                write!(f, "reconnect requested"),
+            Error::Validation(error) =>
+                write!(f, "session failed validation: {:?}", error),
+            Error::Closed =>
+                write!(f, "lode closed"),
+        }
+    }
+}
+
+impl From<MigrationError> for Error {
+    fn from(error: MigrationError) -> Error {
+        Error::Migration(error)
+    }
+}
+
+impl From<SchemaCheckError> for Error {
+    fn from(error: SchemaCheckError) -> Error {
+        Error::SchemaCheck(error)
+    }
+}
+
+impl From<PrepareError> for Error {
+    fn from(error: PrepareError) -> Error {
+        Error::Prepare(error)
+    }
+}
+
+impl From<WarmupError> for Error {
+    fn from(error: WarmupError) -> Error {
+        Error::Warmup(error)
+    }
+}
+
+// Errors that will not go away by reconnecting (bad credentials, a missing
+// keyspace, invalid CQL) are fatal; everything else, including codes not
+// listed here, is considered recoverable.
+pub fn classify(error: &cassandra_cpp::Error) -> ErrorSeverity<(), ()> {
+    let code = match error.kind() {
+        ErrorKind::CassError(code, _) =>
+            code,
+        ErrorKind::CassErrorResult(code, ..) =>
+            code,
+        _ =>
+            return ErrorSeverity::Recoverable { state: (), },
+    };
+    match code {
+        CassErrorCode::SERVER_SYNTAX_ERROR |
+        CassErrorCode::SERVER_BAD_CREDENTIALS |
+        CassErrorCode::SERVER_UNAUTHORIZED |
+        CassErrorCode::SERVER_INVALID_QUERY |
+        CassErrorCode::SERVER_CONFIG_ERROR |
+        CassErrorCode::LIB_UNABLE_TO_SET_KEYSPACE |
+        CassErrorCode::LIB_BAD_PARAMS |
+        CassErrorCode::SSL_INVALID_CERT |
+        CassErrorCode::SSL_INVALID_PRIVATE_KEY |
+        CassErrorCode::SSL_NO_PEER_CERT |
+        CassErrorCode::SSL_INVALID_PEER_CERT |
+        CassErrorCode::SSL_IDENTITY_MISMATCH =>
+            ErrorSeverity::Fatal(()),
+        _ =>
+            ErrorSeverity::Recoverable { state: (), },
+    }
+}
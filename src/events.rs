@@ -0,0 +1,219 @@
+use std::{
+    net::IpAddr,
+    sync::{
+        Weak,
+        Mutex,
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+    collections::HashMap,
+};
+
+use futures::{
+    Future,
+    Stream,
+    sync::mpsc,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use tokio::timer::Interval;
+
+use cassandra_cpp::{
+    Value,
+    Session,
+    Statement,
+};
+
+use log::{
+    debug,
+    warn,
+    error,
+};
+
+use ero::{
+    lode::LodeResource,
+    supervisor::Supervisor,
+};
+
+use super::{
+    spawn_shared,
+    failover::Failover,
+    Shared,
+    Params,
+    SharedSession,
+    spawner::{
+        self,
+        TaskSpawner,
+    },
+};
+
+const PEERS_QUERY: &str = "SELECT peer, data_center, rack FROM system.peers";
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ClusterEvent {
+    SessionUp { contact_points: String, },
+    SessionDown,
+    HostAdded { address: IpAddr, data_center: Option<String>, rack: Option<String>, },
+    HostRemoved { address: IpAddr, },
+}
+
+pub type ClusterEvents = mpsc::UnboundedReceiver<ClusterEvent>;
+
+#[derive(Default)]
+pub(crate) struct EventBus {
+    subscribers: Mutex<Vec<mpsc::UnboundedSender<ClusterEvent>>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&self) -> ClusterEvents {
+        let (tx, rx) = mpsc::unbounded();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    // Receivers dropped since the last call no longer count.
+    pub fn has_subscribers(&self) -> bool {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|tx| !tx.is_closed());
+        !subscribers.is_empty()
+    }
+
+    pub fn publish(&self, event: ClusterEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|tx| !tx.is_closed());
+        if !subscribers.is_empty() {
+            debug!("cluster event: {:?}", event);
+            subscribers.retain(|tx| tx.unbounded_send(event.clone()).is_ok());
+        }
+    }
+}
+
+// Like `spawn_link`, also returning a stream of session and topology events.
+pub fn spawn_link_with_events<N>(
+    supervisor: &Supervisor,
+    params: Params<N>,
+)
+    -> (LodeResource<SharedSession>, ClusterEvents)
+where N: AsRef<str> + Send + 'static,
+{
+    let Params { cluster_params, lode_params, configure, } = params;
+    let shared = Shared::new(lode_params.name.as_ref(), Failover::single(), configure);
+    let events = shared.events.subscribe();
+    let lode = spawn_shared(supervisor, cluster_params, shared, lode_params);
+    (lode, events)
+}
+
+// Polls `system.peers` and reports nodes joining or leaving the cluster
+// until the session is closed or nobody listens anymore.
+pub(crate) fn spawn_topology_watch(interval: Duration, session: Weak<Session>, shared: Weak<Shared>, task_spawner: Option<&TaskSpawner>) {
+    let future = Interval::new(Instant::now(), interval)
+        .map_err(|error| error!("topology watch timer error: {:?}", error))
+        .fold(None, move |known_peers: Option<HashMap<IpAddr, (Option<String>, Option<String>)>>, _instant| {
+            let (session, shared) = match (session.upgrade(), shared.upgrade()) {
+                (Some(session), Some(shared)) =>
+                    (session, shared),
+                _ =>
+                    return Either::A(result(Err(()))),
+            };
+            if !shared.events.has_subscribers() {
+                return Either::A(result(Err(())));
+            }
+            let statement = Statement::new(PEERS_QUERY, 0);
+            let future = session.execute(&statement)
+                .then(move |peers_result| {
+                    let cass_result = match peers_result {
+                        Ok(cass_result) =>
+                            cass_result,
+                        Err(error) => {
+                            warn!("error reading system.peers: {:?}", error);
+                            return Ok(known_peers);
+                        },
+                    };
+                    let peers: HashMap<_, _> = cass_result.iter()
+                        .filter_map(|row| {
+                            let address = row.get_column(0).ok().and_then(|value| read_address(&value))?;
+                            let data_center = row.get_column(1).and_then(|value| value.get_string()).ok();
+                            let rack = row.get_column(2).and_then(|value| value.get_string()).ok();
+                            Some((address, (data_center, rack)))
+                        })
+                        .collect();
+                    if let Some(known_peers) = known_peers {
+                        for (address, (data_center, rack)) in &peers {
+                            if !known_peers.contains_key(address) {
+                                shared.events.publish(ClusterEvent::HostAdded {
+                                    address: *address,
+                                    data_center: data_center.clone(),
+                                    rack: rack.clone(),
+                                });
+                            }
+                        }
+                        for address in known_peers.keys() {
+                            if !peers.contains_key(address) {
+                                shared.events.publish(ClusterEvent::HostRemoved { address: *address, });
+                            }
+                        }
+                    }
+                    Ok(Some(peers))
+                });
+            Either::B(future)
+        })
+        .then(|_| {
+            debug!("topology watch finished");
+            Ok(())
+        });
+    if let Err(error) = spawner::spawn(task_spawner, Box::new(future)) {
+        error!("error spawning topology watch task: {:?}", error);
+    }
+}
+
+pub(crate) fn read_address(value: &Value) -> Option<IpAddr> {
+    let bytes = value.get_bytes().ok()?;
+    match bytes.len() {
+        4 => {
+            let mut octets = [0; 4];
+            octets.copy_from_slice(bytes);
+            Some(IpAddr::from(octets))
+        },
+        16 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(bytes);
+            Some(IpAddr::from(octets))
+        },
+        _ =>
+            None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{
+        Stream,
+        Async,
+    };
+
+    use super::{
+        EventBus,
+        ClusterEvent,
+    };
+
+    #[test]
+    fn dropped_receivers_are_pruned() {
+        let events = EventBus::default();
+        let first = events.subscribe();
+        let mut second = events.subscribe();
+        assert!(events.has_subscribers());
+        drop(first);
+        assert!(events.has_subscribers());
+        events.publish(ClusterEvent::SessionDown);
+        assert_eq!(events.subscribers.lock().unwrap().len(), 1);
+        assert_eq!(second.poll(), Ok(Async::Ready(Some(ClusterEvent::SessionDown))));
+        drop(second);
+        assert!(!events.has_subscribers());
+        assert!(events.subscribers.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+use std::{
+    sync::Arc,
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use tokio::timer::Delay;
+
+use cassandra_cpp::{
+    Session,
+    CassResult,
+    Statement,
+    Consistency,
+};
+
+use super::{
+    SharedSession,
+    AuditedStatement,
+    rate_limit::TokenBucket,
+    limit::{
+        Limiter,
+        Permit,
+    },
+};
+
+// A rate limit token taken for a request that has not been sent yet; it is
+// given back if the future is dropped before that.
+struct Reservation {
+    token_bucket: Arc<TokenBucket>,
+    sent: bool,
+}
+
+impl Reservation {
+    fn sent(mut self) {
+        self.sent = true;
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if !self.sent {
+            self.token_bucket.refund();
+        }
+    }
+}
+
+// Waits for the request's `rate_limit` token to be due, then for a
+// `max_in_flight` slot.
+fn admit(
+    rate_limit: Option<Arc<TokenBucket>>,
+    in_flight: Option<Arc<Limiter>>,
+)
+    -> impl Future<Item = Option<Permit>, Error = cassandra_cpp::Error> + Send
+{
+    let wait = rate_limit.as_ref()
+        .map_or(Duration::from_secs(0), |token_bucket| token_bucket.reserve());
+    let reservation = rate_limit.map(|token_bucket| Reservation { token_bucket, sent: false, });
+    let throttle = if wait > Duration::from_secs(0) {
+        Either::A(Delay::new(Instant::now() + wait)
+            .map_err(|timer_error| -> cassandra_cpp::Error { format!("rate limit timer error: {:?}", timer_error).into() }))
+    } else {
+        Either::B(result(Ok(())))
+    };
+    throttle
+        .and_then(move |()| {
+            match in_flight {
+                Some(ref in_flight) =>
+                    Either::A(Limiter::acquire(in_flight).map(Some)),
+                None =>
+                    Either::B(result(Ok(None))),
+            }
+        })
+        .map(move |permit| {
+            if let Some(reservation) = reservation {
+                reservation.sent();
+            }
+            permit
+        })
+}
+
+impl SharedSession {
+    // Sends `statement` through the circuit breaker, `rate_limit` and
+    // `max_in_flight`. Named apart from `Session::execute`, which stays
+    // reachable through `Deref` and skips all of them.
+    pub fn execute_guarded(&self, statement: Statement) -> impl Future<Item = CassResult, Error = cassandra_cpp::Error> {
+        self.send(move |session| session.execute(&statement))
+    }
+
+    pub(crate) fn execute_audited(
+        &self,
+        statement: Statement,
+        audited: AuditedStatement,
+        consistency: Option<Consistency>,
+    )
+        -> impl Future<Item = CassResult, Error = cassandra_cpp::Error>
+    {
+        self.send_audited(audited, consistency, move |session| session.execute(&statement))
+    }
+
+    pub(crate) fn send<F, R>(&self, send: F) -> impl Future<Item = CassResult, Error = cassandra_cpp::Error> + Send
+    where F: FnOnce(&Session) -> R + Send + 'static,
+          R: Future<Item = CassResult, Error = cassandra_cpp::Error> + Send + 'static,
+    {
+        self.send_audited(AuditedStatement::Unlabeled, None, send)
+    }
+
+    // Every execute helper sends its requests through here. Fails fast while
+    // the circuit breaker is open and records every outcome with it and the
+    // audit sink otherwise; helpers that know what they run pass `audited`
+    // and the consistency they set. A `rate_limit` token is taken right
+    // away, then `send` is only called once the token is due and a
+    // `max_in_flight` slot is free, since the driver puts a request on the
+    // wire as soon as it is executed. Dropping the returned future before
+    // that sends nothing and refunds the token; dropping it later frees the
+    // slot.
+    pub(crate) fn send_audited<F, R>(
+        &self,
+        audited: AuditedStatement,
+        consistency: Option<Consistency>,
+        send: F,
+    )
+        -> impl Future<Item = CassResult, Error = cassandra_cpp::Error> + Send
+    where F: FnOnce(&Session) -> R + Send + 'static,
+          R: Future<Item = CassResult, Error = cassandra_cpp::Error> + Send + 'static,
+    {
+        if self.settings.circuit_breaker.is_some() && self.shared.breaker.is_open() {
+            return Either::A(result(Err("circuit breaker is open".into())));
+        }
+        let session = self.session.clone();
+        let shared_session = self.clone();
+        let started_at = Instant::now();
+        let future = admit(self.settings.rate_limit.clone(), self.settings.in_flight.clone())
+            .and_then(move |permit| {
+                send(&session)
+                    .then(move |execute_result| {
+                        if let Some(permit) = permit {
+                            permit.finish(&execute_result);
+                        }
+                        execute_result
+                    })
+            })
+            .then(move |execute_result| {
+                if execute_result.is_ok() {
+                    shared_session.shared.lode.succeeded();
+                }
+                if let Some(ref circuit_breaker) = shared_session.settings.circuit_breaker {
+                    if shared_session.shared.breaker.record(circuit_breaker, &execute_result) {
+                        shared_session.request_reconnect();
+                    }
+                }
+                shared_session.audit(audited, consistency, started_at, &execute_result);
+                execute_result
+            });
+        Either::B(future)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.settings.in_flight.as_ref()
+            .map_or(0, |in_flight| in_flight.in_flight())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::Duration,
+    };
+
+    use futures::{
+        future,
+        Future,
+    };
+
+    use tokio::{
+        runtime::current_thread::Runtime,
+        timer::Timeout,
+    };
+
+    use super::{
+        admit,
+        super::{
+            RateLimit,
+            limit::Limiter,
+            rate_limit::TokenBucket,
+        },
+    };
+
+    // Admitted and then stuck, like a request whose response never comes.
+    fn stuck(token_bucket: &Arc<TokenBucket>, limiter: &Arc<Limiter>) -> impl Future<Item = (), Error = cassandra_cpp::Error> {
+        admit(Some(token_bucket.clone()), Some(limiter.clone()))
+            .and_then(|permit| future::empty().then(move |never_result| {
+                drop(permit);
+                never_result
+            }))
+    }
+
+    #[test]
+    fn dropped_timeout_frees_in_flight_slot() {
+        let mut runtime = Runtime::new().unwrap();
+        let token_bucket = Arc::new(TokenBucket::new(RateLimit { queries_per_second: 1000.0, burst: 10, }));
+        let limiter = Arc::new(Limiter::new(1, None));
+        let timeout_result = runtime.block_on(Timeout::new(stuck(&token_bucket, &limiter), Duration::from_millis(20)));
+        assert!(timeout_result.unwrap_err().is_elapsed());
+        assert_eq!(limiter.in_flight(), 0);
+    }
+
+    #[test]
+    fn dropped_timeout_frees_waiter() {
+        let mut runtime = Runtime::new().unwrap();
+        let token_bucket = Arc::new(TokenBucket::new(RateLimit { queries_per_second: 1000.0, burst: 10, }));
+        let limiter = Arc::new(Limiter::new(1, None));
+        let permit = Limiter::try_permit(&limiter).unwrap();
+        let timeout_result = runtime.block_on(Timeout::new(stuck(&token_bucket, &limiter), Duration::from_millis(20)));
+        assert!(timeout_result.unwrap_err().is_elapsed());
+        assert_eq!(limiter.waiting(), 0);
+        drop(permit);
+        assert_eq!(limiter.in_flight(), 0);
+    }
+
+    #[test]
+    fn dropped_timeout_refunds_rate_limit_token() {
+        let mut runtime = Runtime::new().unwrap();
+        let token_bucket = Arc::new(TokenBucket::new(RateLimit { queries_per_second: 1.0, burst: 1, }));
+        let limiter = Arc::new(Limiter::new(1, None));
+        assert_eq!(token_bucket.reserve(), Duration::from_secs(0));
+        let timeout_result = runtime.block_on(Timeout::new(stuck(&token_bucket, &limiter), Duration::from_millis(20)));
+        assert!(timeout_result.unwrap_err().is_elapsed());
+        assert_eq!(limiter.in_flight(), 0);
+        // Back to the empty bucket left by the first reservation, plus what
+        // refilled meanwhile.
+        assert!(token_bucket.tokens() >= 0.0 && token_bucket.tokens() < 0.5);
+    }
+}
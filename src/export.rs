@@ -0,0 +1,347 @@
+use std::{
+    io,
+    fmt::Write as FmtWrite,
+};
+
+use futures::{
+    Future,
+    Stream,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use tokio::io::AsyncWrite;
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+};
+
+use super::{
+    SharedSession,
+    value::CqlValue,
+    result_columns::{
+        result_columns,
+        ResultColumn,
+    },
+};
+
+// How `export` renders the header and each page of rows. CSV and NDJSON
+// are provided here, Avro with the `avro` feature; `import` reads CSV and
+// NDJSON back.
+pub trait RecordFormat {
+    fn write_header(&self, columns: &[ResultColumn], output: &mut Vec<u8>);
+    fn write_record(&self, columns: &[ResultColumn], record: &[CqlValue], output: &mut Vec<u8>);
+
+    // Formats that frame rows in blocks override this.
+    fn write_page(&self, columns: &[ResultColumn], records: &[Vec<CqlValue>], output: &mut Vec<u8>) {
+        for record in records {
+            self.write_record(columns, record, output);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Csv {
+    pub delimiter: char,
+    pub header: bool,
+}
+
+impl Default for Csv {
+    fn default() -> Csv {
+        Csv { delimiter: ',', header: true, }
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NdJson;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct ExportStats {
+    pub pages: usize,
+    pub rows: usize,
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Driver(cassandra_cpp::Error),
+    Io(io::Error),
+}
+
+impl From<cassandra_cpp::Error> for ExportError {
+    fn from(error: cassandra_cpp::Error) -> ExportError {
+        ExportError::Driver(error)
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(error: io::Error) -> ExportError {
+        ExportError::Io(error)
+    }
+}
+
+impl RecordFormat for Csv {
+    fn write_header(&self, columns: &[ResultColumn], output: &mut Vec<u8>) {
+        if self.header {
+            let fields: Vec<_> = columns.iter()
+                .map(|column| self.quote(&column.name))
+                .collect();
+            output.extend_from_slice(fields.join(&self.delimiter.to_string()).as_bytes());
+            output.push(b'\n');
+        }
+    }
+
+    fn write_record(&self, _columns: &[ResultColumn], record: &[CqlValue], output: &mut Vec<u8>) {
+        let fields: Vec<_> = record.iter()
+            .map(|value| match value {
+                CqlValue::Null =>
+                    String::new(),
+                CqlValue::Text(text) =>
+                    self.quote(text),
+                CqlValue::List(..) | CqlValue::Set(..) | CqlValue::Map(..) | CqlValue::Udt(..) => {
+                    let mut json = String::new();
+                    write_json(value, &mut json);
+                    self.quote(&json)
+                },
+                other => {
+                    let mut plain = String::new();
+                    write_plain(other, &mut plain);
+                    plain
+                },
+            })
+            .collect();
+        output.extend_from_slice(fields.join(&self.delimiter.to_string()).as_bytes());
+        output.push(b'\n');
+    }
+}
+
+impl Csv {
+    fn quote(&self, field: &str) -> String {
+        if field.contains(self.delimiter) || field.contains('"') || field.contains('\n') || field.contains('\r') {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+}
+
+impl RecordFormat for NdJson {
+    fn write_header(&self, _columns: &[ResultColumn], _output: &mut Vec<u8>) { }
+
+    fn write_record(&self, columns: &[ResultColumn], record: &[CqlValue], output: &mut Vec<u8>) {
+        let mut line = String::from("{");
+        for (index, (column, value)) in columns.iter().zip(record).enumerate() {
+            if index > 0 {
+                line.push(',');
+            }
+            write_json_string(&column.name, &mut line);
+            line.push(':');
+            write_json(value, &mut line);
+        }
+        line.push_str("}\n");
+        output.extend_from_slice(line.as_bytes());
+    }
+}
+
+pub fn write_plain(value: &CqlValue, output: &mut String) {
+    let _ = match value {
+        CqlValue::Null => Ok(()),
+        CqlValue::Text(text) => write!(output, "{}", text),
+        CqlValue::Bool(flag) => write!(output, "{}", flag),
+        CqlValue::TinyInt(number) => write!(output, "{}", number),
+        CqlValue::SmallInt(number) => write!(output, "{}", number),
+        CqlValue::Int(number) => write!(output, "{}", number),
+        CqlValue::BigInt(number) => write!(output, "{}", number),
+        CqlValue::Float(number) => write!(output, "{}", number),
+        CqlValue::Double(number) => write!(output, "{}", number),
+        CqlValue::Uuid(uuid) => write!(output, "{}", uuid),
+        CqlValue::Blob(bytes) => {
+            output.push_str("0x");
+            for byte in bytes {
+                let _ = write!(output, "{:02x}", byte);
+            }
+            Ok(())
+        },
+        CqlValue::List(..) | CqlValue::Set(..) | CqlValue::Map(..) | CqlValue::Udt(..) => {
+            write_json(value, output);
+            Ok(())
+        },
+    };
+}
+
+pub fn write_json(value: &CqlValue, output: &mut String) {
+    match value {
+        CqlValue::Null =>
+            output.push_str("null"),
+        CqlValue::Text(text) =>
+            write_json_string(text, output),
+        CqlValue::Float(number) if !number.is_finite() =>
+            output.push_str("null"),
+        CqlValue::Double(number) if !number.is_finite() =>
+            output.push_str("null"),
+        CqlValue::Bool(..) | CqlValue::TinyInt(..) | CqlValue::SmallInt(..) | CqlValue::Int(..) |
+        CqlValue::BigInt(..) | CqlValue::Float(..) | CqlValue::Double(..) =>
+            write_plain(value, output),
+        CqlValue::Uuid(..) | CqlValue::Blob(..) => {
+            let mut plain = String::new();
+            write_plain(value, &mut plain);
+            write_json_string(&plain, output);
+        },
+        CqlValue::List(items) | CqlValue::Set(items) => {
+            output.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_json(item, output);
+            }
+            output.push(']');
+        },
+        CqlValue::Map(entries) => {
+            output.push('{');
+            for (index, (key, value)) in entries.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                let mut plain_key = String::new();
+                write_plain(key, &mut plain_key);
+                write_json_string(&plain_key, output);
+                output.push(':');
+                write_json(value, output);
+            }
+            output.push('}');
+        },
+        CqlValue::Udt(fields) => {
+            output.push('{');
+            for (index, (name, value)) in fields.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_json_string(name, output);
+                output.push(':');
+                write_json(value, output);
+            }
+            output.push('}');
+        },
+    }
+}
+
+pub fn write_json_string(text: &str, output: &mut String) {
+    output.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(output, "\\u{:04x}", ch as u32);
+            },
+            ch => output.push(ch),
+        }
+    }
+    output.push('"');
+}
+
+pub(crate) fn page_records(page: &CassResult, columns: usize) -> Result<Vec<Vec<CqlValue>>, cassandra_cpp::Error> {
+    page.iter()
+        .map(|row| {
+            (0 .. columns)
+                .map(|index| row.get_column(index).and_then(|value| CqlValue::read(&value)))
+                .collect()
+        })
+        .collect()
+}
+
+impl SharedSession {
+    pub fn export<F, W>(
+        &self,
+        statement: Statement,
+        page_size: i32,
+        format: F,
+        writer: W,
+    )
+        -> impl Future<Item = (W, ExportStats), Error = ExportError>
+    where F: RecordFormat + Send + 'static,
+          W: io::Write + Send + 'static,
+    {
+        self.execute_paged(statement, page_size)
+            .map_err(ExportError::Driver)
+            .fold(
+                (format, writer, ExportStats::default(), None),
+                |(format, mut writer, mut stats, columns), page| -> Result<_, ExportError> {
+                    let (output, columns) = render_page(&format, &page, columns, &mut stats)?;
+                    writer.write_all(&output)?;
+                    Ok((format, writer, stats, Some(columns)))
+                },
+            )
+            .map(|(_format, writer, stats, _columns)| (writer, stats))
+    }
+
+    // Same as `export`, but each page is written to `writer` without
+    // blocking the executor; the writer is flushed at the end.
+    pub fn export_async<F, W>(
+        &self,
+        statement: Statement,
+        page_size: i32,
+        format: F,
+        writer: W,
+    )
+        -> impl Future<Item = (W, ExportStats), Error = ExportError>
+    where F: RecordFormat + Send + 'static,
+          W: AsyncWrite + Send + 'static,
+    {
+        self.execute_paged(statement, page_size)
+            .map_err(ExportError::Driver)
+            .fold(
+                (format, writer, ExportStats::default(), None),
+                |(format, writer, mut stats, columns), page| {
+                    match render_page(&format, &page, columns, &mut stats) {
+                        Ok((output, columns)) => {
+                            let future = tokio::io::write_all(writer, output)
+                                .map_err(ExportError::Io)
+                                .map(move |(writer, _output)| (format, writer, stats, Some(columns)));
+                            Either::A(future)
+                        },
+                        Err(error) =>
+                            Either::B(result(Err(ExportError::Driver(error)))),
+                    }
+                },
+            )
+            .and_then(|(_format, writer, stats, _columns)| {
+                tokio::io::flush(writer)
+                    .map_err(ExportError::Io)
+                    .map(move |writer| (writer, stats))
+            })
+    }
+}
+
+// Renders one page, with the header first when `columns` is not known yet.
+fn render_page<F>(
+    format: &F,
+    page: &CassResult,
+    columns: Option<Vec<ResultColumn>>,
+    stats: &mut ExportStats,
+)
+    -> Result<(Vec<u8>, Vec<ResultColumn>), cassandra_cpp::Error>
+where F: RecordFormat,
+{
+    let mut output = Vec::new();
+    let columns = match columns {
+        Some(columns) =>
+            columns,
+        None => {
+            let columns = result_columns(page)?;
+            format.write_header(&columns, &mut output);
+            columns
+        },
+    };
+    let records = page_records(page, columns.len())?;
+    format.write_page(&columns, &records, &mut output);
+    stats.rows += records.len();
+    stats.pages += 1;
+    Ok((output, columns))
+}
@@ -0,0 +1,237 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    collections::VecDeque,
+};
+
+use futures::{
+    Future,
+    IntoFuture,
+};
+
+use log::{
+    info,
+    warn,
+};
+
+use ero::{
+    Loop,
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+    supervisor::Supervisor,
+};
+
+use super::{
+    spawn,
+    SharedSession,
+    ClusterParams,
+    contact_point,
+};
+
+pub struct FailoverParams<N> {
+    // Primary first, then standbys in the order they should be tried.
+    pub clusters: Vec<ClusterParams>,
+    pub lode_params: ero::Params<N>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FailoverError {
+    NoClusters,
+}
+
+impl fmt::Display for FailoverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FailoverError::NoClusters =>
+                write!(f, "at least one cluster definition is required"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ActiveCluster {
+    pub index: usize,
+    pub contact_points: String,
+}
+
+pub(crate) struct Failover {
+    standby: VecDeque<ClusterParams>,
+    active: usize,
+}
+
+impl Failover {
+    pub fn single() -> Failover {
+        Failover {
+            standby: VecDeque::new(),
+            active: 0,
+        }
+    }
+
+    pub fn active_cluster(&self, params: &ClusterParams) -> ActiveCluster {
+        ActiveCluster {
+            index: self.active,
+            contact_points: contact_point::display(&params.contact_points),
+        }
+    }
+
+    // Puts the failed definition at the back of the queue and hands out the
+    // next one; with no standbys the same definition is retried.
+    pub fn rotate(&mut self, params: ClusterParams) -> ClusterParams {
+        match self.standby.pop_front() {
+            None =>
+                params,
+            Some(next) => {
+                warn!(
+                    "cluster #{} ({}) unavailable, failing over to {}",
+                    self.active,
+                    contact_point::display(&params.contact_points),
+                    contact_point::display(&next.contact_points),
+                );
+                self.standby.push_back(params);
+                self.active = (self.active + 1) % (self.standby.len() + 1);
+                next
+            },
+        }
+    }
+}
+
+pub fn spawn_failover<N>(
+    supervisor: &Supervisor,
+    params: FailoverParams<N>,
+)
+    -> Result<LodeResource<SharedSession>, FailoverError>
+where N: AsRef<str> + Send + 'static,
+{
+    let FailoverParams { clusters, lode_params, } = params;
+    let mut clusters = clusters.into_iter();
+    let primary = clusters.next()
+        .ok_or(FailoverError::NoClusters)?;
+    let failover = Failover {
+        standby: clusters.collect(),
+        active: 0,
+    };
+    Ok(spawn(supervisor, primary, failover, None, lode_params))
+}
+
+impl SharedSession {
+    pub fn active_cluster(&self) -> &ActiveCluster {
+        &self.settings.active_cluster
+    }
+}
+
+pub struct WeightedParams<N> {
+    // Every cluster gets its own lode; the weight is its share of new
+    // acquisitions, e.g. 90 and 10.
+    pub clusters: Vec<(ClusterParams, u32)>,
+    pub lode_params: ero::Params<N>,
+}
+
+struct Weight {
+    weight: i64,
+    current: i64,
+}
+
+// Spreads acquisitions over several clusters at once by weight, for
+// shifting traffic gradually (during a region migration, say) instead of
+// the all-or-nothing switch of `spawn_failover`. Clusters are picked with
+// smooth weighted round robin, so 90/10 means exactly 9 of every 10
+// acquisitions, evenly interleaved.
+#[derive(Clone)]
+pub struct WeightedLode {
+    lodes: Arc<Vec<LodeResource<SharedSession>>>,
+    weights: Arc<Mutex<Vec<Weight>>>,
+}
+
+pub fn spawn_weighted<N>(
+    supervisor: &Supervisor,
+    params: WeightedParams<N>,
+)
+    -> Result<WeightedLode, FailoverError>
+where N: AsRef<str>,
+{
+    let WeightedParams { clusters, lode_params, } = params;
+    if clusters.is_empty() {
+        return Err(FailoverError::NoClusters);
+    }
+    let mut lodes = Vec::with_capacity(clusters.len());
+    let mut weights = Vec::with_capacity(clusters.len());
+    for (index, (cluster_params, weight)) in clusters.into_iter().enumerate() {
+        let lode = spawn(
+            supervisor,
+            cluster_params,
+            Failover::single(),
+            None,
+            ero::Params {
+                name: format!("{}/{}", lode_params.name.as_ref(), index),
+                restart_strategy: lode_params.restart_strategy.clone(),
+            },
+        );
+        lodes.push(lode);
+        weights.push(Weight { weight: i64::from(weight), current: 0, });
+    }
+    Ok(WeightedLode {
+        lodes: Arc::new(lodes),
+        weights: Arc::new(Mutex::new(weights)),
+    })
+}
+
+impl WeightedLode {
+    pub fn lodes(&self) -> Vec<LodeResource<SharedSession>> {
+        self.lodes.iter().cloned().collect()
+    }
+
+    pub fn weights(&self) -> Vec<u32> {
+        self.weights.lock().unwrap().iter().map(|weight| weight.weight as u32).collect()
+    }
+
+    // Takes effect for the next acquisition; missing entries are set to
+    // zero, which stops new acquisitions on that cluster.
+    pub fn set_weights(&self, new_weights: &[u32]) {
+        let mut weights = self.weights.lock().unwrap();
+        for (index, weight) in weights.iter_mut().enumerate() {
+            weight.weight = i64::from(new_weights.get(index).cloned().unwrap_or(0));
+            weight.current = 0;
+        }
+        info!("cluster weights set to {:?}", new_weights);
+    }
+
+    // With every weight at zero the first cluster is used.
+    fn pick(&self) -> LodeResource<SharedSession> {
+        let mut weights = self.weights.lock().unwrap();
+        let total: i64 = weights.iter().map(|weight| weight.weight).sum();
+        if total == 0 {
+            return self.lodes[0].clone();
+        }
+        for weight in weights.iter_mut() {
+            weight.current += weight.weight;
+        }
+        let (index, _) = weights.iter()
+            .enumerate()
+            .filter(|(_, weight)| weight.weight > 0)
+            .max_by_key(|(index, weight)| (weight.current, -(*index as i64)))
+            .expect("total weight is positive");
+        weights[index].current -= total;
+        self.lodes[index].clone()
+    }
+
+    // Same as `LodeResource::using_resource_loop` on the next cluster by
+    // weight; the whole loop, restarts included, stays on that cluster.
+    pub fn using_resource_loop<S, F, R, T, E>(&self, state: S, using: F) -> impl Future<Item = T, Error = ()>
+    where F: FnMut(SharedSession, S) -> R + Send + 'static,
+          R: IntoFuture<Item = (UsingResource<SharedSession>, Loop<T, S>), Error = ErrorSeverity<S, E>>,
+          R::Future: Send + 'static,
+          S: Send + 'static,
+          T: Send + 'static,
+          E: Send + 'static,
+    {
+        self.pick()
+            .using_resource_loop(state, using)
+            .map_err(|_error| ())
+    }
+}
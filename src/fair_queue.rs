@@ -0,0 +1,326 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+};
+
+use futures::{
+    task,
+    Async,
+    Poll,
+    Future,
+    IntoFuture,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use ero::{
+    Loop,
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+};
+
+use super::{
+    SharedSession,
+    spawner,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QueuePolicy {
+    // Waiters are admitted strictly in arrival order.
+    Fifo,
+    // Waiters are grouped by the task name passed to `using_resource_loop`
+    // and the groups take turns, so a busy task cannot starve a rare one.
+    RoundRobin,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FairQueueParams {
+    pub policy: QueuePolicy,
+    // How many `using_resource_loop` calls may run at once.
+    pub max_concurrent: usize,
+    // Callers beyond this many waiting ones fail with
+    // `FairQueueError::Full`; `None` lets them wait without a bound.
+    pub max_waiters: Option<usize>,
+}
+
+impl Default for FairQueueParams {
+    fn default() -> FairQueueParams {
+        FairQueueParams {
+            policy: QueuePolicy::RoundRobin,
+            max_concurrent: 64,
+            max_waiters: Some(1024),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct FairQueueStats {
+    pub active: usize,
+    pub waiting: usize,
+    pub rejected: usize,
+}
+
+#[derive(Debug)]
+pub enum FairQueueError {
+    Full { max_waiters: usize, },
+    LodeGone,
+    ShuttingDown,
+}
+
+impl fmt::Display for FairQueueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FairQueueError::Full { max_waiters, } =>
+                write!(f, "session queue is full ({} waiters)", max_waiters),
+            FairQueueError::LodeGone =>
+                write!(f, "session lode terminated"),
+            FairQueueError::ShuttingDown =>
+                write!(f, "executor is shutting down"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Waiter {
+    granted: bool,
+    task: Option<task::Task>,
+}
+
+type WaiterRef = Arc<Mutex<Waiter>>;
+
+struct Queue {
+    active: usize,
+    waiting: usize,
+    rejected: usize,
+    by_task: HashMap<String, VecDeque<WaiterRef>>,
+    // Task names with someone waiting, in the order they get their turn.
+    turns: VecDeque<String>,
+}
+
+impl Queue {
+    fn next_waiter(&mut self) -> Option<WaiterRef> {
+        let task_name = self.turns.pop_front()?;
+        let waiters = self.by_task.get_mut(&task_name)
+            .expect("every turn has a waiter queue");
+        let waiter = waiters.pop_front()
+            .expect("waiter queues in turn are not empty");
+        if waiters.is_empty() {
+            self.by_task.remove(&task_name);
+        } else {
+            self.turns.push_back(task_name);
+        }
+        self.waiting -= 1;
+        Some(waiter)
+    }
+
+    fn remove_waiter(&mut self, task_name: &str, waiter: &WaiterRef) {
+        let now_empty = match self.by_task.get_mut(task_name) {
+            Some(waiters) => {
+                let before = waiters.len();
+                waiters.retain(|queued| !Arc::ptr_eq(queued, waiter));
+                self.waiting -= before - waiters.len();
+                waiters.is_empty()
+            },
+            None =>
+                return,
+        };
+        if now_empty {
+            self.by_task.remove(task_name);
+            self.turns.retain(|turn| turn != task_name);
+        }
+    }
+}
+
+struct Inner {
+    params: FairQueueParams,
+    queue: Mutex<Queue>,
+}
+
+impl Inner {
+    // Hands the slot straight to the next waiter, if any.
+    fn release(&self) {
+        let mut queue = self.queue.lock().unwrap();
+        match queue.next_waiter() {
+            Some(waiter) => {
+                let mut waiter = waiter.lock().unwrap();
+                waiter.granted = true;
+                if let Some(task) = waiter.task.take() {
+                    task.notify();
+                }
+            },
+            None =>
+                queue.active -= 1,
+        }
+    }
+}
+
+struct Permit {
+    inner: Arc<Inner>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.inner.release();
+    }
+}
+
+struct Waiting {
+    inner: Arc<Inner>,
+    task_name: String,
+    waiter: Option<WaiterRef>,
+}
+
+impl Future for Waiting {
+    type Item = Permit;
+    type Error = FairQueueError;
+
+    fn poll(&mut self) -> Poll<Permit, FairQueueError> {
+        let granted = {
+            let waiter_ref = self.waiter.as_ref()
+                .expect("polled after completion");
+            let mut waiter = waiter_ref.lock().unwrap();
+            if !waiter.granted {
+                waiter.task = Some(task::current());
+            }
+            waiter.granted
+        };
+        if granted {
+            self.waiter = None;
+            Ok(Async::Ready(Permit { inner: self.inner.clone(), }))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+// A waiter dropped after being granted passes its slot on.
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter.take() {
+            let granted = {
+                let mut queue = self.inner.queue.lock().unwrap();
+                let granted = waiter.lock().unwrap().granted;
+                if !granted {
+                    queue.remove_waiter(&self.task_name, &waiter);
+                }
+                granted
+            };
+            if granted {
+                self.inner.release();
+            }
+        }
+    }
+}
+
+// Admission control in front of a lode: the lode itself wakes waiting
+// acquisitions in no particular order, so under load a task that acquires
+// rarely can wait behind a flood of others indefinitely. Here callers get a
+// slot by `policy` and fail fast once `max_waiters` are already queued.
+#[derive(Clone)]
+pub struct FairQueue {
+    resource: LodeResource<SharedSession>,
+    inner: Arc<Inner>,
+}
+
+impl FairQueue {
+    pub fn new(resource: LodeResource<SharedSession>, params: FairQueueParams) -> FairQueue {
+        FairQueue {
+            resource,
+            inner: Arc::new(Inner {
+                params: FairQueueParams { max_concurrent: params.max_concurrent.max(1), ..params },
+                queue: Mutex::new(Queue {
+                    active: 0,
+                    waiting: 0,
+                    rejected: 0,
+                    by_task: HashMap::new(),
+                    turns: VecDeque::new(),
+                }),
+            }),
+        }
+    }
+
+    pub fn stats(&self) -> FairQueueStats {
+        let queue = self.inner.queue.lock().unwrap();
+        FairQueueStats {
+            active: queue.active,
+            waiting: queue.waiting,
+            rejected: queue.rejected,
+        }
+    }
+
+    fn acquire(&self, task_name: &str) -> impl Future<Item = Permit, Error = FairQueueError> {
+        let params = &self.inner.params;
+        let mut queue = self.inner.queue.lock().unwrap();
+        if queue.active < params.max_concurrent && queue.waiting == 0 {
+            queue.active += 1;
+            return Either::A(result(Ok(Permit { inner: self.inner.clone(), })));
+        }
+        if let Some(max_waiters) = params.max_waiters {
+            if queue.waiting >= max_waiters {
+                queue.rejected += 1;
+                return Either::A(result(Err(FairQueueError::Full { max_waiters, })));
+            }
+        }
+        let task_name = match params.policy {
+            QueuePolicy::Fifo => String::new(),
+            QueuePolicy::RoundRobin => task_name.to_string(),
+        };
+        let waiter = Arc::new(Mutex::new(Waiter::default()));
+        if !queue.by_task.contains_key(&task_name) {
+            queue.turns.push_back(task_name.clone());
+        }
+        queue.by_task.entry(task_name.clone())
+            .or_insert_with(VecDeque::new)
+            .push_back(waiter.clone());
+        queue.waiting += 1;
+        Either::B(Waiting {
+            inner: self.inner.clone(),
+            task_name,
+            waiter: Some(waiter),
+        })
+    }
+
+    // Same as `LodeResource::using_resource_loop` once the queue admits the
+    // caller; the slot is held until the loop breaks. `task_name` groups
+    // callers for `QueuePolicy::RoundRobin` and is ignored for `Fifo`.
+    pub fn using_resource_loop<S, F, R, T, E>(
+        &self,
+        task_name: &str,
+        state: S,
+        using: F,
+    )
+        -> impl Future<Item = T, Error = FairQueueError>
+    where F: FnMut(SharedSession, S) -> R + Send + 'static,
+          R: IntoFuture<Item = (UsingResource<SharedSession>, Loop<T, S>), Error = ErrorSeverity<S, E>>,
+          R::Future: Send + 'static,
+          S: Send + 'static,
+          T: Send + 'static,
+          E: Send + 'static,
+    {
+        let resource = self.resource.clone();
+        self.acquire(task_name)
+            .and_then(move |permit| {
+                resource.using_resource_loop(state, using)
+                    .then(move |using_result| {
+                        drop(permit);
+                        using_result.map_err(|_error| if spawner::executor_shutting_down() {
+                            FairQueueError::ShuttingDown
+                        } else {
+                            FairQueueError::LodeGone
+                        })
+                    })
+            })
+    }
+}
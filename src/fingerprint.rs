@@ -0,0 +1,158 @@
+use std::fmt;
+
+use super::slow::sanitize;
+
+// The shape of a statement: literals replaced by `?`, whitespace collapsed
+// and `IN` lists folded to a single marker, so executions differing only in
+// their values share a key and no value ends up in logs or metrics.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct StatementKey(String);
+
+impl StatementKey {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for StatementKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<StatementKey> for String {
+    fn from(key: StatementKey) -> String {
+        key.0
+    }
+}
+
+pub fn fingerprint(cql: &str) -> StatementKey {
+    let sanitized = sanitize(cql);
+    let mut output = String::with_capacity(sanitized.len());
+    let mut rest = sanitized.as_str();
+    while let Some(ch) = rest.chars().next() {
+        if is_word_char(ch) {
+            let end = rest.find(|ch| !is_word_char(ch)).unwrap_or(rest.len());
+            let word = &rest[.. end];
+            if is_literal_word(word) {
+                output.push('?');
+            } else {
+                output.push_str(word);
+            }
+            rest = &rest[end ..];
+        } else if ch == '"' {
+            // Quoted identifiers are kept as they are.
+            let end = rest[1 ..].find('"').map_or(rest.len(), |offset| offset + 2);
+            output.push_str(&rest[.. end]);
+            rest = &rest[end ..];
+        } else {
+            output.push(ch);
+            rest = &rest[ch.len_utf8() ..];
+        }
+    }
+    StatementKey(fold_in_lists(&output))
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' || ch == '?'
+}
+
+// `sanitize` only catches literals starting with a digit: booleans, null
+// and UUIDs starting with a letter are left for here. Such a UUID comes out
+// of it cut at its first group starting with a digit, e.g. `cafe0123-?` or
+// `cafe0123-e89b-?`.
+fn is_literal_word(word: &str) -> bool {
+    match word.to_ascii_lowercase().as_str() {
+        "true" | "false" | "null" | "nan" | "infinity" =>
+            return true,
+        _ =>
+            (),
+    }
+    match word.find('-') {
+        Some(8) => {
+            let (head, tail) = word.split_at(8);
+            head.chars().all(|ch| ch.is_ascii_hexdigit()) &&
+                tail.trim_end_matches("-?").chars().all(|ch| ch == '-' || ch.is_ascii_hexdigit())
+        },
+        _ =>
+            false,
+    }
+}
+
+// `IN (?, ?, ?)` becomes `IN (?)`.
+fn fold_in_lists(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(position) = find_in_list(rest) {
+        let (head, list) = rest.split_at(position);
+        output.push_str(head);
+        let end = list.find(')').unwrap() + 1;
+        output.push_str("(?)");
+        rest = &list[end ..];
+    }
+    output.push_str(rest);
+    output
+}
+
+// Offset of the `(` of the next `IN` list made of markers only.
+fn find_in_list(text: &str) -> Option<usize> {
+    text.match_indices('(')
+        .map(|(position, _)| position)
+        .find(|&position| {
+            let before = text[.. position].trim_end();
+            let keyword_at = before.len().saturating_sub(2);
+            let is_in = before.len() >= 2 &&
+                before.is_char_boundary(keyword_at) &&
+                before[keyword_at ..].eq_ignore_ascii_case("in") &&
+                !before[.. keyword_at].ends_with(|ch: char| ch.is_ascii_alphanumeric() || ch == '_');
+            let list = match text[position ..].find(')') {
+                Some(end) => &text[position + 1 .. position + end],
+                None => return false,
+            };
+            is_in && list.contains('?') && list.chars().all(|ch| ch == '?' || ch == ',' || ch == ' ')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fingerprint;
+
+    fn key(cql: &str) -> String {
+        fingerprint(cql).into()
+    }
+
+    #[test]
+    fn literals_are_masked() {
+        assert_eq!(key("SELECT * FROM t WHERE k = 'abc' AND n = 42 AND x = 1.5e3"), "SELECT * FROM t WHERE k = ? AND n = ? AND x = ?");
+        assert_eq!(key("INSERT INTO t (k, s) VALUES (-7, 'it''s')"), "INSERT INTO t (k, s) VALUES (-?, ?)");
+        assert_eq!(key("SELECT  *\n  FROM t2   WHERE v = ?"), "SELECT * FROM t2 WHERE v = ?");
+    }
+
+    #[test]
+    fn uuids_are_masked() {
+        assert_eq!(key("SELECT * FROM t WHERE id = 123e4567-e89b-12d3-a456-426614174000"), "SELECT * FROM t WHERE id = ?");
+        assert_eq!(key("SELECT * FROM t WHERE id = cafe0123-e89b-12d3-a456-426614174000"), "SELECT * FROM t WHERE id = ?");
+        assert_eq!(key("SELECT * FROM t WHERE id = deadbeef-0000-1000-8000-00805f9b34fb"), "SELECT * FROM t WHERE id = ?");
+    }
+
+    #[test]
+    fn booleans_and_null_are_masked() {
+        assert_eq!(key("UPDATE t SET a = true, b = FALSE, c = null WHERE k = ?"), "UPDATE t SET a = ?, b = ?, c = ? WHERE k = ?");
+    }
+
+    #[test]
+    fn in_lists_are_folded() {
+        assert_eq!(key("SELECT * FROM t WHERE k IN (1, 2, 3)"), "SELECT * FROM t WHERE k IN (?)");
+        assert_eq!(key("SELECT * FROM t WHERE k IN (?, ?) AND c in ('a','b')"), "SELECT * FROM t WHERE k IN (?) AND c in (?)");
+        assert_eq!(key("SELECT * FROM t WHERE k IN (1)"), key("SELECT * FROM t WHERE k IN (1, 2, 3, 4)"));
+        // Not an IN list, and not a tuple of markers only.
+        assert_eq!(key("SELECT * FROM t WHERE join (?, ?)"), "SELECT * FROM t WHERE join (?, ?)");
+        assert_eq!(key("SELECT * FROM t WHERE k IN (?, c)"), "SELECT * FROM t WHERE k IN (?, c)");
+    }
+
+    #[test]
+    fn identifiers_are_kept() {
+        assert_eq!(key("SELECT col1, \"Mixed Case\", \"true\" FROM ks2.t_3 WHERE \"42\" = 42"), "SELECT col1, \"Mixed Case\", \"true\" FROM ks2.t_3 WHERE \"42\" = ?");
+        assert_eq!(key("SELECT deadline, nullable FROM t"), "SELECT deadline, nullable FROM t");
+    }
+}
@@ -0,0 +1,184 @@
+use std::marker::PhantomData;
+
+use cassandra_cpp::Statement;
+
+use super::{
+    SharedSession,
+    bind::ToCqlValue,
+    value::CqlValue,
+    cql::{
+        Op,
+        Table,
+        quote_identifier,
+    },
+};
+
+// Bind arguments of composed fragments: `()` for none, `(T,)` for a single
+// marker and `(A, B)` for two composed fragments, so `a.and(b).and(c)`
+// takes `((a, b), c)`.
+pub trait Args {
+    fn push_args(&self, values: &mut Vec<CqlValue>);
+}
+
+impl Args for () {
+    fn push_args(&self, _values: &mut Vec<CqlValue>) { }
+}
+
+impl<T> Args for (T,) where T: ToCqlValue {
+    fn push_args(&self, values: &mut Vec<CqlValue>) {
+        values.push(self.0.to_cql_value());
+    }
+}
+
+impl<A, B> Args for (A, B) where A: Args, B: Args {
+    fn push_args(&self, values: &mut Vec<CqlValue>) {
+        self.0.push_args(values);
+        self.1.push_args(values);
+    }
+}
+
+pub struct Fragment<A> {
+    parts: Vec<String>,
+    _args: PhantomData<fn(A)>,
+}
+
+impl<A> Clone for Fragment<A> {
+    fn clone(&self) -> Fragment<A> {
+        Fragment { parts: self.parts.clone(), _args: PhantomData, }
+    }
+}
+
+impl Fragment<()> {
+    pub fn empty() -> Fragment<()> {
+        Fragment { parts: Vec::new(), _args: PhantomData, }
+    }
+}
+
+impl<T> Fragment<(T,)> where T: ToCqlValue {
+    pub fn eq(column: &str) -> Fragment<(T,)> {
+        Fragment::op(column, Op::Eq)
+    }
+
+    pub fn op(column: &str, op: Op) -> Fragment<(T,)> {
+        Fragment {
+            parts: vec![format!("{} {} ?", quote_identifier(column), op.as_str())],
+            _args: PhantomData,
+        }
+    }
+
+    // Binds the whole collection to a single marker: `column IN ?`.
+    pub fn in_list(column: &str) -> Fragment<(T,)> {
+        Fragment {
+            parts: vec![format!("{} IN ?", quote_identifier(column))],
+            _args: PhantomData,
+        }
+    }
+}
+
+impl<A> Fragment<A> {
+    pub fn and<B>(self, other: Fragment<B>) -> Fragment<(A, B)> {
+        let mut parts = self.parts;
+        parts.extend(other.parts);
+        Fragment { parts, _args: PhantomData, }
+    }
+
+    fn render(&self, keyword: &str, separator: &str, query: &mut String) {
+        if !self.parts.is_empty() {
+            query.push(' ');
+            query.push_str(keyword);
+            query.push(' ');
+            query.push_str(&self.parts.join(separator));
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct Columns {
+    columns: Vec<String>,
+}
+
+impl Columns {
+    pub fn new<C>(columns: &[C]) -> Columns where C: AsRef<str> {
+        Columns::default().with(columns)
+    }
+
+    pub fn with<C>(mut self, columns: &[C]) -> Columns where C: AsRef<str> {
+        self.columns.extend(columns.iter().map(|column| column.as_ref().to_string()));
+        self
+    }
+
+    pub fn merge(mut self, other: &Columns) -> Columns {
+        self.columns.extend(other.columns.iter().cloned());
+        self
+    }
+
+    fn render(&self) -> String {
+        if self.columns.is_empty() {
+            "*".to_string()
+        } else {
+            self.columns.iter()
+                .map(|column| quote_identifier(column))
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+    }
+}
+
+pub struct TypedQuery<A> {
+    query: String,
+    _args: PhantomData<fn(A)>,
+}
+
+impl<A> Clone for TypedQuery<A> {
+    fn clone(&self) -> TypedQuery<A> {
+        TypedQuery { query: self.query.clone(), _args: PhantomData, }
+    }
+}
+
+impl<A> TypedQuery<A> where A: Args {
+    pub fn select<T>(table: T, columns: &Columns, predicate: &Fragment<A>) -> TypedQuery<A> where T: Into<Table> {
+        let mut query = format!("SELECT {} FROM {}", columns.render(), table.into().quoted());
+        predicate.render("WHERE", " AND ", &mut query);
+        TypedQuery { query, _args: PhantomData, }
+    }
+
+    pub fn delete<T>(table: T, predicate: &Fragment<A>) -> TypedQuery<A> where T: Into<Table> {
+        let mut query = format!("DELETE FROM {}", table.into().quoted());
+        predicate.render("WHERE", " AND ", &mut query);
+        TypedQuery { query, _args: PhantomData, }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn statement(&self, args: &A) -> Result<Statement, cassandra_cpp::Error> {
+        bind_args(&self.query, args)
+    }
+}
+
+impl<S, P> TypedQuery<(S, P)> where S: Args, P: Args {
+    // Assignments are `eq` fragments rendered as `column = ?`.
+    pub fn update<T>(table: T, assignments: &Fragment<S>, predicate: &Fragment<P>) -> TypedQuery<(S, P)> where T: Into<Table> {
+        let mut query = format!("UPDATE {}", table.into().quoted());
+        assignments.render("SET", ", ", &mut query);
+        predicate.render("WHERE", " AND ", &mut query);
+        TypedQuery { query, _args: PhantomData, }
+    }
+}
+
+fn bind_args<A>(query: &str, args: &A) -> Result<Statement, cassandra_cpp::Error> where A: Args {
+    let mut values = Vec::new();
+    args.push_args(&mut values);
+    let mut statement = Statement::new(query, values.len());
+    for (index, value) in values.into_iter().enumerate() {
+        value.bind(&mut statement, index)?;
+    }
+    Ok(statement)
+}
+
+impl SharedSession {
+    pub fn bind_typed<A>(&self, query: &TypedQuery<A>, args: &A) -> Result<Statement, cassandra_cpp::Error> where A: Args {
+        bind_args(&self.rewrite_query(&query.query), args)
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt;
+
+use cassandra_cpp::Statement;
+
+use super::{
+    SharedSession,
+    CqlValue,
+    cql::{
+        bind_values,
+        QueryBuilder,
+    },
+};
+
+const FRAME_HEADER_SIZE: usize = 9;
+// Header, batch type, statement count, consistency, flags, serial
+// consistency and default timestamp.
+pub(crate) const BATCH_OVERHEAD: usize = FRAME_HEADER_SIZE + 1 + 2 + 2 + 1 + 2 + 8;
+const STATEMENT_NAME_LIMIT: usize = 128;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FrameTooLarge {
+    pub statement: String,
+    pub estimated_size: usize,
+    pub max_frame_size: usize,
+}
+
+impl fmt::Display for FrameTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "statement {:?} needs a frame of about {} bytes, over the {} byte limit",
+            self.statement,
+            self.estimated_size,
+            self.max_frame_size,
+        )
+    }
+}
+
+// Upper bound for a native protocol v4 QUERY frame: header, long string
+// query, consistency, flags, values, page size, paging state, serial
+// consistency and default timestamp.
+pub fn estimate_query_size(query: &str, bound_value_sizes: &[usize]) -> usize {
+    let values_size: usize = bound_value_sizes.iter()
+        .map(|size| 4 + size)
+        .sum();
+    FRAME_HEADER_SIZE + 4 + query.len() + 2 + 1 + 2 + values_size + 4 + 4 + 2 + 8
+}
+
+// Size of a bound value without its 4 byte length prefix.
+pub fn value_size(value: &CqlValue) -> usize {
+    match value {
+        CqlValue::Null =>
+            0,
+        CqlValue::Text(text) =>
+            text.len(),
+        CqlValue::Bool(..) | CqlValue::TinyInt(..) =>
+            1,
+        CqlValue::SmallInt(..) =>
+            2,
+        CqlValue::Int(..) | CqlValue::Float(..) =>
+            4,
+        CqlValue::BigInt(..) | CqlValue::Double(..) =>
+            8,
+        CqlValue::Blob(bytes) =>
+            bytes.len(),
+        CqlValue::Uuid(..) =>
+            16,
+        CqlValue::List(items) | CqlValue::Set(items) =>
+            4 + items.iter().map(|item| 4 + value_size(item)).sum::<usize>(),
+        CqlValue::Map(entries) =>
+            4 + entries.iter().map(|(key, value)| 8 + value_size(key) + value_size(value)).sum::<usize>(),
+        CqlValue::Udt(fields) =>
+            fields.iter().map(|(_name, value)| 4 + value_size(value)).sum(),
+    }
+}
+
+pub fn check(query: &str, estimated_size: usize, max_frame_size: usize) -> Result<usize, FrameTooLarge> {
+    if estimated_size > max_frame_size {
+        Err(FrameTooLarge {
+            statement: statement_name(query),
+            estimated_size,
+            max_frame_size,
+        })
+    } else {
+        Ok(estimated_size)
+    }
+}
+
+pub fn statement_name(query: &str) -> String {
+    let query = query.trim();
+    match query.char_indices().nth(STATEMENT_NAME_LIMIT) {
+        Some((index, _)) =>
+            format!("{}...", &query[.. index]),
+        None =>
+            query.to_string(),
+    }
+}
+
+// A statement built by the crate together with its estimated frame size, so
+// `execute_batch` and `BatchWriter` can split batches to fit
+// `max_frame_size` and name the statement that can't fit at all.
+pub struct SizedStatement {
+    statement: Statement,
+    name: String,
+    estimated_size: usize,
+}
+
+impl SizedStatement {
+    pub fn statement(&self) -> &Statement {
+        &self.statement
+    }
+
+    pub fn into_inner(self) -> Statement {
+        self.statement
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn estimated_size(&self) -> usize {
+        self.estimated_size
+    }
+}
+
+impl SharedSession {
+    // Same as `build`, keeping the size estimate with the statement.
+    pub fn build_sized<B>(&self, builder: &B) -> Result<SizedStatement, cassandra_cpp::Error> where B: QueryBuilder {
+        builder.check()?;
+        let query = builder.query();
+        let values = builder.values();
+        let value_sizes: Vec<_> = values.iter().map(value_size).collect();
+        let estimated_size = self.check_frame_size(&query, &value_sizes)
+            .map_err(|frame_too_large| -> cassandra_cpp::Error { frame_too_large.to_string().into() })?;
+        let statement = bind_values(self.statement(&query, values.len()), values)?;
+        Ok(SizedStatement { statement, name: statement_name(&query), estimated_size, })
+    }
+
+    pub fn check_frame_size(&self, query: &str, bound_value_sizes: &[usize]) -> Result<usize, FrameTooLarge> {
+        let estimated_size = estimate_query_size(query, bound_value_sizes);
+        check(query, estimated_size, self.settings.max_frame_size)
+    }
+
+    pub fn max_frame_size(&self) -> usize {
+        self.settings.max_frame_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check,
+        value_size,
+        estimate_query_size,
+        CqlValue,
+    };
+
+    #[test]
+    fn value_sizes() {
+        assert_eq!(value_size(&CqlValue::Null), 0);
+        assert_eq!(value_size(&CqlValue::Text("abc".to_string())), 3);
+        assert_eq!(value_size(&CqlValue::BigInt(1)), 8);
+        assert_eq!(value_size(&CqlValue::List(vec![CqlValue::Int(1), CqlValue::Int(2)])), 4 + 2 * (4 + 4));
+        assert_eq!(value_size(&CqlValue::Map(vec![(CqlValue::Text("k".to_string()), CqlValue::Blob(vec![0; 10]))])), 4 + 8 + 1 + 10);
+    }
+
+    #[test]
+    fn oversized_query_is_named() {
+        let query = "INSERT INTO blobs (id, data) VALUES (?, ?)";
+        let estimated_size = estimate_query_size(query, &[16, 1 << 20]);
+        assert!(check(query, estimated_size, 2 << 20).is_ok());
+        let frame_too_large = check(query, estimated_size, 1 << 20).unwrap_err();
+        assert_eq!(frame_too_large.statement, query);
+        assert_eq!(frame_too_large.estimated_size, estimated_size);
+        assert!(frame_too_large.to_string().contains(query));
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::{
+    Instant,
+    Duration,
+};
+
+use futures::Future;
+
+use tokio::timer::Timeout;
+
+use cassandra_cpp::Statement;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use super::{
+    SharedSession,
+    VALIDATION_QUERY,
+    metrics::{
+        LodeStats,
+        SessionMetrics,
+    },
+};
+
+const HEALTH_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct HealthReport {
+    // The probe query succeeded within the timeout.
+    pub healthy: bool,
+    pub probe_latency: Option<Duration>,
+    pub probe_error: Option<String>,
+    // Includes the probe when it succeeded.
+    pub since_last_success: Option<Duration>,
+    pub metrics: SessionMetrics,
+    pub lode: LodeStats,
+}
+
+impl SharedSession {
+    // Never fails: probe errors and timeouts are reported as unhealthy.
+    pub fn health(&self) -> impl Future<Item = HealthReport, Error = ()> {
+        self.health_with_timeout(HEALTH_PROBE_TIMEOUT)
+    }
+
+    pub fn health_with_timeout(&self, probe_timeout: Duration) -> impl Future<Item = HealthReport, Error = ()> {
+        let statement = Statement::new(VALIDATION_QUERY, 0);
+        let started_at = Instant::now();
+        let session = self.clone();
+        Timeout::new(self.session.execute(&statement), probe_timeout)
+            .then(move |probe_result| {
+                let (probe_latency, probe_error) = match probe_result {
+                    Ok(_cass_result) => {
+                        session.shared.lode.succeeded();
+                        (Some(started_at.elapsed()), None)
+                    },
+                    Err(ref error) if error.is_elapsed() =>
+                        (None, Some(format!("probe timed out after {:?}", probe_timeout))),
+                    Err(error) =>
+                        (None, Some(match error.into_inner() {
+                            Some(error) =>
+                                format!("{:?}", error),
+                            None =>
+                                "probe timer error".to_string(),
+                        })),
+                };
+                Ok(HealthReport {
+                    healthy: probe_error.is_none(),
+                    probe_latency,
+                    probe_error,
+                    since_last_success: session.shared.lode.since_last_success(),
+                    metrics: session.metrics(),
+                    lode: session.lode_stats(),
+                })
+            })
+    }
+}
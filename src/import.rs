@@ -0,0 +1,780 @@
+use std::{
+    io,
+    fmt,
+    mem,
+    str::FromStr,
+    iter::Peekable,
+    str::Chars,
+    sync::Arc,
+};
+
+use futures::{
+    Async,
+    Poll,
+    Future,
+    Stream,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use tokio::io::AsyncRead;
+
+use cassandra_cpp::Uuid;
+
+use ero::{
+    Loop,
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+};
+
+use log::debug;
+
+use super::{
+    cql,
+    spawner,
+    SharedSession,
+    value::CqlValue,
+    schema::{
+        ColumnType,
+        TableSchema,
+    },
+    export::{
+        Csv,
+        NdJson,
+    },
+    ingest::{
+        ingest,
+        IngestError,
+        IngestParams,
+        IngestReport,
+    },
+};
+
+// How `import` reads records back, the counterpart of `RecordFormat`.
+// Input is read line by line; a record may span several lines, e.g. a CSV
+// field with a newline in it.
+pub trait RecordParser {
+    // Whether the first record only names the columns.
+    fn has_header(&self) -> bool;
+
+    // Column names carried by the input itself, read from its first record:
+    // the CSV header or the keys of the first NDJSON object.
+    fn columns(&self, first: &str) -> Result<Option<Vec<String>>, String>;
+
+    // Whether `text`, lines joined with `\n`, holds a whole record.
+    fn is_complete(&self, _text: &str) -> bool {
+        true
+    }
+
+    // One value per column, converted to the column type.
+    fn parse_record(&self, columns: &[ImportColumn], text: &str) -> Result<Vec<CqlValue>, String>;
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ImportColumn {
+    pub name: String,
+    pub column_type: ColumnType,
+}
+
+#[derive(Clone, Default)]
+pub struct ImportParams {
+    // Columns the records are written to, in record order. Taken from the
+    // input when not given; required for CSV without a header.
+    pub columns: Option<Vec<String>>,
+    pub ingest: IngestParams,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    LodeGone,
+    ShuttingDown,
+    Schema(cassandra_cpp::Error),
+    Prepare(cassandra_cpp::Error),
+    Io(io::Error),
+    // Neither `ImportParams::columns` nor the input names the columns.
+    NoColumns,
+    UnknownColumn(String),
+    // The record starting at `line` (1-based); rows before it stay written.
+    Parse { line: usize, reason: String, },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::LodeGone =>
+                write!(f, "session lode terminated"),
+            ImportError::ShuttingDown =>
+                write!(f, "executor is shutting down"),
+            ImportError::Schema(error) =>
+                write!(f, "error reading the table schema: {:?}", error),
+            ImportError::Prepare(error) =>
+                write!(f, "error preparing insert statement: {:?}", error),
+            ImportError::Io(error) =>
+                write!(f, "error reading import input: {}", error),
+            ImportError::NoColumns =>
+                write!(f, "import columns are not known, the input has no header"),
+            ImportError::UnknownColumn(column) =>
+                write!(f, "no column {:?} in the import table", column),
+            ImportError::Parse { line, reason, } =>
+                write!(f, "invalid record at line {}: {}", line, reason),
+        }
+    }
+}
+
+impl From<IngestError<ImportError>> for ImportError {
+    fn from(error: IngestError<ImportError>) -> ImportError {
+        match error {
+            IngestError::LodeGone =>
+                ImportError::LodeGone,
+            IngestError::ShuttingDown =>
+                ImportError::ShuttingDown,
+            IngestError::Prepare(error) =>
+                ImportError::Prepare(error),
+            IngestError::Source(error) =>
+                error,
+        }
+    }
+}
+
+// Reads records from `reader` with `parser` and writes them to
+// `keyspace.table` through `ingest`, converting every value to its column
+// type from the table schema first. A record that does not parse stops the
+// import; one the server rejects is counted in the report like with
+// `ingest`.
+pub fn import<P, R>(
+    resource: LodeResource<SharedSession>,
+    keyspace: &str,
+    table: &str,
+    parser: P,
+    reader: R,
+    params: ImportParams,
+)
+    -> impl Future<Item = IngestReport, Error = ImportError>
+where P: RecordParser + Send + Sync + 'static,
+      R: AsyncRead + Send + 'static,
+{
+    let keyspace = keyspace.to_string();
+    let table = table.to_string();
+    let parser = Arc::new(parser);
+    let records = Records {
+        lines: tokio::io::lines(io::BufReader::new(reader)),
+        parser: parser.clone(),
+        line: 0,
+        start: 0,
+        pending: String::new(),
+    };
+    resource.clone()
+        .using_resource_loop((), |session, ()| -> Result<_, ErrorSeverity<(), ()>> {
+            Ok((UsingResource::Lost, Loop::Break(session)))
+        })
+        .map_err(|_error| if spawner::executor_shutting_down() {
+            ImportError::ShuttingDown
+        } else {
+            ImportError::LodeGone
+        })
+        .and_then({
+            let keyspace = keyspace.clone();
+            let table = table.clone();
+            move |session| session.table_schema(&keyspace, &table).map_err(ImportError::Schema)
+        })
+        .and_then(|schema| {
+            records.into_future()
+                .map_err(|(error, _records)| error)
+                .map(move |(first, records)| (schema, first, records))
+        })
+        .and_then(move |(schema, first, records)| {
+            let (first_line, first) = match first {
+                Some(first) =>
+                    first,
+                None =>
+                    return Either::A(result(Ok(IngestReport::default()))),
+            };
+            let names = match params.columns {
+                Some(columns) =>
+                    columns,
+                None =>
+                    match parser.columns(&first) {
+                        Ok(Some(columns)) =>
+                            columns,
+                        Ok(None) =>
+                            return Either::A(result(Err(ImportError::NoColumns))),
+                        Err(reason) =>
+                            return Either::A(result(Err(ImportError::Parse { line: first_line, reason, }))),
+                    },
+            };
+            let columns = match import_columns(&schema, names) {
+                Ok(columns) =>
+                    columns,
+                Err(error) =>
+                    return Either::A(result(Err(error))),
+            };
+            let query = insert_query(&keyspace, &table, &columns);
+            debug!("importing into {}.{} with: {}", keyspace, table, query);
+            let records: Box<dyn Stream<Item = (usize, String), Error = ImportError> + Send> = if parser.has_header() {
+                Box::new(records)
+            } else {
+                Box::new(futures::stream::once(Ok((first_line, first))).chain(records))
+            };
+            let rows = records
+                .and_then(move |(line, text)| {
+                    parser.parse_record(&columns, &text)
+                        .map_err(|reason| ImportError::Parse { line, reason, })
+                });
+            Either::B(ingest(resource, &query, rows, params.ingest).map_err(ImportError::from))
+        })
+}
+
+fn import_columns(schema: &TableSchema, names: Vec<String>) -> Result<Vec<ImportColumn>, ImportError> {
+    names.into_iter()
+        .map(|name| match schema.column_type(&name) {
+            Some(column_type) =>
+                Ok(ImportColumn { column_type: column_type.clone(), name, }),
+            None =>
+                Err(ImportError::UnknownColumn(name)),
+        })
+        .collect()
+}
+
+fn insert_query(keyspace: &str, table: &str, columns: &[ImportColumn]) -> String {
+    let names: Vec<_> = columns.iter()
+        .map(|column| cql::quote_identifier(&column.name))
+        .collect();
+    let markers: Vec<_> = columns.iter()
+        .map(|_column| "?")
+        .collect();
+    format!(
+        "INSERT INTO {}.{} ({}) VALUES ({})",
+        cql::quote_identifier(keyspace),
+        cql::quote_identifier(table),
+        names.join(", "),
+        markers.join(", "),
+    )
+}
+
+// Joins input lines into whole records, skipping blank lines between them.
+// Yields each record with the line it starts at.
+struct Records<S, P> {
+    lines: S,
+    parser: Arc<P>,
+    line: usize,
+    start: usize,
+    pending: String,
+}
+
+impl<S, P> Stream for Records<S, P> where S: Stream<Item = String, Error = io::Error>, P: RecordParser {
+    type Item = (usize, String);
+    type Error = ImportError;
+
+    fn poll(&mut self) -> Poll<Option<(usize, String)>, ImportError> {
+        loop {
+            match self.lines.poll().map_err(ImportError::Io)? {
+                Async::Ready(Some(line)) => {
+                    self.line += 1;
+                    if self.pending.is_empty() {
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        self.start = self.line;
+                    } else {
+                        self.pending.push('\n');
+                    }
+                    self.pending.push_str(&line);
+                    if self.parser.is_complete(&self.pending) {
+                        return Ok(Async::Ready(Some((self.start, mem::take(&mut self.pending)))));
+                    }
+                },
+                Async::Ready(None) if !self.pending.is_empty() =>
+                    return Err(ImportError::Parse { line: self.start, reason: "record is not terminated".to_string(), }),
+                Async::Ready(None) =>
+                    return Ok(Async::Ready(None)),
+                Async::NotReady =>
+                    return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+impl RecordParser for Csv {
+    fn has_header(&self) -> bool {
+        self.header
+    }
+
+    fn columns(&self, first: &str) -> Result<Option<Vec<String>>, String> {
+        if !self.header {
+            return Ok(None);
+        }
+        let fields = self.split(first)?;
+        Ok(Some(fields.into_iter().map(|(field, _quoted)| field).collect()))
+    }
+
+    // Quotes inside a field are doubled, so a record is complete once the
+    // quotes balance.
+    fn is_complete(&self, text: &str) -> bool {
+        !text.chars().fold(false, |in_quotes, ch| in_quotes != (ch == '"'))
+    }
+
+    fn parse_record(&self, columns: &[ImportColumn], text: &str) -> Result<Vec<CqlValue>, String> {
+        let fields = self.split(text)?;
+        if fields.len() != columns.len() {
+            return Err(format!("{} fields for {} columns", fields.len(), columns.len()));
+        }
+        columns.iter()
+            .zip(fields)
+            .map(|(column, (field, quoted))| {
+                if field.is_empty() && !quoted {
+                    Ok(CqlValue::Null)
+                } else {
+                    from_text(&column.column_type, &field)
+                }
+                    .map_err(|reason| format!("column {:?}: {}", column.name, reason))
+            })
+            .collect()
+    }
+}
+
+impl Csv {
+    // Fields of a record, each with whether it was quoted.
+    fn split(&self, text: &str) -> Result<Vec<(String, bool)>, String> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '"' if field.is_empty() && !quoted => {
+                    quoted = true;
+                    loop {
+                        match chars.next() {
+                            None =>
+                                return Err("unterminated quoted field".to_string()),
+                            Some('"') if chars.peek() == Some(&'"') => {
+                                chars.next();
+                                field.push('"');
+                            },
+                            Some('"') =>
+                                break,
+                            Some(ch) =>
+                                field.push(ch),
+                        }
+                    }
+                    match chars.peek() {
+                        None =>
+                            (),
+                        Some(&next) if next == self.delimiter =>
+                            (),
+                        Some(next) =>
+                            return Err(format!("unexpected {:?} after a quoted field", next)),
+                    }
+                },
+                ch if ch == self.delimiter =>
+                    fields.push((mem::take(&mut field), mem::replace(&mut quoted, false))),
+                ch =>
+                    field.push(ch),
+            }
+        }
+        fields.push((field, quoted));
+        Ok(fields)
+    }
+}
+
+impl RecordParser for NdJson {
+    fn has_header(&self) -> bool {
+        false
+    }
+
+    // `export` writes every column, nulls included, so the first object
+    // names them all; give `ImportParams::columns` for sparser input.
+    fn columns(&self, first: &str) -> Result<Option<Vec<String>>, String> {
+        match parse_json(first)? {
+            Json::Object(fields) =>
+                Ok(Some(fields.into_iter().map(|(name, _value)| name).collect())),
+            _ =>
+                Err("expected a JSON object".to_string()),
+        }
+    }
+
+    // A field missing from the object is written as null.
+    fn parse_record(&self, columns: &[ImportColumn], text: &str) -> Result<Vec<CqlValue>, String> {
+        let mut fields = match parse_json(text)? {
+            Json::Object(fields) =>
+                fields,
+            _ =>
+                return Err("expected a JSON object".to_string()),
+        };
+        let values = columns.iter()
+            .map(|column| {
+                match fields.iter().position(|(name, _value)| name == &column.name) {
+                    Some(position) =>
+                        from_json(&column.column_type, fields.remove(position).1)
+                            .map_err(|reason| format!("column {:?}: {}", column.name, reason)),
+                    None =>
+                        Ok(CqlValue::Null),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match fields.first() {
+            Some((name, _value)) =>
+                Err(format!("field {:?} is not an import column", name)),
+            None =>
+                Ok(values),
+        }
+    }
+}
+
+// Reads a value as `export` writes it: collections and user types as JSON,
+// blobs as `0x` hex and timestamps as milliseconds since the epoch.
+fn from_text(column_type: &ColumnType, text: &str) -> Result<CqlValue, String> {
+    match column_type {
+        ColumnType::Ascii | ColumnType::Text =>
+            column_type.coerce(CqlValue::Text(text.to_string())),
+        ColumnType::Boolean =>
+            match text.to_lowercase().as_str() {
+                "true" => Ok(CqlValue::Bool(true)),
+                "false" => Ok(CqlValue::Bool(false)),
+                _ => Err(format!("{:?} is not a boolean", text)),
+            },
+        ColumnType::TinyInt | ColumnType::SmallInt | ColumnType::Int | ColumnType::BigInt |
+        ColumnType::Counter | ColumnType::Timestamp =>
+            text.trim().parse()
+                .map_err(|_error| format!("{:?} is not an integer", text))
+                .and_then(|number| column_type.coerce(CqlValue::BigInt(number))),
+        ColumnType::Float =>
+            text.trim().parse()
+                .map(CqlValue::Float)
+                .map_err(|_error| format!("{:?} is not a number", text)),
+        ColumnType::Double =>
+            text.trim().parse()
+                .map(CqlValue::Double)
+                .map_err(|_error| format!("{:?} is not a number", text)),
+        ColumnType::Blob =>
+            from_hex(text).map(CqlValue::Blob),
+        ColumnType::Uuid | ColumnType::Timeuuid =>
+            Uuid::from_str(text.trim())
+                .map(CqlValue::Uuid)
+                .map_err(|_error| format!("{:?} is not a uuid", text)),
+        ColumnType::List(..) | ColumnType::Set(..) | ColumnType::Map(..) | ColumnType::Other(..) =>
+            from_json(column_type, parse_json(text)?),
+        ColumnType::Varint | ColumnType::Decimal | ColumnType::Date | ColumnType::Time | ColumnType::Inet =>
+            Err(format!("importing {} values is not supported", column_type)),
+    }
+}
+
+fn from_json(column_type: &ColumnType, json: Json) -> Result<CqlValue, String> {
+    match (column_type, json) {
+        (_, Json::Null) =>
+            Ok(CqlValue::Null),
+        (ColumnType::Other(..), json) =>
+            Ok(untyped(json)),
+        (ColumnType::List(item_type), Json::Array(items)) =>
+            from_json_items(item_type, items).map(CqlValue::List),
+        (ColumnType::Set(item_type), Json::Array(items)) =>
+            from_json_items(item_type, items).map(CqlValue::Set),
+        (ColumnType::Map(key_type, value_type), Json::Object(entries)) =>
+            entries.into_iter()
+                .map(|(key, value)| Ok((from_text(key_type, &key)?, from_json(value_type, value)?)))
+                .collect::<Result<_, String>>()
+                .map(CqlValue::Map),
+        (ColumnType::Boolean, Json::Bool(flag)) =>
+            Ok(CqlValue::Bool(flag)),
+        (ColumnType::List(..), _) | (ColumnType::Set(..), _) =>
+            Err("expected a JSON array".to_string()),
+        (ColumnType::Map(..), _) =>
+            Err("expected a JSON object".to_string()),
+        (column_type, Json::String(text)) | (column_type, Json::Number(text)) =>
+            from_text(column_type, &text),
+        (column_type, _) =>
+            Err(format!("expected a {} value", column_type)),
+    }
+}
+
+fn from_json_items(item_type: &ColumnType, items: Vec<Json>) -> Result<Vec<CqlValue>, String> {
+    items.into_iter()
+        .map(|item| from_json(item_type, item))
+        .collect()
+}
+
+// For values whose type the schema does not spell out, e.g. user types.
+fn untyped(json: Json) -> CqlValue {
+    match json {
+        Json::Null =>
+            CqlValue::Null,
+        Json::Bool(flag) =>
+            CqlValue::Bool(flag),
+        Json::Number(number) =>
+            match number.parse() {
+                Ok(integer) => CqlValue::BigInt(integer),
+                Err(_error) => CqlValue::Double(number.parse().unwrap_or(f64::NAN)),
+            },
+        Json::String(text) =>
+            CqlValue::Text(text),
+        Json::Array(items) =>
+            CqlValue::List(items.into_iter().map(untyped).collect()),
+        Json::Object(fields) =>
+            CqlValue::Udt(fields.into_iter().map(|(name, value)| (name, untyped(value))).collect()),
+    }
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits = text.trim();
+    let digits = if digits.starts_with("0x") || digits.starts_with("0X") { &digits[2 ..] } else { digits };
+    if digits.len() % 2 != 0 {
+        return Err(format!("{:?} has an odd number of hex digits", text));
+    }
+    (0 .. digits.len())
+        .step_by(2)
+        .map(|offset| {
+            u8::from_str_radix(digits.get(offset .. offset + 2).unwrap_or(""), 16)
+                .map_err(|_error| format!("{:?} is not hex", text))
+        })
+        .collect()
+}
+
+// Just enough JSON for what `export` writes; numbers are kept as text so
+// they convert to the column type without going through `f64`.
+#[derive(Clone, PartialEq, Debug)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+fn parse_json(text: &str) -> Result<Json, String> {
+    let mut chars = text.chars().peekable();
+    let json = parse_json_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None =>
+            Ok(json),
+        Some(ch) =>
+            Err(format!("unexpected {:?} after the JSON value", ch)),
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while matches!(chars.peek(), Some(ch) if ch.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn parse_json_value(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    skip_whitespace(chars);
+    match chars.peek().cloned() {
+        None =>
+            Err("unexpected end of JSON".to_string()),
+        Some('"') =>
+            parse_json_string(chars).map(Json::String),
+        Some('[') => {
+            chars.next();
+            let mut items = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Json::Array(items));
+            }
+            loop {
+                items.push(parse_json_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => (),
+                    Some(']') => return Ok(Json::Array(items)),
+                    other => return Err(format!("expected `,` or `]`, found {:?}", other)),
+                }
+            }
+        },
+        Some('{') => {
+            chars.next();
+            let mut fields = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Json::Object(fields));
+            }
+            loop {
+                skip_whitespace(chars);
+                let name = parse_json_string(chars)?;
+                skip_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return Err(format!("expected `:` after {:?}", name));
+                }
+                fields.push((name, parse_json_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => (),
+                    Some('}') => return Ok(Json::Object(fields)),
+                    other => return Err(format!("expected `,` or `}}`, found {:?}", other)),
+                }
+            }
+        },
+        Some(ch) if ch == '-' || ch.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(&ch) = chars.peek() {
+                if !(ch.is_ascii_digit() || ch == '-' || ch == '+' || ch == '.' || ch == 'e' || ch == 'E') {
+                    break;
+                }
+                number.push(ch);
+                chars.next();
+            }
+            Ok(Json::Number(number))
+        },
+        Some(_) => {
+            let mut word = String::new();
+            while let Some(&ch) = chars.peek() {
+                if !ch.is_ascii_alphabetic() {
+                    break;
+                }
+                word.push(ch);
+                chars.next();
+            }
+            match word.as_str() {
+                "null" => Ok(Json::Null),
+                "true" => Ok(Json::Bool(true)),
+                "false" => Ok(Json::Bool(false)),
+                _ => Err(format!("unexpected {:?} in JSON", word)),
+            }
+        },
+    }
+}
+
+fn parse_json_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    if chars.next() != Some('"') {
+        return Err("expected a JSON string".to_string());
+    }
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            None =>
+                return Err("unterminated JSON string".to_string()),
+            Some('"') =>
+                return Ok(text),
+            Some('\\') =>
+                match chars.next() {
+                    Some('n') => text.push('\n'),
+                    Some('r') => text.push('\r'),
+                    Some('t') => text.push('\t'),
+                    Some('b') => text.push('\u{8}'),
+                    Some('f') => text.push('\u{c}'),
+                    Some('u') => {
+                        let high = parse_json_hex(chars)?;
+                        let code = if (0xd800 .. 0xdc00).contains(&high) {
+                            if chars.next() != Some('\\') || chars.next() != Some('u') {
+                                return Err("unpaired surrogate in JSON string".to_string());
+                            }
+                            let low = parse_json_hex(chars)?;
+                            0x10000 + ((high - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff)
+                        } else {
+                            high
+                        };
+                        text.push(std::char::from_u32(code).ok_or("invalid \\u escape in JSON string")?);
+                    },
+                    Some(ch) => text.push(ch),
+                    None => return Err("unterminated JSON string".to_string()),
+                },
+            Some(ch) =>
+                text.push(ch),
+        }
+    }
+}
+
+fn parse_json_hex(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+    let digits: String = chars.take(4).collect();
+    u32::from_str_radix(&digits, 16)
+        .map_err(|_error| format!("invalid \\u escape {:?} in JSON string", digits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, cql_type: &str) -> ImportColumn {
+        ImportColumn { name: name.to_string(), column_type: ColumnType::parse(cql_type), }
+    }
+
+    #[test]
+    fn csv_fields_and_quotes() {
+        let csv = Csv::default();
+        assert_eq!(csv.columns("id,\"full name\"").unwrap(), Some(vec!["id".to_string(), "full name".to_string()]));
+        assert!(!csv.is_complete("1,\"two"));
+        assert!(csv.is_complete("1,\"two\nlines\"\"\""));
+        let columns = [column("k", "int"), column("v", "text"), column("n", "text")];
+        assert_eq!(
+            csv.parse_record(&columns, "1,\"a,\"\"b\"\"\nc\",").unwrap(),
+            vec![CqlValue::Int(1), CqlValue::Text("a,\"b\"\nc".to_string()), CqlValue::Null],
+        );
+        assert_eq!(csv.parse_record(&columns, "1,\"\",").unwrap()[1], CqlValue::Text(String::new()));
+        assert!(csv.parse_record(&columns, "1,2").is_err());
+        assert!(csv.parse_record(&columns, "x,2,3").is_err());
+        assert!(csv.parse_record(&columns, "1,\"a\"b,3").is_err());
+        assert_eq!(Csv { header: false, ..Csv::default() }.columns("1,2").unwrap(), None);
+    }
+
+    #[test]
+    fn text_reads_what_export_writes() {
+        assert_eq!(from_text(&ColumnType::TinyInt, "-5"), Ok(CqlValue::TinyInt(-5)));
+        assert!(from_text(&ColumnType::TinyInt, "300").is_err());
+        assert_eq!(from_text(&ColumnType::Timestamp, "1500000000000"), Ok(CqlValue::BigInt(1_500_000_000_000)));
+        assert_eq!(from_text(&ColumnType::Boolean, "TRUE"), Ok(CqlValue::Bool(true)));
+        assert_eq!(from_text(&ColumnType::Double, "2.5"), Ok(CqlValue::Double(2.5)));
+        assert_eq!(from_text(&ColumnType::Blob, "0x00ff"), Ok(CqlValue::Blob(vec![0x00, 0xff])));
+        assert!(from_text(&ColumnType::Blob, "0x0").is_err());
+        assert!(from_text(&ColumnType::Ascii, "é").is_err());
+        assert!(from_text(&ColumnType::Decimal, "1.5").is_err());
+        assert_eq!(
+            from_text(&ColumnType::parse("list<int>"), "[1,2]"),
+            Ok(CqlValue::List(vec![CqlValue::Int(1), CqlValue::Int(2)])),
+        );
+        assert_eq!(
+            from_text(&ColumnType::parse("map<int, text>"), "{\"1\":\"a\"}"),
+            Ok(CqlValue::Map(vec![(CqlValue::Int(1), CqlValue::Text("a".to_string()))])),
+        );
+        assert_eq!(
+            from_text(&ColumnType::parse("frozen<address>"), "{\"city\":\"x\",\"zip\":7}"),
+            Ok(CqlValue::Udt(vec![("city".to_string(), CqlValue::Text("x".to_string())), ("zip".to_string(), CqlValue::BigInt(7))])),
+        );
+    }
+
+    #[test]
+    fn json_values() {
+        assert_eq!(
+            parse_json(" {\"a\": [1, -2.5e3, null], \"b\": {}, \"c\": \"q\\\"\\u00e9\\ud83d\\ude00\"} "),
+            Ok(Json::Object(vec![
+                ("a".to_string(), Json::Array(vec![Json::Number("1".to_string()), Json::Number("-2.5e3".to_string()), Json::Null])),
+                ("b".to_string(), Json::Object(vec![])),
+                ("c".to_string(), Json::String("q\"é\u{1f600}".to_string())),
+            ])),
+        );
+        assert!(parse_json("[1,]").is_err());
+        assert!(parse_json("{\"a\" 1}").is_err());
+        assert!(parse_json("\"open").is_err());
+        assert!(parse_json("1 2").is_err());
+    }
+
+    #[test]
+    fn ndjson_records() {
+        let columns = [column("k", "int"), column("tags", "set<text>"), column("v", "text")];
+        assert_eq!(
+            NdJson.columns("{\"k\":1,\"tags\":[]}").unwrap(),
+            Some(vec!["k".to_string(), "tags".to_string()]),
+        );
+        assert_eq!(
+            NdJson.parse_record(&columns, "{\"tags\":[\"x\"],\"k\":1}").unwrap(),
+            vec![CqlValue::Int(1), CqlValue::Set(vec![CqlValue::Text("x".to_string())]), CqlValue::Null],
+        );
+        assert!(NdJson.parse_record(&columns, "{\"k\":1,\"other\":2}").is_err());
+        assert!(NdJson.parse_record(&columns, "{\"k\":\"one\"}").is_err());
+        assert!(NdJson.parse_record(&columns, "[1]").is_err());
+    }
+
+    #[test]
+    fn insert_query_quotes_names() {
+        let columns = [column("id", "int"), column("Name", "text")];
+        assert_eq!(insert_query("app", "users", &columns), "INSERT INTO app.users (id, \"Name\") VALUES (?, ?)");
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    fmt,
+    sync::Arc,
+};
+
+use futures::{
+    Future,
+    Stream,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use ero::{
+    Loop,
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+};
+
+use log::{
+    debug,
+    warn,
+};
+
+use super::{
+    SharedSession,
+    spawner,
+    bind::{
+        ToParams,
+        BindParams,
+    },
+};
+
+pub type IngestProgressHook = Arc<dyn Fn(&IngestReport) + Send + Sync>;
+
+#[derive(Clone)]
+pub struct IngestParams {
+    pub concurrency: usize,
+    // How many error messages the report keeps; every failure is counted.
+    pub max_error_samples: usize,
+    // `progress` is called after every this many rows.
+    pub progress_interval: usize,
+    pub progress: Option<IngestProgressHook>,
+}
+
+impl Default for IngestParams {
+    fn default() -> IngestParams {
+        IngestParams {
+            concurrency: 16,
+            max_error_samples: 10,
+            progress_interval: 10_000,
+            progress: None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct IngestReport {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub error_samples: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum IngestError<E> {
+    LodeGone,
+    ShuttingDown,
+    Prepare(cassandra_cpp::Error),
+    // The source stream failed; rows already sent stay written.
+    Source(E),
+}
+
+impl<E> fmt::Display for IngestError<E> where E: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IngestError::LodeGone =>
+                write!(f, "session lode terminated"),
+            IngestError::ShuttingDown =>
+                write!(f, "executor is shutting down"),
+            IngestError::Prepare(error) =>
+                write!(f, "error preparing insert statement: {:?}", error),
+            IngestError::Source(error) =>
+                write!(f, "ingest source failed: {:?}", error),
+        }
+    }
+}
+
+// Writes every item of `rows` with `query` (usually an INSERT) prepared once
+// and bound through `ToParams`, keeping up to `concurrency` writes in
+// flight. Failed rows are counted and do not stop the load.
+pub fn ingest<S>(
+    resource: LodeResource<SharedSession>,
+    query: &str,
+    rows: S,
+    params: IngestParams,
+)
+    -> impl Future<Item = IngestReport, Error = IngestError<S::Error>>
+where S: Stream + Send + 'static,
+      S::Item: ToParams + Send + 'static,
+      S::Error: Send + 'static,
+{
+    let query = query.to_string();
+    resource
+        .using_resource_loop((), |session, ()| -> Result<_, ErrorSeverity<(), ()>> {
+            Ok((UsingResource::Lost, Loop::Break(session)))
+        })
+        .map_err(|_error| if spawner::executor_shutting_down() {
+            IngestError::ShuttingDown
+        } else {
+            IngestError::LodeGone
+        })
+        .and_then(move |session| {
+            debug!("preparing ingest statement: {}", query);
+            match session.session.prepare(&session.rewrite_query(&query)) {
+                Ok(prepare_future) =>
+                    Either::A(prepare_future
+                        .map_err(IngestError::Prepare)
+                        .map(move |prepared| (session, Arc::new(prepared)))),
+                Err(error) =>
+                    Either::B(result(Err(IngestError::Prepare(error)))),
+            }
+        })
+        .and_then(move |(session, prepared)| {
+            let concurrency = params.concurrency.max(1);
+            rows
+                .map_err(IngestError::Source)
+                .map(move |row| {
+                    let mut statement = prepared.bind();
+                    session.apply_statement_defaults(&mut statement);
+                    match statement.bind_params(&row).map(|_statement| ()) {
+                        Ok(()) => {
+                            let future = session.execute_guarded(statement)
+                                .then(|execute_result| Ok(execute_result.map(|_cass_result| ())));
+                            Either::A(future)
+                        },
+                        Err(error) =>
+                            Either::B(result(Ok(Err(error)))),
+                    }
+                })
+                .buffer_unordered(concurrency)
+                .fold(IngestReport::default(), move |mut report, execute_result| -> Result<_, IngestError<S::Error>> {
+                    match execute_result {
+                        Ok(()) =>
+                            report.succeeded += 1,
+                        Err(error) => {
+                            warn!("ingest row failed: {:?}", error);
+                            report.failed += 1;
+                            if report.error_samples.len() < params.max_error_samples {
+                                report.error_samples.push(format!("{:?}", error));
+                            }
+                        },
+                    }
+                    if let Some(ref progress) = params.progress {
+                        if params.progress_interval > 0 && (report.succeeded + report.failed) % params.progress_interval == 0 {
+                            progress(&report);
+                        }
+                    }
+                    Ok(report)
+                })
+        })
+}
@@ -0,0 +1,105 @@
+use std::{
+    sync::{
+        Weak,
+        atomic::Ordering,
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use futures::{
+    Future,
+    Stream,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use tokio::timer::Interval;
+
+use cassandra_cpp::{
+    Session,
+    Statement,
+};
+
+use log::{
+    debug,
+    warn,
+    error,
+};
+
+use super::{
+    Shared,
+    VALIDATION_QUERY,
+    spawner::{
+        self,
+        TaskSpawner,
+    },
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeepaliveParams {
+    pub interval: Duration,
+    // Consecutive probe failures before the lode is asked to reconnect.
+    pub max_failures: usize,
+}
+
+impl Default for KeepaliveParams {
+    fn default() -> KeepaliveParams {
+        KeepaliveParams {
+            interval: Duration::from_secs(30),
+            max_failures: 3,
+        }
+    }
+}
+
+// Probes until the session is closed or a reconnect has been requested.
+pub(crate) fn spawn(params: KeepaliveParams, session: Weak<Session>, shared: Weak<Shared>, task_spawner: Option<&TaskSpawner>) {
+    let future = Interval::new(Instant::now() + params.interval, params.interval)
+        .map_err(|error| error!("keepalive timer error: {:?}", error))
+        .fold(0, move |failures, _instant| {
+            let session = match session.upgrade() {
+                Some(session) =>
+                    session,
+                None =>
+                    return Either::A(result(Err(()))),
+            };
+            let statement = Statement::new(VALIDATION_QUERY, 0);
+            let shared = shared.clone();
+            let future = session.execute(&statement)
+                .then(move |probe_result| {
+                    match probe_result {
+                        Ok(_cass_result) => {
+                            if let Some(shared) = shared.upgrade() {
+                                shared.lode.succeeded();
+                            }
+                            Ok(0)
+                        },
+                        Err(error) => {
+                            let failures = failures + 1;
+                            warn!("keepalive probe failed ({} of {}): {:?}", failures, params.max_failures, error);
+                            if failures < params.max_failures {
+                                Ok(failures)
+                            } else {
+                                if let Some(shared) = shared.upgrade() {
+                                    warn!("session looks dead, requesting reconnect for lode {:?}", shared.lode_name);
+                                    shared.reconnect_requested.store(true, Ordering::SeqCst);
+                                }
+                                Err(())
+                            }
+                        },
+                    }
+                });
+            Either::B(future)
+        })
+        .then(|_| {
+            debug!("keepalive task finished");
+            Ok(())
+        });
+    if let Err(error) = spawner::spawn(task_spawner, Box::new(future)) {
+        error!("error spawning keepalive task: {:?}", error);
+    }
+}
@@ -0,0 +1,79 @@
+use super::cql::{
+    quote_literal,
+    quote_identifier,
+};
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(default, deny_unknown_fields))]
+pub struct KeyspaceSpec {
+    pub replication: Replication,
+    pub durable_writes: bool,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(tag = "class", rename_all = "snake_case"))]
+pub enum Replication {
+    Simple { replication_factor: usize, },
+    NetworkTopology { datacenters: Vec<(String, usize)>, },
+}
+
+impl Default for KeyspaceSpec {
+    fn default() -> KeyspaceSpec {
+        KeyspaceSpec {
+            replication: Replication::Simple { replication_factor: 1, },
+            durable_writes: true,
+        }
+    }
+}
+
+impl KeyspaceSpec {
+    pub fn create_query(&self, keyspace: &str) -> String {
+        let replication = match self.replication {
+            Replication::Simple { replication_factor, } =>
+                format!("{{'class': 'SimpleStrategy', 'replication_factor': {}}}", replication_factor),
+            Replication::NetworkTopology { ref datacenters, } => {
+                let mut replication = "{'class': 'NetworkTopologyStrategy'".to_string();
+                for (datacenter, replication_factor) in datacenters {
+                    replication.push_str(&format!(", {}: {}", quote_literal(datacenter), replication_factor));
+                }
+                replication.push('}');
+                replication
+            },
+        };
+        format!(
+            "CREATE KEYSPACE IF NOT EXISTS {} WITH replication = {} AND durable_writes = {}",
+            quote_identifier(&stored_name(keyspace)),
+            replication,
+            self.durable_writes,
+        )
+    }
+}
+
+// The name a keyspace is stored under: a double quoted name is taken as
+// is, unquoted names are stored lowercased.
+pub(crate) fn stored_name(keyspace: &str) -> String {
+    if keyspace.starts_with('"') {
+        keyspace.trim_matches('"').replace("\"\"", "\"")
+    } else {
+        keyspace.to_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyspaceSpec, stored_name};
+
+    #[test]
+    fn quoted_names_are_not_quoted_twice() {
+        let spec = KeyspaceSpec::default();
+        assert_eq!(
+            spec.create_query("\"MyKeyspace\""),
+            "CREATE KEYSPACE IF NOT EXISTS \"MyKeyspace\" WITH replication = {'class': 'SimpleStrategy', 'replication_factor': 1} AND durable_writes = true",
+        );
+        assert!(spec.create_query("MyKeyspace").starts_with("CREATE KEYSPACE IF NOT EXISTS mykeyspace WITH"));
+        assert_eq!(stored_name("\"a\"\"b\""), "a\"b");
+    }
+}
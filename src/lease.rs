@@ -0,0 +1,166 @@
+use std::{
+    sync::{
+        Arc,
+        Weak,
+        Mutex,
+        atomic::{
+            Ordering,
+            AtomicBool,
+            AtomicUsize,
+        },
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use futures::{
+    Future,
+    Stream,
+};
+
+use tokio::timer::Interval;
+
+use cassandra_cpp::Session;
+
+use log::{
+    debug,
+    warn,
+    error,
+};
+
+use super::{
+    Shared,
+    spawner::{
+        self,
+        TaskSpawner,
+    },
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LeaseParams {
+    // A session still held after this long, neither handed back to the
+    // lode nor dropped, is reported as a likely leak.
+    pub duration: Duration,
+    pub check_interval: Duration,
+    // `spawn_unique` only: an expired session is revoked. The driver can't
+    // take a session away from its holder, so it keeps working, but it is
+    // closed instead of reused once returned and `UniqueSession::revoked`
+    // tells the holder to give it up.
+    pub force_release: bool,
+}
+
+impl Default for LeaseParams {
+    fn default() -> LeaseParams {
+        LeaseParams {
+            duration: Duration::from_secs(60),
+            check_interval: Duration::from_secs(5),
+            force_release: false,
+        }
+    }
+}
+
+pub(crate) struct Lease {
+    acquired_at: Instant,
+    unique: bool,
+    released: AtomicBool,
+    revoked: AtomicBool,
+}
+
+impl Lease {
+    pub(crate) fn release(&self) {
+        self.released.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_revoked(&self) -> bool {
+        self.revoked.load(Ordering::SeqCst)
+    }
+}
+
+// Leases of the sessions currently handed out. A lease ends when the
+// session comes back through `release` or when its last clone is dropped.
+#[derive(Default)]
+pub(crate) struct Leases {
+    active: Mutex<Vec<Weak<Lease>>>,
+    expired: AtomicUsize,
+}
+
+impl Leases {
+    pub(crate) fn start(&self, unique: bool) -> Arc<Lease> {
+        let lease = Arc::new(Lease {
+            acquired_at: Instant::now(),
+            unique,
+            released: AtomicBool::new(false),
+            revoked: AtomicBool::new(false),
+        });
+        self.active.lock().unwrap().push(Arc::downgrade(&lease));
+        lease
+    }
+
+    pub(crate) fn expired(&self) -> usize {
+        self.expired.load(Ordering::Relaxed)
+    }
+
+    // An expired lease is reported once and no longer tracked.
+    fn check(&self, params: &LeaseParams, lode_name: &str) {
+        let mut active = self.active.lock().unwrap();
+        active.retain(|lease| {
+            let lease = match lease.upgrade() {
+                Some(lease) =>
+                    lease,
+                None =>
+                    return false,
+            };
+            if lease.released.load(Ordering::SeqCst) {
+                return false;
+            }
+            let held = lease.acquired_at.elapsed();
+            if held < params.duration {
+                return true;
+            }
+            self.expired.fetch_add(1, Ordering::Relaxed);
+            if lease.unique && params.force_release {
+                lease.revoked.store(true, Ordering::SeqCst);
+                warn!(
+                    "session from lode {:?} held for {:?}, over its lease of {:?}: revoking it",
+                    lode_name,
+                    held,
+                    params.duration,
+                );
+            } else {
+                warn!(
+                    "session from lode {:?} held for {:?}, over its lease of {:?}: possible leak",
+                    lode_name,
+                    held,
+                    params.duration,
+                );
+            }
+            false
+        });
+    }
+}
+
+// Checks leases until the session is closed; the next connect starts a new
+// watch over the same leases.
+pub(crate) fn spawn(params: LeaseParams, session: Weak<Session>, shared: Weak<Shared>, task_spawner: Option<&TaskSpawner>) {
+    let future = Interval::new(Instant::now() + params.check_interval, params.check_interval)
+        .map_err(|error| error!("lease timer error: {:?}", error))
+        .for_each(move |_instant| {
+            match (session.upgrade(), shared.upgrade()) {
+                (Some(_session), Some(shared)) => {
+                    shared.leases.check(&params, &shared.lode_name);
+                    Ok(())
+                },
+                _ =>
+                    Err(()),
+            }
+        })
+        .then(|_| {
+            debug!("lease watch finished");
+            Ok(())
+        });
+    if let Err(error) = spawner::spawn(task_spawner, Box::new(future)) {
+        error!("error spawning lease watch: {:?}", error);
+    }
+}
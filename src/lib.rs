@@ -1,24 +1,59 @@
 use std::{
-    sync::Arc,
-    ops::Deref,
+    iter,
+    borrow::Cow,
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{
+            Ordering,
+            AtomicBool,
+        },
+    },
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    net::IpAddr,
+    ops::{
+        Deref,
+        Range,
+    },
+    time::{
+        Instant,
+        Duration,
+    },
 };
 
 use futures::{
     Future,
     future::{
-        lazy,
         result,
+        join_all,
+        loop_fn,
+        Loop,
         Either,
     },
 };
 
+use tokio::timer::{
+    Delay,
+    Timeout,
+};
+
 use cassandra_cpp::{
     Cluster,
     Session,
+    Statement,
+    Consistency,
+    PreparedStatement,
+    RetryPolicy as CassRetryPolicy,
+    TimestampGen as CassTimestampGen,
 };
 
 use log::{
+    info,
     debug,
+    warn,
     error,
 };
 
@@ -28,9 +63,308 @@ use ero::{
     supervisor::Supervisor,
 };
 
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+pub mod error;
+pub use error::{Error, ErrorHook, InitFailure, InitFailureHook};
+pub mod cql;
+pub use cql::{WriteOptions, Table};
+pub mod fragment;
+pub mod self_test;
+pub mod token;
+#[cfg(feature = "timeuuid")]
+pub mod timeuuid;
+pub mod migrations;
+pub mod maintenance;
+pub mod row;
+pub use row::{FromRow, FromValue, RowError};
+pub mod value;
+pub use value::CqlValue;
+pub mod udt;
+pub mod bind;
+pub use bind::{Blob, ToCqlValue, ToParams, BindParams};
+pub mod codec;
+pub use codec::{Codec, CodecRegistry};
+pub mod named;
+pub use named::{ToNamedParams, NamedBindError, bind_named};
+pub mod schema;
+pub use schema::{ColumnType, ColumnKind, ColumnInfo, TableSchema, ValidationError, BindError};
+pub mod export;
+pub use export::{RecordFormat, ExportError, ExportStats};
+#[cfg(feature = "avro")]
+pub mod avro;
+pub mod import;
+pub use import::{import, RecordParser, ImportColumn, ImportParams, ImportError};
+mod result_columns;
+pub use result_columns::{result_columns, ResultColumn, DescribedRows};
+
+mod schema_check;
+pub use schema_check::{SchemaExpectation, SchemaMismatch, SchemaCheckError};
+
+#[cfg(feature = "admin")]
+pub mod admin;
+
+#[cfg(feature = "async-await")]
+pub mod compat;
+
+#[cfg(feature = "json")]
+pub mod to_json;
+
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
+#[cfg(feature = "derive")]
+pub use ero_cassandra_derive::{FromRow, ToParams, ToNamedParams, Udt, cql};
+
+mod limit;
+pub use limit::{AdaptiveConcurrency, ResourceLimit};
+
+mod metrics;
+pub use metrics::{SessionMetrics, RequestMetrics, ConnectionMetrics, ErrorMetrics, LodeStats, LodeStatsHandle};
+
+mod health;
+pub use health::HealthReport;
+
+#[cfg(feature = "metrics-prometheus")]
+mod metrics_prometheus;
+#[cfg(feature = "metrics-prometheus")]
+pub use metrics_prometheus::PrometheusParams;
+
+mod usage;
+pub use usage::{UsageReport, StatementReport};
+
+#[cfg(feature = "hdr")]
+mod query_stats;
+#[cfg(feature = "hdr")]
+pub use query_stats::QueryStats;
+
+mod retry;
+pub use retry::{RetryConfig, RetryOn, RetryError, IdempotentStatement};
+
+mod retry_budget;
+pub use retry_budget::{RetryBudget, RetryBudgetParams, RetryBudgetStats};
+
+mod slow;
+pub use slow::{SlowQuery, SlowQueryParams, SlowQueryHook};
+
+mod fingerprint;
+pub use fingerprint::{fingerprint, StatementKey};
+
+mod audit_sink;
+pub use audit_sink::{AuditSink, AuditRecord, AuditedStatement, AuditOutcome};
+
+mod audit;
+pub use audit::{DowngradeEvent, DowngradeReport, DowngradeHook, ConsistencyLadder};
+
+mod frame;
+pub use frame::{FrameTooLarge, SizedStatement};
+
+mod prepared;
+pub use prepared::{PreparedRegistry, Queries};
+
+mod paging;
+pub use paging::{PageStream, PagingParams, QueryStreamError, ResumablePageStream, PagingState, InvalidPagingState};
+
+mod scan;
+pub use scan::{scan_table, ScanParams};
+
+mod ingest;
+pub use ingest::{ingest, IngestParams, IngestReport, IngestError, IngestProgressHook};
+
+mod batch;
+pub use batch::BatchStatement;
+
+mod counter;
+pub use counter::{CounterUpdate, CounterError};
+
+mod script;
+pub use script::ScriptError;
+
+mod execute;
+
+mod saturation;
+pub use saturation::{Saturation, SaturationLevel};
+
+mod cache;
+
+mod unique;
+pub use unique::{spawn_unique, UniqueSession};
+
+mod mock;
+pub use mock::{CassSession, CassFuture, Rows, MockSession};
+
+mod rate_limit;
+pub use rate_limit::RateLimit;
+
+mod breaker;
+pub use breaker::CircuitBreakerParams;
+
+mod backoff;
+pub use backoff::{InitBackoff, BackoffCurve};
+
+mod timeout;
+pub use timeout::TimeoutError;
+
+mod deadline;
+pub use deadline::{Deadline, DeadlineExceeded};
+
+mod statement_options;
+pub use statement_options::StatementOptions;
+
+#[cfg(all(unix, feature = "signals"))]
+mod shutdown;
+
+mod blob;
+pub use blob::BlobTable;
+
+mod batch_writer;
+pub use batch_writer::{BatchWriter, BatchWriterParams, BatchWriterStats, BatchWriterError};
+
+mod spill;
+pub use spill::{SpillQueue, SpillQueueParams, SpillQueueStats, SpillQueueError};
+
+mod lwt;
+pub use lwt::LwtResult;
+
+mod consistency;
+pub use consistency::{ConsistencyOverride, ConsistencyLevel, UnknownConsistency, FallbackRead};
+
+mod detached;
+pub use detached::{DetachedStats, DetachedError};
+
+mod rewrite;
+pub use rewrite::KeyspaceRewrite;
+
+mod trace;
+
+mod spawner;
+pub use spawner::{task_spawner, Task, TaskSpawner, SpawnError};
+
+mod keyspace;
+pub use keyspace::{KeyspaceSpec, Replication};
+
+mod throttle;
+pub use throttle::{ConnectThrottle, ConnectPermit};
+
+mod failover;
+pub use failover::{spawn_failover, FailoverParams, FailoverError, ActiveCluster, spawn_weighted, WeightedParams, WeightedLode};
+
+mod registry;
+pub use registry::KeyspaceRegistry;
+
+mod using;
+pub use using::{UsingSession, UsingSessionError, UsingSessionFuture};
+
+mod ready;
+pub use ready::{WaitConnected, WaitConnectedError, WaitConnectedFuture};
+
+mod pool;
+pub use pool::{spawn_pool, PooledLode, PoolError, HedgeParams, HedgeStats, HedgeError};
+
+mod fair_queue;
+pub use fair_queue::{FairQueue, FairQueueParams, FairQueueStats, FairQueueError, QueuePolicy};
+
+mod mirror;
+pub use mirror::{MirroredSession, MirrorStats, MirrorError};
+
+mod keepalive;
+pub use keepalive::KeepaliveParams;
+
+mod lease;
+pub use lease::LeaseParams;
+
+mod warmup;
+pub use warmup::{WarmupParams, WarmupFailure, WarmupError};
+
+mod server_info;
+pub use server_info::ServerInfo;
+
+mod cluster_info;
+pub use cluster_info::{ClusterInfo, NodeInfo};
+
+mod events;
+pub use events::{spawn_link_with_events, ClusterEvent, ClusterEvents};
+
+mod driver_log;
+pub use driver_log::{install_driver_logging, set_driver_log_level, set_server_warning_hook, ServerWarningHook};
+
+mod query_trace;
+pub use query_trace::{QueryTrace, TraceEvent, TracedResult};
+
+mod env;
+pub use env::EnvError;
+
+mod profile;
+pub use profile::{ProfileParams, ProfilePreset, LoadBalancing, ProfileError};
+
+mod auth;
+pub use auth::{auth_provider, AuthProvider, AuthFuture, AuthError};
+
+mod contact_point;
+pub use contact_point::{ContactPoint, InvalidContactPoint, DEFAULT_PORT};
+
+mod discovery;
+pub use discovery::{contact_points_discovery, ContactPointsDiscovery, DiscoveryFuture, DiscoveryError};
+
+mod astra;
+pub use astra::AstraBundle;
+
+mod backend;
+pub use backend::{spawn_backend_link, Backend, BackendFuture, BackendParams, BackendSession};
+#[cfg(any(feature = "backend-scylla", feature = "backend-cdrs-tokio"))]
+pub use backend::RuntimeGone;
+
+#[cfg(feature = "backend-scylla")]
+mod scylla_backend;
+#[cfg(feature = "backend-scylla")]
+pub use scylla_backend::{Scylla, ScyllaError};
+
+#[cfg(feature = "backend-cdrs-tokio")]
+mod cdrs_backend;
+#[cfg(feature = "backend-cdrs-tokio")]
+pub use cdrs_backend::{CdrsTokio, CdrsError, CdrsSession};
+
+#[cfg(feature = "serde")]
+mod config;
+#[cfg(feature = "serde")]
+pub use config::{
+    ClusterConfig,
+    ReconnectPolicyConfig,
+    RetryPolicyConfig,
+    SpeculativeExecutionConfig,
+    ProfileConfig,
+    KeepaliveConfig,
+    LeaseConfig,
+    CircuitBreakerConfig,
+    InitBackoffConfig,
+    BackoffCurveConfig,
+    ConnectThrottleConfig,
+};
+
+const VALIDATION_QUERY: &str = "SELECT key FROM system.local";
+
+const KEYSPACE_CHECK_QUERY: &str = "SELECT keyspace_name FROM system_schema.keyspaces WHERE keyspace_name = ?";
+
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(try_from = "ClusterConfig"))]
 pub struct ClusterParams {
-    pub contact_points: String,
-    pub keyspace: String,
+    pub contact_points: Vec<ContactPoint>,
+    // Used instead of `contact_points` when set.
+    pub astra: Option<AstraBundle>,
+    pub contact_points_discovery: Option<ContactPointsDiscovery>,
+    // Resolve hostnames again before every connect attempt.
+    pub resolve_contact_points: bool,
+    pub keyspace: Option<String>,
+    pub credentials: Option<Credentials>,
+    pub auth_provider: Option<AuthProvider>,
+    pub connect_timeout: Option<Duration>,
+    pub request_timeout: Option<Duration>,
+    pub local_address: Option<IpAddr>,
+    // Source ports for outgoing connections, end exclusive.
+    pub local_port_range: Option<Range<u16>>,
     pub num_threads_io: usize,
     pub queue_size_io: usize,
     pub queue_size_event: usize,
@@ -38,18 +372,124 @@ pub struct ClusterParams {
     pub max_connections_per_host: usize,
     pub max_concurrent_creation: usize,
     pub max_requests_per_flush: usize,
+    pub pool_size: usize,
     pub write_bytes_high_water_mark: usize,
     pub pending_requests_high_water_mark: usize,
+    pub write_bytes_low_water_mark: Option<usize>,
+    pub pending_requests_low_water_mark: Option<usize>,
+    pub max_concurrent_requests_threshold: Option<usize>,
     pub load_balance_round_robin: bool,
     pub token_aware_routing: bool,
     pub use_schema: bool,
+    pub reconnect_policy: ReconnectPolicy,
+    pub init_backoff: Option<InitBackoff>,
+    pub retry_policy: RetryPolicy,
+    pub timestamp_generator: TimestampGen,
+    pub max_detached_in_flight: usize,
+    pub max_in_flight: Option<usize>,
+    // Entries kept for `SharedSession::execute_cached`.
+    pub query_cache_size: Option<usize>,
+    pub rate_limit: Option<RateLimit>,
+    // Shared by `retry` calls without a budget of their own, consistency
+    // downgrades and `SpillQueue` replays.
+    pub retry_budget: Option<RetryBudget>,
+    pub circuit_breaker: Option<CircuitBreakerParams>,
+    pub adaptive_concurrency: Option<AdaptiveConcurrency>,
+    pub speculative_execution: Option<SpeculativeExecution>,
+    pub default_consistency: Option<Consistency>,
+    pub default_serial_consistency: Option<Consistency>,
+    pub execution_profiles: HashMap<String, ProfileParams>,
+    pub keyspace_rewrite: Option<KeyspaceRewrite>,
+    pub create_keyspace: Option<KeyspaceSpec>,
+    // Probe for the keyspace without one before connecting and fail fatally
+    // if it is missing, instead of retrying the connect forever. Ignored
+    // when `create_keyspace` is set.
+    pub check_keyspace: bool,
+    pub migrations: Vec<migrations::Migration>,
+    // Checked after migrations; a mismatch fails init fatally.
+    pub schema_expectations: Vec<SchemaExpectation>,
+    pub max_frame_size: usize,
+    pub drain_timeout: Duration,
+    // Close the sessions and stop the lode on SIGTERM or SIGINT.
+    #[cfg(all(unix, feature = "signals"))]
+    pub shutdown_on_signals: bool,
+    pub validate_on_acquire: bool,
+    pub validation_interval: Duration,
+    pub keepalive: Option<KeepaliveParams>,
+    pub lease: Option<LeaseParams>,
+    pub verify_connection: bool,
+    pub topology_poll_interval: Duration,
+    pub warmup_queries: Vec<String>,
+    pub warmup: WarmupParams,
+    pub prepared: PreparedRegistry,
+    pub paging: PagingParams,
+    // Paging size for statements built by the crate; unset leaves the
+    // driver default.
+    pub default_paging_size: Option<u32>,
+    pub connect_throttle: Option<ConnectThrottle>,
+    pub on_consistency_downgrade: Option<DowngradeHook>,
+    // Called for every request sent through the execute helpers; those that
+    // know the statement key or fingerprint label the record with it.
+    pub audit_sink: Option<AuditSink>,
+    pub on_error: Option<ErrorHook>,
+    pub on_init_failure: Option<InitFailureHook>,
+    pub on_connected: Option<ConnectedHook>,
+    pub on_disconnected: Option<DisconnectedHook>,
+    pub task_spawner: Option<TaskSpawner>,
+    pub slow_query: Option<SlowQueryParams>,
+    #[cfg(feature = "metrics-prometheus")]
+    pub prometheus: Option<PrometheusParams>,
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(deny_unknown_fields))]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Clone)]
+pub enum ReconnectPolicy {
+    Constant { delay: Duration, },
+    Exponential { base_delay: Duration, max_delay: Duration, },
+}
+
+#[derive(Clone)]
+pub enum RetryPolicy {
+    Default,
+    DowngradingConsistency,
+    Fallthrough,
+    Logging(Box<RetryPolicy>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(rename_all = "snake_case"))]
+pub enum TimestampGen {
+    Default,
+    ServerSide,
+    Monotonic,
+}
+
+#[derive(Clone)]
+pub struct SpeculativeExecution {
+    pub constant_delay: Duration,
+    pub max_executions: usize,
 }
 
 impl Default for ClusterParams {
     fn default() -> ClusterParams {
         ClusterParams {
-            contact_points: "127.0.0.1".to_string(),
-            keyspace: "default".to_string(),
+            contact_points: vec![ContactPoint::new("127.0.0.1")],
+            astra: None,
+            contact_points_discovery: None,
+            resolve_contact_points: false,
+            keyspace: Some("default".to_string()),
+            credentials: None,
+            auth_provider: None,
+            connect_timeout: None,
+            request_timeout: None,
+            local_address: None,
+            local_port_range: None,
             num_threads_io: 2,
             queue_size_io: 16384,
             queue_size_event: 32768,
@@ -57,22 +497,96 @@ impl Default for ClusterParams {
             max_connections_per_host: 4,
             max_concurrent_creation: 2,
             max_requests_per_flush: 256,
+            pool_size: 1,
             write_bytes_high_water_mark: 1024 * 1024,
             pending_requests_high_water_mark: 512,
+            write_bytes_low_water_mark: None,
+            pending_requests_low_water_mark: None,
+            max_concurrent_requests_threshold: None,
             load_balance_round_robin: true,
             token_aware_routing: false,
             use_schema: false,
+            reconnect_policy: ReconnectPolicy::Constant {
+                delay: Duration::from_secs(2),
+            },
+            init_backoff: None,
+            retry_policy: RetryPolicy::Default,
+            timestamp_generator: TimestampGen::Default,
+            max_detached_in_flight: 1024,
+            max_in_flight: None,
+            query_cache_size: None,
+            rate_limit: None,
+            retry_budget: None,
+            circuit_breaker: None,
+            adaptive_concurrency: None,
+            speculative_execution: None,
+            default_consistency: None,
+            default_serial_consistency: None,
+            execution_profiles: HashMap::new(),
+            keyspace_rewrite: None,
+            create_keyspace: None,
+            check_keyspace: false,
+            migrations: Vec::new(),
+            schema_expectations: Vec::new(),
+            max_frame_size: 256 * 1024 * 1024,
+            drain_timeout: Duration::from_secs(5),
+            #[cfg(all(unix, feature = "signals"))]
+            shutdown_on_signals: false,
+            validate_on_acquire: false,
+            validation_interval: Duration::from_secs(5),
+            keepalive: None,
+            lease: None,
+            verify_connection: false,
+            topology_poll_interval: Duration::from_secs(10),
+            warmup_queries: Vec::new(),
+            warmup: WarmupParams::default(),
+            prepared: PreparedRegistry::new(),
+            paging: PagingParams::default(),
+            default_paging_size: None,
+            connect_throttle: None,
+            on_consistency_downgrade: None,
+            audit_sink: None,
+            on_error: None,
+            on_init_failure: None,
+            on_connected: None,
+            on_disconnected: None,
+            task_spawner: None,
+            slow_query: None,
+            #[cfg(feature = "metrics-prometheus")]
+            prometheus: None,
         }
     }
 }
 
+// Called in `init` after `ClusterParams` have been applied, for driver
+// options this crate does not wrap.
+pub type ConfigureHook = Arc<dyn Fn(&mut Cluster) -> Result<(), cassandra_cpp::Error> + Send + Sync>;
+
+// Called after every successful init, with the lode's first session.
+pub type ConnectedHook = Arc<dyn Fn(&SharedSession) + Send + Sync>;
+
+// Called whenever the lode drops its sessions: on a reconnect request, a
+// failed validation or when it closes.
+pub type DisconnectedHook = Arc<dyn Fn(&Error) + Send + Sync>;
+
 pub struct Params<N> {
     pub cluster_params: ClusterParams,
     pub lode_params: ero::Params<N>,
+    pub configure: Option<ConfigureHook>,
 }
 
+#[derive(Clone)]
 pub struct SharedSession {
     session: Arc<Session>,
+    detached: Arc<detached::Detached>,
+    prepared: Arc<HashMap<String, PreparedStatement>>,
+    // Registered statements prepared again after the server dropped them.
+    reprepared: Arc<Mutex<HashMap<String, PreparedStatement>>>,
+    settings: Arc<Settings>,
+    server_info: Option<Arc<ServerInfo>>,
+    shared: Arc<Shared>,
+    // Set on the copy handed out by the lode when `lease` is configured.
+    lease: Option<Arc<lease::Lease>>,
 }
 
 impl Deref for SharedSession {
@@ -83,6 +597,88 @@ impl Deref for SharedSession {
     }
 }
 
+// Dropping a future returned by any of the execute helpers frees the driver
+// future and gives back what the crate holds for it: the in-flight slot, a
+// rate limit token not yet used, and the retry, fallback and paging loops,
+// which are plain combinators. A prefetching page stream runs on its own
+// task and stops after the page it is fetching. The driver cannot cancel a
+// request that was already sent, so it keeps its stream id until the
+// response arrives or `request_timeout` elapses; set one when composing
+// with timers.
+impl SharedSession {
+    pub fn rewrite_query<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        match self.settings.keyspace_rewrite {
+            Some(ref keyspace_rewrite) =>
+                Cow::Owned(keyspace_rewrite.rewrite(query)),
+            None =>
+                Cow::Borrowed(query),
+        }
+    }
+
+    pub fn statement(&self, query: &str, parameter_count: usize) -> Statement {
+        let mut statement = Statement::new(&self.rewrite_query(query), parameter_count);
+        self.apply_statement_defaults(&mut statement);
+        statement
+    }
+
+    // Only statements built by the crate (`statement`, `prepared`, the
+    // builders) get `default_paging_size`: the driver cannot tell whether a
+    // caller's statement already has a paging size set.
+    fn apply_statement_defaults(&self, statement: &mut Statement) {
+        if let Some(paging_size) = self.settings.default_paging_size {
+            let paging_size = paging_size.min(i32::max_value() as u32) as i32;
+            if let Err(error) = statement.set_paging_size(paging_size) {
+                warn!("error setting default paging size {}: {:?}", paging_size, error);
+            }
+        }
+    }
+
+    // The lode drops the current connection and runs init again on the next
+    // resource request.
+    pub fn request_reconnect(&self) {
+        self.shared.reconnect_requested.store(true, Ordering::SeqCst);
+    }
+
+    // Unlike `request_reconnect` the current sessions keep serving while
+    // init runs again: the next resource request connects new sessions,
+    // hands them out from then on and closes the old ones once every clone
+    // of them is dropped, however long that takes. If the new connect fails
+    // the old sessions stay in use.
+    pub fn reinit_gracefully(&self) {
+        self.shared.handoff_requested.store(true, Ordering::SeqCst);
+    }
+
+    // Pushes new params (contact points, pool size, timeouts and so on) into
+    // the running lode. The driver takes every cluster option only before
+    // connecting, so they are applied by a `reinit_gracefully` handoff with
+    // `params`; those used once when the lode is spawned (`prometheus`,
+    // `shutdown_on_signals`, `task_spawner`) keep their old values. If the
+    // new params fail to connect they are discarded.
+    pub fn update_params(&self, params: ClusterParams) {
+        *self.shared.pending_params.lock().unwrap() = Some(params);
+        self.shared.handoff_requested.store(true, Ordering::SeqCst);
+    }
+
+    // Sessions reported as held past `lease` since the lode started.
+    pub fn expired_leases(&self) -> usize {
+        self.shared.leases.expired()
+    }
+
+    pub fn reconnect_pending(&self) -> bool {
+        self.shared.reconnect_requested.load(Ordering::SeqCst)
+    }
+
+    // Retargets the lode to `keyspace` (blue/green switches such as `ks_v1`
+    // to `ks_v2`): on the next resource request the current sessions are
+    // drained like on shutdown, for up to `drain_timeout`, and init runs
+    // again against the new keyspace. `keyspace_rewrite`, `create_keyspace`
+    // and `check_keyspace` apply to it as usual.
+    pub fn switch_keyspace<K>(&self, keyspace: K) where K: Into<String> {
+        *self.shared.keyspace_switch.lock().unwrap() = Some(keyspace.into());
+        self.shared.reconnect_requested.store(true, Ordering::SeqCst);
+    }
+}
+
 pub fn spawn_link<N>(
     supervisor: &Supervisor,
     params: Params<N>,
@@ -90,100 +686,431 @@ pub fn spawn_link<N>(
     -> LodeResource<SharedSession>
 where N: AsRef<str> + Send + 'static,
 {
-    let Params { cluster_params, lode_params, } = params;
+    let Params { cluster_params, lode_params, configure, } = params;
+    spawn(supervisor, cluster_params, failover::Failover::single(), configure, lode_params)
+}
+
+fn spawn<N>(
+    supervisor: &Supervisor,
+    cluster_params: ClusterParams,
+    failover: failover::Failover,
+    configure: Option<ConfigureHook>,
+    lode_params: ero::Params<N>,
+)
+    -> LodeResource<SharedSession>
+where N: AsRef<str> + Send + 'static,
+{
+    let shared = Shared::new(lode_params.name.as_ref(), failover, configure);
+    spawn_shared(supervisor, cluster_params, shared, lode_params)
+}
+
+fn spawn_shared<N>(
+    supervisor: &Supervisor,
+    #[allow(unused_mut)]
+    mut cluster_params: ClusterParams,
+    shared: Shared,
+    lode_params: ero::Params<N>,
+)
+    -> LodeResource<SharedSession>
+where N: AsRef<str> + Send + 'static,
+{
+    let shared = Arc::new(Shared { task_spawner: cluster_params.task_spawner.clone(), ..shared });
+    #[cfg(feature = "metrics-prometheus")]
+    {
+        if let Some(prometheus_params) = cluster_params.prometheus.take() {
+            metrics_prometheus::spawn(prometheus_params, Arc::downgrade(&shared), shared.task_spawner.as_ref());
+        }
+    }
+    #[cfg(all(unix, feature = "signals"))]
+    let shutdown_on_signals = if cluster_params.shutdown_on_signals {
+        Some((Arc::downgrade(&shared), shared.task_spawner.clone()))
+    } else {
+        None
+    };
+    let state = State {
+        params: cluster_params,
+        shared,
+        last_failure: None,
+    };
 
-    lode::shared::spawn_link(
+    let resource = lode::shared::spawn_link(
         supervisor,
         lode_params,
-        cluster_params,
+        state,
         init,
-        aquire,
+        aquire_leased,
         release,
         close,
-    )
+    );
+    #[cfg(all(unix, feature = "signals"))]
+    {
+        if let Some((shared, task_spawner)) = shutdown_on_signals {
+            shutdown::spawn(resource.clone(), shared, task_spawner.as_ref());
+        }
+    }
+    resource
+}
+
+struct State {
+    params: ClusterParams,
+    shared: Arc<Shared>,
+    last_failure: Option<InitFailure>,
+}
+
+struct Settings {
+    keyspace_rewrite: Option<KeyspaceRewrite>,
+    max_frame_size: usize,
+    on_consistency_downgrade: Option<DowngradeHook>,
+    paging: PagingParams,
+    default_paging_size: Option<u32>,
+    slow_query: Option<SlowQueryParams>,
+    execution_profiles: HashSet<String>,
+    in_flight: Option<Arc<limit::Limiter>>,
+    query_cache: Option<cache::QueryCache>,
+    cluster_info: Mutex<Option<Arc<cluster_info::ClusterInfo>>>,
+    rate_limit: Option<Arc<rate_limit::TokenBucket>>,
+    retry_budget: Option<RetryBudget>,
+    circuit_breaker: Option<CircuitBreakerParams>,
+    active_cluster: ActiveCluster,
+    prepared_queries: HashMap<String, String>,
+    audit_sink: Option<AuditSink>,
+    default_consistency: Option<Consistency>,
+}
+
+impl Settings {
+    fn new(params: &ClusterParams, active_cluster: ActiveCluster) -> Settings {
+        Settings {
+            keyspace_rewrite: params.keyspace_rewrite.clone(),
+            max_frame_size: params.max_frame_size,
+            on_consistency_downgrade: params.on_consistency_downgrade.clone(),
+            paging: params.paging,
+            default_paging_size: params.default_paging_size,
+            slow_query: params.slow_query.clone(),
+            execution_profiles: params.execution_profiles.keys().cloned().collect(),
+            in_flight: params.max_in_flight.map(|max_in_flight| Arc::new(limit::Limiter::new(max_in_flight, params.adaptive_concurrency))),
+            query_cache: params.query_cache_size.map(cache::QueryCache::new),
+            cluster_info: Mutex::new(None),
+            rate_limit: params.rate_limit.map(|rate_limit| Arc::new(rate_limit::TokenBucket::new(rate_limit))),
+            retry_budget: params.retry_budget.clone(),
+            circuit_breaker: params.circuit_breaker,
+            active_cluster,
+            prepared_queries: params.prepared.queries()
+                .map(|(key, query)| (key.to_string(), query.to_string()))
+                .collect(),
+            audit_sink: params.audit_sink.clone(),
+            default_consistency: params.default_consistency,
+        }
+    }
+}
+
+struct Shared {
+    lode_name: String,
+    usage: usage::Usage,
+    #[cfg(feature = "hdr")]
+    query_histograms: query_stats::QueryHistograms,
+    audit: audit::Audit,
+    lode: metrics::LodeState,
+    reconnect_requested: AtomicBool,
+    // Keyspace for the next init, see `switch_keyspace`.
+    keyspace_switch: Mutex<Option<String>>,
+    handoff_requested: AtomicBool,
+    leases: lease::Leases,
+    // Params for the next handoff, see `update_params`.
+    pending_params: Mutex<Option<ClusterParams>>,
+    shutdown_requested: AtomicBool,
+    failover: Mutex<failover::Failover>,
+    events: events::EventBus,
+    configure: Option<ConfigureHook>,
+    breaker: breaker::CircuitBreaker,
+    task_spawner: Option<TaskSpawner>,
+}
+
+impl Shared {
+    fn new(lode_name: &str, failover: failover::Failover, configure: Option<ConfigureHook>) -> Shared {
+        Shared {
+            lode_name: lode_name.to_string(),
+            usage: usage::Usage::new(),
+            #[cfg(feature = "hdr")]
+            query_histograms: query_stats::QueryHistograms::new(),
+            audit: audit::Audit::new(),
+            lode: metrics::LodeState::default(),
+            reconnect_requested: AtomicBool::new(false),
+            keyspace_switch: Mutex::new(None),
+            handoff_requested: AtomicBool::new(false),
+            leases: lease::Leases::default(),
+            pending_params: Mutex::new(None),
+            shutdown_requested: AtomicBool::new(false),
+            failover: Mutex::new(failover),
+            events: events::EventBus::default(),
+            configure,
+            breaker: breaker::CircuitBreaker::default(),
+            task_spawner: None,
+        }
+    }
 }
 
 struct ConnectedCluster {
     session: SharedSession,
+    pool: Vec<SharedSession>,
+    next: usize,
+    last_validated: Option<Instant>,
     _cluster: Cluster,
     params: ClusterParams,
 }
 
 fn init(
+    state: State,
+)
+    -> impl Future<Item = ConnectedCluster, Error = ErrorSeverity<State, ()>>
+{
+    if spawner::executor_shutting_down() {
+        info!("executor is shutting down, not connecting");
+        return Either::B(result(Err(ErrorSeverity::Fatal(()))));
+    }
+    let State { params, shared, last_failure, } = state;
+    let backoff = match (params.init_backoff, last_failure.as_ref()) {
+        (Some(init_backoff), Some(last_failure)) =>
+            init_backoff.delay(last_failure),
+        _ =>
+            Duration::from_secs(0),
+    };
+    let attempt = last_failure.map_or(1, |last_failure| last_failure.attempt + 1);
+    let backoff = if backoff > Duration::from_secs(0) {
+        debug!("backing off for {:?} before init attempt {}", backoff, attempt);
+        Either::A(Delay::new(Instant::now() + backoff).then(|_timer_result| -> Result<(), tokio::timer::Error> { Ok(()) }))
+    } else {
+        Either::B(result(Ok(())))
+    };
+    // The throttle is only entered once the backoff elapsed.
+    let connect_throttle = params.connect_throttle.clone();
+    let permit = backoff.and_then(move |()| match connect_throttle {
+        Some(ref connect_throttle) =>
+            Either::A(connect_throttle.acquire().map(Some)),
+        None =>
+            Either::B(result(Ok(None))),
+    });
+    let future = permit.then(move |permit_result| match permit_result {
+        Ok(permit) => {
+            let on_error = params.on_error.clone();
+            let on_init_failure = params.on_init_failure.clone();
+            let started_at = Instant::now();
+            shared.lode.init_started();
+            let future = connect(params, shared.clone())
+                .then(move |connect_result| {
+                    drop(permit);
+                    connect_result
+                })
+                .map(move |connected| {
+                    session_up(&connected, started_at);
+                    connected
+                })
+                .map_err(move |error| {
+                    shared.lode.init_failed();
+                    match error {
+                        ErrorSeverity::Recoverable { state: (params, error), } => {
+                            report_error(&on_error, &error);
+                            let last_failure = report_init_failure(&on_init_failure, attempt, error);
+                            let params = shared.failover.lock().unwrap().rotate(params);
+                            ErrorSeverity::Recoverable { state: State { params, shared, last_failure: Some(last_failure), }, }
+                        },
+                        ErrorSeverity::Fatal(error) => {
+                            report_error(&on_error, &error);
+                            ErrorSeverity::Fatal(())
+                        },
+                    }
+                });
+            Either::A(future)
+        },
+        Err(error) => {
+            let error = Error::ConnectThrottle(error);
+            report_error(&params.on_error, &error);
+            let last_failure = report_init_failure(&params.on_init_failure, attempt, error);
+            Either::B(result(Err(ErrorSeverity::Recoverable { state: State { params, shared, last_failure: Some(last_failure), }, })))
+        },
+    });
+    Either::A(future)
+}
+
+// ero lodes only carry `()` as the fatal payload, so the structured error
+// is surfaced through the log and the `on_error` hook.
+fn report_error(on_error: &Option<ErrorHook>, error: &Error) {
+    error!("{}", error);
+    if let Some(ref on_error) = on_error {
+        on_error(error);
+    }
+}
+
+fn disconnected(shared: &Shared, params: &ClusterParams, error: Error) {
+    shared.lode.disconnected();
+    shared.events.publish(ClusterEvent::SessionDown);
+    if let Some(ref on_disconnected) = params.on_disconnected {
+        on_disconnected(&error);
+    }
+}
+
+fn report_init_failure(on_init_failure: &Option<InitFailureHook>, attempt: usize, error: Error) -> InitFailure {
+    warn!("init attempt {} failed, the lode will restart", attempt);
+    let init_failure = InitFailure { attempt, error, };
+    if let Some(ref on_init_failure) = on_init_failure {
+        on_init_failure(&init_failure);
+    }
+    init_failure
+}
+
+fn connect(
     params: ClusterParams,
+    shared: Arc<Shared>,
 )
-    -> Box<dyn Future<Item = ConnectedCluster, Error = ErrorSeverity<ClusterParams, ()>> + Send + 'static>
+    -> Box<dyn Future<Item = ConnectedCluster, Error = ErrorSeverity<(ClusterParams, Error), Error>> + Send + 'static>
 {
-    let future = lazy(move || {
+    let init_span = trace::init_span(&shared.lode_name, &contact_point::display(&params.contact_points), params.keyspace.as_ref().map(String::as_str));
+    let configure_span = trace::span("configure", &shared.lode_name);
+    let configure = shared.configure.clone();
+    let contact_points = discovery::contact_points(&params)
+        .map_err(Error::Discovery);
+    let credentials = auth::credentials(&params)
+        .map_err(Error::Auth);
+    let future = contact_points.join(credentials).then(move |prelude_result| {
+        let (contact_points, credentials) = match prelude_result {
+            Ok(prelude) =>
+                prelude,
+            Err(error) =>
+                return Err(ErrorSeverity::Recoverable { state: (params, error), }),
+        };
         let mut cluster = Cluster::default();
-        debug!("setting contact points: {:?} and configuring cluster", params.contact_points);
-        let config_result = cluster.set_contact_points(&params.contact_points)
-            .map_err(|error| {
-                error!("error setting contact_points: {:?}", error);
-                ErrorSeverity::Recoverable { state: (), }
+        let config_result = trace::in_span(&configure_span, || set_endpoint(&mut cluster, &params, &contact_points)
+            .and_then(|cluster| {
+                if let Some(ref credentials) = credentials {
+                    cluster.set_credentials(&credentials.username, &credentials.password)
+                        .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "credentials", error, }))
+                } else {
+                    Ok(cluster)
+                }
             })
             .and_then(|cluster| {
+                if let Some(connect_timeout) = params.connect_timeout {
+                    cluster.set_connect_timeout(connect_timeout);
+                }
+                if let Some(request_timeout) = params.request_timeout {
+                    cluster.set_request_timeout(request_timeout);
+                }
                 cluster.set_num_threads_io(params.num_threads_io as u32)
-                    .map_err(|error| {
-                        error!("error setting num_threads_io: {:?}", error);
-                        ErrorSeverity::Fatal(())
-                    })
+                    .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "num_threads_io", error, }))
+            })
+            .and_then(|cluster| {
+                if let Some(local_address) = params.local_address {
+                    cluster.set_local_address(&local_address.to_string())
+                        .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "local_address", error, }))
+                } else {
+                    Ok(cluster)
+                }
+            })
+            .and_then(|cluster| {
+                if let Some(ref local_port_range) = params.local_port_range {
+                    cluster.set_local_port_range(local_port_range.start as i32, local_port_range.end as i32)
+                        .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "local_port_range", error, }))
+                } else {
+                    Ok(cluster)
+                }
             })
             .and_then(|cluster| {
                 cluster.set_queue_size_io(params.queue_size_io as u32)
-                    .map_err(|error| {
-                        error!("error setting queue_size_io: {:?}", error);
-                        ErrorSeverity::Fatal(())
-                    })
+                    .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "queue_size_io", error, }))
             })
             .and_then(|cluster| {
                 cluster.set_queue_size_event(params.queue_size_event as u32)
-                    .map_err(|error| {
-                        error!("error setting queue_size_event: {:?}", error);
-                        ErrorSeverity::Fatal(())
-                    })
+                    .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "queue_size_event", error, }))
             })
             .and_then(|cluster| {
                 cluster.set_core_connections_per_host(params.core_connections_per_host as u32)
-                    .map_err(|error| {
-                        error!("error setting core_connections_per_host: {:?}", error);
-                        ErrorSeverity::Fatal(())
-                    })
+                    .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "core_connections_per_host", error, }))
             })
             .and_then(|cluster| {
                 cluster.set_max_connections_per_host(params.max_connections_per_host as u32)
-                    .map_err(|error| {
-                        error!("error setting max_connections_per_host: {:?}", error);
-                        ErrorSeverity::Fatal(())
-                    })
+                    .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "max_connections_per_host", error, }))
             })
             .and_then(|cluster| {
                 cluster.set_max_concurrent_creation(params.max_concurrent_creation as u32)
-                    .map_err(|error| {
-                        error!("error setting max_concurrent_creation: {:?}", error);
-                        ErrorSeverity::Fatal(())
-                    })
+                    .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "max_concurrent_creation", error, }))
             })
             .and_then(|cluster| {
                 cluster.set_max_requests_per_flush(params.max_requests_per_flush as u32)
-                    .map_err(|error| {
-                        error!("error setting max_requests_per_flush: {:?}", error);
-                        ErrorSeverity::Fatal(())
-                    })
+                    .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "max_requests_per_flush", error, }))
             })
             .and_then(|cluster| {
                 cluster.set_write_bytes_high_water_mark(params.write_bytes_high_water_mark as u32)
-                    .map_err(|error| {
-                        error!("error setting write_bytes_high_water_mark: {:?}", error);
-                        ErrorSeverity::Fatal(())
-                    })
+                    .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "write_bytes_high_water_mark", error, }))
             })
             .and_then(|cluster| {
                 cluster.set_pending_requests_high_water_mark(params.pending_requests_high_water_mark as u32)
-                    .map_err(|error| {
-                        error!("error setting pending_requests_high_water_mark: {:?}", error);
-                        ErrorSeverity::Fatal(())
-                    })
+                    .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "pending_requests_high_water_mark", error, }))
+            })
+            .and_then(|cluster| {
+                if let Some(write_bytes_low_water_mark) = params.write_bytes_low_water_mark {
+                    cluster.set_write_bytes_low_water_mark(write_bytes_low_water_mark as u32)
+                        .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "write_bytes_low_water_mark", error, }))
+                } else {
+                    Ok(cluster)
+                }
+            })
+            .and_then(|cluster| {
+                if let Some(pending_requests_low_water_mark) = params.pending_requests_low_water_mark {
+                    cluster.set_pending_requests_low_water_mark(pending_requests_low_water_mark as u32)
+                        .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "pending_requests_low_water_mark", error, }))
+                } else {
+                    Ok(cluster)
+                }
+            })
+            .and_then(|cluster| {
+                if let Some(max_concurrent_requests_threshold) = params.max_concurrent_requests_threshold {
+                    cluster.set_max_concurrent_requests_threshold(max_concurrent_requests_threshold as u32)
+                        .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "max_concurrent_requests_threshold", error, }))
+                } else {
+                    Ok(cluster)
+                }
+            })
+            .and_then(|cluster| {
+                match params.reconnect_policy {
+                    ReconnectPolicy::Constant { delay, } => {
+                        cluster.set_reconnect_wait_time(delay.as_millis() as u32);
+                        Ok(cluster)
+                    },
+                    ReconnectPolicy::Exponential { base_delay, max_delay, } =>
+                        cluster.set_exponential_reconnect(base_delay, max_delay)
+                            .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "exponential_reconnect", error, })),
+                }
+            })
+            .and_then(|cluster| {
+                if let Some(ref speculative_execution) = params.speculative_execution {
+                    cluster.set_constant_speculative_execution_policy(
+                        speculative_execution.constant_delay.as_millis() as i64,
+                        speculative_execution.max_executions as i32,
+                    )
+                        .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "constant_speculative_execution_policy", error, }))
+                } else {
+                    Ok(cluster)
+                }
+            })
+            .and_then(|cluster| {
+                if let Some(consistency) = params.default_consistency {
+                    cluster.set_consistency(consistency)
+                        .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "default consistency", error, }))
+                } else {
+                    Ok(cluster)
+                }
+            })
+            .and_then(|cluster| {
+                if let Some(serial_consistency) = params.default_serial_consistency {
+                    cluster.set_serial_consistency(serial_consistency)
+                        .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "default serial_consistency", error, }))
+                } else {
+                    Ok(cluster)
+                }
+            })
+            .and_then(|cluster| {
+                profile::configure(cluster, &params.execution_profiles)
+                    .map(|()| cluster)
+                    .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "execution_profiles", error, }))
             })
             .map(|cluster| {
                 if params.load_balance_round_robin {
@@ -191,69 +1118,638 @@ fn init(
                 }
                 cluster.set_token_aware_routing(params.token_aware_routing);
                 cluster.set_use_schema(params.use_schema);
+                cluster.set_retry_policy(make_retry_policy(&params.retry_policy));
+                match params.timestamp_generator {
+                    TimestampGen::Default =>
+                        (),
+                    TimestampGen::ServerSide => {
+                        cluster.set_timestamp_gen(&CassTimestampGen::gen_server_side_new());
+                    },
+                    TimestampGen::Monotonic => {
+                        cluster.set_timestamp_gen(&CassTimestampGen::gen_monotonic_new());
+                    },
+                }
+            }))
+            .and_then(|()| {
+                if let Some(configure) = configure {
+                    debug!("running custom cluster configuration");
+                    configure(&mut cluster)
+                        .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "custom configure", error, }))
+                } else {
+                    Ok(())
+                }
             });
         match config_result {
             Ok(()) =>
                 Ok((cluster, params)),
-            Err(ErrorSeverity::Recoverable { state: (), }) =>
-                Err(ErrorSeverity::Recoverable { state: params, }),
-            Err(ErrorSeverity::Fatal(())) =>
-                Err(ErrorSeverity::Fatal(())),
+            Err(ErrorSeverity::Recoverable { state: error, }) =>
+                Err(escalate(params, error)),
+            Err(ErrorSeverity::Fatal(error)) =>
+                Err(ErrorSeverity::Fatal(error)),
         }
     });
     let future = future
         .and_then(|(cluster, params)| {
-            debug!("setting keyspace {:?} and connecting to cluster", params.keyspace);
-            let session = Session::new();
-            match session.connect_keyspace(&cluster, &params.keyspace) {
-                Ok(connect_future) => {
-                    let future = connect_future
-                        .then(move |connect_result| {
-                            match connect_result {
-                                Ok(()) =>
-                                    Ok(ConnectedCluster {
-                                        session: SharedSession { session: Arc::new(session), },
-                                        _cluster: cluster,
-                                        params,
-                                    }),
-                                Err(error) => {
-                                    error!("error connect_future: {:?}", error);
-                                    Err(ErrorSeverity::Recoverable { state: params, })
+            match (target_keyspace(&params), params.create_keyspace.as_ref()) {
+                (Some(keyspace), Some(keyspace_spec)) => {
+                    let query = keyspace_spec.create_query(&keyspace);
+                    Either::A(Either::A(create_keyspace(cluster, params, keyspace, query)))
+                },
+                (Some(keyspace), None) if params.check_keyspace =>
+                    Either::A(Either::B(check_keyspace(cluster, params, keyspace))),
+                _ =>
+                    Either::B(result(Ok((cluster, params)))),
+            }
+        });
+    let future = future
+        .and_then(|(cluster, params)| {
+            let keyspace = target_keyspace(&params);
+            let pool_size = params.pool_size.max(1);
+            match keyspace {
+                Some(ref keyspace) =>
+                    debug!("setting keyspace {:?} and connecting {} session(s) to cluster", keyspace, pool_size),
+                None =>
+                    debug!("connecting {} session(s) to cluster without keyspace", pool_size),
+            }
+            let sessions: Vec<_> = (0 .. pool_size)
+                .map(|_| open_session(&cluster, keyspace.as_ref().map(String::as_str)))
+                .collect();
+            join_all(sessions)
+                .then(move |connect_result| {
+                    match connect_result {
+                        Ok(sessions) => {
+                            let active_cluster = shared.failover.lock().unwrap().active_cluster(&params);
+                            let detached = Arc::new(detached::Detached::new(
+                                params.max_detached_in_flight,
+                                params.adaptive_concurrency,
+                            ));
+                            let settings = Arc::new(Settings::new(&params, active_cluster));
+                            let mut sessions = sessions.into_iter()
+                                .map(|session| SharedSession {
+                                    session: Arc::new(session),
+                                    detached: detached.clone(),
+                                    prepared: Arc::new(HashMap::new()),
+                                    reprepared: Arc::new(Mutex::new(HashMap::new())),
+                                    settings: settings.clone(),
+                                    server_info: None,
+                                    shared: shared.clone(),
+                                    lease: None,
+                                });
+                            let session = sessions.next().unwrap();
+                            Ok(ConnectedCluster {
+                                session,
+                                pool: sessions.collect(),
+                                next: 0,
+                                last_validated: Some(Instant::now()),
+                                _cluster: cluster,
+                                params,
+                            })
+                        },
+                        Err(error) => {
+                            let error = Error::Connect {
+                                contact_points: contact_point::display(&params.contact_points),
+                                keyspace,
+                                error,
+                            };
+                            Err(escalate(params, error))
+                        },
+                    }
+                })
+        });
+    let future = future
+        .and_then(|mut connected| {
+            if connected.params.verify_connection {
+                let future = server_info::fetch(&connected.session.session)
+                    .then(move |fetch_result| {
+                        match fetch_result {
+                            Ok(server_info) => {
+                                let server_info = Arc::new(server_info);
+                                for session in iter::once(&mut connected.session).chain(connected.pool.iter_mut()) {
+                                    session.server_info = Some(server_info.clone());
+                                }
+                                Ok(connected)
+                            },
+                            Err(error) =>
+                                Err(escalate(connected.params, Error::Verify(error))),
+                        }
+                    });
+                Either::A(future)
+            } else {
+                Either::B(result(Ok(connected)))
+            }
+        });
+    let future = future
+        .and_then(|connected| {
+            if connected.params.migrations.is_empty() {
+                Either::A(result(Ok(connected)))
+            } else {
+                let future = migrations::run(
+                    connected.session.session.clone(),
+                    target_keyspace(&connected.params),
+                    connected.params.migrations.clone(),
+                )
+                    .then(move |run_result| {
+                        match run_result {
+                            Ok(()) =>
+                                Ok(connected),
+                            Err(error) =>
+                                Err(ErrorSeverity::Fatal(Error::Migration(error))),
+                        }
+                    });
+                Either::B(future)
+            }
+        });
+    let future = future
+        .and_then(|connected| {
+            if connected.params.schema_expectations.is_empty() {
+                Either::A(result(Ok(connected)))
+            } else {
+                let future = connected.session
+                    .check_schema(connected.params.keyspace.as_ref().map(String::as_str), &connected.params.schema_expectations)
+                    .then(move |check_result| {
+                        match check_result {
+                            Ok(()) =>
+                                Ok(connected),
+                            Err(SchemaCheckError::Driver(error)) =>
+                                Err(escalate(connected.params, Error::SchemaCheck(SchemaCheckError::Driver(error)))),
+                            Err(error) =>
+                                Err(ErrorSeverity::Fatal(Error::SchemaCheck(error))),
+                        }
+                    });
+                Either::B(future)
+            }
+        });
+    let future = future
+        .and_then(|mut connected| {
+            let prepares: Vec<_> = iter::once(&connected.session)
+                .chain(connected.pool.iter())
+                .map(|session| prepared::prepare_all(session, &connected.params.prepared))
+                .collect();
+            join_all(prepares)
+                .then(move |prepare_result| {
+                    match prepare_result {
+                        Ok(prepared) => {
+                            let mut prepared = prepared.into_iter().map(Arc::new);
+                            if let Some(statements) = prepared.next() {
+                                for key in statements.keys() {
+                                    connected.session.shared.usage.register(key);
+                                }
+                                connected.session.prepared = statements;
+                            }
+                            for (session, statements) in connected.pool.iter_mut().zip(prepared) {
+                                session.prepared = statements;
+                            }
+                            Ok(connected)
+                        },
+                        Err(error) =>
+                            Err(escalate(connected.params, Error::Prepare(error))),
+                    }
+                })
+        });
+    let future = future
+        .and_then(|connected| {
+            if connected.params.warmup_queries.is_empty() {
+                Either::A(result(Ok(connected)))
+            } else {
+                let sessions = iter::once(&connected.session)
+                    .chain(connected.pool.iter())
+                    .cloned()
+                    .collect();
+                let future = warmup::run(sessions, connected.params.warmup_queries.clone(), connected.params.warmup)
+                    .then(move |warmup_result| {
+                        match warmup_result {
+                            Ok(()) =>
+                                Ok(connected),
+                            Err(error) =>
+                                match connected.params.warmup.on_failure {
+                                    WarmupFailure::Warn => {
+                                        warn!("{}", Error::Warmup(error));
+                                        Ok(connected)
+                                    },
+                                    WarmupFailure::Reconnect =>
+                                        Err(ErrorSeverity::Recoverable { state: (connected.params, Error::Warmup(error)), }),
                                 },
+                        }
+                    });
+                Either::B(future)
+            }
+        });
+    Box::new(trace::traced(future, &init_span))
+}
+
+fn open_session(
+    cluster: &Cluster,
+    keyspace: Option<&str>,
+)
+    -> impl Future<Item = Session, Error = cassandra_cpp::Error>
+{
+    let session = Session::new();
+    let connect_result = match keyspace {
+        Some(keyspace) =>
+            session.connect_keyspace(cluster, keyspace),
+        None =>
+            Ok(session.connect(cluster)),
+    };
+    result(connect_result)
+        .and_then(|connect_future| connect_future)
+        .map(move |()| session)
+}
+
+fn set_endpoint<'a>(
+    cluster: &'a mut Cluster,
+    params: &ClusterParams,
+    contact_points: &[ContactPoint],
+)
+    -> Result<&'a mut Cluster, ErrorSeverity<Error, Error>>
+{
+    if let Some(ref astra_bundle) = params.astra {
+        return astra::configure(cluster, astra_bundle)
+            .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "secure_connect_bundle", error, }));
+    }
+    let port = contact_points.first().map_or(DEFAULT_PORT, |contact_point| contact_point.port);
+    if contact_points.iter().any(|contact_point| contact_point.port != port) {
+        let error = format!("contact points {} use different ports", contact_point::display(contact_points)).into();
+        return Err(ErrorSeverity::Fatal(Error::Config { step: "port", error, }));
+    }
+    let hosts: Vec<_> = contact_points.iter()
+        .map(|contact_point| contact_point.host.as_str())
+        .collect();
+    let hosts = hosts.join(",");
+    debug!("setting contact points: {:?} and configuring cluster", hosts);
+    cluster.set_contact_points(&hosts)
+        .map_err(|error| ErrorSeverity::Recoverable { state: Error::Config { step: "contact_points", error, }, })
+        .and_then(|cluster| {
+            if port != DEFAULT_PORT {
+                debug!("setting port: {}", port);
+                cluster.set_port(port)
+                    .map_err(|error| ErrorSeverity::Fatal(Error::Config { step: "port", error, }))
+            } else {
+                Ok(cluster)
+            }
+        })
+}
+
+fn escalate(params: ClusterParams, error: Error) -> ErrorSeverity<(ClusterParams, Error), Error> {
+    match error.driver_error().map(error::classify) {
+        Some(ErrorSeverity::Fatal(())) =>
+            ErrorSeverity::Fatal(error),
+        _ =>
+            ErrorSeverity::Recoverable { state: (params, error), },
+    }
+}
+
+fn target_keyspace(params: &ClusterParams) -> Option<String> {
+    params.keyspace.as_ref()
+        .map(|keyspace| match params.keyspace_rewrite {
+            Some(ref keyspace_rewrite) =>
+                keyspace_rewrite.keyspace(keyspace).to_string(),
+            None =>
+                keyspace.clone(),
+        })
+}
+
+fn check_keyspace(
+    cluster: Cluster,
+    params: ClusterParams,
+    keyspace: String,
+)
+    -> impl Future<Item = (Cluster, ClusterParams), Error = ErrorSeverity<(ClusterParams, Error), Error>>
+{
+    debug!("connecting to cluster without keyspace to check keyspace {:?}", keyspace);
+    let keyspace_name = keyspace::stored_name(&keyspace);
+    with_temporary_session(&cluster, move |session| {
+        let mut statement = Statement::new(KEYSPACE_CHECK_QUERY, 1);
+        match statement.bind_string(0, &keyspace_name).map(|_statement| ()) {
+            Ok(()) =>
+                Either::A(session.execute(&statement)),
+            Err(error) =>
+                Either::B(result(Err(error))),
+        }
+    })
+        .then(move |check_result| {
+            match check_result {
+                Ok(ref cass_result) if cass_result.row_count() > 0 =>
+                    Ok((cluster, params)),
+                Ok(_cass_result) =>
+                    Err(ErrorSeverity::Fatal(Error::MissingKeyspace { keyspace, })),
+                Err(error) =>
+                    Err(escalate(params, Error::CheckKeyspace { keyspace, error, })),
+            }
+        })
+}
+
+fn create_keyspace(
+    cluster: Cluster,
+    params: ClusterParams,
+    keyspace: String,
+    query: String,
+)
+    -> impl Future<Item = (Cluster, ClusterParams), Error = ErrorSeverity<(ClusterParams, Error), Error>>
+{
+    debug!("connecting to cluster without keyspace to create keyspace {:?}", keyspace);
+    with_temporary_session(&cluster, move |session| {
+        debug!("creating keyspace: {}", query);
+        session.execute(&Statement::new(&query, 0))
+    })
+        .then(move |create_result| {
+            match create_result {
+                Ok(_cass_result) =>
+                    Ok((cluster, params)),
+                Err(error) =>
+                    Err(escalate(params, Error::CreateKeyspace { keyspace, error, })),
+            }
+        })
+}
+
+// Connects a session of its own for `with_session` and closes it once that
+// resolves: dropping a connected `Session` blocks the reactor thread until
+// the driver has closed it. A session that failed to connect is dropped.
+fn with_temporary_session<F, R>(cluster: &Cluster, with_session: F) -> impl Future<Item = R::Item, Error = cassandra_cpp::Error>
+where F: FnOnce(&Session) -> R,
+      R: Future<Error = cassandra_cpp::Error>,
+{
+    let session = Session::new();
+    session.connect(cluster)
+        .and_then(move |()| {
+            with_session(&session)
+                .then(move |with_result| {
+                    session.close()
+                        .then(move |close_result| {
+                            if let Err(error) = close_result {
+                                warn!("error closing session: {:?}", error);
                             }
-                        });
-                    Either::A(future)
+                            with_result
+                        })
+                })
+        })
+}
+
+fn make_retry_policy(retry_policy: &RetryPolicy) -> CassRetryPolicy {
+    match retry_policy {
+        RetryPolicy::Default =>
+            CassRetryPolicy::default_new(),
+        RetryPolicy::DowngradingConsistency =>
+            CassRetryPolicy::downgrading_consistency_new(),
+        RetryPolicy::Fallthrough =>
+            CassRetryPolicy::fallthrough_new(),
+        RetryPolicy::Logging(child_policy) =>
+            CassRetryPolicy::logging_new(make_retry_policy(child_policy)),
+    }
+}
+
+// Everything that starts along with a freshly connected cluster.
+fn session_up(connected: &ConnectedCluster, started_at: Instant) {
+    connected.session.shared.lode.connected(
+        Arc::downgrade(&connected.session.session),
+        connected.params.retry_budget.clone(),
+        started_at.elapsed(),
+    );
+    let shared = &connected.session.shared;
+    shared.events.publish(ClusterEvent::SessionUp { contact_points: contact_point::display(&connected.params.contact_points), });
+    if shared.events.has_subscribers() {
+        events::spawn_topology_watch(
+            connected.params.topology_poll_interval,
+            Arc::downgrade(&connected.session.session),
+            Arc::downgrade(shared),
+            shared.task_spawner.as_ref(),
+        );
+    }
+    if let Some(ref on_connected) = connected.params.on_connected {
+        on_connected(&connected.session);
+    }
+    if let Some(keepalive_params) = connected.params.keepalive {
+        keepalive::spawn(
+            keepalive_params,
+            Arc::downgrade(&connected.session.session),
+            Arc::downgrade(&connected.session.shared),
+            connected.session.shared.task_spawner.as_ref(),
+        );
+    }
+    if let Some(lease_params) = connected.params.lease {
+        lease::spawn(
+            lease_params,
+            Arc::downgrade(&connected.session.session),
+            Arc::downgrade(&connected.session.shared),
+            connected.session.shared.task_spawner.as_ref(),
+        );
+    }
+}
+
+fn aquire(
+    mut connected: ConnectedCluster,
+)
+    -> impl Future<Item = (SharedSession, ConnectedCluster), Error = ErrorSeverity<State, ()>>
+{
+    let span = trace::span("aquire", &connected.session.shared.lode_name);
+    let executor_shutting_down = spawner::executor_shutting_down();
+    if executor_shutting_down || connected.session.shared.shutdown_requested.load(Ordering::SeqCst) {
+        trace::in_span(&span, || if executor_shutting_down {
+            info!("executor is shutting down, refusing new sessions")
+        } else {
+            info!("shutdown requested, refusing new sessions")
+        });
+        let future = close(connected)
+            .then(|_close_result| Err(ErrorSeverity::Fatal(())));
+        return Either::B(Either::A(Either::A(trace::traced(future, &span))));
+    }
+    if connected.session.shared.handoff_requested.swap(false, Ordering::SeqCst) {
+        trace::in_span(&span, || info!("graceful reinit requested, connecting new sessions"));
+        let future = handoff(connected);
+        return Either::B(Either::A(Either::B(Either::B(trace::traced(future, &span)))));
+    }
+    if connected.session.shared.reconnect_requested.swap(false, Ordering::SeqCst) {
+        let keyspace_switch = connected.session.shared.keyspace_switch.lock().unwrap().take();
+        if let Some(keyspace) = keyspace_switch {
+            trace::in_span(&span, || info!("switching to keyspace {:?}, draining current sessions", keyspace));
+            let future = close(connected)
+                .then(move |close_result| match close_result {
+                    Ok(mut state) => {
+                        state.params.keyspace = Some(keyspace);
+                        Err(ErrorSeverity::Recoverable { state, })
+                    },
+                    Err(()) =>
+                        Err(ErrorSeverity::Fatal(())),
+                });
+            return Either::B(Either::A(Either::B(Either::A(trace::traced(future, &span)))));
+        }
+        trace::in_span(&span, || info!("reconnect requested, dropping current session"));
+        let shared = connected.session.shared.clone();
+        disconnected(&shared, &connected.params, Error::ReconnectRequested);
+        let future = result(Err(ErrorSeverity::Recoverable { state: State { params: connected.params, shared, last_failure: None, }, }));
+        return Either::A(trace::traced(future, &span));
+    }
+    let index = connected.next % (connected.pool.len() + 1);
+    connected.next = connected.next.wrapping_add(1);
+    let session = if index == 0 {
+        connected.session.clone()
+    } else {
+        connected.pool[index - 1].clone()
+    };
+    let validation_due = connected.params.validate_on_acquire && connected.last_validated
+        .map_or(true, |last_validated| last_validated.elapsed() >= connected.params.validation_interval);
+    if !validation_due {
+        connected.session.shared.lode.acquired();
+        return Either::A(trace::traced(result(Ok((session, connected))), &span));
+    }
+    let statement = Statement::new(VALIDATION_QUERY, 0);
+    let future = session.session.execute(&statement)
+        .then(move |probe_result| {
+            match probe_result {
+                Ok(_cass_result) => {
+                    connected.last_validated = Some(Instant::now());
+                    connected.session.shared.lode.succeeded();
+                    connected.session.shared.lode.acquired();
+                    Ok((session, connected))
                 },
                 Err(error) => {
-                    error!("error connect_keyspace: {:?}", error);
-                    Either::B(result(Err(ErrorSeverity::Recoverable { state: params, })))
+                    warn!("session failed validation on aquire, reconnecting: {:?}", error);
+                    let shared = connected.session.shared.clone();
+                    disconnected(&shared, &connected.params, Error::Validation(error));
+                    Err(ErrorSeverity::Recoverable { state: State { params: connected.params, shared, last_failure: None, }, })
                 },
             }
         });
-    Box::new(future)
+    Either::B(Either::B(trace::traced(future, &span)))
 }
 
-fn aquire(
+// Connects with the pending params, or the same ones, while `connected`
+// keeps serving, then retires it in the background.
+fn handoff(
+    connected: ConnectedCluster,
+)
+    -> impl Future<Item = (SharedSession, ConnectedCluster), Error = ErrorSeverity<State, ()>>
+{
+    let shared = connected.session.shared.clone();
+    let params = shared.pending_params.lock().unwrap().take()
+        .unwrap_or_else(|| connected.params.clone());
+    let started_at = Instant::now();
+    shared.lode.init_started();
+    connect(params, shared.clone())
+        .then(move |connect_result| -> Result<_, ErrorSeverity<State, ()>> {
+            let connected = match connect_result {
+                Ok(next_connected) => {
+                    session_up(&next_connected, started_at);
+                    retire(connected);
+                    next_connected
+                },
+                Err(ErrorSeverity::Recoverable { state: (_params, error), }) | Err(ErrorSeverity::Fatal(error)) => {
+                    shared.lode.init_failed();
+                    report_error(&connected.params.on_error, &error);
+                    warn!("graceful reinit failed, keeping current sessions: {}", error);
+                    connected
+                },
+            };
+            connected.session.shared.lode.acquired();
+            Ok((connected.session.clone(), connected))
+        })
+}
+
+// Closes the sessions of a replaced cluster once nobody holds them.
+fn retire(connected: ConnectedCluster) {
+    let ConnectedCluster { session, pool, _cluster: cluster, .. } = connected;
+    let task_spawner = session.shared.task_spawner.clone();
+    debug!("retiring {} session(s) after handoff", pool.len() + 1);
+    let sessions: Vec<_> = iter::once(session)
+        .chain(pool)
+        .map(|shared_session| close_session(shared_session.session))
+        .collect();
+    let future = join_all(sessions)
+        .then(move |_close_result| {
+            drop(cluster);
+            debug!("retired sessions closed");
+            Ok(())
+        });
+    if let Err(error) = spawner::spawn(task_spawner.as_ref(), Box::new(future)) {
+        warn!("error spawning retired sessions close, dropping them: {:?}", error);
+    }
+}
+
+// `aquire` for the shared lode: the unique one leases its own sessions.
+fn aquire_leased(
     connected: ConnectedCluster,
 )
-    -> impl Future<Item = (SharedSession, ConnectedCluster), Error = ErrorSeverity<ClusterParams, ()>>
+    -> impl Future<Item = (SharedSession, ConnectedCluster), Error = ErrorSeverity<State, ()>>
 {
-    result(Ok((SharedSession { session: connected.session.session.clone(), }, connected)))
+    aquire(connected)
+        .map(|(session, connected)| (leased(session, &connected.params, false), connected))
+}
+
+fn leased(mut session: SharedSession, params: &ClusterParams, unique: bool) -> SharedSession {
+    if params.lease.is_some() {
+        session.lease = Some(session.shared.leases.start(unique));
+    }
+    session
 }
 
 fn release(
     connected: ConnectedCluster,
-    _maybe_session: Option<SharedSession>,
+    maybe_session: Option<SharedSession>,
 )
-    -> impl Future<Item = ConnectedCluster, Error = ErrorSeverity<ClusterParams, ()>>
+    -> impl Future<Item = ConnectedCluster, Error = ErrorSeverity<State, ()>>
 {
-    result(Ok(connected))
+    connected.session.shared.lode.released(maybe_session.is_some());
+    if let Some(lease) = maybe_session.as_ref().and_then(|session| session.lease.as_ref()) {
+        lease.release();
+    }
+    let span = trace::span("release", &connected.session.shared.lode_name);
+    trace::traced(result(Ok(connected)), &span)
 }
 
 fn close(
     connected: ConnectedCluster,
 )
-    -> impl Future<Item = ClusterParams, Error = ()>
+    -> impl Future<Item = State, Error = ()>
 {
-    result(Ok(connected.params))
+    let ConnectedCluster { session, pool, _cluster: cluster, params, .. } = connected;
+    let shared = session.shared.clone();
+    let span = trace::span("close", &shared.lode_name);
+    trace::in_span(&span, || debug!("closing {} session(s), draining for up to {:?}", pool.len() + 1, params.drain_timeout));
+    disconnected(&shared, &params, Error::Closed);
+    let sessions: Vec<_> = iter::once(session)
+        .chain(pool)
+        .map(|shared_session| close_session(shared_session.session))
+        .collect();
+    let future = Timeout::new(join_all(sessions), params.drain_timeout)
+        .then(move |close_result| {
+            if close_result.is_err() {
+                warn!("drain timeout of {:?} elapsed, dropping sessions still in use", params.drain_timeout);
+            }
+            drop(cluster);
+            Ok(State { params, shared, last_failure: None, })
+        });
+    trace::traced(future, &span)
+}
+
+// Waits for handed out clones of the session to be dropped, then closes it.
+fn close_session(session: Arc<Session>) -> impl Future<Item = (), Error = ()> {
+    loop_fn(session, |session| {
+        match Arc::try_unwrap(session) {
+            Ok(session) => {
+                let future = session.close()
+                    .then(|close_result| {
+                        if let Err(error) = close_result {
+                            warn!("error closing session: {:?}", error);
+                        }
+                        Ok(Loop::Break(()))
+                    });
+                Either::A(future)
+            },
+            Err(session) => {
+                let future = Delay::new(Instant::now() + DRAIN_POLL_INTERVAL)
+                    .then(move |_timer_result| Ok(Loop::Continue(session)));
+                Either::B(future)
+            },
+        }
+    })
+}
+
+// The session and the lode handle are moved across tokio runtimes and
+// threads: keep these bounds from regressing.
+#[allow(dead_code)]
+fn assert_runtime_agnostic() {
+    fn is_send_sync<T: Send + Sync>() { }
+    fn is_send_clone<T: Send + Clone>() { }
+
+    is_send_sync::<SharedSession>();
+    is_send_clone::<LodeResource<SharedSession>>();
 }
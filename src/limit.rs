@@ -0,0 +1,452 @@
+use std::{
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{
+            Ordering,
+            AtomicBool,
+            AtomicUsize,
+        },
+    },
+    collections::VecDeque,
+};
+
+use futures::{
+    task,
+    Async,
+    Poll,
+    Future,
+};
+
+use cassandra_cpp::{
+    ErrorKind,
+    CassErrorCode,
+};
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(default, deny_unknown_fields))]
+pub struct AdaptiveConcurrency {
+    pub initial_limit: usize,
+    pub min_limit: usize,
+    pub max_limit: usize,
+    pub backoff_ratio: f64,
+}
+
+impl Default for AdaptiveConcurrency {
+    fn default() -> AdaptiveConcurrency {
+        AdaptiveConcurrency {
+            initial_limit: 64,
+            min_limit: 8,
+            max_limit: 4096,
+            backoff_ratio: 0.9,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Success,
+    Overloaded,
+    Failure,
+}
+
+impl Outcome {
+    pub fn of<T>(result: &Result<T, cassandra_cpp::Error>) -> Outcome {
+        match result {
+            Ok(..) =>
+                Outcome::Success,
+            Err(ref error) if is_overloaded(error) =>
+                Outcome::Overloaded,
+            Err(..) =>
+                Outcome::Failure,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ResourceLimit {
+    RequestQueue,
+    StreamIds,
+}
+
+impl ResourceLimit {
+    pub fn of(error: &cassandra_cpp::Error) -> Option<ResourceLimit> {
+        match error.kind() {
+            ErrorKind::CassError(CassErrorCode::LIB_REQUEST_QUEUE_FULL, _) =>
+                Some(ResourceLimit::RequestQueue),
+            ErrorKind::CassError(CassErrorCode::LIB_NO_STREAMS, _) =>
+                Some(ResourceLimit::StreamIds),
+            _ =>
+                None,
+        }
+    }
+}
+
+// The one in-flight limit used by the crate: `max_in_flight` on the execute
+// path, the detached writes budget, connect and maintenance slots.
+// `try_permit` fails fast, `acquire` waits for a slot. With `adaptive` the
+// limit grows by one after a limit's worth of successes and is cut by
+// `backoff_ratio` on overload.
+pub(crate) struct Limiter {
+    in_flight: AtomicUsize,
+    limit: AtomicUsize,
+    adaptive: Option<(AdaptiveConcurrency, Mutex<usize>)>,
+    waiters: Mutex<VecDeque<Arc<Waiter>>>,
+}
+
+#[derive(Default)]
+struct Waiter {
+    task: Mutex<Option<task::Task>>,
+    woken: AtomicBool,
+}
+
+impl Limiter {
+    pub(crate) fn new(static_limit: usize, adaptive: Option<AdaptiveConcurrency>) -> Limiter {
+        let limit = match adaptive {
+            Some(ref params) =>
+                params.initial_limit,
+            None =>
+                static_limit,
+        };
+        Limiter {
+            in_flight: AtomicUsize::new(0),
+            limit: AtomicUsize::new(limit.max(1)),
+            adaptive: adaptive.map(|params| (params, Mutex::new(0))),
+            waiters: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn try_acquire(&self) -> bool {
+        let limit = self.limit.load(Ordering::SeqCst);
+        if self.in_flight.fetch_add(1, Ordering::SeqCst) >= limit {
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            false
+        } else {
+            true
+        }
+    }
+
+    pub(crate) fn try_permit(limiter: &Arc<Limiter>) -> Option<Permit> {
+        if limiter.try_acquire() {
+            Some(Permit { limiter: limiter.clone(), outcome: Outcome::Failure, })
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn acquire(limiter: &Arc<Limiter>) -> Acquire {
+        Acquire { limiter: limiter.clone(), waiter: None, }
+    }
+
+    pub(crate) fn release(&self, outcome: Outcome) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if let Some((ref params, ref successes)) = self.adaptive {
+            let mut successes = successes.lock().unwrap();
+            let limit = self.limit.load(Ordering::SeqCst);
+            self.limit.store(next_limit(params, limit, &mut successes, outcome), Ordering::SeqCst);
+        }
+        self.wake_one();
+    }
+
+    fn wake_one(&self) {
+        if let Some(waiter) = self.waiters.lock().unwrap().pop_front() {
+            waiter.woken.store(true, Ordering::SeqCst);
+            if let Some(task) = waiter.task.lock().unwrap().take() {
+                task.notify();
+            }
+        }
+    }
+
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn limit(&self) -> usize {
+        self.limit.load(Ordering::SeqCst)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn waiting(&self) -> usize {
+        self.waiters.lock().unwrap().len()
+    }
+}
+
+// Additive increase, multiplicative decrease; other failures leave the
+// limit as it is.
+fn next_limit(params: &AdaptiveConcurrency, limit: usize, successes: &mut usize, outcome: Outcome) -> usize {
+    match outcome {
+        Outcome::Success => {
+            *successes += 1;
+            if *successes >= limit {
+                *successes = 0;
+                (limit + 1).min(params.max_limit)
+            } else {
+                limit
+            }
+        },
+        Outcome::Overloaded => {
+            *successes = 0;
+            let reduced = (limit as f64 * params.backoff_ratio) as usize;
+            reduced.max(params.min_limit).max(1)
+        },
+        Outcome::Failure =>
+            limit,
+    }
+}
+
+// Gives the slot back when dropped, with the outcome set by `finish`
+// (`Failure` if the request never completed).
+pub(crate) struct Permit {
+    limiter: Arc<Limiter>,
+    outcome: Outcome,
+}
+
+impl Permit {
+    pub(crate) fn finish<T>(mut self, result: &Result<T, cassandra_cpp::Error>) {
+        self.outcome = Outcome::of(result);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limiter.release(self.outcome);
+    }
+}
+
+// Waiters are woken one at a time as slots free up. A waiter dropped after
+// its wake up passes it on, so no free slot is left with everyone asleep.
+pub(crate) struct Acquire {
+    limiter: Arc<Limiter>,
+    waiter: Option<Arc<Waiter>>,
+}
+
+impl Future for Acquire {
+    type Item = Permit;
+    type Error = cassandra_cpp::Error;
+
+    fn poll(&mut self) -> Poll<Permit, cassandra_cpp::Error> {
+        loop {
+            if self.limiter.try_acquire() {
+                if let Some(waiter) = self.waiter.take() {
+                    self.forget(&waiter);
+                }
+                return Ok(Async::Ready(Permit { limiter: self.limiter.clone(), outcome: Outcome::Failure, }));
+            }
+            let waiter = self.waiter.get_or_insert_with(Default::default).clone();
+            let mut waiters = self.limiter.waiters.lock().unwrap();
+            *waiter.task.lock().unwrap() = Some(task::current());
+            let queued = waiters.iter().any(|queued| Arc::ptr_eq(queued, &waiter));
+            if !queued {
+                waiter.woken.store(false, Ordering::SeqCst);
+                waiters.push_back(waiter);
+            }
+            drop(waiters);
+            // A slot freed before the waiter was queued woke nobody.
+            if self.limiter.in_flight() < self.limiter.limit() {
+                continue;
+            }
+            return Ok(Async::NotReady);
+        }
+    }
+}
+
+impl Acquire {
+    // Returns whether the waiter was still queued, i.e. not woken.
+    fn forget(&self, waiter: &Arc<Waiter>) -> bool {
+        let mut waiters = self.limiter.waiters.lock().unwrap();
+        let before = waiters.len();
+        waiters.retain(|queued| !Arc::ptr_eq(queued, waiter));
+        before != waiters.len()
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter.take() {
+            if !self.forget(&waiter) && waiter.woken.load(Ordering::SeqCst) {
+                self.limiter.wake_one();
+            }
+        }
+    }
+}
+
+fn is_overloaded(error: &cassandra_cpp::Error) -> bool {
+    let code = match error.kind() {
+        ErrorKind::CassError(code, _) =>
+            code,
+        ErrorKind::CassErrorResult(code, ..) =>
+            code,
+        _ =>
+            return false,
+    };
+    match code {
+        CassErrorCode::LIB_REQUEST_TIMED_OUT |
+        CassErrorCode::LIB_REQUEST_QUEUE_FULL |
+        CassErrorCode::SERVER_OVERLOADED |
+        CassErrorCode::SERVER_READ_TIMEOUT |
+        CassErrorCode::SERVER_WRITE_TIMEOUT =>
+            true,
+        _ =>
+            false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{
+            Ordering,
+            AtomicUsize,
+        },
+    };
+
+    use futures::{
+        Async,
+        executor::{
+            self,
+            Notify,
+            NotifyHandle,
+        },
+    };
+
+    use super::{
+        next_limit,
+        Limiter,
+        Outcome,
+        AdaptiveConcurrency,
+    };
+
+    #[derive(Default)]
+    struct Wakes(AtomicUsize);
+
+    impl Notify for Wakes {
+        fn notify(&self, _id: usize) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn wakes() -> (Arc<Wakes>, NotifyHandle) {
+        let wakes = Arc::new(Wakes::default());
+        (wakes.clone(), NotifyHandle::from(wakes))
+    }
+
+    #[test]
+    fn release_wakes_one_waiter() {
+        let limiter = Arc::new(Limiter::new(1, None));
+        let permit = Limiter::try_permit(&limiter).unwrap();
+        let (first_wakes, first_handle) = wakes();
+        let (second_wakes, second_handle) = wakes();
+        let mut first = executor::spawn(Limiter::acquire(&limiter));
+        let mut second = executor::spawn(Limiter::acquire(&limiter));
+        assert!(first.poll_future_notify(&first_handle, 0).unwrap().is_not_ready());
+        assert!(second.poll_future_notify(&second_handle, 0).unwrap().is_not_ready());
+        assert_eq!(limiter.waiting(), 2);
+
+        drop(permit);
+        assert_eq!(first_wakes.0.load(Ordering::SeqCst), 1);
+        assert_eq!(second_wakes.0.load(Ordering::SeqCst), 0);
+        let first_permit = match first.poll_future_notify(&first_handle, 0).unwrap() {
+            Async::Ready(permit) =>
+                permit,
+            Async::NotReady =>
+                panic!("woken waiter did not get the slot"),
+        };
+        assert_eq!(limiter.in_flight(), 1);
+        assert_eq!(limiter.waiting(), 1);
+
+        drop(first_permit);
+        assert_eq!(second_wakes.0.load(Ordering::SeqCst), 1);
+        assert!(second.poll_future_notify(&second_handle, 0).unwrap().is_ready());
+        assert_eq!(limiter.waiting(), 0);
+    }
+
+    #[test]
+    fn dropped_waiter_passes_wake_up_on() {
+        let limiter = Arc::new(Limiter::new(1, None));
+        let permit = Limiter::try_permit(&limiter).unwrap();
+        let (_first_wakes, first_handle) = wakes();
+        let (second_wakes, second_handle) = wakes();
+        let mut first = executor::spawn(Limiter::acquire(&limiter));
+        let mut second = executor::spawn(Limiter::acquire(&limiter));
+        assert!(first.poll_future_notify(&first_handle, 0).unwrap().is_not_ready());
+        assert!(second.poll_future_notify(&second_handle, 0).unwrap().is_not_ready());
+
+        drop(permit);
+        drop(first);
+        assert_eq!(second_wakes.0.load(Ordering::SeqCst), 1);
+        let second_permit = second.poll_future_notify(&second_handle, 0).unwrap();
+        assert!(second_permit.is_ready());
+        assert_eq!(limiter.in_flight(), 1);
+    }
+
+    #[test]
+    fn try_permit_fails_at_limit() {
+        let limiter = Arc::new(Limiter::new(2, None));
+        let first = Limiter::try_permit(&limiter).unwrap();
+        let _second = Limiter::try_permit(&limiter).unwrap();
+        assert!(Limiter::try_permit(&limiter).is_none());
+        drop(first);
+        assert!(Limiter::try_permit(&limiter).is_some());
+    }
+
+    fn aimd(min_limit: usize, max_limit: usize) -> AdaptiveConcurrency {
+        AdaptiveConcurrency { initial_limit: 4, min_limit, max_limit, backoff_ratio: 0.5, }
+    }
+
+    #[test]
+    fn limit_grows_after_a_limits_worth_of_successes() {
+        let params = aimd(1, 100);
+        let mut successes = 0;
+        for _ in 0 .. 3 {
+            assert_eq!(next_limit(&params, 4, &mut successes, Outcome::Success), 4);
+        }
+        assert_eq!(next_limit(&params, 4, &mut successes, Outcome::Success), 5);
+        assert_eq!(successes, 0);
+    }
+
+    #[test]
+    fn limit_growth_is_capped() {
+        let params = aimd(1, 4);
+        let mut successes = 3;
+        assert_eq!(next_limit(&params, 4, &mut successes, Outcome::Success), 4);
+        assert_eq!(successes, 0);
+    }
+
+    #[test]
+    fn overload_cuts_limit_and_successes() {
+        let params = aimd(2, 100);
+        let mut successes = 7;
+        assert_eq!(next_limit(&params, 10, &mut successes, Outcome::Overloaded), 5);
+        assert_eq!(successes, 0);
+        assert_eq!(next_limit(&params, 3, &mut successes, Outcome::Overloaded), 2);
+        assert_eq!(next_limit(&params, 2, &mut successes, Outcome::Overloaded), 2);
+        assert_eq!(next_limit(&aimd(0, 100), 1, &mut successes, Outcome::Overloaded), 1);
+    }
+
+    #[test]
+    fn other_failures_keep_limit() {
+        let params = aimd(1, 100);
+        let mut successes = 2;
+        assert_eq!(next_limit(&params, 4, &mut successes, Outcome::Failure), 4);
+        assert_eq!(successes, 2);
+    }
+
+    #[test]
+    fn released_permits_adjust_limit() {
+        let limiter = Arc::new(Limiter::new(1, Some(aimd(1, 100))));
+        assert_eq!(limiter.limit(), 4);
+        for _ in 0 .. 4 {
+            limiter.try_acquire();
+            limiter.release(Outcome::Success);
+        }
+        assert_eq!(limiter.limit(), 5);
+        limiter.try_acquire();
+        limiter.release(Outcome::Overloaded);
+        assert_eq!(limiter.limit(), 2);
+        assert_eq!(limiter.in_flight(), 0);
+    }
+}
@@ -0,0 +1,63 @@
+use futures::Future;
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+};
+
+use super::{
+    SharedSession,
+    value::CqlValue,
+};
+
+const APPLIED_COLUMN: &str = "[applied]";
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum LwtResult {
+    Applied,
+    // Columns of the conflicting row as returned by the server, without the
+    // `[applied]` sentinel. Empty when the row did not exist.
+    NotApplied { current_row: Vec<(String, CqlValue)>, },
+}
+
+impl LwtResult {
+    pub fn is_applied(&self) -> bool {
+        match self {
+            LwtResult::Applied => true,
+            LwtResult::NotApplied { .. } => false,
+        }
+    }
+
+    pub fn from_result(cass_result: &CassResult) -> Result<LwtResult, cassandra_cpp::Error> {
+        let columns = (0 .. cass_result.column_count() as usize)
+            .map(|index| cass_result.column_name(index).map(|name| name.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let applied_index = columns.iter()
+            .position(|column| column == APPLIED_COLUMN)
+            .ok_or_else(|| -> cassandra_cpp::Error {
+                "conditional statement result has no [applied] column".into()
+            })?;
+        let row = cass_result.first_row()
+            .ok_or_else(|| -> cassandra_cpp::Error {
+                "conditional statement returned no rows".into()
+            })?;
+        if row.get_column(applied_index)?.get_bool()? {
+            return Ok(LwtResult::Applied);
+        }
+        let mut current_row = Vec::with_capacity(columns.len() - 1);
+        for (index, column) in columns.into_iter().enumerate() {
+            if index != applied_index {
+                let value = CqlValue::read(&row.get_column(index)?)?;
+                current_row.push((column, value));
+            }
+        }
+        Ok(LwtResult::NotApplied { current_row, })
+    }
+}
+
+impl SharedSession {
+    pub fn execute_lwt(&self, statement: Statement) -> impl Future<Item = LwtResult, Error = cassandra_cpp::Error> {
+        self.execute_guarded(statement)
+            .and_then(|cass_result| LwtResult::from_result(&cass_result))
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    sync::Arc,
+    time::{
+        Instant,
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use futures::{
+    Future,
+    IntoFuture,
+    future::{
+        result,
+        loop_fn,
+        Loop,
+        Either,
+    },
+};
+
+use tokio::timer::Delay;
+
+use log::debug;
+
+use super::limit::Limiter;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MaintenanceWindow {
+    // Offset from midnight UTC.
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct MaintenanceParams {
+    pub windows: Vec<MaintenanceWindow>,
+    pub max_concurrent_jobs: usize,
+    pub busy_retry_interval: Duration,
+}
+
+impl Default for MaintenanceParams {
+    fn default() -> MaintenanceParams {
+        MaintenanceParams {
+            windows: Vec::new(),
+            max_concurrent_jobs: 1,
+            busy_retry_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MaintenanceError<E> {
+    Timer(tokio::timer::Error),
+    Job(E),
+}
+
+#[derive(Clone)]
+pub struct MaintenanceScheduler {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    params: MaintenanceParams,
+    limiter: Arc<Limiter>,
+}
+
+impl MaintenanceScheduler {
+    pub fn new(params: MaintenanceParams) -> MaintenanceScheduler {
+        let limiter = Arc::new(Limiter::new(params.max_concurrent_jobs, None));
+        MaintenanceScheduler {
+            inner: Arc::new(Inner { params, limiter, }),
+        }
+    }
+
+    pub fn running_jobs(&self) -> usize {
+        self.inner.limiter.in_flight()
+    }
+
+    pub fn time_until_open(&self) -> Duration {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_else(|_| Duration::from_secs(0));
+        let time_of_day = Duration::new(since_epoch.as_secs() % SECONDS_PER_DAY, since_epoch.subsec_nanos());
+        time_until_open(&self.inner.params.windows, time_of_day)
+    }
+
+    pub fn run<F, R>(
+        &self,
+        name: &str,
+        job: F,
+    )
+        -> Box<dyn Future<Item = R::Item, Error = MaintenanceError<R::Error>> + Send + 'static>
+    where F: FnOnce() -> R + Send + 'static,
+          R: IntoFuture + 'static,
+          R::Future: Send + 'static,
+          R::Item: Send + 'static,
+          R::Error: Send + 'static,
+    {
+        let scheduler = self.clone();
+        let job_name = name.to_string();
+        // The slot is held by the permit until the job resolves or is
+        // dropped.
+        let wait = loop_fn((), move |()| {
+            let delay = scheduler.time_until_open();
+            let maybe_permit = if delay == Duration::from_secs(0) {
+                Limiter::try_permit(&scheduler.inner.limiter)
+            } else {
+                None
+            };
+            if let Some(permit) = maybe_permit {
+                Either::A(result(Ok(Loop::Break(permit))))
+            } else {
+                let delay = if delay == Duration::from_secs(0) {
+                    scheduler.inner.params.busy_retry_interval
+                } else {
+                    delay
+                };
+                debug!("maintenance job {:?} deferred for {:?}", job_name, delay);
+                Either::B(
+                    Delay::new(Instant::now() + delay)
+                        .map(|()| Loop::Continue(()))
+                        .map_err(MaintenanceError::Timer)
+                )
+            }
+        });
+
+        let job_name = name.to_string();
+        let future = wait
+            .and_then(move |permit| {
+                debug!("maintenance job {:?} started", job_name);
+                job().into_future()
+                    .then(move |job_result| {
+                        drop(permit);
+                        debug!("maintenance job {:?} finished", job_name);
+                        job_result.map_err(MaintenanceError::Job)
+                    })
+            });
+        Box::new(future)
+    }
+}
+
+fn time_until_open(windows: &[MaintenanceWindow], time_of_day: Duration) -> Duration {
+    let day = Duration::from_secs(SECONDS_PER_DAY);
+    windows.iter()
+        .map(|window| {
+            let end = window.start + window.duration;
+            let inside = if end <= day {
+                time_of_day >= window.start && time_of_day < end
+            } else {
+                time_of_day >= window.start || time_of_day < end - day
+            };
+            if inside {
+                Duration::from_secs(0)
+            } else if time_of_day < window.start {
+                window.start - time_of_day
+            } else {
+                day - time_of_day + window.start
+            }
+        })
+        .min()
+        .unwrap_or(Duration::from_secs(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::future;
+
+    use tokio::{
+        runtime::current_thread::Runtime,
+        timer::Timeout,
+    };
+
+    use super::{
+        time_until_open,
+        MaintenanceParams,
+        MaintenanceWindow,
+        MaintenanceScheduler,
+    };
+
+    fn hours(hours: u64) -> Duration {
+        Duration::from_secs(hours * 60 * 60)
+    }
+
+    fn window(start: u64, duration: u64) -> MaintenanceWindow {
+        MaintenanceWindow { start: hours(start), duration: hours(duration), }
+    }
+
+    #[test]
+    fn open_inside_window() {
+        let windows = [window(2, 3)];
+        assert_eq!(time_until_open(&windows, hours(2)), Duration::from_secs(0));
+        assert_eq!(time_until_open(&windows, hours(4)), Duration::from_secs(0));
+        assert_eq!(time_until_open(&windows, hours(5)), hours(21));
+    }
+
+    #[test]
+    fn waits_for_later_start_today_or_tomorrow() {
+        let windows = [window(2, 1)];
+        assert_eq!(time_until_open(&windows, hours(1)), hours(1));
+        assert_eq!(time_until_open(&windows, hours(3)), hours(23));
+    }
+
+    #[test]
+    fn window_wrapping_midnight() {
+        let windows = [window(22, 4)];
+        assert_eq!(time_until_open(&windows, hours(23)), Duration::from_secs(0));
+        assert_eq!(time_until_open(&windows, hours(1)), Duration::from_secs(0));
+        assert_eq!(time_until_open(&windows, hours(2)), hours(20));
+    }
+
+    #[test]
+    fn nearest_of_several_windows() {
+        let windows = [window(20, 1), window(6, 1)];
+        assert_eq!(time_until_open(&windows, hours(4)), hours(2));
+        assert_eq!(time_until_open(&windows, hours(10)), hours(10));
+    }
+
+    #[test]
+    fn always_open_without_windows() {
+        assert_eq!(time_until_open(&[], hours(12)), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn dropped_job_frees_its_slot() {
+        let mut runtime = Runtime::new().unwrap();
+        let scheduler = MaintenanceScheduler::new(MaintenanceParams::default());
+        let job = scheduler.run("stuck", future::empty::<(), ()>);
+        let timeout_result = runtime.block_on(Timeout::new(job, Duration::from_millis(20)));
+        assert!(timeout_result.unwrap_err().is_elapsed());
+        assert_eq!(scheduler.running_jobs(), 0);
+    }
+}
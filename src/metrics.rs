@@ -0,0 +1,241 @@
+use std::{
+    sync::{
+        Arc,
+        Weak,
+        Mutex,
+        atomic::{
+            Ordering,
+            AtomicBool,
+            AtomicUsize,
+        },
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use cassandra_cpp::Session;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use super::{
+    Shared,
+    SharedSession,
+    RetryBudget,
+    RetryBudgetStats,
+};
+
+const CONNECT_LATENCY_SAMPLES: usize = 1024;
+
+// Latencies are in microseconds, rates in requests per second, as reported
+// by the driver.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RequestMetrics {
+    pub min: u64,
+    pub max: u64,
+    pub mean: u64,
+    pub stddev: u64,
+    pub median: u64,
+    pub percentile_75th: u64,
+    pub percentile_95th: u64,
+    pub percentile_98th: u64,
+    pub percentile_99th: u64,
+    pub percentile_999th: u64,
+    pub mean_rate: f64,
+    pub one_minute_rate: f64,
+    pub five_minute_rate: f64,
+    pub fifteen_minute_rate: f64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ConnectionMetrics {
+    pub total_connections: u64,
+    pub exceeded_pending_requests_water_mark: u64,
+    pub exceeded_write_bytes_water_mark: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ErrorMetrics {
+    pub connection_timeouts: u64,
+    pub pending_request_timeouts: u64,
+    pub request_timeouts: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SessionMetrics {
+    pub requests: RequestMetrics,
+    pub connections: ConnectionMetrics,
+    pub errors: ErrorMetrics,
+    // Set when `ClusterParams::retry_budget` is.
+    pub retry_budget: Option<RetryBudgetStats>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct LodeStats {
+    pub connected: bool,
+    pub init_attempts: usize,
+    pub init_failures: usize,
+    pub connects: usize,
+    pub acquires: usize,
+    pub releases: usize,
+    pub resources_lost: usize,
+}
+
+// Lode lifecycle counters, shared across reconnects.
+#[derive(Default)]
+pub struct LodeState {
+    connected: AtomicBool,
+    init_attempts: AtomicUsize,
+    init_failures: AtomicUsize,
+    connects: AtomicUsize,
+    acquires: AtomicUsize,
+    releases: AtomicUsize,
+    resources_lost: AtomicUsize,
+    last_success: Mutex<Option<Instant>>,
+    connect_latencies: Mutex<Vec<Duration>>,
+    session: Mutex<Option<Weak<Session>>>,
+    retry_budget: Mutex<Option<RetryBudget>>,
+}
+
+impl LodeState {
+    pub fn init_started(&self) {
+        self.init_attempts.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn init_failed(&self) {
+        self.init_failures.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn connected(&self, session: Weak<Session>, retry_budget: Option<RetryBudget>, latency: Duration) {
+        self.connects.fetch_add(1, Ordering::SeqCst);
+        *self.session.lock().unwrap() = Some(session);
+        *self.retry_budget.lock().unwrap() = retry_budget;
+        let mut connect_latencies = self.connect_latencies.lock().unwrap();
+        if connect_latencies.len() < CONNECT_LATENCY_SAMPLES {
+            connect_latencies.push(latency);
+        }
+        self.connected.store(true, Ordering::SeqCst);
+    }
+
+    pub fn disconnected(&self) {
+        self.connected.store(false, Ordering::SeqCst);
+        *self.session.lock().unwrap() = None;
+    }
+
+    pub fn acquired(&self) {
+        self.acquires.fetch_add(1, Ordering::SeqCst);
+    }
+
+    // The lode hands back no session when the user gave it up with
+    // `UsingResource::Lost`.
+    pub fn released(&self, reused: bool) {
+        self.releases.fetch_add(1, Ordering::SeqCst);
+        if !reused {
+            self.resources_lost.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    // A request through one of the tracked paths (keepalive and validation
+    // probes, the shared execute path, `health`) succeeded.
+    pub fn succeeded(&self) {
+        *self.last_success.lock().unwrap() = Some(Instant::now());
+    }
+
+    pub fn since_last_success(&self) -> Option<Duration> {
+        self.last_success.lock().unwrap()
+            .map(|last_success| last_success.elapsed())
+    }
+
+    pub fn stats(&self) -> LodeStats {
+        LodeStats {
+            connected: self.connected.load(Ordering::SeqCst),
+            init_attempts: self.init_attempts.load(Ordering::SeqCst),
+            init_failures: self.init_failures.load(Ordering::SeqCst),
+            connects: self.connects.load(Ordering::SeqCst),
+            acquires: self.acquires.load(Ordering::SeqCst),
+            releases: self.releases.load(Ordering::SeqCst),
+            resources_lost: self.resources_lost.load(Ordering::SeqCst),
+        }
+    }
+
+    // Connect latencies recorded since the previous call.
+    pub fn take_connect_latencies(&self) -> Vec<Duration> {
+        let mut connect_latencies = self.connect_latencies.lock().unwrap();
+        connect_latencies.drain(..).collect()
+    }
+
+    pub fn session_metrics(&self) -> Option<SessionMetrics> {
+        let session = self.session.lock().unwrap()
+            .as_ref()
+            .and_then(Weak::upgrade);
+        let retry_budget = self.retry_budget.lock().unwrap().clone();
+        session.map(|session| session_metrics(&session, retry_budget.as_ref()))
+    }
+}
+
+fn session_metrics(session: &Session, retry_budget: Option<&RetryBudget>) -> SessionMetrics {
+    let metrics = session.get_metrics();
+    SessionMetrics {
+        requests: RequestMetrics {
+            min: metrics.min,
+            max: metrics.max,
+            mean: metrics.mean,
+            stddev: metrics.stddev,
+            median: metrics.median,
+            percentile_75th: metrics.percentile_75th,
+            percentile_95th: metrics.percentile_95th,
+            percentile_98th: metrics.percentile_98th,
+            percentile_99th: metrics.percentile_99th,
+            percentile_999th: metrics.percentile_999th,
+            mean_rate: metrics.mean_rate,
+            one_minute_rate: metrics.one_minute_rate,
+            five_minute_rate: metrics.five_minute_rate,
+            fifteen_minute_rate: metrics.fifteen_minute_rate,
+        },
+        connections: ConnectionMetrics {
+            total_connections: metrics.total_connections,
+            exceeded_pending_requests_water_mark: metrics.exceeded_pending_requests_water_mark,
+            exceeded_write_bytes_water_mark: metrics.exceeded_write_bytes_water_mark,
+        },
+        errors: ErrorMetrics {
+            connection_timeouts: metrics.connection_timeouts,
+            pending_request_timeouts: metrics.pending_request_timeouts,
+            request_timeouts: metrics.request_timeouts,
+        },
+        retry_budget: retry_budget.map(RetryBudget::stats),
+    }
+}
+
+impl SharedSession {
+    pub fn metrics(&self) -> SessionMetrics {
+        session_metrics(&self.session, self.settings.retry_budget.as_ref())
+    }
+
+    pub fn lode_stats(&self) -> LodeStats {
+        self.shared.lode.stats()
+    }
+
+    pub fn lode_stats_handle(&self) -> LodeStatsHandle {
+        LodeStatsHandle { shared: self.shared.clone(), }
+    }
+}
+
+// Reads the lode counters without holding a session, e.g. from a scrape
+// endpoint; stays valid across reconnects.
+#[derive(Clone)]
+pub struct LodeStatsHandle {
+    shared: Arc<Shared>,
+}
+
+impl LodeStatsHandle {
+    pub fn stats(&self) -> LodeStats {
+        self.shared.lode.stats()
+    }
+}
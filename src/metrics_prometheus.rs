@@ -0,0 +1,198 @@
+use std::{
+    sync::Weak,
+    time::Duration,
+};
+
+use futures::{
+    Future,
+    Stream,
+};
+
+use tokio::timer::Interval;
+
+use prometheus::{
+    Opts,
+    Registry,
+    IntGauge,
+    GaugeVec,
+    IntGaugeVec,
+    Histogram,
+    HistogramOpts,
+};
+
+use log::error;
+
+use super::{
+    Shared,
+    spawner::{
+        self,
+        TaskSpawner,
+    },
+};
+
+#[derive(Clone)]
+pub struct PrometheusParams {
+    pub registry: Registry,
+    pub prefix: String,
+    pub sample_interval: Duration,
+}
+
+impl PrometheusParams {
+    pub fn new(registry: Registry) -> PrometheusParams {
+        PrometheusParams {
+            registry,
+            prefix: "ero_cassandra".to_string(),
+            sample_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+struct Collectors {
+    connected: IntGauge,
+    init_attempts: IntGauge,
+    init_failures: IntGauge,
+    connects: IntGauge,
+    acquires: IntGauge,
+    releases: IntGauge,
+    resources_lost: IntGauge,
+    connect_latency: Histogram,
+    request_latency: GaugeVec,
+    request_rate: GaugeVec,
+    connections: IntGauge,
+    timeouts: IntGaugeVec,
+    retry_budget: IntGaugeVec,
+}
+
+impl Collectors {
+    fn register(params: &PrometheusParams) -> Result<Collectors, prometheus::Error> {
+        let name = |metric: &str| format!("{}_{}", params.prefix, metric);
+        let collectors = Collectors {
+            connected: IntGauge::new(name("connected"), "1 when the lode holds a connected session")?,
+            init_attempts: IntGauge::new(name("init_attempts"), "Connection attempts made by the lode")?,
+            init_failures: IntGauge::new(name("init_failures"), "Failed connection attempts")?,
+            connects: IntGauge::new(name("connects"), "Successful connects made by the lode")?,
+            acquires: IntGauge::new(name("acquires"), "Sessions handed out by the lode")?,
+            releases: IntGauge::new(name("releases"), "Sessions given back to the lode")?,
+            resources_lost: IntGauge::new(name("resources_lost"), "Sessions given back as lost")?,
+            connect_latency: Histogram::with_opts(HistogramOpts::new(
+                name("connect_latency_seconds"),
+                "Time from starting init to a connected session",
+            ))?,
+            request_latency: GaugeVec::new(
+                Opts::new(name("request_latency_microseconds"), "Driver request latency"),
+                &["quantile"],
+            )?,
+            request_rate: GaugeVec::new(
+                Opts::new(name("request_rate"), "Driver requests per second"),
+                &["window"],
+            )?,
+            connections: IntGauge::new(name("connections"), "Open driver connections")?,
+            timeouts: IntGaugeVec::new(
+                Opts::new(name("timeouts"), "Driver timeouts since connect"),
+                &["kind"],
+            )?,
+            retry_budget: IntGaugeVec::new(
+                Opts::new(name("retry_budget"), "Retry budget requests, retries and limit in the current window, and retries refused"),
+                &["kind"],
+            )?,
+        };
+        params.registry.register(Box::new(collectors.connected.clone()))?;
+        params.registry.register(Box::new(collectors.init_attempts.clone()))?;
+        params.registry.register(Box::new(collectors.init_failures.clone()))?;
+        params.registry.register(Box::new(collectors.connects.clone()))?;
+        params.registry.register(Box::new(collectors.acquires.clone()))?;
+        params.registry.register(Box::new(collectors.releases.clone()))?;
+        params.registry.register(Box::new(collectors.resources_lost.clone()))?;
+        params.registry.register(Box::new(collectors.connect_latency.clone()))?;
+        params.registry.register(Box::new(collectors.request_latency.clone()))?;
+        params.registry.register(Box::new(collectors.request_rate.clone()))?;
+        params.registry.register(Box::new(collectors.connections.clone()))?;
+        params.registry.register(Box::new(collectors.timeouts.clone()))?;
+        params.registry.register(Box::new(collectors.retry_budget.clone()))?;
+        Ok(collectors)
+    }
+
+    fn sample(&self, shared: &Shared) {
+        let stats = shared.lode.stats();
+        self.connected.set(if stats.connected { 1 } else { 0 });
+        self.init_attempts.set(stats.init_attempts as i64);
+        self.init_failures.set(stats.init_failures as i64);
+        self.connects.set(stats.connects as i64);
+        self.acquires.set(stats.acquires as i64);
+        self.releases.set(stats.releases as i64);
+        self.resources_lost.set(stats.resources_lost as i64);
+        for latency in shared.lode.take_connect_latencies() {
+            self.connect_latency.observe(latency.as_secs() as f64 + latency.subsec_nanos() as f64 / 1e9);
+        }
+        if let Some(metrics) = shared.lode.session_metrics() {
+            let requests = metrics.requests;
+            for &(quantile, value) in &[
+                ("0.5", requests.median),
+                ("0.75", requests.percentile_75th),
+                ("0.95", requests.percentile_95th),
+                ("0.98", requests.percentile_98th),
+                ("0.99", requests.percentile_99th),
+                ("0.999", requests.percentile_999th),
+                ("max", requests.max),
+                ("mean", requests.mean),
+            ] {
+                self.request_latency.with_label_values(&[quantile]).set(value as f64);
+            }
+            for &(window, value) in &[
+                ("mean", requests.mean_rate),
+                ("1m", requests.one_minute_rate),
+                ("5m", requests.five_minute_rate),
+                ("15m", requests.fifteen_minute_rate),
+            ] {
+                self.request_rate.with_label_values(&[window]).set(value);
+            }
+            self.connections.set(metrics.connections.total_connections as i64);
+            let errors = metrics.errors;
+            for &(kind, value) in &[
+                ("connection", errors.connection_timeouts),
+                ("pending_request", errors.pending_request_timeouts),
+                ("request", errors.request_timeouts),
+            ] {
+                self.timeouts.with_label_values(&[kind]).set(value as i64);
+            }
+            if let Some(retry_budget) = metrics.retry_budget {
+                for &(kind, value) in &[
+                    ("requests", retry_budget.requests),
+                    ("retries", retry_budget.retries),
+                    ("max_retries", retry_budget.max_retries),
+                    ("exhausted", retry_budget.exhausted),
+                ] {
+                    self.retry_budget.with_label_values(&[kind]).set(value as i64);
+                }
+            }
+        }
+    }
+}
+
+// Samples until the lode and every session handle are gone.
+pub(crate) fn spawn(params: PrometheusParams, shared: Weak<Shared>, task_spawner: Option<&TaskSpawner>) {
+    let collectors = match Collectors::register(&params) {
+        Ok(collectors) =>
+            collectors,
+        Err(error) => {
+            error!("error registering prometheus collectors: {:?}", error);
+            return;
+        },
+    };
+    let future = Interval::new_interval(params.sample_interval)
+        .map_err(|error| error!("prometheus sampling timer error: {:?}", error))
+        .for_each(move |_instant| {
+            match shared.upgrade() {
+                Some(shared) => {
+                    collectors.sample(&shared);
+                    Ok(())
+                },
+                None =>
+                    Err(()),
+            }
+        })
+        .then(|_| Ok(()));
+    if let Err(error) = spawner::spawn(task_spawner, Box::new(future)) {
+        error!("error spawning prometheus sampling task: {:?}", error);
+    }
+}
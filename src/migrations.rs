@@ -0,0 +1,306 @@
+use std::{
+    process,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::{
+        Instant,
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+    collections::HashSet,
+};
+
+use futures::{
+    stream,
+    Future,
+    Stream,
+    future::{
+        result,
+        loop_fn,
+        Loop,
+        Either,
+    },
+};
+
+use tokio::timer::Delay;
+
+use cassandra_cpp::{
+    Session,
+    CassResult,
+    Statement,
+    Consistency,
+};
+
+use log::{
+    info,
+    warn,
+    debug,
+};
+
+use super::{
+    cql,
+    lwt::LwtResult,
+};
+
+const MIGRATIONS_TABLE: &str = "schema_migrations";
+const LOCK_TABLE: &str = "schema_migrations_lock";
+const LOCK_NAME: &str = "migrations";
+// A lock left behind by a process that died while migrating expires after
+// this. The owner renews it between statements once `LOCK_RENEW_INTERVAL`
+// has passed, so only a single statement running longer than the rest of
+// the TTL lets another process in, and the renewal after it then fails.
+const LOCK_TTL: Duration = Duration::from_secs(300);
+const LOCK_RENEW_INTERVAL: Duration = Duration::from_secs(60);
+const LOCK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug)]
+pub struct Migration {
+    pub name: String,
+    pub script: String,
+}
+
+impl Migration {
+    pub fn new<N, S>(name: N, script: S) -> Migration where N: Into<String>, S: Into<String> {
+        Migration {
+            name: name.into(),
+            script: script.into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MigrationError {
+    pub migration: Option<String>,
+    pub statement: String,
+    pub error: cassandra_cpp::Error,
+}
+
+// Both tables live in `keyspace` when one is given. Only one process
+// applies migrations at a time: the others wait for the lock row, then find
+// the migrations applied.
+pub fn run(
+    session: Arc<Session>,
+    keyspace: Option<String>,
+    migrations: Vec<Migration>,
+)
+    -> Box<dyn Future<Item = (), Error = MigrationError> + Send + 'static>
+{
+    let keyspace = keyspace.as_ref().map(String::as_str);
+    let migrations_table = qualified(keyspace, MIGRATIONS_TABLE);
+    let lock = Lock {
+        table: qualified(keyspace, LOCK_TABLE),
+        owner: lock_owner(),
+        renewed_at: Mutex::new(Instant::now()),
+    };
+    let create_queries = vec![
+        format!("CREATE TABLE IF NOT EXISTS {} (name text PRIMARY KEY, applied_at timestamp)", migrations_table),
+        format!("CREATE TABLE IF NOT EXISTS {} (name text PRIMARY KEY, owner text, locked_at timestamp)", lock.table),
+    ];
+    let create_session = session.clone();
+    let future = stream::iter_ok(create_queries)
+        .for_each(move |query| {
+            execute(&create_session, None, query, None)
+                .map(|_cass_result| ())
+        })
+        .and_then(move |()| acquire_lock(session, lock))
+        .and_then(move |(session, lock)| {
+            let lock = Arc::new(lock);
+            apply_pending(session.clone(), lock.clone(), migrations_table, migrations)
+                .then(move |apply_result| {
+                    release_lock(&session, &lock)
+                        .then(move |release_result| {
+                            if let Err(error) = release_result {
+                                warn!("error releasing the schema migrations lock, it expires in {:?}: {:?}", LOCK_TTL, error);
+                            }
+                            apply_result
+                        })
+                })
+        });
+    Box::new(future)
+}
+
+fn qualified(keyspace: Option<&str>, table: &str) -> String {
+    match keyspace {
+        Some(keyspace) =>
+            format!("{}.{}", cql::quote_identifier(keyspace), table),
+        None =>
+            table.to_string(),
+    }
+}
+
+struct Lock {
+    table: String,
+    owner: String,
+    renewed_at: Mutex<Instant>,
+}
+
+fn lock_owner() -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_secs(0));
+    format!("{}-{}", process::id(), since_epoch.as_nanos())
+}
+
+fn acquire_lock(session: Arc<Session>, lock: Lock) -> impl Future<Item = (Arc<Session>, Lock), Error = MigrationError> {
+    loop_fn((session, lock), |(session, lock)| {
+        let query = format!(
+            "INSERT INTO {} (name, owner, locked_at) VALUES ({}, {}, toTimestamp(now())) IF NOT EXISTS USING TTL {}",
+            lock.table,
+            cql::quote_literal(LOCK_NAME),
+            cql::quote_literal(&lock.owner),
+            LOCK_TTL.as_secs(),
+        );
+        execute(&session, None, query.clone(), None)
+            .and_then(move |cass_result| {
+                LwtResult::from_result(&cass_result)
+                    .map_err(|error| MigrationError { migration: None, statement: query, error, })
+            })
+            .and_then(move |lwt_result| match lwt_result {
+                LwtResult::Applied => {
+                    debug!("schema migrations lock taken as {:?}", lock.owner);
+                    *lock.renewed_at.lock().unwrap() = Instant::now();
+                    Either::A(result(Ok(Loop::Break((session, lock)))))
+                },
+                LwtResult::NotApplied { current_row, } => {
+                    info!("schema migrations are locked ({:?}), waiting", current_row);
+                    let future = Delay::new(Instant::now() + LOCK_POLL_INTERVAL)
+                        .map_err(|timer_error| MigrationError {
+                            migration: None,
+                            statement: String::new(),
+                            error: format!("schema migrations lock timer error: {:?}", timer_error).into(),
+                        })
+                        .map(move |()| Loop::Continue((session, lock)));
+                    Either::B(future)
+                },
+            })
+    })
+}
+
+fn release_lock(session: &Session, lock: &Lock) -> impl Future<Item = (), Error = MigrationError> {
+    let query = format!(
+        "DELETE FROM {} WHERE name = {} IF owner = {}",
+        lock.table,
+        cql::quote_literal(LOCK_NAME),
+        cql::quote_literal(&lock.owner),
+    );
+    execute(session, None, query, None)
+        .map(|_cass_result| ())
+}
+
+// Pushes the lock expiry another `LOCK_TTL` away once `LOCK_RENEW_INTERVAL`
+// has passed since it was taken or last renewed. Fails when the lock has
+// expired and someone else holds it now.
+fn renew_lock(session: &Session, lock: Arc<Lock>, migration: &str) -> impl Future<Item = (), Error = MigrationError> {
+    if lock.renewed_at.lock().unwrap().elapsed() < LOCK_RENEW_INTERVAL {
+        return Either::A(result(Ok(())));
+    }
+    let query = format!(
+        "UPDATE {} USING TTL {} SET locked_at = toTimestamp(now()) WHERE name = {} IF owner = {}",
+        lock.table,
+        LOCK_TTL.as_secs(),
+        cql::quote_literal(LOCK_NAME),
+        cql::quote_literal(&lock.owner),
+    );
+    let renewed_at = Instant::now();
+    let migration = migration.to_string();
+    let future = execute(session, Some(&migration), query.clone(), None)
+        .and_then(move |cass_result| {
+            let lwt_result = LwtResult::from_result(&cass_result)
+                .map_err(|error| MigrationError { migration: Some(migration.clone()), statement: query.clone(), error, })?;
+            match lwt_result {
+                LwtResult::Applied => {
+                    *lock.renewed_at.lock().unwrap() = renewed_at;
+                    Ok(())
+                },
+                LwtResult::NotApplied { current_row, } =>
+                    Err(MigrationError {
+                        migration: Some(migration),
+                        statement: query,
+                        error: format!("schema migrations lock of {:?} expired and was taken over ({:?})", lock.owner, current_row).into(),
+                    }),
+            }
+        });
+    Either::B(future)
+}
+
+fn apply_pending(
+    session: Arc<Session>,
+    lock: Arc<Lock>,
+    migrations_table: String,
+    migrations: Vec<Migration>,
+)
+    -> impl Future<Item = (), Error = MigrationError>
+{
+    // Read at QUORUM like the records are written, so a migration another
+    // process just applied is never run twice.
+    let select_query = format!("SELECT name FROM {}", migrations_table);
+    execute(&session, None, select_query, Some(Consistency::QUORUM))
+        .and_then(move |cass_result| {
+            let mut applied = HashSet::new();
+            for row in cass_result.iter() {
+                if let Ok(name) = row.get_column(0).and_then(|value| value.get_string()) {
+                    applied.insert(name);
+                }
+            }
+            let pending: Vec<_> = migrations.into_iter()
+                .filter(|migration| !applied.contains(&migration.name))
+                .collect();
+            debug!("{} schema migrations already applied, {} pending", applied.len(), pending.len());
+            stream::iter_ok(pending)
+                .for_each(move |migration| apply(session.clone(), lock.clone(), &migrations_table, migration))
+        })
+}
+
+fn apply(
+    session: Arc<Session>,
+    lock: Arc<Lock>,
+    migrations_table: &str,
+    migration: Migration,
+)
+    -> impl Future<Item = (), Error = MigrationError>
+{
+    info!("applying schema migration {:?}", migration.name);
+    let Migration { name, script, } = migration;
+    let record_query = format!(
+        "INSERT INTO {} (name, applied_at) VALUES ({}, toTimestamp(now()))",
+        migrations_table,
+        cql::quote_literal(&name),
+    );
+    let statements_session = session.clone();
+    let statements_name = name.clone();
+    stream::iter_ok(cql::split_statements(&script))
+        .for_each(move |query| {
+            let session = statements_session.clone();
+            let name = statements_name.clone();
+            renew_lock(&statements_session, lock.clone(), &statements_name)
+                .and_then(move |()| execute(&session, Some(&name), query, None))
+                .map(|_cass_result| ())
+        })
+        .and_then(move |()| {
+            execute(&session, Some(&name), record_query, Some(Consistency::QUORUM))
+                .map(|_cass_result| ())
+        })
+}
+
+fn execute(
+    session: &Session,
+    migration: Option<&str>,
+    query: String,
+    consistency: Option<Consistency>,
+)
+    -> impl Future<Item = CassResult, Error = MigrationError>
+{
+    let migration = migration.map(str::to_string);
+    let mut statement = Statement::new(&query, 0);
+    if let Some(consistency) = consistency {
+        if let Err(error) = statement.set_consistency(consistency) {
+            return Either::A(result(Err(MigrationError { migration, statement: query, error, })));
+        }
+    }
+    let future = session.execute(&statement)
+        .map_err(move |error| MigrationError { migration, statement: query, error, });
+    Either::B(future)
+}
@@ -0,0 +1,169 @@
+use std::sync::{
+    Arc,
+    atomic::{
+        Ordering,
+        AtomicUsize,
+    },
+};
+
+use futures::{
+    Future,
+    future::result,
+};
+
+use cassandra_cpp::CassResult;
+
+use ero::{
+    Loop,
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+};
+
+use log::warn;
+
+use super::{
+    SharedSession,
+    value::CqlValue,
+    bind::{
+        ToParams,
+        BindParams,
+    },
+    spawner::{
+        self,
+        TaskSpawner,
+    },
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct MirrorStats {
+    pub writes: usize,
+    pub shadow_writes: usize,
+    pub shadow_failures: usize,
+    pub shadow_dropped: usize,
+}
+
+#[derive(Debug)]
+pub enum MirrorError {
+    Primary(cassandra_cpp::Error),
+    PrimaryGone,
+}
+
+#[derive(Default)]
+struct Counters {
+    writes: AtomicUsize,
+    shadow_writes: AtomicUsize,
+    shadow_failures: AtomicUsize,
+    shadow_dropped: AtomicUsize,
+}
+
+// Sends writes to both lodes, e.g. while migrating to a new cluster. Only
+// the primary result is returned: shadow writes run as background tasks
+// and their failures are logged and counted. Each lode restarts on its own,
+// so a shadow outage never blocks the primary. Reads go to `primary`.
+#[derive(Clone)]
+pub struct MirroredSession {
+    primary: LodeResource<SharedSession>,
+    shadow: LodeResource<SharedSession>,
+    task_spawner: Option<TaskSpawner>,
+    counters: Arc<Counters>,
+}
+
+impl MirroredSession {
+    pub fn new(primary: LodeResource<SharedSession>, shadow: LodeResource<SharedSession>) -> MirroredSession {
+        MirroredSession {
+            primary,
+            shadow,
+            task_spawner: None,
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    pub fn with_task_spawner(self, task_spawner: TaskSpawner) -> MirroredSession {
+        MirroredSession { task_spawner: Some(task_spawner), ..self }
+    }
+
+    pub fn primary(&self) -> &LodeResource<SharedSession> {
+        &self.primary
+    }
+
+    pub fn shadow(&self) -> &LodeResource<SharedSession> {
+        &self.shadow
+    }
+
+    // Statements cannot be cloned, so the query is built on each side from
+    // the text and the bound values.
+    pub fn write<P>(&self, query: &str, params: &P) -> impl Future<Item = CassResult, Error = MirrorError>
+    where P: ToParams + ?Sized,
+    {
+        let values = params.to_params();
+        self.counters.writes.fetch_add(1, Ordering::Relaxed);
+        self.write_shadow(query.to_string(), values.clone());
+
+        let query = query.to_string();
+        self.primary.clone()
+            .using_resource_loop((), move |session, ()| {
+                execute(&session, &query, &values)
+                    .then(|execute_result| -> Result<_, ErrorSeverity<(), ()>> {
+                        Ok((UsingResource::Lost, Loop::Break(execute_result)))
+                    })
+            })
+            .then(|using_result| match using_result {
+                Ok(Ok(cass_result)) =>
+                    Ok(cass_result),
+                Ok(Err(error)) =>
+                    Err(MirrorError::Primary(error)),
+                Err(_error) =>
+                    Err(MirrorError::PrimaryGone),
+            })
+    }
+
+    fn write_shadow(&self, query: String, values: Vec<CqlValue>) {
+        let counters = self.counters.clone();
+        let dropped = self.counters.clone();
+        let task = self.shadow.clone()
+            .using_resource_loop((), move |session, ()| {
+                let counters = counters.clone();
+                execute(&session, &query, &values)
+                    .then(move |execute_result| -> Result<_, ErrorSeverity<(), ()>> {
+                        match execute_result {
+                            Ok(_cass_result) => {
+                                counters.shadow_writes.fetch_add(1, Ordering::Relaxed);
+                            },
+                            Err(error) => {
+                                warn!("shadow write failed: {:?}", error);
+                                counters.shadow_failures.fetch_add(1, Ordering::Relaxed);
+                            },
+                        }
+                        Ok((UsingResource::Lost, Loop::Break(())))
+                    })
+            })
+            .map_err(move |_error| {
+                warn!("shadow lode terminated, write dropped");
+                dropped.shadow_dropped.fetch_add(1, Ordering::Relaxed);
+            });
+        if let Err(spawn_error) = spawner::spawn(self.task_spawner.as_ref(), Box::new(task)) {
+            warn!("shadow write dropped: {:?}", spawn_error);
+            self.counters.shadow_dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn stats(&self) -> MirrorStats {
+        MirrorStats {
+            writes: self.counters.writes.load(Ordering::Relaxed),
+            shadow_writes: self.counters.shadow_writes.load(Ordering::Relaxed),
+            shadow_failures: self.counters.shadow_failures.load(Ordering::Relaxed),
+            shadow_dropped: self.counters.shadow_dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn execute(session: &SharedSession, query: &str, values: &[CqlValue]) -> impl Future<Item = CassResult, Error = cassandra_cpp::Error> {
+    let mut statement = session.statement(query, values.len());
+    let bind_result = statement.bind_params(values).map(|_statement| ());
+    let session = session.clone();
+    result(bind_result)
+        .and_then(move |()| session.execute_guarded(statement))
+}
@@ -0,0 +1,161 @@
+use std::{
+    sync::{
+        Arc,
+        Mutex,
+    },
+    collections::VecDeque,
+};
+
+use futures::{
+    Future,
+    future::result,
+};
+
+use cassandra_cpp::{
+    BatchType,
+    Statement,
+};
+
+use super::{
+    SharedSession,
+    SessionMetrics,
+    value::CqlValue,
+    export::page_records,
+};
+
+pub type CassFuture<T> = Box<dyn Future<Item = T, Error = cassandra_cpp::Error> + Send + 'static>;
+
+// Rows decoded column by column, so they can be produced without a driver
+// result.
+pub type Rows = Vec<Vec<CqlValue>>;
+
+// The part of `SharedSession` that query logic usually needs, for code that
+// should also run against `MockSession`. The names differ from `Session`'s
+// own methods so importing the trait does not change what
+// `shared_session.execute(..)` resolves to.
+pub trait CassSession: Clone + Send + Sync + 'static {
+    fn execute_rows(&self, statement: &Statement) -> CassFuture<Rows>;
+
+    // Prepares `query` and returns a statement bound to it.
+    fn prepare_statement(&self, query: &str) -> CassFuture<Statement>;
+
+    // Resolves with the number of batches executed.
+    fn execute_batches(&self, kind: BatchType, statements: Vec<Statement>, max_batch_size: usize) -> CassFuture<usize>;
+
+    fn session_metrics(&self) -> SessionMetrics;
+}
+
+impl CassSession for SharedSession {
+    fn execute_rows(&self, statement: &Statement) -> CassFuture<Rows> {
+        Box::new(self.session.execute(statement).and_then(|cass_result| {
+            page_records(&cass_result, cass_result.column_count() as usize)
+        }))
+    }
+
+    fn prepare_statement(&self, query: &str) -> CassFuture<Statement> {
+        match self.session.prepare(&self.rewrite_query(query)) {
+            Ok(prepare_future) =>
+                Box::new(prepare_future.map(|prepared| prepared.bind())),
+            Err(error) =>
+                Box::new(result(Err(error))),
+        }
+    }
+
+    fn execute_batches(&self, kind: BatchType, statements: Vec<Statement>, max_batch_size: usize) -> CassFuture<usize> {
+        Box::new(self.execute_batch(kind, statements, max_batch_size).map(|results| results.len()))
+    }
+
+    fn session_metrics(&self) -> SessionMetrics {
+        self.metrics()
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    responses: VecDeque<Result<Rows, String>>,
+    executed: usize,
+    prepared: Vec<String>,
+    batched: Vec<usize>,
+    metrics: SessionMetrics,
+}
+
+// Answers `execute_rows` and `execute_batches` calls with canned responses
+// in the order they were pushed; a call with none left fails. Statements
+// cannot be inspected, so only prepared query texts are recorded.
+#[derive(Clone, Default)]
+pub struct MockSession {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockSession {
+    pub fn new() -> MockSession {
+        MockSession::default()
+    }
+
+    pub fn push_rows(&self, rows: Rows) -> &MockSession {
+        self.state.lock().unwrap().responses.push_back(Ok(rows));
+        self
+    }
+
+    pub fn push_error<E>(&self, error: E) -> &MockSession where E: Into<String> {
+        self.state.lock().unwrap().responses.push_back(Err(error.into()));
+        self
+    }
+
+    pub fn set_metrics(&self, metrics: SessionMetrics) {
+        self.state.lock().unwrap().metrics = metrics;
+    }
+
+    pub fn executed(&self) -> usize {
+        self.state.lock().unwrap().executed
+    }
+
+    pub fn prepared_queries(&self) -> Vec<String> {
+        self.state.lock().unwrap().prepared.clone()
+    }
+
+    // Statement counts of every `execute_batches` call.
+    pub fn batched(&self) -> Vec<usize> {
+        self.state.lock().unwrap().batched.clone()
+    }
+
+    pub fn pending_responses(&self) -> usize {
+        self.state.lock().unwrap().responses.len()
+    }
+
+    fn next_response(&self) -> Result<Rows, cassandra_cpp::Error> {
+        let mut state = self.state.lock().unwrap();
+        state.executed += 1;
+        match state.responses.pop_front() {
+            Some(Ok(rows)) =>
+                Ok(rows),
+            Some(Err(error)) =>
+                Err(error.into()),
+            None =>
+                Err("mock session has no response left".into()),
+        }
+    }
+}
+
+impl CassSession for MockSession {
+    fn execute_rows(&self, _statement: &Statement) -> CassFuture<Rows> {
+        Box::new(result(self.next_response()))
+    }
+
+    fn prepare_statement(&self, query: &str) -> CassFuture<Statement> {
+        self.state.lock().unwrap().prepared.push(query.to_string());
+        let parameter_count = query.matches('?').count();
+        Box::new(result(Ok(Statement::new(query, parameter_count))))
+    }
+
+    // Takes one canned response per call; its rows are ignored.
+    fn execute_batches(&self, _kind: BatchType, statements: Vec<Statement>, max_batch_size: usize) -> CassFuture<usize> {
+        self.state.lock().unwrap().batched.push(statements.len());
+        let batches = (statements.len() + max_batch_size.max(1) - 1) / max_batch_size.max(1);
+        Box::new(result(self.next_response().map(|_rows| batches)))
+    }
+
+    fn session_metrics(&self) -> SessionMetrics {
+        self.state.lock().unwrap().metrics
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    fmt,
+    collections::{
+        HashMap,
+        BTreeMap,
+    },
+};
+
+use cassandra_cpp::Statement;
+
+use super::{
+    SharedSession,
+    ToCqlValue,
+    value::CqlValue,
+    cql::skip_quoted,
+};
+
+// Values for `:name` markers. Names are matched case insensitively, like
+// unquoted CQL identifiers.
+pub trait ToNamedParams {
+    fn to_named_params(&self) -> Vec<(String, CqlValue)>;
+}
+
+impl<K, V> ToNamedParams for HashMap<K, V> where K: AsRef<str>, V: ToCqlValue {
+    fn to_named_params(&self) -> Vec<(String, CqlValue)> {
+        self.iter()
+            .map(|(name, value)| (name.as_ref().to_string(), value.to_cql_value()))
+            .collect()
+    }
+}
+
+impl<K, V> ToNamedParams for BTreeMap<K, V> where K: AsRef<str>, V: ToCqlValue {
+    fn to_named_params(&self) -> Vec<(String, CqlValue)> {
+        self.iter()
+            .map(|(name, value)| (name.as_ref().to_string(), value.to_cql_value()))
+            .collect()
+    }
+}
+
+impl<'a, P> ToNamedParams for &'a P where P: ToNamedParams + ?Sized {
+    fn to_named_params(&self) -> Vec<(String, CqlValue)> {
+        (**self).to_named_params()
+    }
+}
+
+#[derive(Debug)]
+pub enum NamedBindError {
+    // Every marker without a value, in query order.
+    Unbound(Vec<String>),
+    PositionalMarker,
+    Driver(cassandra_cpp::Error),
+}
+
+impl fmt::Display for NamedBindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NamedBindError::Unbound(markers) =>
+                write!(f, "no values for markers: {}", markers.join(", ")),
+            NamedBindError::PositionalMarker =>
+                write!(f, "query mixes `?` with named markers"),
+            NamedBindError::Driver(error) =>
+                write!(f, "error binding named markers: {:?}", error),
+        }
+    }
+}
+
+// Builds the statement for `query` and binds every `:name` marker from
+// `params`. Markers are bound by position, so a name used twice gets the
+// same value at both places.
+pub fn bind_named<P>(query: &str, params: &P) -> Result<Statement, NamedBindError> where P: ToNamedParams + ?Sized {
+    let markers = named_markers(query)?;
+    let mut statement = Statement::new(query, markers.len());
+    bind_markers(&mut statement, &markers, params)?;
+    Ok(statement)
+}
+
+// Names of the `:name` markers in query order, skipping string literals
+// and quoted identifiers.
+pub fn named_markers(query: &str) -> Result<Vec<String>, NamedBindError> {
+    let bytes = query.as_bytes();
+    let mut markers = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        match bytes[offset] {
+            quote @ b'\'' | quote @ b'"' =>
+                offset = skip_quoted(bytes, offset, quote),
+            b'?' =>
+                return Err(NamedBindError::PositionalMarker),
+            b':' if bytes.get(offset + 1).map_or(false, |&byte| byte.is_ascii_alphabetic() || byte == b'_') => {
+                let start = offset + 1;
+                offset = start;
+                while offset < bytes.len() && (bytes[offset].is_ascii_alphanumeric() || bytes[offset] == b'_') {
+                    offset += 1;
+                }
+                markers.push(query[start .. offset].to_lowercase());
+            },
+            _ =>
+                offset += 1,
+        }
+    }
+    Ok(markers)
+}
+
+fn bind_markers<P>(statement: &mut Statement, markers: &[String], params: &P) -> Result<(), NamedBindError> where P: ToNamedParams + ?Sized {
+    let values: HashMap<_, _> = params.to_named_params()
+        .into_iter()
+        .map(|(name, value)| (name.to_lowercase(), value))
+        .collect();
+    let mut unbound: Vec<String> = Vec::new();
+    for marker in markers {
+        if !values.contains_key(marker) && !unbound.contains(marker) {
+            unbound.push(marker.clone());
+        }
+    }
+    if !unbound.is_empty() {
+        return Err(NamedBindError::Unbound(unbound));
+    }
+    for (index, marker) in markers.iter().enumerate() {
+        values[marker].clone().bind(statement, index)
+            .map_err(NamedBindError::Driver)?;
+    }
+    Ok(())
+}
+
+impl SharedSession {
+    // Like `bind_named`, with the keyspace rewrite and statement defaults of
+    // `statement` applied.
+    pub fn bind_named<P>(&self, query: &str, params: &P) -> Result<Statement, NamedBindError> where P: ToNamedParams + ?Sized {
+        let markers = named_markers(query)?;
+        let mut statement = self.statement(query, markers.len());
+        bind_markers(&mut statement, &markers, params)?;
+        Ok(statement)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        bind_named,
+        named_markers,
+        NamedBindError,
+    };
+
+    fn markers(query: &str) -> Vec<String> {
+        named_markers(query).unwrap()
+    }
+
+    #[test]
+    fn markers_in_query_order() {
+        assert_eq!(markers("SELECT * FROM t WHERE k = :key AND c > :from_1"), vec!["key", "from_1"]);
+        assert_eq!(markers("UPDATE t SET v = :V WHERE k = :k AND c = :v"), vec!["v", "k", "v"]);
+        assert!(markers("SELECT * FROM t").is_empty());
+    }
+
+    #[test]
+    fn quoted_markers_are_skipped() {
+        assert_eq!(markers("SELECT * FROM t WHERE s = ':not' AND \"a:b\" = :b"), vec!["b"]);
+        assert_eq!(markers("INSERT INTO t (s, v) VALUES ('it''s :x', :v)"), vec!["v"]);
+    }
+
+    #[test]
+    fn colons_without_a_name_are_skipped() {
+        assert_eq!(markers("INSERT INTO t (m) VALUES ({'a': :value, 'b': 1})"), vec!["value"]);
+        assert_eq!(markers("SELECT * FROM t WHERE k = :1"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn positional_markers_are_rejected() {
+        assert!(matches!(named_markers("SELECT * FROM t WHERE k = :k AND c = ?"), Err(NamedBindError::PositionalMarker)));
+        assert_eq!(markers("SELECT * FROM t WHERE s = '?' AND k = :k"), vec!["k"]);
+    }
+
+    #[test]
+    fn unbound_markers_are_listed_once() {
+        let mut params = HashMap::new();
+        params.insert("K", 1);
+        match bind_named("SELECT * FROM t WHERE k = :k AND a = :a AND b = :b AND c = :a", &params) {
+            Err(NamedBindError::Unbound(unbound)) =>
+                assert_eq!(unbound, vec!["a", "b"]),
+            other =>
+                panic!("expected unbound markers, got {:?}", other.map(|_statement| ())),
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+use futures::{
+    stream,
+    sync::mpsc,
+    Sink,
+    Future,
+    Stream,
+};
+
+use tokio::executor::{
+    Executor,
+    DefaultExecutor,
+};
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+};
+
+use log::warn;
+
+use super::{
+    SharedSession,
+    spawner,
+    row::{
+        FromRow,
+        RowError,
+    },
+};
+
+#[cfg(feature = "serde")]
+use serde::{
+    Serialize,
+    Deserialize,
+};
+
+pub type PageStream = Box<dyn Stream<Item = CassResult, Error = cassandra_cpp::Error> + Send + 'static>;
+
+// Every page comes with the state to resume after it, `None` for the last.
+pub type ResumablePageStream = Box<dyn Stream<Item = (CassResult, Option<PagingState>), Error = cassandra_cpp::Error> + Send + 'static>;
+
+// Opaque driver paging state. It can be stored (as bytes, or as hex through
+// `Display` and `FromStr`) and handed back to `execute_paged_resumable` to
+// continue a scan, also from another process, with the same statement and
+// page size. The server may reject it after an upgrade.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PagingState(Vec<u8>);
+
+impl PagingState {
+    pub fn from_bytes(bytes: Vec<u8>) -> PagingState {
+        PagingState(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl fmt::Display for PagingState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct InvalidPagingState(pub String);
+
+impl fmt::Display for InvalidPagingState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid paging state: {:?}", self.0)
+    }
+}
+
+impl FromStr for PagingState {
+    type Err = InvalidPagingState;
+
+    fn from_str(value: &str) -> Result<PagingState, InvalidPagingState> {
+        if value.len() % 2 != 0 || !value.is_ascii() {
+            return Err(InvalidPagingState(value.to_string()));
+        }
+        (0 .. value.len())
+            .step_by(2)
+            .map(|offset| u8::from_str_radix(&value[offset .. offset + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map(PagingState)
+            .map_err(|_parse_error| InvalidPagingState(value.to_string()))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(default, deny_unknown_fields))]
+pub struct PagingParams {
+    pub page_size: i32,
+    // Pages requested before the consumer asks for them; 0 fetches on demand.
+    pub fetch_ahead: usize,
+    pub max_buffered_pages: usize,
+}
+
+impl Default for PagingParams {
+    fn default() -> PagingParams {
+        PagingParams {
+            page_size: 5000,
+            fetch_ahead: 0,
+            max_buffered_pages: 4,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum QueryStreamError {
+    Driver(cassandra_cpp::Error),
+    Row(RowError),
+    TooManyRows { max_rows: usize, },
+}
+
+impl fmt::Display for QueryStreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryStreamError::Driver(error) =>
+                write!(f, "error fetching page: {:?}", error),
+            QueryStreamError::Row(error) =>
+                write!(f, "{}", error),
+            QueryStreamError::TooManyRows { max_rows, } =>
+                write!(f, "query returned more than {} rows", max_rows),
+        }
+    }
+}
+
+impl SharedSession {
+    pub fn paging_params(&self) -> PagingParams {
+        self.settings.paging
+    }
+
+    pub fn execute_paged_default(&self, statement: Statement) -> PageStream {
+        self.execute_paged_with(statement, self.settings.paging)
+    }
+
+    pub fn execute_paged(&self, statement: Statement, page_size: i32) -> PageStream {
+        let paging = PagingParams { page_size, ..self.settings.paging };
+        self.execute_paged_with(statement, paging)
+    }
+
+    pub fn execute_paged_with(&self, statement: Statement, paging: PagingParams) -> PageStream {
+        let pages = self.pages(statement, paging.page_size);
+        let buffer = paging.fetch_ahead.min(paging.max_buffered_pages);
+        if buffer == 0 {
+            return pages;
+        }
+        let task_spawner = self.shared.task_spawner.as_ref();
+        if task_spawner.is_none() {
+            if let Err(status_error) = DefaultExecutor::current().status() {
+                warn!("page prefetch unavailable, falling back to on-demand paging: {:?}", status_error);
+                return pages;
+            }
+        }
+
+        // The channel holds `buffer` pages plus one slot per sender.
+        let (pages_tx, pages_rx) = mpsc::channel(buffer - 1);
+        let producer = pages
+            .then(|page_result| -> Result<_, ()> { Ok(page_result) })
+            .forward(pages_tx.sink_map_err(|_send_error| ()))
+            .map(|_| ());
+        if let Err(spawn_error) = spawner::spawn(task_spawner, Box::new(producer)) {
+            return Box::new(stream::once(Err(format!("error spawning page prefetch: {:?}", spawn_error).into())));
+        }
+        let pages = pages_rx
+            .then(|item| match item {
+                Ok(page_result) =>
+                    page_result,
+                Err(()) =>
+                    Err("page prefetch terminated".into()),
+            });
+        Box::new(pages)
+    }
+
+    // A row that fails to map is yielded as an error and the stream goes on
+    // with the next row; a driver error ends the stream. Each page is mapped
+    // as a whole, so the next one is fetched once its rows are consumed.
+    pub fn query_stream<T>(
+        &self,
+        statement: Statement,
+        page_size: i32,
+    )
+        -> impl Stream<Item = T, Error = QueryStreamError> + Send + 'static
+    where T: FromRow + Send + 'static,
+    {
+        self.execute_paged(statement, page_size)
+            .map_err(QueryStreamError::Driver)
+            .map(|page| {
+                let rows: Vec<_> = page.iter()
+                    .map(|row| T::from_row(&row).map_err(QueryStreamError::Row))
+                    .collect();
+                stream::iter_result(rows)
+            })
+            .flatten()
+    }
+
+    // Collects the whole result, failing on the first row that does not map
+    // or as soon as more than `max_rows` rows have been seen.
+    pub fn fetch_all<T>(
+        &self,
+        statement: Statement,
+        page_size: i32,
+        max_rows: usize,
+    )
+        -> impl Future<Item = Vec<T>, Error = QueryStreamError> + Send + 'static
+    where T: FromRow + Send + 'static,
+    {
+        self.query_stream(statement, page_size)
+            .fold(Vec::new(), move |mut rows, row| {
+                if rows.len() >= max_rows {
+                    return Err(QueryStreamError::TooManyRows { max_rows, });
+                }
+                rows.push(row);
+                Ok(rows)
+            })
+    }
+
+    // Same as `execute_paged`, starting after `resume_from` if given.
+    pub fn execute_paged_resumable(
+        &self,
+        mut statement: Statement,
+        page_size: i32,
+        resume_from: Option<PagingState>,
+    )
+        -> ResumablePageStream
+    {
+        if let Err(error) = statement.set_paging_size(page_size) {
+            return Box::new(stream::once(Err(error)));
+        }
+        if let Some(paging_state) = resume_from {
+            if let Err(error) = statement.set_paging_state_token(paging_state.as_bytes()) {
+                return Box::new(stream::once(Err(error)));
+            }
+        }
+
+        let session = self.clone();
+        let pages = stream::unfold(Some(Arc::new(Mutex::new(statement))), move |maybe_statement| {
+            maybe_statement.map(|statement| {
+                let page_statement = statement.clone();
+                session.send(move |session| session.execute(&page_statement.lock().unwrap()))
+                    .and_then(move |cass_result| -> Result<_, cassandra_cpp::Error> {
+                        let maybe_token = if cass_result.has_more_pages() {
+                            cass_result.paging_state_token()?
+                        } else {
+                            None
+                        };
+                        match maybe_token {
+                            Some(token) => {
+                                statement.lock().unwrap().set_paging_state_token(&token)?;
+                                Ok(((cass_result, Some(PagingState(token))), Some(statement)))
+                            },
+                            None =>
+                                Ok(((cass_result, None), None)),
+                        }
+                    })
+            })
+        });
+        Box::new(pages)
+    }
+
+    // Rows borrow the page they belong to, so the stream yields whole result
+    // pages; the next page is requested only when the stream is polled again.
+    fn pages(&self, mut statement: Statement, page_size: i32) -> PageStream {
+        if let Err(error) = statement.set_paging_size(page_size) {
+            return Box::new(stream::once(Err(error)));
+        }
+
+        let session = self.clone();
+        let pages = stream::unfold(Some(Arc::new(Mutex::new(statement))), move |maybe_statement| {
+            maybe_statement.map(|statement| {
+                let page_statement = statement.clone();
+                session.send(move |session| session.execute(&page_statement.lock().unwrap()))
+                    .and_then(move |cass_result| -> Result<_, cassandra_cpp::Error> {
+                        let next_statement = if cass_result.has_more_pages() {
+                            statement.lock().unwrap().set_paging_state(&cass_result)?;
+                            Some(statement)
+                        } else {
+                            None
+                        };
+                        Ok((cass_result, next_statement))
+                    })
+            })
+        });
+        Box::new(pages)
+    }
+}
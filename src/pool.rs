@@ -0,0 +1,427 @@
+use std::{
+    fmt,
+    hash::{
+        Hash,
+        Hasher,
+    },
+    collections::hash_map::DefaultHasher,
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{
+            Ordering,
+            AtomicUsize,
+        },
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use futures::{
+    Future,
+    IntoFuture,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use tokio::timer::Delay;
+
+use cassandra_cpp::CassResult;
+
+use ero::{
+    Loop,
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+    supervisor::Supervisor,
+};
+
+use log::debug;
+
+use super::{
+    spawn_link,
+    Params,
+    SharedSession,
+    IdempotentStatement,
+    spawner,
+};
+
+const HEDGE_LATENCY_SAMPLES: usize = 1024;
+
+// Below this many samples the hedge waits `max_delay`.
+const HEDGE_MIN_SAMPLES: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct HedgeParams {
+    // The second attempt starts once the first has taken longer than this
+    // quantile of recent hedged read latencies, clamped to the bounds.
+    pub quantile: f64,
+    pub min_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for HedgeParams {
+    fn default() -> HedgeParams {
+        HedgeParams {
+            quantile: 0.95,
+            min_delay: Duration::from_millis(5),
+            max_delay: Duration::from_millis(200),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct HedgeStats {
+    pub requests: usize,
+    pub hedged: usize,
+    pub hedge_wins: usize,
+}
+
+#[derive(Debug)]
+pub enum HedgeError {
+    Driver(cassandra_cpp::Error),
+    LodeGone,
+    ShuttingDown,
+    Timer(tokio::timer::Error),
+}
+
+#[derive(Debug)]
+pub enum PoolError {
+    LodeGone,
+    ShuttingDown,
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PoolError::LodeGone =>
+                write!(f, "session lode terminated"),
+            PoolError::ShuttingDown =>
+                write!(f, "executor is shutting down"),
+        }
+    }
+}
+
+impl PoolError {
+    fn gone() -> PoolError {
+        if spawner::executor_shutting_down() {
+            PoolError::ShuttingDown
+        } else {
+            PoolError::LodeGone
+        }
+    }
+}
+
+struct Hedging {
+    params: HedgeParams,
+    latencies: Mutex<Latencies>,
+    requests: AtomicUsize,
+    hedged: AtomicUsize,
+    hedge_wins: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Latencies {
+    samples: Vec<Duration>,
+    next_sample: usize,
+}
+
+impl Hedging {
+    fn delay(&self) -> Duration {
+        let latencies = self.latencies.lock().unwrap();
+        if latencies.samples.len() < HEDGE_MIN_SAMPLES {
+            return self.params.max_delay;
+        }
+        let mut samples = latencies.samples.clone();
+        samples.sort();
+        let index = ((samples.len() - 1) as f64 * self.params.quantile.max(0.0).min(1.0)).round() as usize;
+        samples[index].max(self.params.min_delay).min(self.params.max_delay)
+    }
+
+    fn record(&self, latency: Duration) {
+        let mut latencies = self.latencies.lock().unwrap();
+        if latencies.samples.len() < HEDGE_LATENCY_SAMPLES {
+            latencies.samples.push(latency);
+        } else {
+            let next_sample = latencies.next_sample;
+            latencies.samples[next_sample] = latency;
+        }
+        latencies.next_sample = (latencies.next_sample + 1) % HEDGE_LATENCY_SAMPLES;
+    }
+}
+
+struct Member {
+    lode: LodeResource<SharedSession>,
+    outstanding: AtomicUsize,
+}
+
+// `n` independent lodes spawned from the same params, named
+// "<lode name>/<index>". Every call goes to the lode with the fewest
+// acquisitions in progress; ties are broken round robin.
+#[derive(Clone)]
+pub struct PooledLode {
+    members: Arc<Vec<Member>>,
+    next: Arc<AtomicUsize>,
+    hedging: Option<Arc<Hedging>>,
+}
+
+struct Outstanding {
+    members: Arc<Vec<Member>>,
+    index: usize,
+}
+
+impl Drop for Outstanding {
+    fn drop(&mut self) {
+        self.members[self.index].outstanding.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn spawn_pool<N>(
+    supervisor: &Supervisor,
+    params: Params<N>,
+    n: usize,
+)
+    -> PooledLode
+where N: AsRef<str>,
+{
+    let Params { cluster_params, lode_params, configure, } = params;
+    let members = (0 .. n.max(1))
+        .map(|index| {
+            #[allow(unused_mut)]
+            let mut cluster_params = cluster_params.clone();
+            // Collector names are fixed, so only the first lode exports.
+            #[cfg(feature = "metrics-prometheus")]
+            {
+                if index > 0 {
+                    cluster_params.prometheus = None;
+                }
+            }
+            let lode = spawn_link(
+                supervisor,
+                Params {
+                    cluster_params,
+                    lode_params: ero::Params {
+                        name: format!("{}/{}", lode_params.name.as_ref(), index),
+                        restart_strategy: lode_params.restart_strategy.clone(),
+                    },
+                    configure: configure.clone(),
+                },
+            );
+            Member { lode, outstanding: AtomicUsize::new(0), }
+        })
+        .collect();
+    PooledLode {
+        members: Arc::new(members),
+        next: Arc::new(AtomicUsize::new(0)),
+        hedging: None,
+    }
+}
+
+impl PooledLode {
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn lodes(&self) -> Vec<LodeResource<SharedSession>> {
+        self.members.iter().map(|member| member.lode.clone()).collect()
+    }
+
+    // Acquisitions in progress per lode.
+    pub fn outstanding(&self) -> Vec<usize> {
+        self.members.iter().map(|member| member.outstanding.load(Ordering::SeqCst)).collect()
+    }
+
+    // Opts in to `execute_hedged`.
+    pub fn with_hedging(self, params: HedgeParams) -> PooledLode {
+        let hedging = Hedging {
+            params,
+            latencies: Mutex::new(Latencies::default()),
+            requests: AtomicUsize::new(0),
+            hedged: AtomicUsize::new(0),
+            hedge_wins: AtomicUsize::new(0),
+        };
+        PooledLode { hedging: Some(Arc::new(hedging)), ..self }
+    }
+
+    pub fn hedge_stats(&self) -> HedgeStats {
+        self.hedging.as_ref()
+            .map_or(HedgeStats::default(), |hedging| HedgeStats {
+                requests: hedging.requests.load(Ordering::Relaxed),
+                hedged: hedging.hedged.load(Ordering::Relaxed),
+                hedge_wins: hedging.hedge_wins.load(Ordering::Relaxed),
+            })
+    }
+
+    fn pick(&self) -> (LodeResource<SharedSession>, Outstanding) {
+        self.pick_except(None)
+    }
+
+    // With a single lode the second pick goes to the same lode, which hands
+    // out its next pooled session.
+    fn pick_except(&self, except: Option<usize>) -> (LodeResource<SharedSession>, Outstanding) {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let count = self.members.len();
+        let index = (0 .. count)
+            .map(|offset| (start + offset) % count)
+            .filter(|&index| count == 1 || Some(index) != except)
+            .min_by_key(|&index| self.members[index].outstanding.load(Ordering::SeqCst))
+            .unwrap_or(0);
+        let member = &self.members[index];
+        member.outstanding.fetch_add(1, Ordering::SeqCst);
+        (member.lode.clone(), Outstanding { members: self.members.clone(), index, })
+    }
+
+    // Always the same lode for equal keys (for the lifetime of the process),
+    // whatever its load.
+    fn pick_keyed<K>(&self, key: &K) -> (LodeResource<SharedSession>, Outstanding) where K: Hash + ?Sized {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let index = (hasher.finish() % self.members.len() as u64) as usize;
+        let member = &self.members[index];
+        member.outstanding.fetch_add(1, Ordering::SeqCst);
+        (member.lode.clone(), Outstanding { members: self.members.clone(), index, })
+    }
+
+    pub fn lode_for_key<K>(&self, key: &K) -> LodeResource<SharedSession> where K: Hash + ?Sized {
+        let (lode, _outstanding) = self.pick_keyed(key);
+        lode
+    }
+
+    // Same as `LodeResource::using_resource_loop` on the least busy lode;
+    // the whole loop, restarts included, stays on that lode.
+    pub fn using_resource_loop<S, F, R, T, E>(&self, state: S, using: F) -> impl Future<Item = T, Error = PoolError>
+    where F: FnMut(SharedSession, S) -> R + Send + 'static,
+          R: IntoFuture<Item = (UsingResource<SharedSession>, Loop<T, S>), Error = ErrorSeverity<S, E>>,
+          R::Future: Send + 'static,
+          S: Send + 'static,
+          T: Send + 'static,
+          E: Send + 'static,
+    {
+        let (lode, outstanding) = self.pick();
+        lode.using_resource_loop(state, using)
+            .then(move |using_result| {
+                drop(outstanding);
+                using_result.map_err(|_error| PoolError::gone())
+            })
+    }
+
+    // `using_resource_loop` routed by an affinity key (a tenant id, say) so
+    // the same key keeps hitting the same session and its prepared
+    // statements; `None` falls back to the least busy lode.
+    pub fn using_resource_loop_keyed<K, S, F, R, T, E>(
+        &self,
+        key: Option<&K>,
+        state: S,
+        using: F,
+    )
+        -> impl Future<Item = T, Error = PoolError>
+    where K: Hash + ?Sized,
+          F: FnMut(SharedSession, S) -> R + Send + 'static,
+          R: IntoFuture<Item = (UsingResource<SharedSession>, Loop<T, S>), Error = ErrorSeverity<S, E>>,
+          R::Future: Send + 'static,
+          S: Send + 'static,
+          T: Send + 'static,
+          E: Send + 'static,
+    {
+        let (lode, outstanding) = match key {
+            Some(key) =>
+                self.pick_keyed(key),
+            None =>
+                self.pick(),
+        };
+        lode.using_resource_loop(state, using)
+            .then(move |using_result| {
+                drop(outstanding);
+                using_result.map_err(|_error| PoolError::gone())
+            })
+    }
+
+    // Without `with_hedging` this is a plain execute on the least busy lode.
+    // If the first attempt is still running after the hedge delay, the
+    // statement is sent again through another lode and the first result
+    // wins; the other future is dropped. A failed first attempt is returned
+    // as is, a failed hedge leaves the first attempt running.
+    pub fn execute_hedged(&self, statement: IdempotentStatement) -> impl Future<Item = CassResult, Error = HedgeError> {
+        let statement = Arc::new(Mutex::new(statement));
+        let (lode, outstanding) = self.pick();
+        let first_index = outstanding.index;
+        let started_at = Instant::now();
+        let first = execute_on(lode, outstanding, statement.clone());
+        let hedging = match self.hedging {
+            Some(ref hedging) =>
+                hedging.clone(),
+            None =>
+                return Either::A(first),
+        };
+        hedging.requests.fetch_add(1, Ordering::Relaxed);
+        let delay = hedging.delay();
+        let pool = self.clone();
+        let hedge_hedging = hedging.clone();
+        let second = Delay::new(started_at + delay)
+            .map_err(HedgeError::Timer)
+            .and_then(move |()| {
+                debug!("first attempt exceeded {:?}, sending hedged attempt", delay);
+                hedge_hedging.hedged.fetch_add(1, Ordering::Relaxed);
+                let (lode, outstanding) = pool.pick_except(Some(first_index));
+                execute_on(lode, outstanding, statement)
+            });
+        let future = first.select2(second)
+            .then(move |select_result| match select_result {
+                Ok(Either::A((cass_result, _second))) => {
+                    hedging.record(started_at.elapsed());
+                    Either::A(result(Ok(cass_result)))
+                },
+                Ok(Either::B((cass_result, _first))) => {
+                    hedging.hedge_wins.fetch_add(1, Ordering::Relaxed);
+                    hedging.record(started_at.elapsed());
+                    Either::A(result(Ok(cass_result)))
+                },
+                Err(Either::A((error, _second))) =>
+                    Either::A(result(Err(error))),
+                Err(Either::B((_error, first))) =>
+                    Either::B(first.map(move |cass_result| {
+                        hedging.record(started_at.elapsed());
+                        cass_result
+                    })),
+            });
+        Either::B(future)
+    }
+}
+
+fn execute_on(
+    lode: LodeResource<SharedSession>,
+    outstanding: Outstanding,
+    statement: Arc<Mutex<IdempotentStatement>>,
+)
+    -> Box<dyn Future<Item = CassResult, Error = HedgeError> + Send + 'static>
+{
+    let future = lode
+        .using_resource_loop((), |session, ()| -> Result<_, ErrorSeverity<(), ()>> {
+            Ok((UsingResource::Lost, Loop::Break(session)))
+        })
+        .map_err(|_error| match PoolError::gone() {
+            PoolError::ShuttingDown =>
+                HedgeError::ShuttingDown,
+            PoolError::LodeGone =>
+                HedgeError::LodeGone,
+        })
+        .and_then(move |session| {
+            session.send(move |cass_session| cass_session.execute(statement.lock().unwrap().statement()))
+                .map_err(HedgeError::Driver)
+        })
+        .then(move |execute_result| {
+            drop(outstanding);
+            execute_result
+        });
+    Box::new(future)
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use futures::{
+    Future,
+    future::{
+        result,
+        join_all,
+        Either,
+    },
+};
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+    ErrorKind,
+    CassErrorCode,
+    PreparedStatement,
+};
+
+use log::{
+    debug,
+    warn,
+};
+
+use super::{
+    SharedSession,
+    audit_sink::AuditedStatement,
+};
+
+#[derive(Clone, Default, Debug)]
+pub struct PreparedRegistry {
+    statements: Vec<(String, String)>,
+}
+
+// Implemented by enums declared with `queries!`; every variant is one
+// statement, keyed by "Enum::Variant".
+pub trait Queries: Copy + 'static {
+    const ALL: &'static [Self];
+
+    fn key(self) -> &'static str;
+
+    fn cql(self) -> &'static str;
+}
+
+// Declares an enum of application queries:
+//
+//     queries! {
+//         pub enum UserQueries {
+//             GetUser => "SELECT name FROM users WHERE id = ?",
+//             PutUser => "INSERT INTO users (id, name) VALUES (?, ?)",
+//         }
+//     }
+//
+// Registered with `PreparedRegistry::register_queries`, all of them are
+// prepared by `init`, so a typo fails the connect instead of a request.
+#[macro_export]
+macro_rules! queries {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident { $($(#[$variant_meta:meta])* $variant:ident => $cql:expr),* $(,)* }) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+        $vis enum $name {
+            $($(#[$variant_meta])* $variant,)*
+        }
+
+        impl $crate::Queries for $name {
+            const ALL: &'static [Self] = &[$($name::$variant,)*];
+
+            fn key(self) -> &'static str {
+                match self {
+                    $($name::$variant => concat!(stringify!($name), "::", stringify!($variant)),)*
+                }
+            }
+
+            fn cql(self) -> &'static str {
+                match self {
+                    $($name::$variant => $cql,)*
+                }
+            }
+        }
+    };
+}
+
+#[derive(Debug)]
+pub struct PrepareError {
+    pub key: String,
+    pub error: cassandra_cpp::Error,
+}
+
+impl PreparedRegistry {
+    pub fn new() -> PreparedRegistry {
+        PreparedRegistry::default()
+    }
+
+    pub fn register<K, Q>(mut self, key: K, query: Q) -> PreparedRegistry where K: Into<String>, Q: Into<String> {
+        self.statements.push((key.into(), query.into()));
+        self
+    }
+
+    pub fn register_queries<Q>(mut self) -> PreparedRegistry where Q: Queries {
+        for &query in Q::ALL {
+            self = self.register(query.key(), query.cql());
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    pub(crate) fn queries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.statements.iter().map(|(key, query)| (key.as_str(), query.as_str()))
+    }
+}
+
+pub fn prepare_all(
+    session: &SharedSession,
+    registry: &PreparedRegistry,
+)
+    -> impl Future<Item = HashMap<String, PreparedStatement>, Error = PrepareError>
+{
+    let futures: Vec<_> = registry.statements.iter()
+        .map(|(key, query)| {
+            debug!("preparing registered statement {:?}", key);
+            let key = key.clone();
+            match session.session.prepare(&session.rewrite_query(query)) {
+                Ok(prepare_future) =>
+                    Either::A(prepare_future.then(move |prepare_result| {
+                        match prepare_result {
+                            Ok(prepared) =>
+                                Ok((key, prepared)),
+                            Err(error) =>
+                                Err(PrepareError { key, error, }),
+                        }
+                    })),
+                Err(error) =>
+                    Either::B(result(Err(PrepareError { key, error, }))),
+            }
+        })
+        .collect();
+    join_all(futures)
+        .map(|prepared| prepared.into_iter().collect())
+}
+
+impl SharedSession {
+    pub fn prepared(&self, key: &str) -> Option<Statement> {
+        if let Some(prepared) = self.reprepared.lock().unwrap().get(key) {
+            let mut statement = prepared.bind();
+            self.apply_statement_defaults(&mut statement);
+            return Some(statement);
+        }
+        self.prepared.get(key)
+            .map(|prepared| {
+                let mut statement = prepared.bind();
+                self.apply_statement_defaults(&mut statement);
+                statement
+            })
+    }
+
+    // Runs the registered statement `key`, bound by `bind`. If the server no
+    // longer knows the prepared id (after a schema change, typically), the
+    // query is prepared again, kept for later calls on this session, and the
+    // statement is retried once.
+    pub fn execute_prepared<F>(&self, key: &str, bind: F) -> impl Future<Item = CassResult, Error = cassandra_cpp::Error>
+    where F: Fn(&mut Statement) -> Result<(), cassandra_cpp::Error> + Send + 'static,
+    {
+        let mut statement = match self.prepared(key) {
+            Some(statement) =>
+                statement,
+            None =>
+                return Either::A(result(Err(format!("prepared statement {:?} is not registered", key).into()))),
+        };
+        if let Err(error) = bind(&mut statement) {
+            return Either::A(result(Err(error)));
+        }
+        let session = self.clone();
+        let key = key.to_string();
+        let future = self.execute_audited(statement, AuditedStatement::Key(key.clone()), None)
+            .then(move |execute_result| match execute_result {
+                Err(ref error) if is_unprepared(error) => {
+                    warn!("prepared statement {:?} is no longer known to the server, preparing it again", key);
+                    Either::A(session.reprepare(&key).and_then(move |mut statement| {
+                        match bind(&mut statement) {
+                            Ok(()) =>
+                                Either::A(session.execute_audited(statement, AuditedStatement::Key(key), None)),
+                            Err(error) =>
+                                Either::B(result(Err(error))),
+                        }
+                    }))
+                },
+                execute_result =>
+                    Either::B(result(execute_result)),
+            });
+        Either::B(future)
+    }
+
+    fn reprepare(&self, key: &str) -> impl Future<Item = Statement, Error = cassandra_cpp::Error> {
+        let query = match self.settings.prepared_queries.get(key) {
+            Some(query) =>
+                query,
+            None =>
+                return Either::A(result(Err(format!("prepared statement {:?} is not registered", key).into()))),
+        };
+        let prepare_future = match self.session.prepare(&self.rewrite_query(query)) {
+            Ok(prepare_future) =>
+                prepare_future,
+            Err(error) =>
+                return Either::A(result(Err(error))),
+        };
+        let session = self.clone();
+        let key = key.to_string();
+        Either::B(prepare_future.map(move |prepared| {
+            let mut statement = prepared.bind();
+            session.apply_statement_defaults(&mut statement);
+            session.reprepared.lock().unwrap().insert(key, prepared);
+            statement
+        }))
+    }
+
+    // Fails if `Q` was not registered with `register_queries`.
+    pub fn stmt<Q>(&self, query: Q) -> Result<Statement, cassandra_cpp::Error> where Q: Queries {
+        self.prepared(query.key())
+            .ok_or_else(|| format!("query {} is not registered in ClusterParams::prepared", query.key()).into())
+    }
+}
+
+fn is_unprepared(error: &cassandra_cpp::Error) -> bool {
+    match error.kind() {
+        ErrorKind::CassError(CassErrorCode::SERVER_UNPREPARED, _) |
+        ErrorKind::CassErrorResult(CassErrorCode::SERVER_UNPREPARED, ..) =>
+            true,
+        _ =>
+            false,
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    fmt,
+    time::Duration,
+    collections::HashMap,
+};
+
+use cassandra_cpp::{
+    Cluster,
+    Statement,
+    Consistency,
+    ExecProfile,
+};
+
+use super::{
+    make_retry_policy,
+    SharedSession,
+    RetryPolicy,
+};
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+// Settings for one workload. Anything left unset falls back to the cluster
+// wide configuration.
+#[derive(Clone, Default)]
+pub struct ProfileParams {
+    pub consistency: Option<Consistency>,
+    pub serial_consistency: Option<Consistency>,
+    pub request_timeout: Option<Duration>,
+    pub retry_policy: Option<RetryPolicy>,
+    pub load_balancing: Option<LoadBalancing>,
+    pub token_aware_routing: Option<bool>,
+}
+
+// Common per data center consistency setups, usually paired with
+// `LoadBalancing::DcAware` so the local quorum is the coordinator's DC.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(rename_all = "snake_case"))]
+pub enum ProfilePreset {
+    LocalOneReads,
+    LocalQuorumReads,
+    LocalQuorumWrites,
+    EachQuorumWrites,
+}
+
+impl ProfilePreset {
+    pub fn params(self) -> ProfileParams {
+        match self {
+            ProfilePreset::LocalOneReads =>
+                ProfileParams::local_one_reads(),
+            ProfilePreset::LocalQuorumReads =>
+                ProfileParams::local_quorum_reads(),
+            ProfilePreset::LocalQuorumWrites =>
+                ProfileParams::local_quorum_writes(),
+            ProfilePreset::EachQuorumWrites =>
+                ProfileParams::each_quorum_writes(),
+        }
+    }
+}
+
+impl ProfileParams {
+    pub fn local_one_reads() -> ProfileParams {
+        ProfileParams {
+            consistency: Some(Consistency::LOCAL_ONE),
+            ..ProfileParams::default()
+        }
+    }
+
+    pub fn local_quorum_reads() -> ProfileParams {
+        ProfileParams {
+            consistency: Some(Consistency::LOCAL_QUORUM),
+            serial_consistency: Some(Consistency::LOCAL_SERIAL),
+            ..ProfileParams::default()
+        }
+    }
+
+    pub fn local_quorum_writes() -> ProfileParams {
+        ProfileParams {
+            consistency: Some(Consistency::LOCAL_QUORUM),
+            serial_consistency: Some(Consistency::LOCAL_SERIAL),
+            ..ProfileParams::default()
+        }
+    }
+
+    // Waits for a quorum in every data center; lightweight transactions
+    // use a cluster wide `SERIAL`.
+    pub fn each_quorum_writes() -> ProfileParams {
+        ProfileParams {
+            consistency: Some(Consistency::EACH_QUORUM),
+            serial_consistency: Some(Consistency::SERIAL),
+            ..ProfileParams::default()
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(tag = "policy", rename_all = "snake_case", deny_unknown_fields))]
+pub enum LoadBalancing {
+    RoundRobin,
+    DcAware {
+        local_dc: String,
+        used_hosts_per_remote_dc: usize,
+        allow_remote_dcs_for_local_cl: bool,
+    },
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    UnknownProfile(String),
+    Driver(cassandra_cpp::Error),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileError::UnknownProfile(name) =>
+                write!(f, "unknown execution profile {:?}", name),
+            ProfileError::Driver(error) =>
+                write!(f, "error selecting execution profile: {:?}", error),
+        }
+    }
+}
+
+pub(crate) fn configure(
+    cluster: &mut Cluster,
+    profiles: &HashMap<String, ProfileParams>,
+)
+    -> Result<(), cassandra_cpp::Error>
+{
+    for (name, profile_params) in profiles {
+        let mut profile = ExecProfile::new();
+        if let Some(consistency) = profile_params.consistency {
+            profile.set_consistency(consistency)?;
+        }
+        if let Some(serial_consistency) = profile_params.serial_consistency {
+            profile.set_serial_consistency(serial_consistency)?;
+        }
+        if let Some(request_timeout) = profile_params.request_timeout {
+            profile.set_request_timeout(request_timeout)?;
+        }
+        if let Some(ref retry_policy) = profile_params.retry_policy {
+            profile.set_retry_policy(make_retry_policy(retry_policy))?;
+        }
+        match profile_params.load_balancing {
+            None =>
+                (),
+            Some(LoadBalancing::RoundRobin) =>
+                profile.set_load_balance_round_robin()?,
+            Some(LoadBalancing::DcAware { ref local_dc, used_hosts_per_remote_dc, allow_remote_dcs_for_local_cl, }) =>
+                profile.set_load_balance_dc_aware(local_dc, used_hosts_per_remote_dc as u32, allow_remote_dcs_for_local_cl)?,
+        }
+        if let Some(token_aware_routing) = profile_params.token_aware_routing {
+            profile.set_token_aware_routing(token_aware_routing)?;
+        }
+        cluster.set_execution_profile(name, &profile)?;
+    }
+    Ok(())
+}
+
+impl SharedSession {
+    pub fn execution_profiles(&self) -> impl Iterator<Item = &str> {
+        self.settings.execution_profiles.iter().map(String::as_str)
+    }
+
+    // The driver silently falls back to the cluster defaults for a name it
+    // does not know, so unknown profiles are rejected here instead.
+    pub fn set_profile(&self, statement: &mut Statement, profile: &str) -> Result<(), ProfileError> {
+        if !self.settings.execution_profiles.contains(profile) {
+            return Err(ProfileError::UnknownProfile(profile.to_string()));
+        }
+        statement.set_execution_profile(Some(profile))
+            .map_err(ProfileError::Driver)
+    }
+
+    pub fn profile_statement(&self, query: &str, parameter_count: usize, profile: &str) -> Result<Statement, ProfileError> {
+        let mut statement = self.statement(query, parameter_count);
+        self.set_profile(&mut statement, profile)?;
+        Ok(statement)
+    }
+}
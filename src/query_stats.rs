@@ -0,0 +1,96 @@
+use std::{
+    sync::Mutex,
+    time::Duration,
+    collections::HashMap,
+};
+
+use hdrhistogram::Histogram;
+
+use super::SharedSession;
+
+// Latencies are recorded in microseconds; slower requests are clamped.
+const MAX_LATENCY_MICROS: u64 = 60_000_000;
+
+const SIGNIFICANT_DIGITS: u8 = 3;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct QueryStats {
+    pub key: String,
+    pub count: u64,
+    pub p50: Duration,
+    pub p99: Duration,
+    pub p999: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+}
+
+// Per statement key latency histograms, fed by the same executions as
+// `usage_report`, but without sampling.
+pub struct QueryHistograms {
+    histograms: Mutex<HashMap<String, Histogram<u64>>>,
+}
+
+impl QueryHistograms {
+    pub fn new() -> QueryHistograms {
+        QueryHistograms {
+            histograms: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record(&self, key: &str, latency: Duration) {
+        let mut histograms = self.histograms.lock().unwrap();
+        if !histograms.contains_key(key) {
+            let histogram = Histogram::new_with_bounds(1, MAX_LATENCY_MICROS, SIGNIFICANT_DIGITS)
+                .expect("valid histogram bounds");
+            histograms.insert(key.to_string(), histogram);
+        }
+        let micros = latency.as_secs() * 1_000_000 + latency.subsec_micros() as u64;
+        histograms.get_mut(key).unwrap().saturating_record(micros.max(1));
+    }
+
+    pub fn stats(&self, key: &str) -> Option<QueryStats> {
+        self.histograms.lock().unwrap()
+            .get(key)
+            .map(|histogram| query_stats(key, histogram))
+    }
+
+    pub fn all(&self) -> Vec<QueryStats> {
+        let histograms = self.histograms.lock().unwrap();
+        let mut stats: Vec<_> = histograms.iter()
+            .map(|(key, histogram)| query_stats(key, histogram))
+            .collect();
+        stats.sort_by(|a, b| a.key.cmp(&b.key));
+        stats
+    }
+
+    pub fn reset(&self) {
+        self.histograms.lock().unwrap().clear();
+    }
+}
+
+fn query_stats(key: &str, histogram: &Histogram<u64>) -> QueryStats {
+    QueryStats {
+        key: key.to_string(),
+        count: histogram.len(),
+        p50: Duration::from_micros(histogram.value_at_quantile(0.5)),
+        p99: Duration::from_micros(histogram.value_at_quantile(0.99)),
+        p999: Duration::from_micros(histogram.value_at_quantile(0.999)),
+        max: Duration::from_micros(histogram.max()),
+        mean: Duration::from_micros(histogram.mean() as u64),
+    }
+}
+
+impl SharedSession {
+    // Only `execute_tracked` calls are recorded, under their key.
+    pub fn query_stats(&self, key: &str) -> Option<QueryStats> {
+        self.shared.query_histograms.stats(key)
+    }
+
+    pub fn all_query_stats(&self) -> Vec<QueryStats> {
+        self.shared.query_histograms.all()
+    }
+
+    pub fn reset_query_stats(&self) {
+        self.shared.query_histograms.reset();
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    net::IpAddr,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use futures::{
+    Async,
+    Poll,
+    Future,
+    future::{
+        result,
+        loop_fn,
+        Loop,
+        Either,
+    },
+};
+
+use tokio::timer::Delay;
+
+use cassandra_cpp::{
+    Uuid,
+    CassFuture,
+    CassResult,
+    Statement,
+};
+
+use super::{
+    SharedSession,
+    events::read_address,
+};
+
+// Trace rows are written asynchronously by the server.
+const TRACE_FETCH_ATTEMPTS: usize = 5;
+const TRACE_FETCH_DELAY: Duration = Duration::from_millis(200);
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct QueryTrace {
+    pub tracing_id: Uuid,
+    pub coordinator: Option<IpAddr>,
+    pub request: Option<String>,
+    pub started_at: Option<i64>,
+    pub duration: Option<Duration>,
+    pub events: Vec<TraceEvent>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TraceEvent {
+    pub activity: Option<String>,
+    pub source: Option<IpAddr>,
+    pub source_elapsed: Option<Duration>,
+    pub thread: Option<String>,
+}
+
+pub struct TracedResult {
+    pub result: CassResult,
+    pub tracing_id: Option<Uuid>,
+    pub trace: Option<QueryTrace>,
+}
+
+// Keeps the tracing id, which the driver only exposes on the future.
+struct WithTracingId {
+    future: CassFuture<CassResult>,
+    tracing_id: Arc<Mutex<Option<Uuid>>>,
+}
+
+impl Future for WithTracingId {
+    type Item = CassResult;
+    type Error = cassandra_cpp::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.future.poll()? {
+            Async::Ready(cass_result) => {
+                *self.tracing_id.lock().unwrap() = self.future.tracing_id().ok();
+                Ok(Async::Ready(cass_result))
+            },
+            Async::NotReady =>
+                Ok(Async::NotReady),
+        }
+    }
+}
+
+impl SharedSession {
+    pub fn execute_traced(
+        &self,
+        mut statement: Statement,
+        fetch_trace: bool,
+    )
+        -> impl Future<Item = TracedResult, Error = cassandra_cpp::Error>
+    {
+        if let Err(error) = statement.set_tracing(true) {
+            return Either::A(result(Err(error)));
+        }
+        let session = self.clone();
+        let tracing_id_slot = Arc::new(Mutex::new(None));
+        let sent_slot = tracing_id_slot.clone();
+        let future = self.send(move |session| WithTracingId { future: session.execute(&statement), tracing_id: sent_slot, })
+            .and_then(move |cass_result| {
+                let tracing_id = tracing_id_slot.lock().unwrap().take();
+                match tracing_id {
+                    Some(id) if fetch_trace => {
+                        let future = session.fetch_trace(id)
+                            .map(move |trace| TracedResult { result: cass_result, tracing_id, trace, });
+                        Either::A(future)
+                    },
+                    _ =>
+                        Either::B(result(Ok(TracedResult { result: cass_result, tracing_id, trace: None, }))),
+                }
+            });
+        Either::B(future)
+    }
+
+    // Resolves to `None` if the trace is still incomplete after a few
+    // attempts.
+    pub fn fetch_trace(&self, tracing_id: Uuid) -> impl Future<Item = Option<QueryTrace>, Error = cassandra_cpp::Error> {
+        let session = self.clone();
+        loop_fn(1, move |attempt| {
+            let events_session = session.clone();
+            session.fetch_trace_session(tracing_id)
+                .and_then(move |maybe_trace| match maybe_trace {
+                    Some(trace) =>
+                        Either::A(events_session.fetch_trace_events(trace).map(|trace| Loop::Break(Some(trace)))),
+                    None if attempt >= TRACE_FETCH_ATTEMPTS =>
+                        Either::B(Either::A(result(Ok(Loop::Break(None))))),
+                    None => {
+                        let future = Delay::new(Instant::now() + TRACE_FETCH_DELAY)
+                            .then(move |_timer_result| Ok(Loop::Continue(attempt + 1)));
+                        Either::B(Either::B(future))
+                    },
+                })
+        })
+    }
+
+    // `None` until the coordinator has recorded the request duration.
+    fn fetch_trace_session(&self, tracing_id: Uuid) -> impl Future<Item = Option<QueryTrace>, Error = cassandra_cpp::Error> {
+        let mut statement = Statement::new(
+            "SELECT coordinator, request, started_at, duration FROM system_traces.sessions WHERE session_id = ?",
+            1,
+        );
+        if let Err(error) = statement.bind_uuid(0, tracing_id) {
+            return Either::A(result(Err(error)));
+        }
+        let future = self.execute_guarded(statement)
+            .map(move |cass_result| {
+                let row = cass_result.first_row()?;
+                let duration = row.get_column(3).and_then(|value| value.get_i32()).ok()?;
+                Some(QueryTrace {
+                    tracing_id,
+                    coordinator: row.get_column(0).ok().and_then(|value| read_address(&value)),
+                    request: row.get_column(1).and_then(|value| value.get_string()).ok(),
+                    started_at: row.get_column(2).and_then(|value| value.get_i64()).ok(),
+                    duration: Some(Duration::from_micros(duration as u64)),
+                    events: Vec::new(),
+                })
+            });
+        Either::B(future)
+    }
+
+    fn fetch_trace_events(&self, mut trace: QueryTrace) -> impl Future<Item = QueryTrace, Error = cassandra_cpp::Error> {
+        let mut statement = Statement::new(
+            "SELECT activity, source, source_elapsed, thread FROM system_traces.events WHERE session_id = ?",
+            1,
+        );
+        if let Err(error) = statement.bind_uuid(0, trace.tracing_id) {
+            return Either::A(result(Err(error)));
+        }
+        let future = self.execute_guarded(statement)
+            .map(move |cass_result| {
+                trace.events = cass_result.iter()
+                    .map(|row| TraceEvent {
+                        activity: row.get_column(0).and_then(|value| value.get_string()).ok(),
+                        source: row.get_column(1).ok().and_then(|value| read_address(&value)),
+                        source_elapsed: row.get_column(2)
+                            .and_then(|value| value.get_i32())
+                            .ok()
+                            .map(|micros| Duration::from_micros(micros as u64)),
+                        thread: row.get_column(3).and_then(|value| value.get_string()).ok(),
+                    })
+                    .collect();
+                trace
+            });
+        Either::B(future)
+    }
+}
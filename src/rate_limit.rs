@@ -0,0 +1,152 @@
+use std::{
+    sync::Mutex,
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(deny_unknown_fields))]
+pub struct RateLimit {
+    pub queries_per_second: f64,
+    // Queries allowed back to back after an idle period.
+    pub burst: usize,
+}
+
+pub(crate) struct TokenBucket {
+    limit: RateLimit,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(limit: RateLimit) -> TokenBucket {
+        TokenBucket::starting_at(limit, Instant::now())
+    }
+
+    fn starting_at(limit: RateLimit, now: Instant) -> TokenBucket {
+        TokenBucket {
+            limit,
+            state: Mutex::new(BucketState {
+                tokens: limit.burst.max(1) as f64,
+                refilled_at: now,
+            }),
+        }
+    }
+
+    // Takes a token, possibly borrowing against future refills, and returns
+    // how long the caller has to wait before the query may be sent. Callers
+    // are served in the order they reserve.
+    pub(crate) fn reserve(&self) -> Duration {
+        self.reserve_at(Instant::now())
+    }
+
+    fn reserve_at(&self, now: Instant) -> Duration {
+        let rate = self.limit.queries_per_second;
+        if rate.is_nan() || rate <= 0.0 {
+            return Duration::from_secs(0);
+        }
+        let mut state = self.state.lock().unwrap();
+        let elapsed = now.duration_since(state.refilled_at);
+        let elapsed_secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+        state.tokens = (state.tokens + elapsed_secs * rate).min(self.limit.burst.max(1) as f64);
+        state.refilled_at = now;
+        state.tokens -= 1.0;
+        if state.tokens >= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            let wait_secs = -state.tokens / rate;
+            Duration::new(wait_secs as u64, (wait_secs.fract() * 1e9) as u32)
+        }
+    }
+
+    pub(crate) fn refund(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.tokens = (state.tokens + 1.0).min(self.limit.burst.max(1) as f64);
+    }
+
+    #[cfg(test)]
+    pub(crate) fn tokens(&self) -> f64 {
+        self.state.lock().unwrap().tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{
+        Instant,
+        Duration,
+    };
+
+    use super::{
+        RateLimit,
+        TokenBucket,
+    };
+
+    fn millis(duration: Duration) -> u64 {
+        duration.as_secs() * 1000 + duration.subsec_millis() as u64
+    }
+
+    #[test]
+    fn burst_then_paced() {
+        let start = Instant::now();
+        let bucket = TokenBucket::starting_at(RateLimit { queries_per_second: 10.0, burst: 3, }, start);
+        for _ in 0 .. 3 {
+            assert_eq!(bucket.reserve_at(start), Duration::from_secs(0));
+        }
+        assert_eq!(millis(bucket.reserve_at(start)), 100);
+        assert_eq!(millis(bucket.reserve_at(start)), 200);
+    }
+
+    #[test]
+    fn refill_is_capped_at_burst() {
+        let start = Instant::now();
+        let bucket = TokenBucket::starting_at(RateLimit { queries_per_second: 10.0, burst: 2, }, start);
+        assert_eq!(bucket.reserve_at(start), Duration::from_secs(0));
+        assert_eq!(bucket.reserve_at(start), Duration::from_secs(0));
+        let later = start + Duration::from_secs(60);
+        assert_eq!(bucket.reserve_at(later), Duration::from_secs(0));
+        assert_eq!(bucket.reserve_at(later), Duration::from_secs(0));
+        assert_eq!(millis(bucket.reserve_at(later)), 100);
+    }
+
+    #[test]
+    fn partial_refill_shortens_wait() {
+        let start = Instant::now();
+        let bucket = TokenBucket::starting_at(RateLimit { queries_per_second: 10.0, burst: 1, }, start);
+        assert_eq!(bucket.reserve_at(start), Duration::from_secs(0));
+        assert_eq!(millis(bucket.reserve_at(start + Duration::from_millis(40))), 60);
+    }
+
+    #[test]
+    fn refund_returns_a_token() {
+        let start = Instant::now();
+        let bucket = TokenBucket::starting_at(RateLimit { queries_per_second: 10.0, burst: 1, }, start);
+        assert_eq!(bucket.reserve_at(start), Duration::from_secs(0));
+        assert_eq!(millis(bucket.reserve_at(start)), 100);
+        bucket.refund();
+        assert_eq!(millis(bucket.reserve_at(start)), 100);
+        bucket.refund();
+        bucket.refund();
+        bucket.refund();
+        assert_eq!(bucket.reserve_at(start), Duration::from_secs(0));
+        assert_eq!(millis(bucket.reserve_at(start)), 100);
+    }
+
+    #[test]
+    fn zero_rate_never_waits() {
+        let start = Instant::now();
+        let bucket = TokenBucket::starting_at(RateLimit { queries_per_second: 0.0, burst: 1, }, start);
+        for _ in 0 .. 10 {
+            assert_eq!(bucket.reserve_at(start), Duration::from_secs(0));
+        }
+    }
+}
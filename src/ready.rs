@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use futures::Future;
+
+use tokio::timer::Timeout;
+
+use ero::{
+    Loop,
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+};
+
+use super::spawner;
+
+#[derive(Debug)]
+pub enum WaitConnectedError {
+    Timeout,
+    LodeGone,
+    ShuttingDown,
+    Timer(tokio::timer::Error),
+}
+
+pub type WaitConnectedFuture = Box<dyn Future<Item = (), Error = WaitConnectedError> + Send + 'static>;
+
+// Resolves once the lode hands out a session, i.e. after its first
+// successful `init` (or right away if it is connected already), so serving
+// traffic can wait for Cassandra to be up.
+pub trait WaitConnected {
+    fn wait_connected(self, timeout: Duration) -> WaitConnectedFuture;
+}
+
+impl<R> WaitConnected for LodeResource<R> where R: Send + 'static {
+    fn wait_connected(self, timeout: Duration) -> WaitConnectedFuture {
+        let connected = self
+            .using_resource_loop((), |resource, ()| -> Result<_, ErrorSeverity<(), ()>> {
+                Ok((UsingResource::Reused(resource), Loop::Break(())))
+            })
+            .map_err(|_error| if spawner::executor_shutting_down() {
+                WaitConnectedError::ShuttingDown
+            } else {
+                WaitConnectedError::LodeGone
+            });
+        let future = Timeout::new(connected, timeout)
+            .map_err(|error| {
+                if error.is_elapsed() {
+                    WaitConnectedError::Timeout
+                } else if error.is_timer() {
+                    WaitConnectedError::Timer(error.into_timer().unwrap())
+                } else {
+                    error.into_inner().unwrap_or(WaitConnectedError::LodeGone)
+                }
+            });
+        Box::new(future)
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    sync::Mutex,
+    collections::HashMap,
+};
+
+use log::info;
+
+use ero::{
+    RestartStrategy,
+    lode::LodeResource,
+    supervisor::Supervisor,
+};
+
+use super::{
+    spawn_link,
+    Params,
+    SharedSession,
+    ClusterParams,
+};
+
+pub struct KeyspaceRegistry {
+    supervisor: Supervisor,
+    template: ClusterParams,
+    name: String,
+    restart_strategy: RestartStrategy,
+    lodes: Mutex<HashMap<String, LodeResource<SharedSession>>>,
+}
+
+impl KeyspaceRegistry {
+    // Every lode is spawned from `template` with only the keyspace replaced
+    // and is named "<lode name>/<keyspace>".
+    pub fn new<N>(supervisor: &Supervisor, template: ClusterParams, lode_params: ero::Params<N>) -> KeyspaceRegistry where N: AsRef<str> {
+        KeyspaceRegistry {
+            supervisor: supervisor.clone(),
+            template,
+            name: lode_params.name.as_ref().to_string(),
+            restart_strategy: lode_params.restart_strategy,
+            lodes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn lode(&self, keyspace: &str) -> LodeResource<SharedSession> {
+        let mut lodes = self.lodes.lock().unwrap();
+        if let Some(lode) = lodes.get(keyspace) {
+            return lode.clone();
+        }
+        info!("spawning lode for keyspace {:?}", keyspace);
+        let mut cluster_params = self.template.clone();
+        cluster_params.keyspace = Some(keyspace.to_string());
+        // Collector names are fixed, so per keyspace exporters would clash.
+        #[cfg(feature = "metrics-prometheus")]
+        {
+            cluster_params.prometheus = None;
+        }
+        let lode = spawn_link(
+            &self.supervisor,
+            Params {
+                cluster_params,
+                lode_params: ero::Params {
+                    name: format!("{}/{}", self.name, keyspace),
+                    restart_strategy: self.restart_strategy.clone(),
+                },
+                configure: None,
+            },
+        );
+        lodes.insert(keyspace.to_string(), lode.clone());
+        lode
+    }
+
+    pub fn keyspaces(&self) -> Vec<String> {
+        self.lodes.lock().unwrap().keys().cloned().collect()
+    }
+}
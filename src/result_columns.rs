@@ -0,0 +1,84 @@
+use futures::Future;
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+    ValueType,
+};
+
+use super::{
+    SharedSession,
+    mock::Rows,
+    schema::ColumnType,
+    export::page_records,
+};
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ResultColumn {
+    pub position: usize,
+    pub name: String,
+    pub value_type: ValueType,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct DescribedRows {
+    pub columns: Vec<ResultColumn>,
+    pub rows: Rows,
+}
+
+impl ResultColumn {
+    // The driver reports only the outer type of collections, user types and
+    // tuples, so those come out as `ColumnType::Other` with the bare name.
+    pub fn column_type(&self) -> ColumnType {
+        match self.value_type {
+            ValueType::ASCII => ColumnType::Ascii,
+            ValueType::TEXT | ValueType::VARCHAR => ColumnType::Text,
+            ValueType::BOOLEAN => ColumnType::Boolean,
+            ValueType::TINY_INT => ColumnType::TinyInt,
+            ValueType::SMALL_INT => ColumnType::SmallInt,
+            ValueType::INT => ColumnType::Int,
+            ValueType::BIGINT => ColumnType::BigInt,
+            ValueType::COUNTER => ColumnType::Counter,
+            ValueType::VARINT => ColumnType::Varint,
+            ValueType::FLOAT => ColumnType::Float,
+            ValueType::DOUBLE => ColumnType::Double,
+            ValueType::DECIMAL => ColumnType::Decimal,
+            ValueType::BLOB => ColumnType::Blob,
+            ValueType::UUID => ColumnType::Uuid,
+            ValueType::TIMEUUID => ColumnType::Timeuuid,
+            ValueType::TIMESTAMP => ColumnType::Timestamp,
+            ValueType::DATE => ColumnType::Date,
+            ValueType::TIME => ColumnType::Time,
+            ValueType::INET => ColumnType::Inet,
+            ValueType::LIST => ColumnType::Other("list".to_string()),
+            ValueType::SET => ColumnType::Other("set".to_string()),
+            ValueType::MAP => ColumnType::Other("map".to_string()),
+            ValueType::UDT => ColumnType::Other("udt".to_string()),
+            ValueType::TUPLE => ColumnType::Other("tuple".to_string()),
+            ref other => ColumnType::Other(format!("{:?}", other).to_lowercase()),
+        }
+    }
+}
+
+pub fn result_columns(cass_result: &CassResult) -> Result<Vec<ResultColumn>, cassandra_cpp::Error> {
+    (0 .. cass_result.column_count() as usize)
+        .map(|position| Ok(ResultColumn {
+            position,
+            name: cass_result.column_name(position)?.to_string(),
+            value_type: cass_result.column_type(position),
+        }))
+        .collect()
+}
+
+impl SharedSession {
+    // Rows of an arbitrary query together with what the result says about
+    // its columns, for tooling that does not know the schema up front.
+    pub fn execute_described(&self, statement: Statement) -> impl Future<Item = DescribedRows, Error = cassandra_cpp::Error> {
+        self.execute_guarded(statement)
+            .and_then(|cass_result| {
+                let columns = result_columns(&cass_result)?;
+                let rows = page_records(&cass_result, columns.len())?;
+                Ok(DescribedRows { columns, rows, })
+            })
+    }
+}
@@ -0,0 +1,322 @@
+use std::{
+    time::{
+        Instant,
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{
+            Ordering,
+            AtomicUsize,
+        },
+    },
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        loop_fn,
+        Loop,
+        Either,
+    },
+};
+
+use tokio::timer::Delay;
+
+use cassandra_cpp::{
+    ErrorKind,
+    CassResult,
+    Statement,
+    CassErrorCode,
+};
+
+use ero::ErrorSeverity;
+
+use log::warn;
+
+use super::{
+    SharedSession,
+    Deadline,
+    RetryBudget,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RetryOn {
+    Timeout,
+    Unavailable,
+    Overloaded,
+}
+
+#[derive(Clone, Debug)]
+pub struct RetryConfig {
+    pub max_attempts: usize,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub retry_on: Vec<RetryOn>,
+    // Every attempt gets the time left as its request timeout, and no
+    // attempt is started after it.
+    pub deadline: Option<Deadline>,
+    // Shared with other call sites; once it is spent failures are returned
+    // without retrying. Unset uses `ClusterParams::retry_budget`.
+    pub budget: Option<RetryBudget>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> RetryConfig {
+        RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(2),
+            retry_on: vec![RetryOn::Timeout, RetryOn::Unavailable, RetryOn::Overloaded],
+            deadline: None,
+            budget: None,
+        }
+    }
+}
+
+// `Recoverable` carries the last error of a retryable failure after all
+// attempts were spent, so a `using_resource_loop` body can restart with a
+// fresh session; anything else is `Fatal`.
+pub type RetryError = ErrorSeverity<cassandra_cpp::Error, cassandra_cpp::Error>;
+
+impl RetryOn {
+    pub fn of(error: &cassandra_cpp::Error) -> Option<RetryOn> {
+        let code = match error.kind() {
+            ErrorKind::CassError(code, _) =>
+                code,
+            ErrorKind::CassErrorResult(code, ..) =>
+                code,
+            _ =>
+                return None,
+        };
+        match code {
+            CassErrorCode::LIB_REQUEST_TIMED_OUT |
+            CassErrorCode::SERVER_READ_TIMEOUT |
+            CassErrorCode::SERVER_WRITE_TIMEOUT =>
+                Some(RetryOn::Timeout),
+            CassErrorCode::SERVER_UNAVAILABLE |
+            CassErrorCode::LIB_NO_HOSTS_AVAILABLE =>
+                Some(RetryOn::Unavailable),
+            CassErrorCode::SERVER_OVERLOADED |
+            CassErrorCode::SERVER_IS_BOOTSTRAPPING |
+            CassErrorCode::LIB_REQUEST_QUEUE_FULL =>
+                Some(RetryOn::Overloaded),
+            _ =>
+                None,
+        }
+    }
+}
+
+impl RetryConfig {
+    // Full jitter: uniformly random between zero and the capped exponential
+    // delay for this attempt.
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        full_jitter(self.base_delay, self.max_delay, attempt)
+    }
+}
+
+pub(crate) fn full_jitter(base_delay: Duration, max_delay: Duration, attempt: usize) -> Duration {
+    let factor = 1u32.checked_shl(attempt.min(31) as u32).unwrap_or(u32::max_value());
+    let ceiling = base_delay.checked_mul(factor)
+        .unwrap_or(max_delay)
+        .min(max_delay);
+    let ceiling_nanos = ceiling.as_secs() * 1_000_000_000 + ceiling.subsec_nanos() as u64;
+    if ceiling_nanos == 0 {
+        Duration::from_secs(0)
+    } else {
+        Duration::from_nanos(jitter_seed() % (ceiling_nanos + 1))
+    }
+}
+
+fn jitter_seed() -> u64 {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.subsec_nanos() as u64)
+        .unwrap_or(0);
+    let mut x = nanos ^ (COUNTER.fetch_add(1, Ordering::Relaxed) as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x
+}
+
+// A statement explicitly marked safe to send more than once. The driver
+// flag is set as well, so speculative execution only applies to these.
+pub struct IdempotentStatement(Statement);
+
+impl IdempotentStatement {
+    pub fn new(mut statement: Statement) -> Result<IdempotentStatement, cassandra_cpp::Error> {
+        statement.set_is_idempotent(true)?;
+        Ok(IdempotentStatement(statement))
+    }
+
+    pub fn statement(&self) -> &Statement {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> Statement {
+        self.0
+    }
+}
+
+impl RetryOn {
+    // Whether the coordinator may have applied the request before failing.
+    fn may_have_executed(&self) -> bool {
+        match self {
+            RetryOn::Timeout => true,
+            RetryOn::Unavailable | RetryOn::Overloaded => false,
+        }
+    }
+}
+
+impl SharedSession {
+    // Statements not marked idempotent are only retried when the request was
+    // rejected before execution; timeouts are returned as is.
+    pub fn execute_with_retry(
+        &self,
+        statement: Statement,
+        config: RetryConfig,
+    )
+        -> impl Future<Item = CassResult, Error = RetryError>
+    {
+        self.retry_loop(statement, false, config)
+    }
+
+    pub fn execute_idempotent_with_retry(
+        &self,
+        statement: IdempotentStatement,
+        config: RetryConfig,
+    )
+        -> impl Future<Item = CassResult, Error = RetryError>
+    {
+        self.retry_loop(statement.into_inner(), true, config)
+    }
+
+    fn retry_loop(
+        &self,
+        statement: Statement,
+        is_idempotent: bool,
+        mut config: RetryConfig,
+    )
+        -> impl Future<Item = CassResult, Error = RetryError>
+    {
+        let session = self.clone();
+        let statement = Arc::new(Mutex::new(statement));
+        if config.budget.is_none() {
+            config.budget = self.settings.retry_budget.clone();
+        }
+        loop_fn((statement, 1), move |(statement, attempt)| {
+            let config = config.clone();
+            if attempt == 1 {
+                if let Some(ref budget) = config.budget {
+                    budget.record_request();
+                }
+            }
+            if let Some(deadline) = config.deadline {
+                if let Err(deadline_exceeded) = deadline.apply(&mut statement.lock().unwrap()) {
+                    let error: cassandra_cpp::Error = format!("{} before attempt {}", deadline_exceeded, attempt).into();
+                    return Either::A(result(Err(ErrorSeverity::Fatal(error))));
+                }
+            }
+            let attempt_statement = statement.clone();
+            let future = session.send(move |session| session.execute(&attempt_statement.lock().unwrap()))
+                .then(move |execute_result| match execute_result {
+                    Ok(cass_result) =>
+                        Either::A(result(Ok(Loop::Break(cass_result)))),
+                    Err(error) =>
+                        match RetryOn::of(&error) {
+                            Some(retry_on) if retry_on.may_have_executed() && !is_idempotent =>
+                                Either::A(result(Err(ErrorSeverity::Fatal(error)))),
+                            Some(retry_on) if config.retry_on.contains(&retry_on) => {
+                                let delay = config.backoff(attempt - 1);
+                                let past_deadline = config.deadline
+                                    .map_or(false, |deadline| deadline.remaining().map_or(true, |remaining| remaining <= delay));
+                                if attempt >= config.max_attempts {
+                                    warn!("giving up after {} attempts: {:?}", attempt, error);
+                                    Either::A(result(Err(ErrorSeverity::Recoverable { state: error, })))
+                                } else if past_deadline {
+                                    warn!("giving up after {} attempts, no time left before the deadline: {:?}", attempt, error);
+                                    Either::A(result(Err(ErrorSeverity::Recoverable { state: error, })))
+                                } else if !config.budget.as_ref().map_or(true, RetryBudget::try_retry) {
+                                    warn!("giving up after {} attempts, retry budget exhausted: {:?}", attempt, error);
+                                    Either::A(result(Err(ErrorSeverity::Recoverable { state: error, })))
+                                } else {
+                                    warn!("attempt {} failed ({:?}), retrying in {:?}: {:?}", attempt, retry_on, delay, error);
+                                    let future = Delay::new(Instant::now() + delay)
+                                        .then(move |_timer_result| Ok(Loop::Continue((statement, attempt + 1))));
+                                    Either::B(future)
+                                }
+                            },
+                            _ =>
+                                Either::A(result(Err(ErrorSeverity::Fatal(error)))),
+                        },
+                });
+            Either::B(future)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cassandra_cpp::{
+        ErrorKind,
+        CassErrorCode,
+    };
+
+    use super::{
+        full_jitter,
+        RetryOn,
+        RetryConfig,
+    };
+
+    #[test]
+    fn jitter_stays_under_exponential_ceiling() {
+        let base_delay = Duration::from_millis(10);
+        let max_delay = Duration::from_secs(1);
+        for attempt in 0 .. 10 {
+            let ceiling = (base_delay * (1 << attempt)).min(max_delay);
+            for _ in 0 .. 100 {
+                assert!(full_jitter(base_delay, max_delay, attempt) <= ceiling);
+            }
+        }
+    }
+
+    #[test]
+    fn jitter_is_capped_for_large_attempts() {
+        let max_delay = Duration::from_millis(250);
+        for &attempt in [31, 32, 64, usize::MAX].iter() {
+            assert!(full_jitter(Duration::from_secs(1), max_delay, attempt) <= max_delay);
+        }
+    }
+
+    #[test]
+    fn jitter_spreads_delays() {
+        let delays: Vec<_> = (0 .. 50)
+            .map(|_| full_jitter(Duration::from_secs(1), Duration::from_secs(1), 0))
+            .collect();
+        assert!(delays.iter().any(|&delay| delay != delays[0]));
+    }
+
+    #[test]
+    fn zero_delay_stays_zero() {
+        assert_eq!(full_jitter(Duration::from_secs(0), Duration::from_secs(1), 5), Duration::from_secs(0));
+        assert_eq!(RetryConfig { max_delay: Duration::from_secs(0), ..RetryConfig::default() }.backoff(3), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn retryable_errors_are_classified() {
+        let error = |code| -> cassandra_cpp::Error { ErrorKind::CassError(code, String::new()).into() };
+        assert_eq!(RetryOn::of(&error(CassErrorCode::SERVER_WRITE_TIMEOUT)), Some(RetryOn::Timeout));
+        assert_eq!(RetryOn::of(&error(CassErrorCode::SERVER_UNAVAILABLE)), Some(RetryOn::Unavailable));
+        assert_eq!(RetryOn::of(&error(CassErrorCode::SERVER_OVERLOADED)), Some(RetryOn::Overloaded));
+        assert_eq!(RetryOn::of(&error(CassErrorCode::SERVER_SYNTAX_ERROR)), None);
+        assert_eq!(RetryOn::of(&"not a driver error".into()), None);
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{
+            Ordering,
+            AtomicUsize,
+        },
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+const SLOTS: u32 = 10;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RetryBudgetParams {
+    // Retries allowed as a share of first attempts within `window`.
+    pub ratio: f64,
+    pub window: Duration,
+    // Allowed regardless of `ratio`, so a quiet process can still retry.
+    pub min_retries_per_second: usize,
+}
+
+impl Default for RetryBudgetParams {
+    fn default() -> RetryBudgetParams {
+        RetryBudgetParams {
+            ratio: 0.2,
+            window: Duration::from_secs(10),
+            min_retries_per_second: 10,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RetryBudgetStats {
+    // Within the current window.
+    pub requests: usize,
+    pub retries: usize,
+    pub max_retries: usize,
+    // Retries refused since the budget was created.
+    pub exhausted: usize,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Slot {
+    index: u64,
+    requests: usize,
+    retries: usize,
+}
+
+struct Inner {
+    params: RetryBudgetParams,
+    started_at: Instant,
+    slots: Mutex<Vec<Slot>>,
+    exhausted: AtomicUsize,
+}
+
+// Caps retries across every call site sharing the handle (usually one per
+// process, set as `ClusterParams::retry_budget` or in a `RetryConfig`), so a brown out is not multiplied by
+// every caller retrying on its own. Counts are kept in a sliding window of
+// `SLOTS` slots.
+#[derive(Clone)]
+pub struct RetryBudget {
+    inner: Arc<Inner>,
+}
+
+impl fmt::Debug for RetryBudget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RetryBudget")
+            .field("params", &self.inner.params)
+            .finish()
+    }
+}
+
+impl RetryBudget {
+    pub fn new(params: RetryBudgetParams) -> RetryBudget {
+        RetryBudget::starting_at(params, Instant::now())
+    }
+
+    fn starting_at(params: RetryBudgetParams, now: Instant) -> RetryBudget {
+        RetryBudget {
+            inner: Arc::new(Inner {
+                params,
+                started_at: now,
+                slots: Mutex::new(vec![Slot::default(); SLOTS as usize]),
+                exhausted: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    pub fn params(&self) -> RetryBudgetParams {
+        self.inner.params
+    }
+
+    pub(crate) fn record_request(&self) {
+        self.record_request_at(Instant::now());
+    }
+
+    fn record_request_at(&self, now: Instant) {
+        self.with_window(now, |slot, _requests, _retries| slot.requests += 1);
+    }
+
+    // Takes a retry from the budget if there is one left.
+    pub(crate) fn try_retry(&self) -> bool {
+        self.try_retry_at(Instant::now())
+    }
+
+    fn try_retry_at(&self, now: Instant) -> bool {
+        let allowed = self.with_window(now, |slot, requests, retries| {
+            if retries < self.max_retries_for(requests) {
+                slot.retries += 1;
+                true
+            } else {
+                false
+            }
+        });
+        if !allowed {
+            self.inner.exhausted.fetch_add(1, Ordering::Relaxed);
+        }
+        allowed
+    }
+
+    pub fn stats(&self) -> RetryBudgetStats {
+        self.stats_at(Instant::now())
+    }
+
+    fn stats_at(&self, now: Instant) -> RetryBudgetStats {
+        let (requests, retries) = self.with_window(now, |_slot, requests, retries| (requests, retries));
+        RetryBudgetStats {
+            requests,
+            retries,
+            max_retries: self.max_retries_for(requests),
+            exhausted: self.inner.exhausted.load(Ordering::Relaxed),
+        }
+    }
+
+    fn max_retries_for(&self, requests: usize) -> usize {
+        let params = &self.inner.params;
+        let floor = params.min_retries_per_second as f64 * params.window.as_secs_f64();
+        (requests as f64 * params.ratio).max(floor) as usize
+    }
+
+    // Runs `f` on the slot for `now` with the request and retry totals of
+    // the whole window, clearing slots that fell out of it.
+    fn with_window<F, T>(&self, now: Instant, f: F) -> T where F: FnOnce(&mut Slot, usize, usize) -> T {
+        let slot_length = (self.inner.params.window / SLOTS).max(Duration::from_millis(1));
+        let elapsed = now.saturating_duration_since(self.inner.started_at);
+        let index = (elapsed.as_nanos() / slot_length.as_nanos()) as u64;
+        let mut slots = self.inner.slots.lock().unwrap();
+        let (mut requests, mut retries) = (0, 0);
+        for slot in slots.iter_mut() {
+            if slot.index + u64::from(SLOTS) <= index {
+                *slot = Slot { index: 0, requests: 0, retries: 0, };
+            } else {
+                requests += slot.requests;
+                retries += slot.retries;
+            }
+        }
+        let slot = &mut slots[(index % u64::from(SLOTS)) as usize];
+        if slot.index != index {
+            requests -= slot.requests;
+            retries -= slot.retries;
+            *slot = Slot { index, requests: 0, retries: 0, };
+        }
+        f(slot, requests, retries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{
+        Instant,
+        Duration,
+    };
+
+    use super::{
+        RetryBudget,
+        RetryBudgetParams,
+    };
+
+    fn params(ratio: f64, min_retries_per_second: usize) -> RetryBudgetParams {
+        RetryBudgetParams {
+            ratio,
+            window: Duration::from_secs(10),
+            min_retries_per_second,
+        }
+    }
+
+    #[test]
+    fn window_sums_every_live_slot() {
+        let start = Instant::now();
+        let budget = RetryBudget::starting_at(params(0.5, 0), start);
+        for second in 0 .. 5 {
+            budget.record_request_at(start + Duration::from_secs(second));
+            budget.record_request_at(start + Duration::from_secs(second));
+        }
+        let stats = budget.stats_at(start + Duration::from_millis(4500));
+        assert_eq!(stats.requests, 10);
+        assert_eq!(stats.max_retries, 5);
+    }
+
+    #[test]
+    fn slots_fall_out_of_the_window() {
+        let start = Instant::now();
+        let budget = RetryBudget::starting_at(params(0.5, 0), start);
+        budget.record_request_at(start);
+        budget.record_request_at(start + Duration::from_secs(3));
+        budget.record_request_at(start + Duration::from_secs(9));
+        assert_eq!(budget.stats_at(start + Duration::from_millis(9500)).requests, 3);
+        assert_eq!(budget.stats_at(start + Duration::from_secs(10)).requests, 2);
+        assert_eq!(budget.stats_at(start + Duration::from_secs(13)).requests, 1);
+        assert_eq!(budget.stats_at(start + Duration::from_secs(30)).requests, 0);
+    }
+
+    #[test]
+    fn reused_slot_starts_empty() {
+        let start = Instant::now();
+        let budget = RetryBudget::starting_at(params(1.0, 0), start);
+        budget.record_request_at(start);
+        assert!(budget.try_retry_at(start));
+        // Same ring position one window later.
+        let later = start + Duration::from_secs(10);
+        budget.record_request_at(later);
+        let stats = budget.stats_at(later);
+        assert_eq!(stats.requests, 1);
+        assert_eq!(stats.retries, 0);
+    }
+
+    #[test]
+    fn retries_are_capped_by_ratio() {
+        let start = Instant::now();
+        let budget = RetryBudget::starting_at(params(0.2, 0), start);
+        for _ in 0 .. 10 {
+            budget.record_request_at(start);
+        }
+        assert!(budget.try_retry_at(start));
+        assert!(budget.try_retry_at(start));
+        assert!(!budget.try_retry_at(start));
+        let stats = budget.stats_at(start);
+        assert_eq!(stats.retries, 2);
+        assert_eq!(stats.exhausted, 1);
+        // Spent retries free up once their slot leaves the window.
+        let later = start + Duration::from_secs(10);
+        for _ in 0 .. 5 {
+            budget.record_request_at(later);
+        }
+        assert!(budget.try_retry_at(later));
+    }
+
+    #[test]
+    fn floor_allows_retries_without_requests() {
+        let start = Instant::now();
+        let budget = RetryBudget::starting_at(params(0.2, 1), start);
+        for _ in 0 .. 10 {
+            assert!(budget.try_retry_at(start));
+        }
+        assert!(!budget.try_retry_at(start));
+    }
+}
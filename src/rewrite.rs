@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use super::cql::skip_quoted;
+
+// Words after which a qualified `keyspace.table` name may follow; anywhere
+// else `a.b` is e.g. UDT field access and is left alone.
+const TABLE_POSITIONS: &[&str] = &["from", "into", "update", "table", "keyspace", "use", "truncate", "on", "type", "view"];
+
+#[derive(Clone, Default, Debug)]
+pub struct KeyspaceRewrite {
+    mapping: HashMap<String, String>,
+}
+
+impl KeyspaceRewrite {
+    pub fn new() -> KeyspaceRewrite {
+        KeyspaceRewrite::default()
+    }
+
+    pub fn replace<F, T>(mut self, from: F, to: T) -> KeyspaceRewrite where F: AsRef<str>, T: Into<String> {
+        self.mapping.insert(from.as_ref().to_lowercase(), to.into());
+        self
+    }
+
+    pub fn prefix<K>(mut self, prefix: &str, keyspaces: &[K]) -> KeyspaceRewrite where K: AsRef<str> {
+        for keyspace in keyspaces {
+            let keyspace = keyspace.as_ref();
+            self.mapping.insert(keyspace.to_lowercase(), format!("{}{}", prefix, keyspace));
+        }
+        self
+    }
+
+    pub fn keyspace<'a>(&'a self, keyspace: &'a str) -> &'a str {
+        self.mapping.get(&keyspace.to_lowercase())
+            .map(String::as_str)
+            .unwrap_or(keyspace)
+    }
+
+    pub fn rewrite(&self, cql: &str) -> String {
+        let bytes = cql.as_bytes();
+        let mut output = String::with_capacity(cql.len());
+        let mut expect_keyspace = false;
+        let mut expect_table = false;
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let start = offset;
+            match bytes[offset] {
+                b'\'' => {
+                    offset = skip_quoted(bytes, offset, b'\'');
+                    output.push_str(&cql[start .. offset]);
+                },
+                b'$' if bytes.get(offset + 1) == Some(&b'$') => {
+                    offset = match cql[offset + 2 ..].find("$$") {
+                        Some(index) => offset + 2 + index + 2,
+                        None => bytes.len(),
+                    };
+                    output.push_str(&cql[start .. offset]);
+                },
+                b'-' if bytes.get(offset + 1) == Some(&b'-') =>
+                    offset = copy_line_comment(cql, offset, &mut output),
+                b'/' if bytes.get(offset + 1) == Some(&b'/') =>
+                    offset = copy_line_comment(cql, offset, &mut output),
+                b'/' if bytes.get(offset + 1) == Some(&b'*') => {
+                    offset = match cql[offset + 2 ..].find("*/") {
+                        Some(index) => offset + 2 + index + 2,
+                        None => bytes.len(),
+                    };
+                    output.push_str(&cql[start .. offset]);
+                },
+                b'"' => {
+                    offset = skip_quoted(bytes, offset, b'"');
+                    let name = cql[start + 1 .. offset - 1].replace("\"\"", "\"");
+                    let is_keyspace = expect_keyspace || (expect_table && bytes.get(offset) == Some(&b'.'));
+                    match self.mapping.get(&name.to_lowercase()) {
+                        Some(target) if is_keyspace => {
+                            output.push('"');
+                            output.push_str(&target.replace('"', "\"\""));
+                            output.push('"');
+                        },
+                        _ =>
+                            output.push_str(&cql[start .. offset]),
+                    }
+                    expect_keyspace = false;
+                    expect_table = false;
+                },
+                byte if byte.is_ascii_alphabetic() || byte == b'_' => {
+                    while offset < bytes.len() && (bytes[offset].is_ascii_alphanumeric() || bytes[offset] == b'_') {
+                        offset += 1;
+                    }
+                    let word = &cql[start .. offset];
+                    let lowercase = word.to_lowercase();
+                    let is_keyspace = expect_keyspace || (expect_table && bytes.get(offset) == Some(&b'.'));
+                    match lowercase.as_str() {
+                        "use" | "keyspace" =>
+                            expect_keyspace = true,
+                        "if" | "not" | "exists" if expect_keyspace || expect_table =>
+                            (),
+                        _ =>
+                            expect_keyspace = false,
+                    }
+                    if !matches!(lowercase.as_str(), "if" | "not" | "exists") {
+                        expect_table = TABLE_POSITIONS.contains(&lowercase.as_str());
+                    }
+                    match self.mapping.get(&lowercase) {
+                        Some(target) if is_keyspace && !expect_keyspace =>
+                            output.push_str(target),
+                        _ =>
+                            output.push_str(word),
+                    }
+                },
+                _ => {
+                    let ch = cql[offset ..].chars().next().unwrap();
+                    offset += ch.len_utf8();
+                    output.push(ch);
+                },
+            }
+        }
+        output
+    }
+}
+
+fn copy_line_comment(cql: &str, offset: usize, output: &mut String) -> usize {
+    let end = match cql[offset ..].find('\n') {
+        Some(index) => offset + index,
+        None => cql.len(),
+    };
+    output.push_str(&cql[offset .. end]);
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyspaceRewrite;
+
+    fn rewrite(cql: &str) -> String {
+        KeyspaceRewrite::new()
+            .replace("app", "app_test")
+            .replace("addr", "wrong")
+            .rewrite(cql)
+    }
+
+    #[test]
+    fn rewrites_qualified_tables() {
+        assert_eq!(rewrite("SELECT * FROM app.users"), "SELECT * FROM app_test.users");
+        assert_eq!(rewrite("INSERT INTO \"app\".users (id) VALUES (?)"), "INSERT INTO \"app_test\".users (id) VALUES (?)");
+        assert_eq!(rewrite("UPDATE app.users SET a = 1"), "UPDATE app_test.users SET a = 1");
+        assert_eq!(rewrite("CREATE TABLE IF NOT EXISTS app.t (k int PRIMARY KEY)"), "CREATE TABLE IF NOT EXISTS app_test.t (k int PRIMARY KEY)");
+        assert_eq!(rewrite("USE app"), "USE app_test");
+        assert_eq!(rewrite("DROP KEYSPACE IF EXISTS app"), "DROP KEYSPACE IF EXISTS app_test");
+    }
+
+    #[test]
+    fn leaves_udt_field_access_alone() {
+        assert_eq!(
+            rewrite("SELECT addr.city FROM app.users WHERE app = 'app.users'"),
+            "SELECT addr.city FROM app_test.users WHERE app = 'app.users'",
+        );
+        assert_eq!(rewrite("UPDATE app.users SET addr.city = ? WHERE id = ?"), "UPDATE app_test.users SET addr.city = ? WHERE id = ?");
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    fmt,
+    any,
+    hash::Hash,
+    collections::{
+        HashMap,
+        HashSet,
+    },
+};
+
+use cassandra_cpp::{
+    Uuid,
+    Value,
+    ValueType,
+};
+
+pub use cassandra_cpp::Row;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ColumnRef {
+    Index(usize),
+    Name(String),
+}
+
+#[derive(Debug)]
+pub struct RowError {
+    pub column: ColumnRef,
+    pub expected: &'static str,
+    pub error: cassandra_cpp::Error,
+}
+
+impl fmt::Display for ColumnRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColumnRef::Index(index) =>
+                write!(f, "#{}", index),
+            ColumnRef::Name(name) =>
+                write!(f, "{:?}", name),
+        }
+    }
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error reading column {} as {}: {}", self.column, self.expected, self.error)
+    }
+}
+
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, cassandra_cpp::Error>;
+}
+
+pub trait FromRow: Sized {
+    fn from_row(row: &Row) -> Result<Self, RowError>;
+}
+
+pub fn column<T>(row: &Row, index: usize) -> Result<T, RowError> where T: FromValue {
+    row.get_column(index)
+        .and_then(|value| T::from_value(&value))
+        .map_err(|error| RowError {
+            column: ColumnRef::Index(index),
+            expected: any::type_name::<T>(),
+            error,
+        })
+}
+
+pub fn column_by_name<T>(row: &Row, name: &str) -> Result<T, RowError> where T: FromValue {
+    row.get_column_by_name(name)
+        .and_then(|value| T::from_value(&value))
+        .map_err(|error| RowError {
+            column: ColumnRef::Name(name.to_string()),
+            expected: any::type_name::<T>(),
+            error,
+        })
+}
+
+macro_rules! impl_from_value {
+    ($($type:ty => $getter:ident),* $(,)*) => {
+        $(
+            impl FromValue for $type {
+                fn from_value(value: &Value) -> Result<Self, cassandra_cpp::Error> {
+                    value.$getter()
+                }
+            }
+        )*
+    };
+}
+
+impl_from_value! {
+    String => get_string,
+    bool => get_bool,
+    i8 => get_i8,
+    i16 => get_i16,
+    i32 => get_i32,
+    i64 => get_i64,
+    u32 => get_u32,
+    f32 => get_f32,
+    f64 => get_f64,
+    Uuid => get_uuid,
+}
+
+impl FromValue for Vec<u8> {
+    fn from_value(value: &Value) -> Result<Self, cassandra_cpp::Error> {
+        value.get_bytes().map(|bytes| bytes.to_vec())
+    }
+}
+
+impl<T> FromValue for Vec<T> where T: FromValue {
+    fn from_value(value: &Value) -> Result<Self, cassandra_cpp::Error> {
+        expect_type(value, &[ValueType::LIST, ValueType::SET], "list")?;
+        value.get_set()?
+            .enumerate()
+            .map(|(position, item)| element::<T>(&item, "element", position))
+            .collect()
+    }
+}
+
+impl<T> FromValue for HashSet<T> where T: FromValue + Eq + Hash {
+    fn from_value(value: &Value) -> Result<Self, cassandra_cpp::Error> {
+        expect_type(value, &[ValueType::SET, ValueType::LIST], "set")?;
+        value.get_set()?
+            .enumerate()
+            .map(|(position, item)| element::<T>(&item, "element", position))
+            .collect()
+    }
+}
+
+impl<K, V> FromValue for HashMap<K, V> where K: FromValue + Eq + Hash, V: FromValue {
+    fn from_value(value: &Value) -> Result<Self, cassandra_cpp::Error> {
+        expect_type(value, &[ValueType::MAP], "map")?;
+        value.get_map()?
+            .enumerate()
+            .map(|(position, (key, value))| Ok((element::<K>(&key, "key", position)?, element::<V>(&value, "value", position)?)))
+            .collect()
+    }
+}
+
+fn expect_type(value: &Value, expected: &[ValueType], name: &str) -> Result<(), cassandra_cpp::Error> {
+    let value_type = value.get_type();
+    if expected.contains(&value_type) {
+        Ok(())
+    } else {
+        Err(format!("expected a {} value, got {:?}", name, value_type).into())
+    }
+}
+
+fn element<T>(value: &Value, what: &str, position: usize) -> Result<T, cassandra_cpp::Error> where T: FromValue {
+    T::from_value(value)
+        .map_err(|error| format!("error reading {} #{} as {}: {}", what, position, any::type_name::<T>(), error).into())
+}
+
+impl<T> FromValue for Option<T> where T: FromValue {
+    fn from_value(value: &Value) -> Result<Self, cassandra_cpp::Error> {
+        if value.is_null() {
+            Ok(None)
+        } else {
+            T::from_value(value).map(Some)
+        }
+    }
+}
+
+macro_rules! impl_from_row_tuple {
+    ($($type:ident : $index:tt),*) => {
+        impl<$($type),*> FromRow for ($($type,)*) where $($type: FromValue),* {
+            fn from_row(row: &Row) -> Result<Self, RowError> {
+                Ok(($(column::<$type>(row, $index)?,)*))
+            }
+        }
+    };
+}
+
+impl_from_row_tuple!(A: 0);
+impl_from_row_tuple!(A: 0, B: 1);
+impl_from_row_tuple!(A: 0, B: 1, C: 2);
+impl_from_row_tuple!(A: 0, B: 1, C: 2, D: 3);
+impl_from_row_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_from_row_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_from_row_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_from_row_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+impl_from_row_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8);
+impl_from_row_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9);
+impl_from_row_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10);
+impl_from_row_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11);
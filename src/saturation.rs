@@ -0,0 +1,73 @@
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use super::SharedSession;
+
+// `max_in_flight` usage from which `saturation` reports `Elevated`.
+const ELEVATED_IN_FLIGHT_RATIO: f64 = 0.8;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "snake_case"))]
+pub enum SaturationLevel {
+    Normal,
+    // Close to `max_in_flight`: a good time to start shedding load.
+    Elevated,
+    // Connections are past their high water marks (the driver is about to
+    // reject requests) or `max_in_flight` is used up.
+    Saturated,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Saturation {
+    pub level: SaturationLevel,
+    pub connections: u64,
+    pub connections_over_pending_requests_mark: u64,
+    pub connections_over_write_bytes_mark: u64,
+    // Requests in flight through the shared execute path and its limit, when
+    // `max_in_flight` is configured.
+    pub in_flight: Option<usize>,
+    pub max_in_flight: Option<usize>,
+}
+
+impl Saturation {
+    pub fn is_saturated(&self) -> bool {
+        self.level == SaturationLevel::Saturated
+    }
+}
+
+impl SharedSession {
+    // A cheap snapshot meant to be polled by producers before they send
+    // more work; the driver only counts connections over the
+    // `pending_requests_high_water_mark` and `write_bytes_high_water_mark`,
+    // not the pending requests themselves.
+    pub fn saturation(&self) -> Saturation {
+        let connections = self.metrics().connections;
+        let (in_flight, max_in_flight) = match self.settings.in_flight {
+            Some(ref in_flight) =>
+                (Some(in_flight.in_flight()), Some(in_flight.limit())),
+            None =>
+                (None, None),
+        };
+        let level = if connections.exceeded_pending_requests_water_mark > 0 || connections.exceeded_write_bytes_water_mark > 0 {
+            SaturationLevel::Saturated
+        } else {
+            match (in_flight, max_in_flight) {
+                (Some(in_flight), Some(limit)) if in_flight >= limit =>
+                    SaturationLevel::Saturated,
+                (Some(in_flight), Some(limit)) if in_flight as f64 >= limit as f64 * ELEVATED_IN_FLIGHT_RATIO =>
+                    SaturationLevel::Elevated,
+                _ =>
+                    SaturationLevel::Normal,
+            }
+        };
+        Saturation {
+            level,
+            connections: connections.total_connections,
+            connections_over_pending_requests_mark: connections.exceeded_pending_requests_water_mark,
+            connections_over_write_bytes_mark: connections.exceeded_write_bytes_water_mark,
+            in_flight,
+            max_in_flight,
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use futures::{
+    stream,
+    Future,
+    Stream,
+};
+
+use ero::{
+    Loop,
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+};
+
+use super::{
+    SharedSession,
+    row::FromRow,
+    cql::Table,
+    paging::QueryStreamError,
+    token::{
+        self,
+        TokenRange,
+    },
+};
+
+type RowStream<T> = Box<dyn Stream<Item = T, Error = QueryStreamError> + Send + 'static>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ScanParams {
+    // The ring is split into this many ranges, queried one at a time per
+    // worker.
+    pub ranges: usize,
+    pub concurrency: usize,
+    pub page_size: i32,
+}
+
+impl Default for ScanParams {
+    fn default() -> ScanParams {
+        ScanParams {
+            ranges: 64,
+            concurrency: 4,
+            page_size: 5000,
+        }
+    }
+}
+
+// Reads the whole table as `concurrency` workers, each going through its
+// share of the token ranges with a session acquired from the lode per
+// range, so the load is spread over coordinators and pooled sessions. Rows
+// come out as the workers produce them, in no particular order. `columns`
+// empty selects `*`; `partition_key` lists the key columns in order.
+pub fn scan_table<T, N>(
+    resource: LodeResource<SharedSession>,
+    table: N,
+    columns: &[&str],
+    partition_key: &[&str],
+    params: ScanParams,
+)
+    -> impl Stream<Item = T, Error = QueryStreamError> + Send + 'static
+where T: FromRow + Send + 'static,
+      N: Into<Table>,
+{
+    let selection = if columns.is_empty() {
+        "*".to_string()
+    } else {
+        columns.join(", ")
+    };
+    let query = format!("SELECT {} FROM {} WHERE {}", selection, table.into().quoted(), token::token_range_clause(partition_key));
+    let ranges = TokenRange::full_ring().split(params.ranges);
+    let concurrency = params.concurrency.max(1).min(ranges.len());
+
+    let mut workers: Vec<Vec<TokenRange>> = vec![Vec::new(); concurrency];
+    for (index, range) in ranges.into_iter().enumerate() {
+        workers[index % concurrency].push(range);
+    }
+    let mut merged: Option<RowStream<T>> = None;
+    for worker_ranges in workers {
+        let resource = resource.clone();
+        let query = query.clone();
+        let worker = stream::iter_ok::<_, QueryStreamError>(worker_ranges)
+            .map(move |range| scan_range::<T>(resource.clone(), query.clone(), range, params.page_size))
+            .flatten();
+        merged = Some(match merged {
+            None =>
+                Box::new(worker),
+            Some(merged) =>
+                Box::new(merged.select(worker)),
+        });
+    }
+    merged.expect("at least one scan worker")
+}
+
+fn scan_range<T>(
+    resource: LodeResource<SharedSession>,
+    query: String,
+    range: TokenRange,
+    page_size: i32,
+)
+    -> RowStream<T>
+where T: FromRow + Send + 'static,
+{
+    let rows = resource
+        .using_resource_loop((), |session, ()| -> Result<_, ErrorSeverity<(), ()>> {
+            Ok((UsingResource::Lost, Loop::Break(session)))
+        })
+        .map_err(|_error| QueryStreamError::Driver("session lode terminated".into()))
+        .and_then(move |session| {
+            let mut statement = session.statement(&query, 2);
+            token::bind_token_range(&mut statement, 0, &range)
+                .map_err(QueryStreamError::Driver)
+                .map(|()| session.query_stream::<T>(statement, page_size))
+        })
+        .flatten_stream();
+    Box::new(rows)
+}
@@ -0,0 +1,508 @@
+use std::{
+    fmt,
+    collections::HashMap,
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+};
+
+use super::{
+    SharedSession,
+    value::CqlValue,
+};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ColumnType {
+    Ascii,
+    Text,
+    Boolean,
+    TinyInt,
+    SmallInt,
+    Int,
+    BigInt,
+    Counter,
+    Varint,
+    Float,
+    Double,
+    Decimal,
+    Blob,
+    Uuid,
+    Timeuuid,
+    Timestamp,
+    Date,
+    Time,
+    Inet,
+    List(Box<ColumnType>),
+    Set(Box<ColumnType>),
+    Map(Box<ColumnType>, Box<ColumnType>),
+    Other(String),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ValidationError {
+    pub column: String,
+    pub column_type: ColumnType,
+    pub reason: String,
+}
+
+#[derive(Debug)]
+pub enum BindError {
+    Validation(ValidationError),
+    Driver(cassandra_cpp::Error),
+    // A `Codec` for a type the installed `CodecRegistry` has no codec for.
+    NoCodec(&'static str),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ColumnKind {
+    PartitionKey,
+    Clustering,
+    Static,
+    Regular,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub kind: ColumnKind,
+    // Position within the partition or clustering key; -1 otherwise.
+    pub position: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct TableSchema {
+    pub keyspace: String,
+    pub table: String,
+    columns: HashMap<String, ColumnType>,
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColumnType::Ascii => write!(f, "ascii"),
+            ColumnType::Text => write!(f, "text"),
+            ColumnType::Boolean => write!(f, "boolean"),
+            ColumnType::TinyInt => write!(f, "tinyint"),
+            ColumnType::SmallInt => write!(f, "smallint"),
+            ColumnType::Int => write!(f, "int"),
+            ColumnType::BigInt => write!(f, "bigint"),
+            ColumnType::Counter => write!(f, "counter"),
+            ColumnType::Varint => write!(f, "varint"),
+            ColumnType::Float => write!(f, "float"),
+            ColumnType::Double => write!(f, "double"),
+            ColumnType::Decimal => write!(f, "decimal"),
+            ColumnType::Blob => write!(f, "blob"),
+            ColumnType::Uuid => write!(f, "uuid"),
+            ColumnType::Timeuuid => write!(f, "timeuuid"),
+            ColumnType::Timestamp => write!(f, "timestamp"),
+            ColumnType::Date => write!(f, "date"),
+            ColumnType::Time => write!(f, "time"),
+            ColumnType::Inet => write!(f, "inet"),
+            ColumnType::List(item) => write!(f, "list<{}>", item),
+            ColumnType::Set(item) => write!(f, "set<{}>", item),
+            ColumnType::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            ColumnType::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid value for column {:?} of type {}: {}", self.column, self.column_type, self.reason)
+    }
+}
+
+impl ColumnKind {
+    fn parse(kind: &str) -> ColumnKind {
+        match kind {
+            "partition_key" => ColumnKind::PartitionKey,
+            "clustering" => ColumnKind::Clustering,
+            "static" => ColumnKind::Static,
+            _ => ColumnKind::Regular,
+        }
+    }
+}
+
+impl ColumnType {
+    pub fn parse(cql_type: &str) -> ColumnType {
+        let cql_type = cql_type.trim();
+        let (name, arguments) = match cql_type.find('<') {
+            Some(index) if cql_type.ends_with('>') =>
+                (&cql_type[.. index], Some(&cql_type[index + 1 .. cql_type.len() - 1])),
+            _ =>
+                (cql_type, None),
+        };
+        match (name.trim().to_lowercase().as_str(), arguments) {
+            ("ascii", None) => ColumnType::Ascii,
+            ("text", None) | ("varchar", None) => ColumnType::Text,
+            ("boolean", None) => ColumnType::Boolean,
+            ("tinyint", None) => ColumnType::TinyInt,
+            ("smallint", None) => ColumnType::SmallInt,
+            ("int", None) => ColumnType::Int,
+            ("bigint", None) => ColumnType::BigInt,
+            ("counter", None) => ColumnType::Counter,
+            ("varint", None) => ColumnType::Varint,
+            ("float", None) => ColumnType::Float,
+            ("double", None) => ColumnType::Double,
+            ("decimal", None) => ColumnType::Decimal,
+            ("blob", None) => ColumnType::Blob,
+            ("uuid", None) => ColumnType::Uuid,
+            ("timeuuid", None) => ColumnType::Timeuuid,
+            ("timestamp", None) => ColumnType::Timestamp,
+            ("date", None) => ColumnType::Date,
+            ("time", None) => ColumnType::Time,
+            ("inet", None) => ColumnType::Inet,
+            ("frozen", Some(inner)) =>
+                ColumnType::parse(inner),
+            ("list", Some(item)) =>
+                ColumnType::List(Box::new(ColumnType::parse(item))),
+            ("set", Some(item)) =>
+                ColumnType::Set(Box::new(ColumnType::parse(item))),
+            ("map", Some(arguments)) =>
+                match split_type_arguments(arguments).as_slice() {
+                    [key, value] =>
+                        ColumnType::Map(Box::new(ColumnType::parse(key)), Box::new(ColumnType::parse(value))),
+                    _ =>
+                        ColumnType::Other(cql_type.to_string()),
+                },
+            _ =>
+                ColumnType::Other(cql_type.to_string()),
+        }
+    }
+
+    pub fn coerce(&self, value: CqlValue) -> Result<CqlValue, String> {
+        match (self, value) {
+            (_, CqlValue::Null) =>
+                Ok(CqlValue::Null),
+            (ColumnType::Ascii, CqlValue::Text(text)) =>
+                match text.char_indices().find(|&(_, ch)| !ch.is_ascii()) {
+                    Some((position, ch)) =>
+                        Err(format!("non-ascii character {:?} at byte {}", ch, position)),
+                    None =>
+                        Ok(CqlValue::Text(text)),
+                },
+            (ColumnType::Text, value @ CqlValue::Text(..)) |
+            (ColumnType::Boolean, value @ CqlValue::Bool(..)) |
+            (ColumnType::Float, value @ CqlValue::Float(..)) |
+            (ColumnType::Double, value @ CqlValue::Double(..)) |
+            (ColumnType::Blob, value @ CqlValue::Blob(..)) |
+            (ColumnType::Uuid, value @ CqlValue::Uuid(..)) |
+            (ColumnType::Timeuuid, value @ CqlValue::Uuid(..)) =>
+                Ok(value),
+            (ColumnType::TinyInt, value) =>
+                integer(&value).and_then(|number| {
+                    if number < i8::MIN as i64 || number > i8::MAX as i64 {
+                        Err(format!("{} is out of tinyint range", number))
+                    } else {
+                        Ok(CqlValue::TinyInt(number as i8))
+                    }
+                }),
+            (ColumnType::SmallInt, value) =>
+                integer(&value).and_then(|number| {
+                    if number < i16::MIN as i64 || number > i16::MAX as i64 {
+                        Err(format!("{} is out of smallint range", number))
+                    } else {
+                        Ok(CqlValue::SmallInt(number as i16))
+                    }
+                }),
+            (ColumnType::Int, value) =>
+                integer(&value).and_then(|number| {
+                    if number < i32::MIN as i64 || number > i32::MAX as i64 {
+                        Err(format!("{} is out of int range", number))
+                    } else {
+                        Ok(CqlValue::Int(number as i32))
+                    }
+                }),
+            (ColumnType::BigInt, value) | (ColumnType::Counter, value) | (ColumnType::Timestamp, value) =>
+                integer(&value).map(CqlValue::BigInt),
+            (ColumnType::List(item_type), CqlValue::List(items)) =>
+                coerce_items(item_type, items).map(CqlValue::List),
+            (ColumnType::Set(item_type), CqlValue::Set(items)) =>
+                coerce_items(item_type, items).map(CqlValue::Set),
+            (ColumnType::Map(key_type, value_type), CqlValue::Map(entries)) =>
+                entries.into_iter()
+                    .enumerate()
+                    .map(|(position, (key, value))| -> Result<(CqlValue, CqlValue), String> {
+                        let key = key_type.coerce(key)
+                            .map_err(|reason| format!("map key #{}: {}", position, reason))?;
+                        let value = value_type.coerce(value)
+                            .map_err(|reason| format!("map value #{}: {}", position, reason))?;
+                        Ok((key, value))
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(CqlValue::Map),
+            (ColumnType::Other(..), value) =>
+                Ok(value),
+            (column_type, value) =>
+                Err(format!("{} value can't be stored as {}", value.kind(), column_type)),
+        }
+    }
+}
+
+fn integer(value: &CqlValue) -> Result<i64, String> {
+    match *value {
+        CqlValue::TinyInt(number) => Ok(number as i64),
+        CqlValue::SmallInt(number) => Ok(number as i64),
+        CqlValue::Int(number) => Ok(number as i64),
+        CqlValue::BigInt(number) => Ok(number),
+        ref other => Err(format!("expected an integer, got {} value", other.kind())),
+    }
+}
+
+fn coerce_items(item_type: &ColumnType, items: Vec<CqlValue>) -> Result<Vec<CqlValue>, String> {
+    items.into_iter()
+        .enumerate()
+        .map(|(position, item)| {
+            item_type.coerce(item)
+                .map_err(|reason| format!("element #{}: {}", position, reason))
+        })
+        .collect()
+}
+
+fn split_type_arguments(arguments: &str) -> Vec<&str> {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut parts = Vec::new();
+    for (index, ch) in arguments.char_indices() {
+        match ch {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(arguments[start .. index].trim());
+                start = index + 1;
+            },
+            _ => (),
+        }
+    }
+    parts.push(arguments[start ..].trim());
+    parts
+}
+
+impl TableSchema {
+    pub fn column_type(&self, column: &str) -> Option<&ColumnType> {
+        self.columns.get(column)
+    }
+
+    pub fn validate(&self, column: &str, value: CqlValue) -> Result<CqlValue, ValidationError> {
+        match self.columns.get(column) {
+            Some(column_type) =>
+                column_type.coerce(value)
+                    .map_err(|reason| ValidationError {
+                        column: column.to_string(),
+                        column_type: column_type.clone(),
+                        reason,
+                    }),
+            None =>
+                Err(ValidationError {
+                    column: column.to_string(),
+                    column_type: ColumnType::Other("unknown".to_string()),
+                    reason: format!("no such column in {}.{}", self.keyspace, self.table),
+                }),
+        }
+    }
+
+    pub fn bind_validated(&self, statement: &mut Statement, columns: &[&str], values: Vec<CqlValue>) -> Result<(), BindError> {
+        let validated = columns.iter()
+            .zip(values)
+            .map(|(column, value)| self.validate(column, value))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(BindError::Validation)?;
+        for (index, value) in validated.into_iter().enumerate() {
+            value.bind(statement, index)
+                .map_err(BindError::Driver)?;
+        }
+        Ok(())
+    }
+}
+
+impl SharedSession {
+    pub fn table_schema(&self, keyspace: &str, table: &str) -> impl Future<Item = TableSchema, Error = cassandra_cpp::Error> {
+        let keyspace = self.schema_keyspace(keyspace);
+        let table = table.to_string();
+        self.query_schema(
+            "SELECT column_name, type FROM system_schema.columns WHERE keyspace_name = ? AND table_name = ?",
+            &[keyspace.as_str(), table.as_str()],
+        )
+            .map(move |cass_result| {
+                let mut columns = HashMap::new();
+                for row in cass_result.iter() {
+                    let column = row.get_column(0).and_then(|value| value.get_string());
+                    let cql_type = row.get_column(1).and_then(|value| value.get_string());
+                    if let (Ok(column), Ok(cql_type)) = (column, cql_type) {
+                        columns.insert(column, ColumnType::parse(&cql_type));
+                    }
+                }
+                TableSchema { keyspace, table, columns, }
+            })
+    }
+
+    pub fn keyspaces(&self) -> impl Future<Item = Vec<String>, Error = cassandra_cpp::Error> {
+        self.query_schema("SELECT keyspace_name FROM system_schema.keyspaces", &[])
+            .map(|cass_result| names(&cass_result))
+    }
+
+    pub fn tables(&self, keyspace: &str) -> impl Future<Item = Vec<String>, Error = cassandra_cpp::Error> {
+        let keyspace = self.schema_keyspace(keyspace);
+        self.query_schema("SELECT table_name FROM system_schema.tables WHERE keyspace_name = ?", &[keyspace.as_str()])
+            .map(|cass_result| names(&cass_result))
+    }
+
+    // Partition key columns first, then clustering columns, each in key order,
+    // followed by the rest sorted by name.
+    pub fn columns(&self, keyspace: &str, table: &str) -> impl Future<Item = Vec<ColumnInfo>, Error = cassandra_cpp::Error> {
+        let keyspace = self.schema_keyspace(keyspace);
+        self.query_schema(
+            "SELECT column_name, type, kind, position FROM system_schema.columns WHERE keyspace_name = ? AND table_name = ?",
+            &[keyspace.as_str(), table],
+        )
+            .map(|cass_result| {
+                let mut columns: Vec<_> = cass_result.iter()
+                    .filter_map(|row| {
+                        let name = row.get_column(0).and_then(|value| value.get_string()).ok()?;
+                        let cql_type = row.get_column(1).and_then(|value| value.get_string()).ok()?;
+                        let kind = row.get_column(2).and_then(|value| value.get_string()).ok()?;
+                        let position = row.get_column(3).and_then(|value| value.get_i32()).unwrap_or(-1);
+                        Some(ColumnInfo {
+                            name,
+                            column_type: ColumnType::parse(&cql_type),
+                            kind: ColumnKind::parse(&kind),
+                            position,
+                        })
+                    })
+                    .collect();
+                columns.sort_by(|a, b| (a.kind, a.position, &a.name).cmp(&(b.kind, b.position, &b.name)));
+                columns
+            })
+    }
+
+    pub fn table_exists(&self, keyspace: &str, table: &str) -> impl Future<Item = bool, Error = cassandra_cpp::Error> {
+        let keyspace = self.schema_keyspace(keyspace);
+        self.query_schema(
+            "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ? AND table_name = ?",
+            &[keyspace.as_str(), table],
+        )
+            .map(|cass_result| cass_result.row_count() > 0)
+    }
+
+    fn schema_keyspace(&self, keyspace: &str) -> String {
+        match self.settings.keyspace_rewrite {
+            Some(ref keyspace_rewrite) =>
+                keyspace_rewrite.keyspace(keyspace).to_string(),
+            None =>
+                keyspace.to_string(),
+        }
+    }
+
+    fn query_schema(&self, query: &str, values: &[&str]) -> impl Future<Item = CassResult, Error = cassandra_cpp::Error> {
+        let mut statement = Statement::new(query, values.len());
+        let bind_result = values.iter()
+            .enumerate()
+            .try_for_each(|(index, value)| statement.bind_string(index, value).map(|_statement| ()));
+        match bind_result {
+            Ok(()) =>
+                Either::A(self.execute_guarded(statement)),
+            Err(error) =>
+                Either::B(result(Err(error))),
+        }
+    }
+}
+
+fn names(cass_result: &CassResult) -> Vec<String> {
+    let mut names: Vec<_> = cass_result.iter()
+        .filter_map(|row| row.get_column(0).and_then(|value| value.get_string()).ok())
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ColumnType,
+        super::value::CqlValue,
+    };
+
+    #[test]
+    fn parses_simple_types() {
+        assert_eq!(ColumnType::parse("int"), ColumnType::Int);
+        assert_eq!(ColumnType::parse(" VARCHAR "), ColumnType::Text);
+        assert_eq!(ColumnType::parse("timeuuid"), ColumnType::Timeuuid);
+        assert_eq!(ColumnType::parse("duration"), ColumnType::Other("duration".to_string()));
+    }
+
+    #[test]
+    fn parses_collections() {
+        assert_eq!(ColumnType::parse("list<text>"), ColumnType::List(Box::new(ColumnType::Text)));
+        assert_eq!(ColumnType::parse("frozen<set<uuid>>"), ColumnType::Set(Box::new(ColumnType::Uuid)));
+        assert_eq!(
+            ColumnType::parse("map<text, frozen<map<int, list<bigint>>>>"),
+            ColumnType::Map(
+                Box::new(ColumnType::Text),
+                Box::new(ColumnType::Map(Box::new(ColumnType::Int), Box::new(ColumnType::List(Box::new(ColumnType::BigInt))))),
+            ),
+        );
+        assert_eq!(ColumnType::parse("map<text>"), ColumnType::Other("map<text>".to_string()));
+        assert_eq!(ColumnType::parse("tuple<int, text>"), ColumnType::Other("tuple<int, text>".to_string()));
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for cql_type in &["bigint", "list<text>", "map<text, set<int>>", "frozen<udt>"] {
+            let column_type = ColumnType::parse(cql_type);
+            assert_eq!(ColumnType::parse(&column_type.to_string()), column_type);
+        }
+    }
+
+    #[test]
+    fn integers_are_narrowed_in_range() {
+        assert_eq!(ColumnType::TinyInt.coerce(CqlValue::Int(-128)), Ok(CqlValue::TinyInt(-128)));
+        assert!(ColumnType::TinyInt.coerce(CqlValue::Int(128)).is_err());
+        assert_eq!(ColumnType::SmallInt.coerce(CqlValue::BigInt(300)), Ok(CqlValue::SmallInt(300)));
+        assert!(ColumnType::Int.coerce(CqlValue::BigInt(i64::from(i32::MAX) + 1)).is_err());
+        assert_eq!(ColumnType::Timestamp.coerce(CqlValue::Int(5)), Ok(CqlValue::BigInt(5)));
+        assert!(ColumnType::Int.coerce(CqlValue::Text("5".to_string())).is_err());
+    }
+
+    #[test]
+    fn ascii_rejects_other_characters() {
+        assert_eq!(ColumnType::Ascii.coerce(CqlValue::Text("plain".to_string())), Ok(CqlValue::Text("plain".to_string())));
+        assert_eq!(
+            ColumnType::Ascii.coerce(CqlValue::Text("naïve".to_string())),
+            Err("non-ascii character 'ï' at byte 2".to_string()),
+        );
+    }
+
+    #[test]
+    fn collections_are_coerced_per_element() {
+        let list = ColumnType::parse("list<smallint>");
+        assert_eq!(list.coerce(CqlValue::List(vec![CqlValue::Int(1), CqlValue::Null])), Ok(CqlValue::List(vec![CqlValue::SmallInt(1), CqlValue::Null])));
+        assert_eq!(
+            list.coerce(CqlValue::List(vec![CqlValue::Int(1), CqlValue::Int(70_000)])),
+            Err("element #1: 70000 is out of smallint range".to_string()),
+        );
+        let map = ColumnType::parse("map<text, int>");
+        assert_eq!(
+            map.coerce(CqlValue::Map(vec![(CqlValue::Text("a".to_string()), CqlValue::Bool(true))])),
+            Err("map value #0: expected an integer, got boolean value".to_string()),
+        );
+        assert!(list.coerce(CqlValue::Set(vec![])).is_err());
+    }
+
+    #[test]
+    fn null_and_other_types_pass_through() {
+        assert_eq!(ColumnType::Int.coerce(CqlValue::Null), Ok(CqlValue::Null));
+        assert_eq!(ColumnType::parse("duration").coerce(CqlValue::Int(1)), Ok(CqlValue::Int(1)));
+        assert_eq!(ColumnType::Blob.coerce(CqlValue::Text("x".to_string())), Err("text value can't be stored as blob".to_string()));
+    }
+}
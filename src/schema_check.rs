@@ -0,0 +1,142 @@
+use std::fmt;
+
+use futures::{
+    future::join_all,
+    Future,
+};
+
+use super::{
+    SharedSession,
+    cql::Table,
+    schema::ColumnType,
+};
+
+// A table the binary relies on, with the columns (and their types) it reads
+// or writes. Columns not listed are not checked, so adding a column to the
+// deployed table does not break an older binary.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SchemaExpectation {
+    // Unqualified tables are looked up in the session keyspace.
+    pub table: Table,
+    pub columns: Vec<(String, ColumnType)>,
+}
+
+impl SchemaExpectation {
+    pub fn table<T>(table: T) -> SchemaExpectation where T: Into<Table> {
+        SchemaExpectation {
+            table: table.into(),
+            columns: Vec::new(),
+        }
+    }
+
+    pub fn column<N>(mut self, name: N, column_type: ColumnType) -> SchemaExpectation where N: Into<String> {
+        self.columns.push((name.into(), column_type));
+        self
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum SchemaMismatch {
+    // An unqualified table with no session keyspace to look it up in.
+    NoKeyspace { table: String, },
+    MissingTable { table: String, },
+    MissingColumn { table: String, column: String, expected: ColumnType, },
+    ColumnType { table: String, column: String, expected: ColumnType, actual: ColumnType, },
+}
+
+impl fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaMismatch::NoKeyspace { table, } =>
+                write!(f, "table {} has no keyspace and the session has none either", table),
+            SchemaMismatch::MissingTable { table, } =>
+                write!(f, "table {} does not exist", table),
+            SchemaMismatch::MissingColumn { table, column, expected, } =>
+                write!(f, "table {} has no column {} (expected {})", table, column, expected),
+            SchemaMismatch::ColumnType { table, column, expected, actual, } =>
+                write!(f, "column {}.{} is {}, expected {}", table, column, actual, expected),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SchemaCheckError {
+    Driver(cassandra_cpp::Error),
+    Mismatch(Vec<SchemaMismatch>),
+}
+
+impl fmt::Display for SchemaCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaCheckError::Driver(error) =>
+                write!(f, "error reading schema metadata: {:?}", error),
+            SchemaCheckError::Mismatch(mismatches) => {
+                write!(f, "deployed schema does not match:")?;
+                for mismatch in mismatches {
+                    write!(f, "\n  {}", mismatch)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl SharedSession {
+    // Every difference is collected, not just the first one.
+    pub fn check_schema(
+        &self,
+        keyspace: Option<&str>,
+        expectations: &[SchemaExpectation],
+    )
+        -> impl Future<Item = (), Error = SchemaCheckError>
+    {
+        let mut mismatches = Vec::new();
+        let mut checks = Vec::new();
+        for expectation in expectations {
+            let keyspace = match expectation.table.keyspace().or(keyspace) {
+                Some(keyspace) =>
+                    keyspace.to_string(),
+                None => {
+                    mismatches.push(SchemaMismatch::NoKeyspace { table: expectation.table.to_string(), });
+                    continue;
+                },
+            };
+            let expectation = expectation.clone();
+            let check = self.columns(&keyspace, expectation.table.name())
+                .map(move |columns| {
+                    let table = format!("{}.{}", keyspace, expectation.table.name());
+                    if columns.is_empty() {
+                        return vec![SchemaMismatch::MissingTable { table, }];
+                    }
+                    expectation.columns.into_iter()
+                        .filter_map(|(column, expected)| {
+                            match columns.iter().find(|info| info.name == column) {
+                                None =>
+                                    Some(SchemaMismatch::MissingColumn { table: table.clone(), column, expected, }),
+                                Some(info) if info.column_type != expected =>
+                                    Some(SchemaMismatch::ColumnType {
+                                        table: table.clone(),
+                                        column,
+                                        expected,
+                                        actual: info.column_type.clone(),
+                                    }),
+                                Some(..) =>
+                                    None,
+                            }
+                        })
+                        .collect()
+                });
+            checks.push(check);
+        }
+        join_all(checks)
+            .map_err(SchemaCheckError::Driver)
+            .and_then(move |found| {
+                mismatches.extend(found.into_iter().flatten());
+                if mismatches.is_empty() {
+                    Ok(())
+                } else {
+                    Err(SchemaCheckError::Mismatch(mismatches))
+                }
+            })
+    }
+}
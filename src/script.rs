@@ -0,0 +1,78 @@
+use std::{
+    io,
+    fs,
+    fmt,
+    path::Path,
+};
+
+use futures::{
+    stream,
+    Future,
+    Stream,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use log::debug;
+
+use super::{
+    SharedSession,
+    cql::{
+        self,
+        ScriptStatement,
+    },
+};
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(io::Error),
+    Statement {
+        // 0-based position of the statement in the script.
+        index: usize,
+        line: usize,
+        statement: String,
+        error: cassandra_cpp::Error,
+    },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScriptError::Io(error) =>
+                write!(f, "error reading script: {}", error),
+            ScriptError::Statement { index, line, statement, error, } =>
+                write!(f, "statement #{} at line {} failed: {:?}: {}", index + 1, line, error, statement),
+        }
+    }
+}
+
+impl SharedSession {
+    // Runs the statements one after another and stops at the first failure;
+    // statements before it stay applied. Resolves to the number of
+    // statements executed.
+    pub fn execute_script(&self, script: &str) -> impl Future<Item = usize, Error = ScriptError> {
+        let statements = cql::split_script(script);
+        let count = statements.len();
+        debug!("executing script of {} statement(s)", count);
+        let session = self.clone();
+        stream::iter_ok(statements.into_iter().enumerate())
+            .for_each(move |(index, ScriptStatement { line, text, })| {
+                let statement = session.statement(&text, 0);
+                session.execute_guarded(statement)
+                    .map(|_cass_result| ())
+                    .map_err(move |error| ScriptError::Statement { index, line, statement: text, error, })
+            })
+            .map(move |()| count)
+    }
+
+    pub fn execute_script_file<P>(&self, path: P) -> impl Future<Item = usize, Error = ScriptError> where P: AsRef<Path> {
+        match fs::read_to_string(path) {
+            Ok(script) =>
+                Either::A(self.execute_script(&script)),
+            Err(error) =>
+                Either::B(result(Err(ScriptError::Io(error)))),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    fmt,
+    sync::Arc,
+};
+
+use scylla::{
+    Session,
+    SessionBuilder,
+    transport::errors::{
+        QueryError,
+        NewSessionError,
+    },
+};
+
+use super::{
+    Credentials,
+    VALIDATION_QUERY,
+    backend::{
+        on_runtime,
+        Backend,
+        BackendFuture,
+        RuntimeGone,
+    },
+};
+
+// scylla-rust-driver as a lode backend. `runtime` is the tokio 1.x runtime
+// the driver runs on, kept alive by the caller for as long as the lode.
+#[derive(Clone)]
+pub struct Scylla {
+    // `host:port` pairs.
+    pub known_nodes: Vec<String>,
+    pub keyspace: Option<String>,
+    pub credentials: Option<Credentials>,
+    pub runtime: tokio1::runtime::Handle,
+}
+
+#[derive(Debug)]
+pub enum ScyllaError {
+    Connect(NewSessionError),
+    Validate(QueryError),
+    RuntimeGone,
+}
+
+impl fmt::Display for ScyllaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScyllaError::Connect(error) =>
+                write!(f, "error connecting scylla session: {}", error),
+            ScyllaError::Validate(error) =>
+                write!(f, "scylla session failed validation: {}", error),
+            ScyllaError::RuntimeGone =>
+                write!(f, "scylla runtime was shut down"),
+        }
+    }
+}
+
+impl From<RuntimeGone> for ScyllaError {
+    fn from(_error: RuntimeGone) -> ScyllaError {
+        ScyllaError::RuntimeGone
+    }
+}
+
+impl Backend for Scylla {
+    type Session = Session;
+    type Error = ScyllaError;
+
+    fn connect(&self) -> BackendFuture<Session, ScyllaError> {
+        let mut builder = SessionBuilder::new()
+            .known_nodes(&self.known_nodes);
+        if let Some(ref credentials) = self.credentials {
+            builder = builder.user(&credentials.username, &credentials.password);
+        }
+        if let Some(ref keyspace) = self.keyspace {
+            builder = builder.use_keyspace(keyspace, false);
+        }
+        on_runtime(&self.runtime, async move {
+            builder.build().await
+                .map_err(ScyllaError::Connect)
+        })
+    }
+
+    fn validate(&self, session: Arc<Session>) -> BackendFuture<(), ScyllaError> {
+        on_runtime(&self.runtime, async move {
+            session.query(VALIDATION_QUERY, ()).await
+                .map(|_query_result| ())
+                .map_err(ScyllaError::Validate)
+        })
+    }
+
+    // The driver has no explicit close: its connections shut down when the
+    // session is dropped, which happens on the runtime they run on.
+    fn close(&self, session: Session) -> BackendFuture<(), ScyllaError> {
+        on_runtime(&self.runtime, async move {
+            drop(session);
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    fmt,
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use futures::{
+    Future,
+    Stream,
+    IntoFuture,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use cassandra_cpp::{
+    UuidGen,
+    Statement,
+};
+
+use super::{
+    SharedSession,
+    lwt::LwtResult,
+    cql::{
+        quote_table,
+        Insert,
+        Select,
+    },
+};
+
+#[derive(Clone, Debug)]
+pub struct SelfTestParams {
+    pub table: String,
+    pub ttl: Duration,
+}
+
+impl Default for SelfTestParams {
+    fn default() -> SelfTestParams {
+        SelfTestParams {
+            table: "ero_cassandra_self_test".to_string(),
+            ttl: Duration::from_secs(300),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SelfTestStep {
+    Connect,
+    CreateTable,
+    Write,
+    Read,
+    Lwt,
+    Paged,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct StepReport {
+    pub step: SelfTestStep,
+    pub elapsed: Duration,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct SelfTestReport {
+    pub steps: Vec<StepReport>,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.error.is_none())
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &StepReport> {
+        self.steps.iter().filter(|step| step.error.is_some())
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for step in &self.steps {
+            match step.error {
+                None =>
+                    writeln!(f, "{:?}: ok ({:?})", step.step, step.elapsed)?,
+                Some(ref error) =>
+                    writeln!(f, "{:?}: FAILED ({:?}): {}", step.step, step.elapsed, error)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Every step runs even if an earlier one failed, so a single report shows
+// all the problems at once.
+pub fn self_test(session: &SharedSession, params: SelfTestParams) -> impl Future<Item = SelfTestReport, Error = ()> {
+    let id = UuidGen::default().gen_random();
+    let lwt_id = UuidGen::default().gen_random();
+
+    let connect_session = session.clone();
+    let create_session = session.clone();
+    let write_session = session.clone();
+    let read_session = session.clone();
+    let lwt_session = session.clone();
+    let paged_session = session.clone();
+    let create_params = params.clone();
+    let write_params = params.clone();
+    let read_params = params.clone();
+    let lwt_params = params.clone();
+    let paged_params = params;
+
+    result(Ok(Vec::new()))
+        .and_then(move |reports| run_step(reports, SelfTestStep::Connect, move || {
+            let statement = Statement::new("SELECT release_version FROM system.local", 0);
+            connect_session.execute_guarded(statement).map(|_cass_result| ())
+        }))
+        .and_then(move |reports| run_step(reports, SelfTestStep::CreateTable, move || {
+            let query = format!(
+                "CREATE TABLE IF NOT EXISTS {} (id uuid PRIMARY KEY, value text)",
+                quote_table(&create_params.table),
+            );
+            let statement = create_session.statement(&query, 0);
+            create_session.execute_guarded(statement).map(|_cass_result| ())
+        }))
+        .and_then(move |reports| run_step(reports, SelfTestStep::Write, move || {
+            let insert = Insert::table(&write_params.table)
+                .value("id", id)
+                .value("value", "self test".to_string())
+                .ttl(write_params.ttl);
+            match write_session.build(&insert) {
+                Ok(statement) =>
+                    Either::A(write_session.execute_guarded(statement).map(|_cass_result| ()).map_err(|error| format!("{:?}", error))),
+                Err(error) =>
+                    Either::B(result(Err(format!("{:?}", error)))),
+            }
+        }))
+        .and_then(move |reports| run_step(reports, SelfTestStep::Read, move || {
+            let select = Select::table(&read_params.table)
+                .columns(&["value"])
+                .where_eq("id", id);
+            match read_session.build(&select) {
+                Ok(statement) => {
+                    let future = read_session.execute_guarded(statement)
+                        .map_err(|error| format!("{:?}", error))
+                        .and_then(|cass_result| {
+                            if cass_result.row_count() == 1 {
+                                Ok(())
+                            } else {
+                                Err(format!("expected the written row back, got {} rows", cass_result.row_count()))
+                            }
+                        });
+                    Either::A(future)
+                },
+                Err(error) =>
+                    Either::B(result(Err(format!("{:?}", error)))),
+            }
+        }))
+        .and_then(move |reports| run_step(reports, SelfTestStep::Lwt, move || {
+            let insert = Insert::table(&lwt_params.table)
+                .value("id", lwt_id)
+                .value("value", "self test".to_string())
+                .if_not_exists()
+                .ttl(lwt_params.ttl);
+            let statements = lwt_session.build(&insert)
+                .and_then(|first| lwt_session.build(&insert).map(|second| (first, second)));
+            match statements {
+                Ok((first, second)) => {
+                    let session = lwt_session.clone();
+                    let future = lwt_session.execute_lwt(first)
+                        .and_then(move |first_result| {
+                            session.execute_lwt(second)
+                                .map(move |second_result| (first_result, second_result))
+                        })
+                        .map_err(|error| format!("{:?}", error))
+                        .and_then(|results| match results {
+                            (LwtResult::Applied, LwtResult::NotApplied { .. }) =>
+                                Ok(()),
+                            (first_result, second_result) =>
+                                Err(format!("unexpected lwt results: {:?} then {:?}", first_result, second_result)),
+                        });
+                    Either::A(future)
+                },
+                Err(error) =>
+                    Either::B(result(Err(format!("{:?}", error)))),
+            }
+        }))
+        .and_then(move |reports| run_step(reports, SelfTestStep::Paged, move || {
+            let select = Select::table(&paged_params.table)
+                .columns(&["id"])
+                .where_in("id", vec![id, lwt_id]);
+            match paged_session.build(&select) {
+                Ok(statement) => {
+                    let future = paged_session.execute_paged(statement, 1)
+                        .fold(0, |rows, page| -> Result<_, cassandra_cpp::Error> { Ok(rows + page.row_count()) })
+                        .map_err(|error| format!("{:?}", error))
+                        .and_then(|rows| {
+                            if rows == 2 {
+                                Ok(())
+                            } else {
+                                Err(format!("expected 2 rows across pages, got {}", rows))
+                            }
+                        });
+                    Either::A(future)
+                },
+                Err(error) =>
+                    Either::B(result(Err(format!("{:?}", error)))),
+            }
+        }))
+        .map(|steps| SelfTestReport { steps, })
+}
+
+fn run_step<F, R>(
+    mut reports: Vec<StepReport>,
+    step: SelfTestStep,
+    make_future: F,
+)
+    -> impl Future<Item = Vec<StepReport>, Error = ()>
+where F: FnOnce() -> R,
+      R: IntoFuture<Item = ()>,
+      R::Error: fmt::Display,
+{
+    let started_at = Instant::now();
+    make_future().into_future()
+        .then(move |step_result| {
+            reports.push(StepReport {
+                step,
+                elapsed: started_at.elapsed(),
+                error: step_result.err().map(|error| error.to_string()),
+            });
+            Ok(reports)
+        })
+}
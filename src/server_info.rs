@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use futures::Future;
+
+use cassandra_cpp::{
+    Session,
+    Statement,
+};
+
+use log::{
+    info,
+    warn,
+};
+
+use super::{
+    SharedSession,
+    row::FromRow,
+};
+
+const SERVER_INFO_QUERY: &str = "SELECT release_version, data_center FROM system.local";
+
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct ServerInfo {
+    pub release_version: Option<String>,
+    pub data_center: Option<String>,
+}
+
+pub(crate) fn fetch(session: &Session) -> impl Future<Item = ServerInfo, Error = cassandra_cpp::Error> {
+    let statement = Statement::new(SERVER_INFO_QUERY, 0);
+    session.execute(&statement)
+        .map(|cass_result| {
+            let server_info = match cass_result.first_row().map(|row| <(Option<String>, Option<String>)>::from_row(&row)) {
+                Some(Ok((release_version, data_center))) =>
+                    ServerInfo { release_version, data_center, },
+                Some(Err(error)) => {
+                    warn!("error reading server info: {}", error);
+                    ServerInfo::default()
+                },
+                None =>
+                    ServerInfo::default(),
+            };
+            info!(
+                "connected to cassandra {} in data center {}",
+                server_info.release_version.as_ref().map_or("<unknown>", String::as_str),
+                server_info.data_center.as_ref().map_or("<unknown>", String::as_str),
+            );
+            server_info
+        })
+}
+
+impl SharedSession {
+    // Only available when `ClusterParams::verify_connection` is set.
+    pub fn server_info(&self) -> Option<&ServerInfo> {
+        self.server_info.as_ref().map(Arc::as_ref)
+    }
+}
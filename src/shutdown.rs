@@ -0,0 +1,69 @@
+use std::sync::{
+    Weak,
+    atomic::Ordering,
+};
+
+use futures::{
+    Future,
+    Stream,
+    future::result,
+};
+
+use tokio_signal::unix::{
+    Signal,
+    SIGINT,
+    SIGTERM,
+};
+
+use ero::lode::{
+    LodeResource,
+    UsingResource,
+};
+
+use log::{
+    info,
+    error,
+};
+
+use super::{
+    Shared,
+    SharedSession,
+    spawner::{
+        self,
+        TaskSpawner,
+    },
+};
+
+// Waits for SIGTERM or SIGINT, then marks the lode as shutting down and asks
+// it for a session once more: `aquire` refuses it, closes the sessions within
+// `drain_timeout` and terminates the lode. The task holds a lode resource
+// until a signal arrives, so the lode is not stopped by dropping every other
+// handle while it is listening.
+pub(crate) fn spawn(resource: LodeResource<SharedSession>, shared: Weak<Shared>, task_spawner: Option<&TaskSpawner>) {
+    let signals = Signal::new(SIGTERM).flatten_stream()
+        .select(Signal::new(SIGINT).flatten_stream());
+    let future = signals.into_future()
+        .map_err(|(error, _signals)| error!("error listening for shutdown signals: {:?}", error))
+        .and_then(move |(maybe_signal, _signals)| {
+            let shared = match (maybe_signal, shared.upgrade()) {
+                (Some(signal), Some(shared)) => {
+                    info!("received signal {}, shutting down lode {}", signal, shared.lode_name);
+                    shared
+                },
+                _ =>
+                    return Err(()),
+            };
+            shared.shutdown_requested.store(true, Ordering::SeqCst);
+            drop(shared);
+            Ok(resource)
+        })
+        .and_then(|resource| {
+            resource.using_resource_loop((), |_session, ()| {
+                result(Ok((UsingResource::Lost, ero::Loop::Break(()))))
+            })
+        })
+        .then(|_| Ok(()));
+    if let Err(error) = spawner::spawn(task_spawner, Box::new(future)) {
+        error!("error spawning shutdown signals task: {:?}", error);
+    }
+}
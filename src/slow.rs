@@ -0,0 +1,166 @@
+use std::{
+    sync::Arc,
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use futures::Future;
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+};
+
+use log::warn;
+
+use super::{
+    SharedSession,
+    cql::skip_quoted,
+    audit_sink::AuditedStatement,
+    fingerprint::fingerprint,
+};
+
+const MAX_LOGGED_QUERY_LEN: usize = 1024;
+
+pub type SlowQueryHook = Arc<dyn Fn(&SlowQuery) + Send + Sync>;
+
+#[derive(Clone)]
+pub struct SlowQueryParams {
+    pub threshold: Duration,
+    pub on_slow_query: Option<SlowQueryHook>,
+}
+
+impl Default for SlowQueryParams {
+    fn default() -> SlowQueryParams {
+        SlowQueryParams {
+            threshold: Duration::from_millis(500),
+            on_slow_query: None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SlowQuery {
+    // The statement fingerprint, see `fingerprint`.
+    pub query: String,
+    pub latency: Duration,
+    pub failed: bool,
+}
+
+pub fn sanitize(cql: &str) -> String {
+    let bytes = cql.as_bytes();
+    let mut output = String::with_capacity(cql.len().min(MAX_LOGGED_QUERY_LEN));
+    let mut offset = 0;
+    let mut last_was_space = true;
+    while offset < bytes.len() && output.len() < MAX_LOGGED_QUERY_LEN {
+        let start = offset;
+        match bytes[offset] {
+            b'\'' => {
+                offset = skip_quoted(bytes, offset, b'\'');
+                output.push('?');
+                last_was_space = false;
+            },
+            b'"' => {
+                offset = skip_quoted(bytes, offset, b'"');
+                output.push_str(&cql[start .. offset]);
+                last_was_space = false;
+            },
+            byte if byte.is_ascii_digit() && !is_identifier_tail(&output) => {
+                while offset < bytes.len() && (bytes[offset].is_ascii_alphanumeric() || bytes[offset] == b'.' || bytes[offset] == b'-') {
+                    offset += 1;
+                }
+                output.push('?');
+                last_was_space = false;
+            },
+            byte if byte.is_ascii_whitespace() => {
+                offset += 1;
+                if !last_was_space {
+                    output.push(' ');
+                    last_was_space = true;
+                }
+            },
+            _ => {
+                let ch = cql[offset ..].chars().next().unwrap();
+                offset += ch.len_utf8();
+                output.push(ch);
+                last_was_space = false;
+            },
+        }
+    }
+    output.trim_end().to_string()
+}
+
+fn is_identifier_tail(output: &str) -> bool {
+    output.chars().last().map_or(false, |ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+impl SharedSession {
+    // Usage and latency stats are recorded under the statement fingerprint.
+    pub fn execute_logged(&self, query: &str, statement: Statement) -> impl Future<Item = CassResult, Error = cassandra_cpp::Error> {
+        let session = self.clone();
+        let slow_query = self.settings.slow_query.clone();
+        let key = fingerprint(query);
+        let now = Instant::now();
+        self.execute_audited(statement, AuditedStatement::Query(key.to_string()), None)
+            .then(move |result| {
+                let latency = now.elapsed();
+                session.shared.usage.record(key.as_str(), latency, result.is_err());
+                #[cfg(feature = "hdr")]
+                session.shared.query_histograms.record(key.as_str(), latency);
+                if let Some(slow_query) = slow_query {
+                    if latency >= slow_query.threshold {
+                        let event = SlowQuery {
+                            query: key.into(),
+                            latency,
+                            failed: result.is_err(),
+                        };
+                        warn!("slow query ({:?}{}): {}", event.latency, if event.failed { ", failed" } else { "" }, event.query);
+                        if let Some(ref on_slow_query) = slow_query.on_slow_query {
+                            on_slow_query(&event);
+                        }
+                    }
+                }
+                result
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        sanitize,
+        MAX_LOGGED_QUERY_LEN,
+    };
+
+    #[test]
+    fn string_literals_are_masked() {
+        assert_eq!(sanitize("SELECT * FROM t WHERE k = 'secret'"), "SELECT * FROM t WHERE k = ?");
+        assert_eq!(sanitize("INSERT INTO t (s) VALUES ('it''s; fine')"), "INSERT INTO t (s) VALUES (?)");
+        assert_eq!(sanitize("SELECT * FROM t WHERE k = 'unterminated"), "SELECT * FROM t WHERE k = ?");
+    }
+
+    #[test]
+    fn numbers_are_masked_but_identifiers_kept() {
+        assert_eq!(sanitize("UPDATE t2 SET v = 42, w = 1.5e-3 WHERE k = 7"), "UPDATE t2 SET v = ?, w = ? WHERE k = ?");
+        assert_eq!(sanitize("SELECT col_1 FROM ks2.t WHERE id = 123e4567-e89b-12d3-a456-426614174000"), "SELECT col_1 FROM ks2.t WHERE id = ?");
+    }
+
+    #[test]
+    fn quoted_identifiers_are_kept() {
+        assert_eq!(sanitize("SELECT \"Secret 42\" FROM t WHERE \"k\"\"ey\" = 1"), "SELECT \"Secret 42\" FROM t WHERE \"k\"\"ey\" = ?");
+    }
+
+    #[test]
+    fn whitespace_is_collapsed() {
+        assert_eq!(sanitize("  SELECT *\n\tFROM t  \n"), "SELECT * FROM t");
+    }
+
+    #[test]
+    fn output_is_bounded() {
+        let query = format!("SELECT {} FROM t", "a, ".repeat(MAX_LOGGED_QUERY_LEN));
+        assert!(sanitize(&query).len() <= MAX_LOGGED_QUERY_LEN);
+        assert!(sanitize(&query).starts_with("SELECT a, a, "));
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use futures::{
+    Future,
+    future::{
+        Executor as FuturesExecutor,
+        ExecuteErrorKind,
+    },
+};
+
+use tokio::executor::{
+    Executor,
+    DefaultExecutor,
+};
+
+pub type Task = Box<dyn Future<Item = (), Error = ()> + Send>;
+
+// Spawns the crate's background tasks: keepalive probes, topology watch,
+// detached requests, page prefetch, metrics sampling and the signal
+// listener. Without one they go to tokio's `DefaultExecutor`. Timers still
+// need a tokio timer on the thread that polls them.
+pub type TaskSpawner = Arc<dyn Fn(Task) -> Result<(), SpawnError> + Send + Sync>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SpawnError {
+    Shutdown,
+    AtCapacity,
+}
+
+pub fn task_spawner<E>(executor: E) -> TaskSpawner where E: FuturesExecutor<Task> + Send + Sync + 'static {
+    Arc::new(move |task| {
+        executor.execute(task)
+            .map_err(|execute_error| match execute_error.kind() {
+                ExecuteErrorKind::NoCapacity =>
+                    SpawnError::AtCapacity,
+                _ =>
+                    SpawnError::Shutdown,
+            })
+    })
+}
+
+// Whether the executor running the current task is shutting down (or there
+// is none). The lode checks it before connecting and on every acquisition,
+// and acquisitions that fail use it to report `ShuttingDown` instead of a
+// lode that just terminated.
+pub(crate) fn executor_shutting_down() -> bool {
+    DefaultExecutor::current().status()
+        .err()
+        .map_or(false, |spawn_error| spawn_error.is_shutdown())
+}
+
+pub(crate) fn spawn(task_spawner: Option<&TaskSpawner>, task: Task) -> Result<(), SpawnError> {
+    match task_spawner {
+        Some(task_spawner) =>
+            task_spawner(task),
+        None =>
+            DefaultExecutor::current().spawn(task)
+                .map_err(|spawn_error| if spawn_error.is_shutdown() {
+                    SpawnError::Shutdown
+                } else {
+                    SpawnError::AtCapacity
+                }),
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{
+            Ordering,
+            AtomicUsize,
+        },
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+    collections::VecDeque,
+};
+
+use futures::{
+    stream,
+    Future,
+    Stream,
+    sync::mpsc,
+    future::{
+        result,
+        loop_fn,
+        Loop,
+        Either,
+    },
+};
+
+use tokio::timer::Delay;
+
+use ero::{
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+    supervisor::Supervisor,
+};
+
+use log::{
+    debug,
+    warn,
+    error,
+};
+
+use super::{
+    SharedSession,
+    RetryOn,
+    IdempotentStatement,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SpillQueueParams {
+    // Writes beyond this many pending ones are rejected and counted as
+    // dropped.
+    pub capacity: usize,
+    // Pause before reconnecting after a write failed because the cluster is
+    // unavailable, timed out or overloaded. Stretched to the budget window
+    // while `ClusterParams::retry_budget` is spent.
+    pub retry_delay: Duration,
+}
+
+impl Default for SpillQueueParams {
+    fn default() -> SpillQueueParams {
+        SpillQueueParams {
+            capacity: 10_000,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct SpillQueueStats {
+    pub depth: usize,
+    pub queued: usize,
+    pub written: usize,
+    // Rejected by the cluster with a non retryable error.
+    pub failed: usize,
+    pub dropped: usize,
+}
+
+#[derive(Debug)]
+pub enum SpillQueueError {
+    Full { capacity: usize, },
+    Closed,
+}
+
+impl fmt::Display for SpillQueueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpillQueueError::Full { capacity, } =>
+                write!(f, "spill queue is full ({} pending writes)", capacity),
+            SpillQueueError::Closed =>
+                write!(f, "spill queue writer has terminated"),
+        }
+    }
+}
+
+struct Inner {
+    queue: Mutex<VecDeque<IdempotentStatement>>,
+    capacity: usize,
+    queued: AtomicUsize,
+    written: AtomicUsize,
+    failed: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+// In memory queue for fire-and-forget writers. Writes are executed one at a
+// time in the order they were queued; while the cluster is unreachable they
+// stay queued, the lode reconnects and the queue is replayed from the write
+// that failed. Pending writes are lost if the process exits.
+#[derive(Clone)]
+pub struct SpillQueue {
+    inner: Arc<Inner>,
+    wake_tx: mpsc::UnboundedSender<()>,
+}
+
+impl SpillQueue {
+    pub fn spawn_link(
+        supervisor: &Supervisor,
+        resource: LodeResource<SharedSession>,
+        params: SpillQueueParams,
+    )
+        -> SpillQueue
+    {
+        let (wake_tx, wake_rx) = mpsc::unbounded();
+        let inner = Arc::new(Inner {
+            queue: Mutex::new(VecDeque::new()),
+            capacity: params.capacity,
+            queued: AtomicUsize::new(0),
+            written: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        });
+        supervisor.spawn_link(run(resource, wake_rx, inner.clone(), params));
+        SpillQueue { inner, wake_tx, }
+    }
+
+    // Only idempotent statements are accepted: a write that timed out may
+    // still have been applied and is replayed anyway.
+    pub fn write(&self, statement: IdempotentStatement) -> Result<(), SpillQueueError> {
+        {
+            let mut queue = self.inner.queue.lock().unwrap();
+            if queue.len() >= self.inner.capacity {
+                self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+                return Err(SpillQueueError::Full { capacity: self.inner.capacity, });
+            }
+            queue.push_back(statement);
+        }
+        self.inner.queued.fetch_add(1, Ordering::Relaxed);
+        self.wake_tx.unbounded_send(())
+            .map_err(|_send_error| SpillQueueError::Closed)
+    }
+
+    pub fn stats(&self) -> SpillQueueStats {
+        SpillQueueStats {
+            depth: self.inner.queue.lock().unwrap().len(),
+            queued: self.inner.queued.load(Ordering::Relaxed),
+            written: self.inner.written.load(Ordering::Relaxed),
+            failed: self.inner.failed.load(Ordering::Relaxed),
+            dropped: self.inner.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Runs until every `SpillQueue` handle is dropped, writing what is left.
+fn run(
+    resource: LodeResource<SharedSession>,
+    wake_rx: mpsc::UnboundedReceiver<()>,
+    inner: Arc<Inner>,
+    params: SpillQueueParams,
+)
+    -> impl Future<Item = (), Error = ()>
+{
+    let wakes = wake_rx
+        .map(|()| true)
+        .chain(stream::once(Ok(false)));
+    loop_fn(wakes, move |wakes| {
+        let resource = resource.clone();
+        let inner = inner.clone();
+        wakes.into_future()
+            .map_err(|((), _wakes)| ())
+            .and_then(move |(maybe_wake, wakes)| {
+                let next = match maybe_wake {
+                    Some(true) =>
+                        Loop::Continue(wakes),
+                    Some(false) | None =>
+                        Loop::Break(()),
+                };
+                drain(resource, inner, params).map(move |()| next)
+            })
+    })
+}
+
+fn drain(
+    resource: LodeResource<SharedSession>,
+    inner: Arc<Inner>,
+    params: SpillQueueParams,
+)
+    -> impl Future<Item = (), Error = ()>
+{
+    if inner.queue.lock().unwrap().is_empty() {
+        return Either::A(result(Ok(())));
+    }
+    // `retrying` is set while the head write is being replayed after a
+    // failure, so it is counted once as a request with the retry budget.
+    let future = resource
+        .using_resource_loop(false, move |session, retrying| {
+            let inner = inner.clone();
+            loop_fn((session, retrying), move |(session, retrying)| {
+                let inner = inner.clone();
+                let budget = session.settings.retry_budget.clone();
+                // Only this task pops, so the head stays put while it runs.
+                let maybe_execute = inner.queue.lock().unwrap()
+                    .front()
+                    .map(|statement| session.session.execute(statement.statement()));
+                let execute = match maybe_execute {
+                    Some(execute) =>
+                        execute,
+                    None =>
+                        return Either::A(result(Ok(Loop::Break(session)))),
+                };
+                if !retrying {
+                    if let Some(ref budget) = budget {
+                        budget.record_request();
+                    }
+                }
+                let future = execute
+                    .then(move |execute_result| match execute_result {
+                        Ok(_cass_result) => {
+                            inner.queue.lock().unwrap().pop_front();
+                            inner.written.fetch_add(1, Ordering::Relaxed);
+                            Either::A(result(Ok(Loop::Continue((session, false)))))
+                        },
+                        Err(ref error) if RetryOn::of(error).is_some() => {
+                            // The write stays queued either way; an exhausted
+                            // budget only holds the replay off for a window.
+                            let retry_delay = match budget {
+                                Some(ref budget) if !budget.try_retry() =>
+                                    budget.params().window.max(params.retry_delay),
+                                _ =>
+                                    params.retry_delay,
+                            };
+                            warn!("spilled write failed, reconnecting in {:?}: {:?}", retry_delay, error);
+                            let future = Delay::new(Instant::now() + retry_delay)
+                                .then(|_timer_result| -> Result<Loop<SharedSession, (SharedSession, bool)>, ErrorSeverity<bool, ()>> {
+                                    Err(ErrorSeverity::Recoverable { state: true, })
+                                });
+                            Either::B(future)
+                        },
+                        Err(error) => {
+                            error!("spilled write rejected, dropping it: {:?}", error);
+                            inner.queue.lock().unwrap().pop_front();
+                            inner.failed.fetch_add(1, Ordering::Relaxed);
+                            Either::A(result(Ok(Loop::Continue((session, false)))))
+                        },
+                    });
+                Either::B(future)
+            })
+            .map(|session| {
+                debug!("spill queue drained");
+                (UsingResource::Reused(session), ero::Loop::Break(()))
+            })
+        })
+        .map_err(|_error| error!("spill queue session lode terminated"));
+    Either::B(future)
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use futures::{
+    Future,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+    CustomPayload,
+};
+
+use super::{
+    make_retry_policy,
+    SharedSession,
+    RetryPolicy,
+};
+
+// Per statement overrides of what the cluster (or the execution profile)
+// would otherwise decide. Unset fields leave the statement as it is.
+#[derive(Clone, Default)]
+pub struct StatementOptions {
+    // Sent along with the request, for server side query handlers.
+    pub custom_payload: Vec<(String, Vec<u8>)>,
+    pub request_timeout: Option<Duration>,
+    pub idempotent: Option<bool>,
+    pub retry_policy: Option<RetryPolicy>,
+}
+
+impl StatementOptions {
+    pub fn custom_payload<N, V>(mut self, name: N, value: V) -> StatementOptions where N: Into<String>, V: Into<Vec<u8>> {
+        self.custom_payload.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn request_timeout(self, request_timeout: Duration) -> StatementOptions {
+        StatementOptions { request_timeout: Some(request_timeout), ..self }
+    }
+
+    pub fn idempotent(self, idempotent: bool) -> StatementOptions {
+        StatementOptions { idempotent: Some(idempotent), ..self }
+    }
+
+    pub fn retry_policy(self, retry_policy: RetryPolicy) -> StatementOptions {
+        StatementOptions { retry_policy: Some(retry_policy), ..self }
+    }
+
+    // The execute helpers all take a `Statement`, so options applied here
+    // carry over to whichever of them runs it.
+    pub fn apply(&self, statement: &mut Statement) -> Result<(), cassandra_cpp::Error> {
+        if !self.custom_payload.is_empty() {
+            let custom_payload = CustomPayload::default();
+            for (name, value) in &self.custom_payload {
+                custom_payload.set(name.clone(), value)?;
+            }
+            statement.set_custom_payload(custom_payload)?;
+        }
+        if let Some(request_timeout) = self.request_timeout {
+            statement.set_request_timeout(Some(request_timeout));
+        }
+        if let Some(idempotent) = self.idempotent {
+            statement.set_is_idempotent(idempotent)?;
+        }
+        if let Some(ref retry_policy) = self.retry_policy {
+            statement.set_retry_policy(make_retry_policy(retry_policy))?;
+        }
+        Ok(())
+    }
+}
+
+impl SharedSession {
+    pub fn statement_with_options(
+        &self,
+        query: &str,
+        parameter_count: usize,
+        options: &StatementOptions,
+    )
+        -> Result<Statement, cassandra_cpp::Error>
+    {
+        let mut statement = self.statement(query, parameter_count);
+        options.apply(&mut statement)?;
+        Ok(statement)
+    }
+
+    pub fn execute_with_options(
+        &self,
+        mut statement: Statement,
+        options: &StatementOptions,
+    )
+        -> impl Future<Item = CassResult, Error = cassandra_cpp::Error>
+    {
+        match options.apply(&mut statement) {
+            Ok(()) =>
+                Either::A(self.execute_guarded(statement)),
+            Err(error) =>
+                Either::B(result(Err(error))),
+        }
+    }
+}
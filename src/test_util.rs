@@ -0,0 +1,90 @@
+use std::{
+    process,
+    sync::atomic::{
+        Ordering,
+        AtomicUsize,
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use testcontainers::{
+    clients::Cli,
+    images::generic::{
+        WaitFor,
+        GenericImage,
+    },
+    Docker,
+    Container,
+};
+
+use super::{
+    ClusterParams,
+    ContactPoint,
+    KeyspaceSpec,
+};
+
+const CQL_PORT: u16 = 9042;
+
+static KEYSPACE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// A single node cluster in a docker container, stopped and removed together
+// with its data when dropped. `cluster_params` points at a keyspace of its
+// own which the lode creates on its first connect.
+pub struct TestCluster<'d> {
+    container: Container<'d, Cli, GenericImage>,
+    keyspace: String,
+}
+
+impl<'d> TestCluster<'d> {
+    pub fn cassandra(docker: &'d Cli) -> TestCluster<'d> {
+        let image = GenericImage::new("cassandra:3.11")
+            .with_wait_for(WaitFor::message_on_stdout("Starting listening for CQL clients"));
+        TestCluster::start(docker, image)
+    }
+
+    pub fn scylla(docker: &'d Cli) -> TestCluster<'d> {
+        let image = GenericImage::new("scylladb/scylla:4.1")
+            .with_args(vec!["--smp".to_string(), "1".to_string(), "--developer-mode".to_string(), "1".to_string()])
+            .with_wait_for(WaitFor::message_on_stderr("initialization completed"));
+        TestCluster::start(docker, image)
+    }
+
+    // `image` has to expose CQL on 9042 and only report ready once it
+    // accepts clients.
+    pub fn start(docker: &'d Cli, image: GenericImage) -> TestCluster<'d> {
+        let container = docker.run(image);
+        TestCluster {
+            container,
+            keyspace: unique_keyspace(),
+        }
+    }
+
+    pub fn keyspace(&self) -> &str {
+        &self.keyspace
+    }
+
+    pub fn contact_point(&self) -> ContactPoint {
+        let port = self.container.get_host_port(CQL_PORT)
+            .expect("test cluster container exposes the CQL port");
+        ContactPoint::new("127.0.0.1").with_port(port)
+    }
+
+    pub fn cluster_params(&self) -> ClusterParams {
+        ClusterParams {
+            contact_points: vec![self.contact_point()],
+            keyspace: Some(self.keyspace.clone()),
+            create_keyspace: Some(KeyspaceSpec::default()),
+            ..ClusterParams::default()
+        }
+    }
+}
+
+fn unique_keyspace() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or(0);
+    format!("test_{}_{}_{}", process::id(), nanos, KEYSPACE_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
@@ -0,0 +1,107 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{
+            Ordering,
+            AtomicUsize,
+        },
+    },
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        loop_fn,
+        Loop,
+        Either,
+    },
+};
+
+use tokio::timer::Delay;
+
+use log::{
+    debug,
+    warn,
+};
+
+use super::limit::{
+    Limiter,
+    Outcome,
+};
+
+#[derive(Clone)]
+pub struct ConnectThrottle {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    limiter: Limiter,
+    waiting: AtomicUsize,
+    retry_interval: Duration,
+}
+
+pub struct ConnectPermit {
+    inner: Arc<Inner>,
+}
+
+impl ConnectThrottle {
+    pub fn new(max_concurrent_connects: usize, retry_interval: Duration) -> ConnectThrottle {
+        ConnectThrottle {
+            inner: Arc::new(Inner {
+                limiter: Limiter::new(max_concurrent_connects.max(1), None),
+                waiting: AtomicUsize::new(0),
+                retry_interval,
+            }),
+        }
+    }
+
+    pub fn connecting(&self) -> usize {
+        self.inner.limiter.in_flight()
+    }
+
+    pub fn waiting(&self) -> usize {
+        self.inner.waiting.load(Ordering::SeqCst)
+    }
+
+    pub fn acquire(&self) -> impl Future<Item = ConnectPermit, Error = tokio::timer::Error> {
+        let inner = self.inner.clone();
+        loop_fn(false, move |queued| {
+            if inner.limiter.try_acquire() {
+                if queued {
+                    inner.waiting.fetch_sub(1, Ordering::SeqCst);
+                }
+                return Either::A(result(Ok(Loop::Break(ConnectPermit { inner: inner.clone(), }))));
+            }
+            if !queued {
+                let waiting = inner.waiting.fetch_add(1, Ordering::SeqCst) + 1;
+                if waiting >= inner.limiter.limit() {
+                    warn!("connection storm detected: {} lodes waiting to connect, {} connecting", waiting, inner.limiter.in_flight());
+                }
+            }
+            debug!("connect attempt deferred for {:?}", inner.retry_interval);
+            let inner = inner.clone();
+            Either::B(
+                Delay::new(Instant::now() + inner.retry_interval)
+                    .then(move |delay_result| match delay_result {
+                        Ok(()) =>
+                            Ok(Loop::Continue(true)),
+                        Err(error) => {
+                            inner.waiting.fetch_sub(1, Ordering::SeqCst);
+                            Err(error)
+                        },
+                    })
+            )
+        })
+    }
+}
+
+impl Drop for ConnectPermit {
+    fn drop(&mut self) {
+        self.inner.limiter.release(Outcome::Success);
+    }
+}
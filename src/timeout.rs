@@ -0,0 +1,64 @@
+use std::{
+    fmt,
+    time::{
+        Instant,
+        Duration,
+    },
+};
+
+use futures::Future;
+
+use tokio::timer::Timeout;
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+};
+
+use super::SharedSession;
+
+#[derive(Debug)]
+pub enum TimeoutError {
+    Driver(cassandra_cpp::Error),
+    TimedOut { elapsed: Duration, },
+    Timer(tokio::timer::Error),
+}
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeoutError::Driver(error) =>
+                write!(f, "error executing statement: {:?}", error),
+            TimeoutError::TimedOut { elapsed, } =>
+                write!(f, "statement timed out after {:?}", elapsed),
+            TimeoutError::Timer(error) =>
+                write!(f, "statement timer error: {:?}", error),
+        }
+    }
+}
+
+impl SharedSession {
+    // The same deadline is set as the driver request timeout, so the driver
+    // gives up the request (and its stream id) when the timer fires instead
+    // of waiting for the cluster wide `request_timeout`.
+    pub fn execute_timeout(
+        &self,
+        mut statement: Statement,
+        timeout: Duration,
+    )
+        -> impl Future<Item = CassResult, Error = TimeoutError>
+    {
+        statement.set_request_timeout(Some(timeout));
+        let started_at = Instant::now();
+        Timeout::new(self.execute_guarded(statement), timeout)
+            .map_err(move |error| {
+                if error.is_elapsed() {
+                    TimeoutError::TimedOut { elapsed: started_at.elapsed(), }
+                } else if error.is_timer() {
+                    TimeoutError::Timer(error.into_timer().unwrap())
+                } else {
+                    TimeoutError::Driver(error.into_inner().unwrap())
+                }
+            })
+    }
+}
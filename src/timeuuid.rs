@@ -0,0 +1,190 @@
+use std::{
+    str::FromStr,
+    time::{
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use cassandra_cpp::Value;
+
+use super::{
+    bind::ToCqlValue,
+    row::FromValue,
+    value::CqlValue,
+};
+
+// 100ns intervals between the UUID epoch (1582-10-15) and the Unix epoch.
+const GREGORIAN_OFFSET: u64 = 0x01b2_1dd2_1381_4000;
+
+// What Cassandra's `minTimeuuid` and `maxTimeuuid` put after the timestamp:
+// it compares these bytes as signed, so 0x80 sorts first and 0x7f last.
+const MIN_CLOCK_SEQ_AND_NODE: u8 = 0x80;
+const MAX_CLOCK_SEQ_AND_NODE: u8 = 0x7f;
+
+pub fn to_cass_uuid(uuid: &uuid::Uuid) -> cassandra_cpp::Uuid {
+    cassandra_cpp::Uuid::from_str(&uuid.to_hyphenated().to_string())
+        .expect("hyphenated uuid is accepted by the driver")
+}
+
+pub fn from_cass_uuid(uuid: &cassandra_cpp::Uuid) -> uuid::Uuid {
+    uuid::Uuid::parse_str(&uuid.to_string())
+        .expect("driver formats uuids in the standard form")
+}
+
+impl ToCqlValue for uuid::Uuid {
+    fn to_cql_value(&self) -> CqlValue {
+        CqlValue::Uuid(to_cass_uuid(self))
+    }
+}
+
+impl FromValue for uuid::Uuid {
+    fn from_value(value: &Value) -> Result<Self, cassandra_cpp::Error> {
+        value.get_uuid().map(|uuid| from_cass_uuid(&uuid))
+    }
+}
+
+// A version 1 uuid for `time` with a random clock sequence and node, so
+// uuids generated in the same 100ns tick still differ.
+pub fn timeuuid(time: SystemTime) -> uuid::Uuid {
+    let random = uuid::Uuid::new_v4();
+    let random = random.as_bytes();
+    let mut clock_seq_and_node = [0; 8];
+    clock_seq_and_node.copy_from_slice(&random[8 ..]);
+    clock_seq_and_node[0] = (clock_seq_and_node[0] & 0x3f) | 0x80;
+    timeuuid_bytes(time, clock_seq_and_node)
+}
+
+pub fn timeuuid_now() -> uuid::Uuid {
+    timeuuid(SystemTime::now())
+}
+
+// Range bounds for time slices of a timeuuid column:
+// `WHERE id > ? AND id < ?` with `min_timeuuid(from)` and
+// `max_timeuuid(to)` selects everything generated between the two times.
+pub fn min_timeuuid(time: SystemTime) -> uuid::Uuid {
+    timeuuid_bytes(time, [MIN_CLOCK_SEQ_AND_NODE; 8])
+}
+
+pub fn max_timeuuid(time: SystemTime) -> uuid::Uuid {
+    timeuuid_bytes(time, [MAX_CLOCK_SEQ_AND_NODE; 8])
+}
+
+// `None` for uuids other than version 1.
+pub fn timeuuid_time(uuid: &uuid::Uuid) -> Option<SystemTime> {
+    let bytes = uuid.as_bytes();
+    if bytes[6] >> 4 != 1 {
+        return None;
+    }
+    let ticks = (u64::from(bytes[6] & 0x0f) << 56) |
+        (u64::from(bytes[7]) << 48) |
+        (u64::from(bytes[4]) << 40) |
+        (u64::from(bytes[5]) << 32) |
+        (u64::from(bytes[0]) << 24) |
+        (u64::from(bytes[1]) << 16) |
+        (u64::from(bytes[2]) << 8) |
+        u64::from(bytes[3]);
+    Some(if ticks >= GREGORIAN_OFFSET {
+        UNIX_EPOCH + ticks_duration(ticks - GREGORIAN_OFFSET)
+    } else {
+        UNIX_EPOCH - ticks_duration(GREGORIAN_OFFSET - ticks)
+    })
+}
+
+fn timeuuid_bytes(time: SystemTime, clock_seq_and_node: [u8; 8]) -> uuid::Uuid {
+    let ticks = match time.duration_since(UNIX_EPOCH) {
+        Ok(since_epoch) =>
+            GREGORIAN_OFFSET.saturating_add(duration_ticks(since_epoch)),
+        Err(error) =>
+            GREGORIAN_OFFSET.saturating_sub(duration_ticks(error.duration())),
+    };
+    let mut bytes = [0; 16];
+    bytes[0] = (ticks >> 24) as u8;
+    bytes[1] = (ticks >> 16) as u8;
+    bytes[2] = (ticks >> 8) as u8;
+    bytes[3] = ticks as u8;
+    bytes[4] = (ticks >> 40) as u8;
+    bytes[5] = (ticks >> 32) as u8;
+    bytes[6] = ((ticks >> 56) as u8 & 0x0f) | 0x10;
+    bytes[7] = (ticks >> 48) as u8;
+    bytes[8 ..].copy_from_slice(&clock_seq_and_node);
+    uuid::Uuid::from_bytes(bytes)
+}
+
+fn duration_ticks(duration: Duration) -> u64 {
+    duration.as_secs()
+        .saturating_mul(10_000_000)
+        .saturating_add(u64::from(duration.subsec_nanos() / 100))
+}
+
+fn ticks_duration(ticks: u64) -> Duration {
+    Duration::new(ticks / 10_000_000, (ticks % 10_000_000) as u32 * 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{
+        Duration,
+        UNIX_EPOCH,
+    };
+
+    use super::{
+        timeuuid,
+        min_timeuuid,
+        max_timeuuid,
+        timeuuid_time,
+        to_cass_uuid,
+        from_cass_uuid,
+    };
+
+    #[test]
+    fn time_round_trips() {
+        for &since_epoch in [Duration::from_secs(0), Duration::new(1_356_998_700, 123_456_700), Duration::from_millis(4_102_444_800_000)].iter() {
+            let time = UNIX_EPOCH + since_epoch;
+            assert_eq!(timeuuid_time(&timeuuid(time)), Some(time));
+            assert_eq!(timeuuid_time(&min_timeuuid(time)), Some(time));
+            assert_eq!(timeuuid_time(&max_timeuuid(time)), Some(time));
+        }
+        let before_epoch = UNIX_EPOCH - Duration::from_secs(86_400 * 365);
+        assert_eq!(timeuuid_time(&timeuuid(before_epoch)), Some(before_epoch));
+    }
+
+    #[test]
+    fn time_is_truncated_to_ticks() {
+        let time = UNIX_EPOCH + Duration::new(1, 199);
+        assert_eq!(timeuuid_time(&timeuuid(time)), Some(UNIX_EPOCH + Duration::new(1, 100)));
+    }
+
+    #[test]
+    fn timeuuid_is_version_1_rfc_variant() {
+        let uuid = timeuuid(UNIX_EPOCH + Duration::from_secs(1_356_998_700));
+        assert_eq!(uuid.get_version_num(), 1);
+        assert_eq!(uuid.as_bytes()[8] & 0xc0, 0x80);
+        assert_ne!(uuid, timeuuid(UNIX_EPOCH + Duration::from_secs(1_356_998_700)));
+    }
+
+    #[test]
+    fn bounds_match_cassandra() {
+        // `minTimeuuid('1970-01-01 00:00+0000')` and
+        // `minTimeuuid('2013-01-01 00:05+0000')`.
+        assert_eq!(min_timeuuid(UNIX_EPOCH).to_string(), "13814000-1dd2-11b2-8080-808080808080");
+        assert_eq!(
+            min_timeuuid(UNIX_EPOCH + Duration::from_secs(1_356_998_700)).to_string(),
+            "e23f1e00-53a6-11e2-8080-808080808080",
+        );
+        assert_eq!(max_timeuuid(UNIX_EPOCH).to_string(), "13814000-1dd2-11b2-7f7f-7f7f7f7f7f7f");
+    }
+
+    #[test]
+    fn other_versions_have_no_time() {
+        assert_eq!(timeuuid_time(&uuid::Uuid::new_v4()), None);
+        assert_eq!(timeuuid_time(&uuid::Uuid::nil()), None);
+    }
+
+    #[test]
+    fn driver_uuid_round_trips() {
+        let uuid = timeuuid(UNIX_EPOCH + Duration::from_secs(1_356_998_700));
+        assert_eq!(from_cass_uuid(&to_cass_uuid(&uuid)), uuid);
+    }
+}
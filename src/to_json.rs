@@ -0,0 +1,191 @@
+use std::fmt::Write as FmtWrite;
+
+use cassandra_cpp::{
+    Row,
+    Value,
+    ValueType,
+    CassResult,
+};
+
+use serde_json::{
+    Map,
+    Number,
+    Value as Json,
+};
+
+use super::value::CqlValue;
+
+// Days between 0000-03-01 and 1970-01-01 in the proleptic Gregorian calendar.
+const EPOCH_SHIFT_DAYS: i64 = 719_468;
+
+// `date` columns are stored as days since the epoch, centered at 2^31.
+const DATE_EPOCH: i64 = 1 << 31;
+
+// Every row of the result as an object keyed by column name.
+pub fn result(cass_result: &CassResult) -> Result<Vec<Json>, cassandra_cpp::Error> {
+    let columns = column_names(cass_result)?;
+    cass_result.iter()
+        .map(|row| row_with_columns(&row, &columns).map(Json::Object))
+        .collect()
+}
+
+fn column_names(cass_result: &CassResult) -> Result<Vec<String>, cassandra_cpp::Error> {
+    (0 .. cass_result.column_count() as usize)
+        .map(|index| cass_result.column_name(index).map(|name| name.to_string()))
+        .collect()
+}
+
+pub fn row_with_columns(row: &Row, columns: &[String]) -> Result<Map<String, Json>, cassandra_cpp::Error> {
+    columns.iter()
+        .enumerate()
+        .map(|(index, column)| Ok((column.clone(), value(&row.get_column(index)?)?)))
+        .collect()
+}
+
+// Timestamps become RFC 3339 strings in UTC and dates "YYYY-MM-DD", uuids
+// are strings, blobs are "0x" prefixed hex, map keys are rendered as plain
+// text and non finite floats become `null`.
+pub fn value(value: &Value) -> Result<Json, cassandra_cpp::Error> {
+    if value.is_null() {
+        return Ok(Json::Null);
+    }
+    match value.get_type() {
+        ValueType::TIMESTAMP =>
+            value.get_i64().map(|millis| Json::String(rfc3339(millis))),
+        ValueType::DATE =>
+            value.get_u32().map(|days| Json::String(date(days as i64 - DATE_EPOCH))),
+        ValueType::LIST | ValueType::SET =>
+            value.get_set()?
+                .map(|item| self::value(&item))
+                .collect::<Result<_, _>>()
+                .map(Json::Array),
+        ValueType::MAP =>
+            value.get_map()?
+                .map(|(key, value)| Ok((key_string(self::value(&key)?), self::value(&value)?)))
+                .collect::<Result<_, cassandra_cpp::Error>>()
+                .map(Json::Object),
+        ValueType::UDT =>
+            value.get_user_type()?
+                .map(|(name, field)| Ok((name, self::value(&field)?)))
+                .collect::<Result<_, cassandra_cpp::Error>>()
+                .map(Json::Object),
+        _ =>
+            CqlValue::read(value).map(from_cql),
+    }
+}
+
+pub fn from_cql(value: CqlValue) -> Json {
+    match value {
+        CqlValue::Null =>
+            Json::Null,
+        CqlValue::Text(text) =>
+            Json::String(text),
+        CqlValue::Bool(flag) =>
+            Json::Bool(flag),
+        CqlValue::TinyInt(number) =>
+            Json::from(number),
+        CqlValue::SmallInt(number) =>
+            Json::from(number),
+        CqlValue::Int(number) =>
+            Json::from(number),
+        CqlValue::BigInt(number) =>
+            Json::from(number),
+        CqlValue::Float(number) =>
+            Number::from_f64(number as f64).map_or(Json::Null, Json::Number),
+        CqlValue::Double(number) =>
+            Number::from_f64(number).map_or(Json::Null, Json::Number),
+        CqlValue::Uuid(uuid) =>
+            Json::String(uuid.to_string()),
+        CqlValue::Blob(bytes) => {
+            let mut hex = String::with_capacity(2 + bytes.len() * 2);
+            hex.push_str("0x");
+            for byte in bytes {
+                let _ = write!(hex, "{:02x}", byte);
+            }
+            Json::String(hex)
+        },
+        CqlValue::List(items) | CqlValue::Set(items) =>
+            Json::Array(items.into_iter().map(from_cql).collect()),
+        CqlValue::Map(entries) =>
+            Json::Object(entries.into_iter().map(|(key, value)| (key_string(from_cql(key)), from_cql(value))).collect()),
+        CqlValue::Udt(fields) =>
+            Json::Object(fields.into_iter().map(|(name, value)| (name, from_cql(value))).collect()),
+    }
+}
+
+fn key_string(key: Json) -> String {
+    match key {
+        Json::String(text) =>
+            text,
+        other =>
+            other.to_string(),
+    }
+}
+
+fn rfc3339(millis: i64) -> String {
+    let days = millis.div_euclid(86_400_000);
+    let millis_of_day = millis.rem_euclid(86_400_000);
+    let seconds = millis_of_day / 1000;
+    format!(
+        "{}T{:02}:{:02}:{:02}.{:03}Z",
+        date(days),
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        millis_of_day % 1000,
+    )
+}
+
+// Converts days since the epoch to a civil date (Howard Hinnant's algorithm).
+fn date(days: i64) -> String {
+    let days = days + EPOCH_SHIFT_DAYS;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{
+        date,
+        rfc3339,
+        from_cql,
+        super::value::CqlValue,
+    };
+
+    #[test]
+    fn dates_are_civil() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(-1), "1969-12-31");
+        assert_eq!(date(11_016), "2000-02-29");
+        assert_eq!(date(11_017), "2000-03-01");
+        assert_eq!(date(-719_528), "0000-01-01");
+        assert_eq!(date(2_932_896), "9999-12-31");
+    }
+
+    #[test]
+    fn timestamps_are_rfc3339_utc() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(rfc3339(1_356_998_700_123), "2013-01-01T00:05:00.123Z");
+        assert_eq!(rfc3339(951_868_799_999), "2000-02-29T23:59:59.999Z");
+        assert_eq!(rfc3339(-1), "1969-12-31T23:59:59.999Z");
+    }
+
+    #[test]
+    fn values_convert_to_json() {
+        assert_eq!(from_cql(CqlValue::Blob(vec![0x00, 0xab, 0x10])), json!("0x00ab10"));
+        assert_eq!(from_cql(CqlValue::Double(f64::NAN)), json!(null));
+        assert_eq!(
+            from_cql(CqlValue::Map(vec![(CqlValue::Int(1), CqlValue::List(vec![CqlValue::Bool(true), CqlValue::Null]))])),
+            json!({ "1": [true, null] }),
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use std::i64;
+
+use futures::Future;
+
+use cassandra_cpp::Statement;
+
+use super::{
+    SharedSession,
+    value::CqlValue,
+    schema::{
+        ColumnKind,
+        ValidationError,
+    },
+};
+
+const C1: u64 = 0x87c3_7b91_1142_53d5;
+const C2: u64 = 0x4cf5_ad43_2745_937f;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Token(pub i64);
+
+impl Token {
+    pub const MIN: Token = Token(i64::MIN);
+    pub const MAX: Token = Token(i64::MAX);
+}
+
+// Murmur3 partitioner range: `start` is exclusive and `end` is inclusive,
+// matching `token(pk) > ? AND token(pk) <= ?`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TokenRange {
+    pub start: Token,
+    pub end: Token,
+}
+
+impl TokenRange {
+    pub fn full_ring() -> TokenRange {
+        TokenRange { start: Token::MIN, end: Token::MAX, }
+    }
+
+    pub fn split(&self, parts: usize) -> Vec<TokenRange> {
+        let parts = parts.max(1) as i128;
+        let start = self.start.0 as i128;
+        let width = self.end.0 as i128 - start;
+        (0 .. parts)
+            .map(|index| TokenRange {
+                start: Token((start + width * index / parts) as i64),
+                end: Token((start + width * (index + 1) / parts) as i64),
+            })
+            .collect()
+    }
+
+    pub fn contains(&self, token: Token) -> bool {
+        token > self.start && token <= self.end
+    }
+}
+
+pub fn token_expr(partition_key: &[&str]) -> String {
+    format!("token({})", partition_key.join(", "))
+}
+
+pub fn token_range_clause(partition_key: &[&str]) -> String {
+    let expr = token_expr(partition_key);
+    format!("{} > ? AND {} <= ?", expr, expr)
+}
+
+pub fn bind_token_range(stmt: &mut Statement, index: usize, range: &TokenRange) -> Result<(), cassandra_cpp::Error> {
+    stmt.bind_int64(index, range.start.0)?;
+    stmt.bind_int64(index + 1, range.end.0)?;
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum PartitionTokenError {
+    UnknownTable,
+    KeyCount {
+        expected: usize,
+        actual: usize,
+    },
+    Validation(ValidationError),
+    InvalidKey(String),
+    Driver(cassandra_cpp::Error),
+}
+
+// Token of a serialized partition key under the Murmur3 partitioner,
+// including Cassandra's sign extension of the tail bytes. Like the
+// partitioner, an empty key maps to the minimum token, not to its hash.
+pub fn murmur3_token(key: &[u8]) -> Token {
+    if key.is_empty() {
+        return Token::MIN;
+    }
+    let (mut h1, mut h2) = (0u64, 0u64);
+    let mut blocks = key.chunks_exact(16);
+    for block in &mut blocks {
+        let mut k1 = u64::from_le_bytes([block[0], block[1], block[2], block[3], block[4], block[5], block[6], block[7]]);
+        let mut k2 = u64::from_le_bytes([block[8], block[9], block[10], block[11], block[12], block[13], block[14], block[15]]);
+        k1 = k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+        h1 ^= k1;
+        h1 = h1.rotate_left(27).wrapping_add(h2).wrapping_mul(5).wrapping_add(0x52dc_e729);
+        k2 = k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+        h2 ^= k2;
+        h2 = h2.rotate_left(31).wrapping_add(h1).wrapping_mul(5).wrapping_add(0x3849_5ab5);
+    }
+    let tail = blocks.remainder();
+    let (mut k1, mut k2) = (0u64, 0u64);
+    for (index, &byte) in tail.iter().enumerate() {
+        let byte = byte as i8 as i64 as u64;
+        if index >= 8 {
+            k2 ^= byte << ((index - 8) * 8);
+        } else {
+            k1 ^= byte << (index * 8);
+        }
+    }
+    if tail.len() > 8 {
+        h2 ^= k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+    }
+    if !tail.is_empty() {
+        h1 ^= k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+    }
+    h1 ^= key.len() as u64;
+    h2 ^= key.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix(h1);
+    h2 = fmix(h2);
+    match h1.wrapping_add(h2) as i64 {
+        i64::MIN =>
+            Token::MAX,
+        token =>
+            Token(token),
+    }
+}
+
+fn fmix(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    k ^= k >> 33;
+    k
+}
+
+// A single column key is its value alone; a composite key is every
+// component as a 16 bit length, the value and a zero byte. Values must
+// already have the column types, e.g. `Int` for an `int` column.
+pub fn partition_key_bytes(key_values: &[CqlValue]) -> Result<Vec<u8>, String> {
+    if let [value] = key_values {
+        return value_bytes(value);
+    }
+    let mut key = Vec::new();
+    for value in key_values {
+        let bytes = value_bytes(value)?;
+        if bytes.len() > u16::max_value() as usize {
+            return Err(format!("partition key component of {} bytes is too long", bytes.len()));
+        }
+        key.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+        key.extend_from_slice(&bytes);
+        key.push(0);
+    }
+    Ok(key)
+}
+
+fn value_bytes(value: &CqlValue) -> Result<Vec<u8>, String> {
+    match value {
+        CqlValue::Text(text) =>
+            Ok(text.as_bytes().to_vec()),
+        CqlValue::Bool(flag) =>
+            Ok(vec![*flag as u8]),
+        CqlValue::TinyInt(number) =>
+            Ok(number.to_be_bytes().to_vec()),
+        CqlValue::SmallInt(number) =>
+            Ok(number.to_be_bytes().to_vec()),
+        CqlValue::Int(number) =>
+            Ok(number.to_be_bytes().to_vec()),
+        CqlValue::BigInt(number) =>
+            Ok(number.to_be_bytes().to_vec()),
+        CqlValue::Float(number) =>
+            Ok(number.to_bits().to_be_bytes().to_vec()),
+        CqlValue::Double(number) =>
+            Ok(number.to_bits().to_be_bytes().to_vec()),
+        CqlValue::Blob(bytes) =>
+            Ok(bytes.clone()),
+        CqlValue::Uuid(uuid) => {
+            let hex: Vec<u8> = uuid.to_string().bytes()
+                .filter_map(|digit| (digit as char).to_digit(16))
+                .map(|digit| digit as u8)
+                .collect();
+            Ok(hex.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect())
+        },
+        other =>
+            Err(format!("{} value can't be hashed as a partition key", other.kind())),
+    }
+}
+
+impl SharedSession {
+    // Assumes the Murmur3 partitioner. Values are coerced to the partition
+    // key column types first, so an `Int` literal works for a `bigint` key.
+    pub fn partition_token(
+        &self,
+        keyspace: &str,
+        table: &str,
+        key_values: Vec<CqlValue>,
+    )
+        -> impl Future<Item = Token, Error = PartitionTokenError>
+    {
+        self.columns(keyspace, table)
+            .map_err(PartitionTokenError::Driver)
+            .and_then(move |columns| {
+                let partition_key: Vec<_> = columns.into_iter()
+                    .filter(|column| column.kind == ColumnKind::PartitionKey)
+                    .collect();
+                if partition_key.is_empty() {
+                    return Err(PartitionTokenError::UnknownTable);
+                }
+                if partition_key.len() != key_values.len() {
+                    return Err(PartitionTokenError::KeyCount { expected: partition_key.len(), actual: key_values.len(), });
+                }
+                let values = partition_key.into_iter()
+                    .zip(key_values)
+                    .map(|(column, value)| {
+                        column.column_type.coerce(value)
+                            .and_then(|value| value_bytes(&value).map(|_bytes| value))
+                            .map_err(|reason| PartitionTokenError::Validation(ValidationError {
+                                column: column.name,
+                                column_type: column.column_type,
+                                reason,
+                            }))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                partition_key_bytes(&values)
+                    .map(|key| murmur3_token(&key))
+                    .map_err(PartitionTokenError::InvalidKey)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        murmur3_token,
+        partition_key_bytes,
+        Token,
+        super::value::CqlValue,
+    };
+
+    #[test]
+    fn int_keys_match_cassandra() {
+        // `SELECT token(k)` for an `int` partition key.
+        let tokens: Vec<_> = [1, 2, 3].iter()
+            .map(|&k| murmur3_token(&partition_key_bytes(&[CqlValue::Int(k)]).unwrap()))
+            .collect();
+        assert_eq!(tokens, vec![Token(-4069959284402364209), Token(-3248873570005575792), Token(9010454139840013625)]);
+    }
+
+    #[test]
+    fn empty_key_is_min_token() {
+        assert_eq!(murmur3_token(b""), Token::MIN);
+    }
+
+    #[test]
+    fn fixed_keys() {
+        assert_eq!(murmur3_token(b"hello"), Token(-3758069500696749310));
+        // Exactly one block and no tail.
+        assert_eq!(murmur3_token(b"0123456789abcdef"), Token(5467490433528156583));
+        assert_eq!(murmur3_token(b"the quick brown fox jumps over the lazy dog"), Token(-4835482818955082061));
+    }
+
+    #[test]
+    fn tail_bytes_are_sign_extended() {
+        assert_eq!(murmur3_token(&[0xff, 0x80, 0x7f]), Token(38824423781480973));
+        assert_eq!(murmur3_token(&[0x80; 20]), Token(-2331765004752948948));
+    }
+}
@@ -0,0 +1,77 @@
+use futures::Future;
+
+#[cfg(feature = "tracing")]
+use futures::Poll;
+
+#[cfg(feature = "tracing")]
+pub type Span = tracing::Span;
+
+#[cfg(not(feature = "tracing"))]
+#[derive(Clone)]
+pub struct Span;
+
+#[cfg(feature = "tracing")]
+pub struct Traced<F> {
+    future: F,
+    span: Span,
+}
+
+#[cfg(feature = "tracing")]
+impl<F> Future for Traced<F> where F: Future {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let _enter = self.span.enter();
+        self.future.poll()
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub type Traced<F> = F;
+
+#[cfg(feature = "tracing")]
+pub fn span(stage: &'static str, lode: &str) -> Span {
+    tracing::info_span!("ero_cassandra", stage = stage, lode = lode)
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn span(_stage: &'static str, _lode: &str) -> Span {
+    Span
+}
+
+#[cfg(feature = "tracing")]
+pub fn init_span(lode: &str, contact_points: &str, keyspace: Option<&str>) -> Span {
+    tracing::info_span!(
+        "ero_cassandra",
+        stage = "init",
+        lode = lode,
+        contact_points = contact_points,
+        keyspace = keyspace.unwrap_or(""),
+    )
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn init_span(_lode: &str, _contact_points: &str, _keyspace: Option<&str>) -> Span {
+    Span
+}
+
+#[cfg(feature = "tracing")]
+pub fn in_span<F, T>(span: &Span, f: F) -> T where F: FnOnce() -> T {
+    span.in_scope(f)
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn in_span<F, T>(_span: &Span, f: F) -> T where F: FnOnce() -> T {
+    f()
+}
+
+#[cfg(feature = "tracing")]
+pub fn traced<F>(future: F, span: &Span) -> Traced<F> where F: Future {
+    Traced { future, span: span.clone(), }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub fn traced<F>(future: F, _span: &Span) -> Traced<F> where F: Future {
+    future
+}
@@ -0,0 +1,106 @@
+use std::any;
+
+pub use cassandra_cpp::{
+    Value,
+    Error,
+};
+
+use cassandra_cpp::{
+    DataType,
+    UserType,
+    ValueType,
+};
+
+use super::{
+    row::FromValue,
+    value::CqlValue,
+};
+
+// Fields of a UDT value as read from a row, in declaration order.
+pub fn fields(value: &Value) -> Result<Vec<(String, Value)>, cassandra_cpp::Error> {
+    Ok(value.get_user_type()?.collect())
+}
+
+pub fn field<T>(fields: &[(String, Value)], name: &str) -> Result<T, cassandra_cpp::Error> where T: FromValue {
+    match fields.iter().find(|&&(ref field_name, _)| field_name == name) {
+        Some((_, value)) =>
+            T::from_value(value)
+                .map_err(|error| format!("error reading UDT field {:?} as {}: {}", name, any::type_name::<T>(), error).into()),
+        None =>
+            Err(format!("UDT field {:?} is missing", name).into()),
+    }
+}
+
+// The data type is derived from the values themselves, so fields must be
+// listed in the order the UDT declares them.
+pub(crate) fn user_type(fields: Vec<(String, CqlValue)>) -> Result<UserType, cassandra_cpp::Error> {
+    let data_type = udt_data_type(&fields)?;
+    let mut user_type = data_type.new_user_type();
+    for (name, value) in fields {
+        set_field(&mut user_type, &name, value)?;
+    }
+    Ok(user_type)
+}
+
+fn udt_data_type(fields: &[(String, CqlValue)]) -> Result<DataType, cassandra_cpp::Error> {
+    let mut data_type = DataType::new_udt(fields.len());
+    for (name, value) in fields {
+        match value {
+            CqlValue::Udt(nested) =>
+                data_type.add_sub_type_by_name(name, udt_data_type(nested)?)?,
+            other =>
+                data_type.add_sub_value_type_by_name(name, value_type(other)?)?,
+        };
+    }
+    Ok(data_type)
+}
+
+fn value_type(value: &CqlValue) -> Result<ValueType, cassandra_cpp::Error> {
+    match value {
+        // A null is encoded the same way for every type.
+        CqlValue::Null => Ok(ValueType::BLOB),
+        CqlValue::Text(..) => Ok(ValueType::TEXT),
+        CqlValue::Bool(..) => Ok(ValueType::BOOLEAN),
+        CqlValue::TinyInt(..) => Ok(ValueType::TINY_INT),
+        CqlValue::SmallInt(..) => Ok(ValueType::SMALL_INT),
+        CqlValue::Int(..) => Ok(ValueType::INT),
+        CqlValue::BigInt(..) => Ok(ValueType::BIGINT),
+        CqlValue::Float(..) => Ok(ValueType::FLOAT),
+        CqlValue::Double(..) => Ok(ValueType::DOUBLE),
+        CqlValue::Blob(..) => Ok(ValueType::BLOB),
+        CqlValue::Uuid(..) => Ok(ValueType::UUID),
+        other =>
+            Err(format!("{} fields are not supported in UDT values", other.kind()).into()),
+    }
+}
+
+fn set_field(user_type: &mut UserType, name: &str, value: CqlValue) -> Result<(), cassandra_cpp::Error> {
+    match value {
+        CqlValue::Null =>
+            user_type.set_null_by_name(name),
+        CqlValue::Text(value) =>
+            user_type.set_string_by_name(name, &value),
+        CqlValue::Bool(value) =>
+            user_type.set_bool_by_name(name, value),
+        CqlValue::TinyInt(value) =>
+            user_type.set_int8_by_name(name, value),
+        CqlValue::SmallInt(value) =>
+            user_type.set_int16_by_name(name, value),
+        CqlValue::Int(value) =>
+            user_type.set_int32_by_name(name, value),
+        CqlValue::BigInt(value) =>
+            user_type.set_int64_by_name(name, value),
+        CqlValue::Float(value) =>
+            user_type.set_float_by_name(name, value),
+        CqlValue::Double(value) =>
+            user_type.set_double_by_name(name, value),
+        CqlValue::Blob(value) =>
+            user_type.set_bytes_by_name(name, value),
+        CqlValue::Uuid(value) =>
+            user_type.set_uuid_by_name(name, value),
+        CqlValue::Udt(fields) =>
+            user_type.set_user_type_by_name(name, &self::user_type(fields)?),
+        other =>
+            Err(format!("{} fields are not supported in UDT values", other.kind()).into()),
+    }.map(|_user_type| ())
+}
@@ -0,0 +1,298 @@
+use std::{
+    mem,
+    iter,
+    ops::Deref,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+use futures::{
+    Future,
+    future::{
+        result,
+        Either,
+    },
+};
+
+use ero::{
+    lode::{
+        self,
+        LodeResource,
+    },
+    supervisor::Supervisor,
+    ErrorSeverity,
+};
+
+use log::{
+    debug,
+    warn,
+};
+
+use super::{
+    init,
+    aquire,
+    leased,
+    close,
+    close_session,
+    open_session,
+    disconnected,
+    target_keyspace,
+    contact_point,
+    failover,
+    spawner,
+    Error,
+    Params,
+    Shared,
+    State,
+    SharedSession,
+    ConnectedCluster,
+};
+
+// A session handed out to a single user at a time. Return it with
+// `UsingResource::Reused` to give it back to the idle list, or with
+// `UsingResource::Lost` when it was changed (e.g. by `USE`) and should not
+// be seen by the next user: it is then dropped from the pool and closed.
+pub struct UniqueSession {
+    session: SharedSession,
+    // Set when the lode takes it back; a session dropped without it was
+    // given up with `UsingResource::Lost`.
+    returned: bool,
+    lost: Arc<Mutex<Vec<SharedSession>>>,
+}
+
+impl UniqueSession {
+    pub fn shared_session(&self) -> &SharedSession {
+        &self.session
+    }
+
+    // Held past its lease with `force_release` set: give it back with
+    // `UsingResource::Lost` as soon as possible, it will not be reused.
+    pub fn revoked(&self) -> bool {
+        self.session.lease.as_ref().map_or(false, |lease| lease.is_revoked())
+    }
+}
+
+impl Drop for UniqueSession {
+    fn drop(&mut self) {
+        if !self.returned {
+            self.lost.lock().unwrap().push(self.session.clone());
+        }
+    }
+}
+
+impl Deref for UniqueSession {
+    type Target = SharedSession;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+struct ConnectedUnique {
+    connected: ConnectedCluster,
+    idle: Vec<SharedSession>,
+    lost: Arc<Mutex<Vec<SharedSession>>>,
+}
+
+impl ConnectedUnique {
+    fn hand_out(&self, session: SharedSession) -> UniqueSession {
+        let session = leased(session, &self.connected.params, true);
+        UniqueSession { session, returned: false, lost: self.lost.clone(), }
+    }
+
+    fn owns(&self, session: &SharedSession) -> bool {
+        iter::once(&self.connected.session)
+            .chain(self.connected.pool.iter())
+            .any(|owned| Arc::ptr_eq(&owned.session, &session.session))
+    }
+}
+
+// Like `spawn_link`, but every `aquire` gets a session of its own: an idle
+// one if there is any, otherwise a new one is connected. Up to `pool_size`
+// returned sessions are kept idle, the rest are closed.
+pub fn spawn_unique<N>(
+    supervisor: &Supervisor,
+    params: Params<N>,
+)
+    -> LodeResource<UniqueSession>
+where N: AsRef<str> + Send + 'static,
+{
+    let Params { cluster_params, lode_params, configure, } = params;
+    let shared = Shared {
+        task_spawner: cluster_params.task_spawner.clone(),
+        ..Shared::new(lode_params.name.as_ref(), failover::Failover::single(), configure)
+    };
+    let state = State {
+        params: cluster_params,
+        shared: Arc::new(shared),
+        last_failure: None,
+    };
+
+    lode::shared::spawn_link(
+        supervisor,
+        lode_params,
+        state,
+        init_unique,
+        aquire_unique,
+        release_unique,
+        close_unique,
+    )
+}
+
+fn init_unique(
+    state: State,
+)
+    -> impl Future<Item = ConnectedUnique, Error = ErrorSeverity<State, ()>>
+{
+    init(state)
+        .map(|connected| {
+            let idle = iter::once(&connected.session)
+                .chain(connected.pool.iter())
+                .cloned()
+                .collect();
+            ConnectedUnique { connected, idle, lost: Arc::new(Mutex::new(Vec::new())), }
+        })
+}
+
+// The regular `aquire` runs first, so reconnect requests, shutdown and
+// validation behave as for the shared lode.
+fn aquire_unique(
+    connected_unique: ConnectedUnique,
+)
+    -> impl Future<Item = (UniqueSession, ConnectedUnique), Error = ErrorSeverity<State, ()>>
+{
+    let ConnectedUnique { connected, mut idle, lost, } = connected_unique;
+    aquire(connected)
+        .and_then(move |(_session, connected)| {
+            let mut connected_unique = ConnectedUnique { connected, idle: Vec::new(), lost, };
+            if let Some(session) = idle.pop() {
+                connected_unique.idle = idle;
+                let unique_session = connected_unique.hand_out(session);
+                return Either::A(result(Ok((unique_session, connected_unique))));
+            }
+            debug!("no idle session, connecting a new one");
+            let future = connect_session(connected_unique)
+                .map(|(session, connected_unique)| {
+                    let unique_session = connected_unique.hand_out(session);
+                    (unique_session, connected_unique)
+                });
+            Either::B(future)
+        })
+}
+
+// A new session sharing everything but the driver session with the main one.
+fn connect_session(
+    connected_unique: ConnectedUnique,
+)
+    -> impl Future<Item = (SharedSession, ConnectedUnique), Error = ErrorSeverity<State, ()>>
+{
+    let keyspace = target_keyspace(&connected_unique.connected.params);
+    open_session(&connected_unique.connected._cluster, keyspace.as_ref().map(String::as_str))
+        .then(move |connect_result| {
+            let connected = connected_unique.connected;
+            match connect_result {
+                Ok(session) => {
+                    let session = SharedSession {
+                        session: Arc::new(session),
+                        lease: None,
+                        ..connected.session.clone()
+                    };
+                    Ok((session, ConnectedUnique { connected, ..connected_unique }))
+                },
+                Err(error) => {
+                    warn!("error connecting a unique session, reconnecting: {:?}", error);
+                    let shared = connected.session.shared.clone();
+                    let error = Error::Connect {
+                        contact_points: contact_point::display(&connected.params.contact_points),
+                        keyspace,
+                        error,
+                    };
+                    disconnected(&shared, &connected.params, error);
+                    Err(ErrorSeverity::Recoverable { state: State { params: connected.params, shared, last_failure: None, }, })
+                },
+            }
+        })
+}
+
+fn release_unique(
+    mut connected_unique: ConnectedUnique,
+    maybe_session: Option<UniqueSession>,
+)
+    -> impl Future<Item = ConnectedUnique, Error = ErrorSeverity<State, ()>>
+{
+    connected_unique.connected.session.shared.lode.released(maybe_session.is_some());
+    let mut retired: Vec<_> = connected_unique.lost.lock().unwrap()
+        .drain(..)
+        .collect();
+    if let Some(mut unique_session) = maybe_session {
+        unique_session.returned = true;
+        let revoked = match unique_session.session.lease.take() {
+            Some(lease) => {
+                lease.release();
+                lease.is_revoked()
+            },
+            None =>
+                false,
+        };
+        let session = unique_session.session.clone();
+        if revoked {
+            retired.push(session);
+        } else if connected_unique.idle.len() < connected_unique.connected.params.pool_size.max(1) {
+            connected_unique.idle.push(session);
+        } else if !connected_unique.owns(&session) {
+            retired.push(session);
+        }
+    }
+    let mut main_retired = false;
+    for mut session in retired {
+        if let Some(lease) = session.lease.take() {
+            lease.release();
+        }
+        if Arc::ptr_eq(&session.session, &connected_unique.connected.session.session) {
+            main_retired = true;
+            continue;
+        }
+        connected_unique.connected.pool.retain(|pooled| !Arc::ptr_eq(&pooled.session, &session.session));
+        retire(session);
+    }
+    if !main_retired {
+        return Either::A(result(Ok(connected_unique)));
+    }
+    debug!("main session given up, connecting a replacement");
+    let future = connect_session(connected_unique)
+        .map(|(session, mut connected_unique)| {
+            retire(mem::replace(&mut connected_unique.connected.session, session));
+            connected_unique
+        });
+    Either::B(future)
+}
+
+// Closes a session given up by its user once the last clone of it is gone.
+fn retire(session: SharedSession) {
+    let task_spawner = session.shared.task_spawner.clone();
+    let future = close_session(session.session);
+    if let Err(error) = spawner::spawn(task_spawner.as_ref(), Box::new(future)) {
+        warn!("error spawning a lost session close, dropping it: {:?}", error);
+    }
+}
+
+// Sessions connected on demand are handed to `close` with the pool, so they
+// are drained the same way.
+fn close_unique(
+    connected_unique: ConnectedUnique,
+)
+    -> impl Future<Item = State, Error = ()>
+{
+    let ConnectedUnique { mut connected, idle, .. } = connected_unique;
+    for session in idle {
+        let owned = iter::once(&connected.session)
+            .chain(connected.pool.iter())
+            .any(|pooled| Arc::ptr_eq(&pooled.session, &session.session));
+        if !owned {
+            connected.pool.push(session);
+        }
+    }
+    close(connected)
+}
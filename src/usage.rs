@@ -0,0 +1,143 @@
+use std::{
+    sync::Mutex,
+    time::{
+        Instant,
+        Duration,
+    },
+    collections::HashMap,
+};
+
+use futures::Future;
+
+use cassandra_cpp::{
+    CassResult,
+    Statement,
+};
+
+use super::{
+    SharedSession,
+    audit_sink::AuditedStatement,
+};
+
+const LATENCY_SAMPLES: usize = 1024;
+
+pub struct Usage {
+    statements: Mutex<HashMap<String, StatementUsage>>,
+}
+
+#[derive(Default)]
+struct StatementUsage {
+    invocations: u64,
+    errors: u64,
+    latencies: Vec<Duration>,
+    next_sample: usize,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct StatementReport {
+    pub key: String,
+    pub invocations: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub latency_p50: Duration,
+    pub latency_p90: Duration,
+    pub latency_p99: Duration,
+}
+
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct UsageReport {
+    pub statements: Vec<StatementReport>,
+}
+
+impl Usage {
+    pub fn new() -> Usage {
+        Usage {
+            statements: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn register(&self, key: &str) {
+        let mut statements = self.statements.lock().unwrap();
+        if !statements.contains_key(key) {
+            statements.insert(key.to_string(), StatementUsage::default());
+        }
+    }
+
+    pub fn record(&self, key: &str, latency: Duration, is_error: bool) {
+        let mut statements = self.statements.lock().unwrap();
+        if !statements.contains_key(key) {
+            statements.insert(key.to_string(), StatementUsage::default());
+        }
+        let usage = statements.get_mut(key).unwrap();
+        usage.invocations += 1;
+        if is_error {
+            usage.errors += 1;
+        }
+        if usage.latencies.len() < LATENCY_SAMPLES {
+            usage.latencies.push(latency);
+        } else {
+            usage.latencies[usage.next_sample] = latency;
+        }
+        usage.next_sample = (usage.next_sample + 1) % LATENCY_SAMPLES;
+    }
+
+    pub fn report(&self) -> UsageReport {
+        let statements = self.statements.lock().unwrap();
+        let mut report = UsageReport {
+            statements: statements.iter()
+                .map(|(key, usage)| {
+                    let mut latencies = usage.latencies.clone();
+                    latencies.sort();
+                    StatementReport {
+                        key: key.clone(),
+                        invocations: usage.invocations,
+                        errors: usage.errors,
+                        error_rate: if usage.invocations == 0 {
+                            0.0
+                        } else {
+                            usage.errors as f64 / usage.invocations as f64
+                        },
+                        latency_p50: percentile(&latencies, 0.50),
+                        latency_p90: percentile(&latencies, 0.90),
+                        latency_p99: percentile(&latencies, 0.99),
+                    }
+                })
+                .collect(),
+        };
+        report.statements.sort_by(|a, b| a.key.cmp(&b.key));
+        report
+    }
+}
+
+fn percentile(sorted: &[Duration], quantile: f64) -> Duration {
+    if sorted.is_empty() {
+        Duration::from_secs(0)
+    } else {
+        let index = ((sorted.len() - 1) as f64 * quantile).round() as usize;
+        sorted[index]
+    }
+}
+
+impl SharedSession {
+    pub fn register_statement(&self, key: &str) {
+        self.shared.usage.register(key);
+    }
+
+    pub fn execute_tracked(&self, key: &str, statement: Statement) -> impl Future<Item = CassResult, Error = cassandra_cpp::Error> {
+        let shared = self.shared.clone();
+        let key = key.to_string();
+        let now = Instant::now();
+        self.execute_audited(statement, AuditedStatement::Key(key.clone()), None)
+            .then(move |result| {
+                let latency = now.elapsed();
+                shared.usage.record(&key, latency, result.is_err());
+                #[cfg(feature = "hdr")]
+                shared.query_histograms.record(&key, latency);
+                result
+            })
+    }
+
+    pub fn usage_report(&self) -> UsageReport {
+        self.shared.usage.report()
+    }
+}
@@ -0,0 +1,81 @@
+use futures::{
+    Future,
+    IntoFuture,
+};
+
+use ero::{
+    Loop,
+    ErrorSeverity,
+    lode::{
+        LodeResource,
+        UsingResource,
+    },
+};
+
+use super::{
+    SharedSession,
+    spawner,
+};
+
+#[derive(Debug)]
+pub enum UsingSessionError<E> {
+    Fatal(E),
+    LodeGone,
+    ShuttingDown,
+}
+
+pub type UsingSessionFuture<T, E> = Box<dyn Future<Item = T, Error = UsingSessionError<E>> + Send + 'static>;
+
+// `using_resource_loop` for the common "run until done" case: the body
+// resolves to its result, `ErrorSeverity::Recoverable { state }` reconnects
+// the lode and runs the body again with `state` on the fresh session, and
+// `ErrorSeverity::Fatal(error)` ends with `UsingSessionError::Fatal` while
+// the lode keeps running. Bodies written as async blocks can be passed
+// through `compat::into_01`.
+pub trait UsingSession {
+    fn using_session<S, F, R, T, E>(self, state: S, body: F) -> UsingSessionFuture<T, E>
+    where F: FnMut(SharedSession, S) -> R + Send + 'static,
+          R: IntoFuture<Item = T, Error = ErrorSeverity<S, E>>,
+          R::Future: Send + 'static,
+          S: Send + 'static,
+          T: Send + 'static,
+          E: Send + 'static;
+}
+
+impl UsingSession for LodeResource<SharedSession> {
+    fn using_session<S, F, R, T, E>(self, state: S, mut body: F) -> UsingSessionFuture<T, E>
+    where F: FnMut(SharedSession, S) -> R + Send + 'static,
+          R: IntoFuture<Item = T, Error = ErrorSeverity<S, E>>,
+          R::Future: Send + 'static,
+          S: Send + 'static,
+          T: Send + 'static,
+          E: Send + 'static,
+    {
+        let future = self
+            .using_resource_loop(state, move |session, state| {
+                body(session, state).into_future()
+                    .then(|body_result| -> Result<_, ErrorSeverity<S, ()>> {
+                        match body_result {
+                            Ok(value) =>
+                                Ok((UsingResource::Lost, Loop::Break(Ok(value)))),
+                            Err(ErrorSeverity::Recoverable { state, }) =>
+                                Err(ErrorSeverity::Recoverable { state, }),
+                            // Passed out as a value, so only the caller gives up.
+                            Err(ErrorSeverity::Fatal(error)) =>
+                                Ok((UsingResource::Lost, Loop::Break(Err(error)))),
+                        }
+                    })
+            })
+            .then(|using_result| match using_result {
+                Ok(Ok(value)) =>
+                    Ok(value),
+                Ok(Err(error)) =>
+                    Err(UsingSessionError::Fatal(error)),
+                Err(_error) if spawner::executor_shutting_down() =>
+                    Err(UsingSessionError::ShuttingDown),
+                Err(_error) =>
+                    Err(UsingSessionError::LodeGone),
+            });
+        Box::new(future)
+    }
+}
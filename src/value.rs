@@ -0,0 +1,208 @@
+use cassandra_cpp::{
+    Map,
+    Set,
+    List,
+    Uuid,
+    Value,
+    ValueType,
+    Statement,
+    CassCollection,
+};
+
+use super::udt;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum CqlValue {
+    Null,
+    Text(String),
+    Bool(bool),
+    TinyInt(i8),
+    SmallInt(i16),
+    Int(i32),
+    BigInt(i64),
+    Float(f32),
+    Double(f64),
+    Blob(Vec<u8>),
+    Uuid(Uuid),
+    List(Vec<CqlValue>),
+    Set(Vec<CqlValue>),
+    Map(Vec<(CqlValue, CqlValue)>),
+    // Field name and value pairs in declaration order.
+    Udt(Vec<(String, CqlValue)>),
+}
+
+impl CqlValue {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CqlValue::Null => "null",
+            CqlValue::Text(..) => "text",
+            CqlValue::Bool(..) => "boolean",
+            CqlValue::TinyInt(..) => "tinyint",
+            CqlValue::SmallInt(..) => "smallint",
+            CqlValue::Int(..) => "int",
+            CqlValue::BigInt(..) => "bigint",
+            CqlValue::Float(..) => "float",
+            CqlValue::Double(..) => "double",
+            CqlValue::Blob(..) => "blob",
+            CqlValue::Uuid(..) => "uuid",
+            CqlValue::List(..) => "list",
+            CqlValue::Set(..) => "set",
+            CqlValue::Map(..) => "map",
+            CqlValue::Udt(..) => "udt",
+        }
+    }
+
+    pub fn read(value: &Value) -> Result<CqlValue, cassandra_cpp::Error> {
+        if value.is_null() {
+            return Ok(CqlValue::Null);
+        }
+        match value.get_type() {
+            ValueType::ASCII | ValueType::TEXT | ValueType::VARCHAR =>
+                value.get_string().map(CqlValue::Text),
+            ValueType::BOOLEAN =>
+                value.get_bool().map(CqlValue::Bool),
+            ValueType::TINY_INT =>
+                value.get_i8().map(CqlValue::TinyInt),
+            ValueType::SMALL_INT =>
+                value.get_i16().map(CqlValue::SmallInt),
+            ValueType::INT =>
+                value.get_i32().map(CqlValue::Int),
+            ValueType::BIGINT | ValueType::COUNTER | ValueType::TIMESTAMP =>
+                value.get_i64().map(CqlValue::BigInt),
+            ValueType::FLOAT =>
+                value.get_f32().map(CqlValue::Float),
+            ValueType::DOUBLE =>
+                value.get_f64().map(CqlValue::Double),
+            ValueType::BLOB =>
+                value.get_bytes().map(|bytes| CqlValue::Blob(bytes.to_vec())),
+            ValueType::UUID | ValueType::TIMEUUID =>
+                value.get_uuid().map(CqlValue::Uuid),
+            ValueType::LIST =>
+                value.get_set()?
+                    .map(|item| CqlValue::read(&item))
+                    .collect::<Result<_, _>>()
+                    .map(CqlValue::List),
+            ValueType::SET =>
+                value.get_set()?
+                    .map(|item| CqlValue::read(&item))
+                    .collect::<Result<_, _>>()
+                    .map(CqlValue::Set),
+            ValueType::MAP =>
+                value.get_map()?
+                    .map(|(key, value)| Ok((CqlValue::read(&key)?, CqlValue::read(&value)?)))
+                    .collect::<Result<_, cassandra_cpp::Error>>()
+                    .map(CqlValue::Map),
+            ValueType::UDT =>
+                value.get_user_type()?
+                    .map(|(name, field)| Ok((name, CqlValue::read(&field)?)))
+                    .collect::<Result<_, cassandra_cpp::Error>>()
+                    .map(CqlValue::Udt),
+            other =>
+                Err(format!("reading {:?} values is not supported", other).into()),
+        }
+    }
+
+    pub fn bind(self, statement: &mut Statement, index: usize) -> Result<(), cassandra_cpp::Error> {
+        match self {
+            CqlValue::Null =>
+                statement.bind_null(index),
+            CqlValue::Text(value) =>
+                statement.bind_string(index, &value),
+            CqlValue::Bool(value) =>
+                statement.bind_bool(index, value),
+            CqlValue::TinyInt(value) =>
+                statement.bind_int8(index, value),
+            CqlValue::SmallInt(value) =>
+                statement.bind_int16(index, value),
+            CqlValue::Int(value) =>
+                statement.bind_int32(index, value),
+            CqlValue::BigInt(value) =>
+                statement.bind_int64(index, value),
+            CqlValue::Float(value) =>
+                statement.bind_float(index, value),
+            CqlValue::Double(value) =>
+                statement.bind_double(index, value),
+            CqlValue::Blob(value) =>
+                statement.bind_bytes(index, value),
+            CqlValue::Uuid(value) =>
+                statement.bind_uuid(index, value),
+            CqlValue::List(items) => {
+                check_elements("list", items.iter())?;
+                let mut list = List::new(items.len());
+                for item in items {
+                    append(&mut list, item)?;
+                }
+                statement.bind_list(index, list)
+            },
+            CqlValue::Set(items) => {
+                check_elements("set", items.iter())?;
+                let mut set = Set::new(items.len());
+                for item in items {
+                    append(&mut set, item)?;
+                }
+                statement.bind_set(index, set)
+            },
+            CqlValue::Map(entries) => {
+                check_elements("map key", entries.iter().map(|(key, _)| key))?;
+                check_elements("map value", entries.iter().map(|(_, value)| value))?;
+                let mut map = Map::new(entries.len());
+                for (key, value) in entries {
+                    append(&mut map, key)?;
+                    append(&mut map, value)?;
+                }
+                statement.bind_map(index, map)
+            },
+            CqlValue::Udt(fields) =>
+                statement.bind_user_type(index, &udt::user_type(fields)?),
+        }.map(|_statement| ())
+    }
+}
+
+// Collections are homogeneous: catch mixed element types here rather than
+// as an opaque server error.
+fn check_elements<'a, I>(collection: &str, items: I) -> Result<(), cassandra_cpp::Error> where I: Iterator<Item = &'a CqlValue> {
+    let mut kinds = items.enumerate().filter(|&(_, item)| *item != CqlValue::Null);
+    if let Some((_, first)) = kinds.next() {
+        for (position, item) in kinds {
+            if item.kind() != first.kind() {
+                return Err(format!(
+                    "{} element #{} is {} but the first element is {}",
+                    collection,
+                    position,
+                    item.kind(),
+                    first.kind(),
+                ).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn append<C>(collection: &mut C, value: CqlValue) -> Result<(), cassandra_cpp::Error> where C: CassCollection {
+    match value {
+        CqlValue::Text(value) =>
+            collection.append_string(&value),
+        CqlValue::Bool(value) =>
+            collection.append_bool(value),
+        CqlValue::TinyInt(value) =>
+            collection.append_int8(value),
+        CqlValue::SmallInt(value) =>
+            collection.append_int16(value),
+        CqlValue::Int(value) =>
+            collection.append_int32(value),
+        CqlValue::BigInt(value) =>
+            collection.append_int64(value),
+        CqlValue::Float(value) =>
+            collection.append_float(value),
+        CqlValue::Double(value) =>
+            collection.append_double(value),
+        CqlValue::Blob(value) =>
+            collection.append_bytes(value),
+        CqlValue::Uuid(value) =>
+            collection.append_uuid(value),
+        CqlValue::Udt(fields) =>
+            collection.append_user_type(&udt::user_type(fields)?),
+        other =>
+            return Err(format!("{} values can't be appended to a collection", other.kind()).into()),
+    }.map(|_collection| ())
+}
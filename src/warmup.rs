@@ -0,0 +1,91 @@
+use futures::{
+    Future,
+    future::{
+        result,
+        join_all,
+        loop_fn,
+        Loop,
+        Either,
+    },
+};
+
+use cassandra_cpp::Statement;
+
+use log::debug;
+
+use super::SharedSession;
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(rename_all = "snake_case"))]
+pub enum WarmupFailure {
+    Warn,
+    Reconnect,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(default, deny_unknown_fields))]
+pub struct WarmupParams {
+    // Prepare each query and execute the bound statement instead of the
+    // plain one, warming the server side prepared cache as well.
+    pub prepare: bool,
+    pub on_failure: WarmupFailure,
+}
+
+impl Default for WarmupParams {
+    fn default() -> WarmupParams {
+        WarmupParams {
+            prepare: false,
+            on_failure: WarmupFailure::Warn,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct WarmupError {
+    pub query: String,
+    pub error: cassandra_cpp::Error,
+}
+
+// Queries run in order on each session; the sessions are warmed in parallel.
+pub(crate) fn run(
+    sessions: Vec<SharedSession>,
+    queries: Vec<String>,
+    params: WarmupParams,
+)
+    -> impl Future<Item = (), Error = WarmupError>
+{
+    let futures: Vec<_> = sessions.into_iter()
+        .map(|session| {
+            let queries = queries.clone();
+            loop_fn(0, move |index| {
+                if index >= queries.len() {
+                    return Either::A(result(Ok(Loop::Break(()))));
+                }
+                let query = session.rewrite_query(&queries[index]).into_owned();
+                debug!("running warmup query: {}", query);
+                let future = execute(&session, &query, params.prepare)
+                    .map(move |()| Loop::Continue(index + 1))
+                    .map_err(move |error| WarmupError { query, error, });
+                Either::B(future)
+            })
+        })
+        .collect();
+    join_all(futures).map(|_| ())
+}
+
+fn execute(session: &SharedSession, query: &str, prepare: bool) -> impl Future<Item = (), Error = cassandra_cpp::Error> {
+    if prepare {
+        let session = session.clone();
+        let future = result(session.session.prepare(query))
+            .and_then(|prepare_future| prepare_future)
+            .and_then(move |prepared| session.execute_guarded(prepared.bind()))
+            .map(|_cass_result| ());
+        Either::A(future)
+    } else {
+        let statement = Statement::new(query, 0);
+        Either::B(session.execute_guarded(statement).map(|_cass_result| ()))
+    }
+}